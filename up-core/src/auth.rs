@@ -7,3 +7,12 @@ pub enum Role {
     Member,
     Viewer,
 }
+
+/// What an API key is allowed to do, on top of the roles of the user it belongs to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Scope {
+    ReadOnly,
+    ManageChecks,
+    ManageNotifications,
+}
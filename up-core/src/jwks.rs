@@ -32,10 +32,11 @@ impl Jwks {
     }
 
     pub fn key_ids(&self) -> Vec<&str> {
-        self.keys
-            .iter()
-            .map(|k| k.kid.as_deref().unwrap())
-            .collect()
+        self.keys.iter().filter_map(|k| k.kid.as_deref()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
     }
 }
 
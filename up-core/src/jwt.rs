@@ -13,6 +13,7 @@ use crate::{auth::Role, Error};
 
 pub const DEFAULT_ISSUER: &str = "up.sector42.io/auth";
 pub const DEFAULT_AUDIENCE: &str = "up.sector42.io/server";
+pub const ACKNOWLEDGEMENT_AUDIENCE: &str = "up.sector42.io/acknowledgement";
 
 pub struct Generator {
     private_key: PKey<Private>,
@@ -43,6 +44,17 @@ impl Generator {
         subject: &str,
         expiry_hours: i64,
         roles: Option<Vec<Role>>,
+    ) -> Result<String, Error> {
+        self.generate_with_expiry(subject, Duration::hours(expiry_hours), roles)
+    }
+
+    /// Generates a JWT expiring after `expiry`, or that has already expired if `expiry`
+    /// is negative.
+    pub fn generate_with_expiry(
+        &self,
+        subject: &str,
+        expiry: Duration,
+        roles: Option<Vec<Role>>,
     ) -> Result<String, Error> {
         let header = Header {
             key_id: self.key_id.clone(),
@@ -53,7 +65,7 @@ impl Generator {
             &self.audience,
             subject,
             roles.as_deref(),
-            expiry_hours,
+            expiry,
         );
 
         let header_json = serde_json::to_string(&header)?;
@@ -100,7 +112,22 @@ impl Verifier {
         self.internal_jwks.key_ids()
     }
 
+    /// The public keys JWTs are verified against.
+    pub fn jwks(&self) -> &Jwks {
+        &self.internal_jwks
+    }
+
     pub fn verify(&self, jwt: &str) -> Result<Claims, Error> {
+        self.verify_with_audience(jwt, self.audience.as_deref())
+    }
+
+    /// Verifies a JWT issued for another audience than the one the verifier was created
+    /// with, e.g. an acknowledgement link verified against the server's keys.
+    pub fn verify_for_audience(&self, jwt: &str, audience: &str) -> Result<Claims, Error> {
+        self.verify_with_audience(jwt, Some(audience))
+    }
+
+    fn verify_with_audience(&self, jwt: &str, audience: Option<&str>) -> Result<Claims, Error> {
         if let Some(kid) = alcoholic_jwt::token_kid(jwt)? {
             let jwk = match self.jwks.find(&kid) {
                 Some(jwk) => jwk,
@@ -111,7 +138,7 @@ impl Verifier {
             if let Some(issuer) = &self.issuer {
                 validations.push(Validation::Issuer(issuer.to_owned()));
             }
-            if let Some(audience) = &self.audience {
+            if let Some(audience) = audience {
                 validations.push(Validation::Audience(audience.to_owned()));
             }
 
@@ -163,20 +190,19 @@ impl Claims {
         audience: &str,
         subject: &str,
         roles: Option<&[Role]>,
-        expiry_hours: i64,
+        expiry: Duration,
     ) -> Self {
         let now = Utc::now().naive_utc();
-        let hours = Duration::hours(expiry_hours.abs());
-        let expires_at = if expiry_hours < 0 {
-            now - hours
-        } else {
-            now + hours
-        };
+        let expires_at = now + expiry;
 
         Self {
             issuer: Some(issuer.to_string()),
             audience: Some(audience.to_string()),
-            issued_at: if expiry_hours < 0 { expires_at } else { now },
+            issued_at: if expiry < Duration::zero() {
+                expires_at
+            } else {
+                now
+            },
             expires_at,
             subject: Some(subject.to_string()),
             roles: roles.map(|r| r.to_vec()).unwrap_or_else(Vec::new),
@@ -0,0 +1,3 @@
+ALTER TYPE notification_type ADD VALUE IF NOT EXISTS 'SMS';
+
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS phone_number TEXT;
@@ -0,0 +1,6 @@
+-- per-account overrides of the server-wide sender and branding settings used in notifications,
+-- NULL means use the server default.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS email_from     TEXT;
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS email_reply_to TEXT;
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS product_name   TEXT;
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS base_url       TEXT;
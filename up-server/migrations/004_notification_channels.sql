@@ -0,0 +1,22 @@
+-- notifications without a check are channels, defined once per project and attached
+-- to any number of checks in that project.
+ALTER TABLE notifications ALTER COLUMN check_id DROP NOT NULL;
+
+CREATE TABLE IF NOT EXISTS check_notifications (
+    check_id        BIGINT NOT NULL REFERENCES checks (id),
+    notification_id BIGINT NOT NULL REFERENCES notifications (id),
+    created_at      TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    created_by      BIGINT NOT NULL REFERENCES users(id),
+
+    PRIMARY KEY (check_id, notification_id)
+);
+
+-- a channel can alert for many checks, so alerts record which check they are for.
+ALTER TABLE notification_alerts ADD COLUMN IF NOT EXISTS check_id BIGINT REFERENCES checks (id);
+
+UPDATE notification_alerts a
+SET check_id = n.check_id
+FROM notifications n
+WHERE n.id = a.notification_id AND a.check_id IS NULL;
+
+ALTER TABLE notification_alerts ALTER COLUMN check_id SET NOT NULL;
@@ -0,0 +1,16 @@
+-- alerts are exposed through the API, so they need a public identifier.
+ALTER TABLE notification_alerts ADD COLUMN IF NOT EXISTS uuid UUID NOT NULL DEFAULT gen_random_uuid();
+ALTER TABLE notification_alerts ADD CONSTRAINT notification_alerts_unique_uuid UNIQUE (uuid);
+
+-- every attempt to deliver an alert, with what the provider told us.
+CREATE TABLE IF NOT EXISTS notification_alert_attempts (
+    id                  BIGSERIAL PRIMARY KEY,
+    alert_id            BIGINT NOT NULL REFERENCES notification_alerts (id),
+    delivery_status     alert_delivery_status NOT NULL,
+    http_status         INTEGER,
+    provider_message_id TEXT,
+    error               TEXT,
+    attempted_at        TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
+
+CREATE INDEX IF NOT EXISTS notification_alert_attempts_alert_id ON notification_alert_attempts (alert_id);
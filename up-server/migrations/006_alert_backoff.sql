@@ -0,0 +1,8 @@
+-- failed alerts are retried with exponential backoff, starting at retry_backoff_seconds
+-- and doubling on every failure up to retry_backoff_max_seconds.
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS retry_backoff_seconds INTEGER NOT NULL DEFAULT 30;
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS retry_backoff_max_seconds INTEGER NOT NULL DEFAULT 3600;
+ALTER TABLE notifications ADD CONSTRAINT notifications_retry_backoff_valid
+    CHECK (retry_backoff_seconds > 0 AND retry_backoff_max_seconds >= retry_backoff_seconds);
+
+ALTER TABLE notification_alerts ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc');
@@ -0,0 +1,3 @@
+-- alerts are claimed by marking them RUNNING before delivery, claimed_at lets stale
+-- claims from deliveries that never finished be reclaimed.
+ALTER TABLE notification_alerts ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMP WITHOUT TIME ZONE;
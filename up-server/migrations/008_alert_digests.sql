@@ -0,0 +1,5 @@
+-- notifications with a digest window collect the alerts queued within the window and
+-- send them as a single message.
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS digest_window_seconds INTEGER;
+ALTER TABLE notifications ADD CONSTRAINT notifications_digest_window_valid
+    CHECK (digest_window_seconds IS NULL OR digest_window_seconds > 0);
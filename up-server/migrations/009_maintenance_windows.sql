@@ -0,0 +1,38 @@
+-- maintenance windows suppress alerts for the checks they cover. a window covers the
+-- checks with its tag if it has one, otherwise the checks listed for it, otherwise
+-- every check in the project. recurring windows repeat every repeat_every units,
+-- starting at starts_at.
+CREATE TABLE IF NOT EXISTS maintenance_windows (
+    id                 BIGSERIAL PRIMARY KEY,
+    account_id         BIGINT NOT NULL REFERENCES accounts (id),
+    project_id         BIGINT NOT NULL REFERENCES projects (id),
+    uuid               UUID NOT NULL DEFAULT gen_random_uuid(),
+    shortid            TEXT NOT NULL,
+    name               TEXT NOT NULL DEFAULT '',
+    starts_at          TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    ends_at            TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    repeat_every       INTEGER,
+    repeat_every_units period_units,
+    tag                TEXT,
+    created_at         TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    created_by         BIGINT NOT NULL REFERENCES users(id),
+    deleted            BOOLEAN NOT NULL DEFAULT false,
+    deleted_at         TIMESTAMP WITHOUT TIME ZONE,
+    deleted_by         BIGINT REFERENCES users(id),
+
+    CONSTRAINT maintenance_windows_unique_uuid UNIQUE (uuid),
+    CONSTRAINT maintenance_windows_unique_shortid UNIQUE (shortid),
+    CONSTRAINT maintenance_windows_valid_period CHECK (ends_at > starts_at),
+    CONSTRAINT maintenance_windows_valid_repeat CHECK (
+        (repeat_every IS NULL AND repeat_every_units IS NULL)
+        OR
+        (repeat_every > 0 AND repeat_every_units IS NOT NULL)
+    )
+);
+
+CREATE TABLE IF NOT EXISTS maintenance_window_checks (
+    maintenance_window_id BIGINT NOT NULL REFERENCES maintenance_windows (id),
+    check_id              BIGINT NOT NULL REFERENCES checks (id),
+
+    PRIMARY KEY (maintenance_window_id, check_id)
+);
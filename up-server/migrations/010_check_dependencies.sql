@@ -0,0 +1,11 @@
+-- a check depends on its parent checks, alerts for a check are suppressed while any
+-- check it depends on, directly or indirectly, is DOWN.
+CREATE TABLE IF NOT EXISTS check_dependencies (
+    check_id        BIGINT NOT NULL REFERENCES checks (id),
+    parent_check_id BIGINT NOT NULL REFERENCES checks (id),
+    created_at      TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    created_by      BIGINT NOT NULL REFERENCES users(id),
+
+    PRIMARY KEY (check_id, parent_check_id),
+    CONSTRAINT check_dependencies_not_self CHECK (check_id <> parent_check_id)
+);
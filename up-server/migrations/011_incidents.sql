@@ -0,0 +1,22 @@
+-- an incident is a period during which a check is DOWN, opened when the check goes
+-- overdue and resolved by the next ping. alerts belong to the incident they were
+-- sent for, and acknowledging an incident stops any further alerts for it.
+CREATE TABLE IF NOT EXISTS incidents (
+    id              BIGSERIAL PRIMARY KEY,
+    uuid            UUID NOT NULL DEFAULT gen_random_uuid(),
+    check_id        BIGINT NOT NULL REFERENCES checks (id),
+    started_at      TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    resolved_at     TIMESTAMP WITHOUT TIME ZONE,
+    acknowledged_at TIMESTAMP WITHOUT TIME ZONE,
+    acknowledged_by TEXT,
+
+    CONSTRAINT incidents_unique_uuid UNIQUE (uuid)
+);
+
+-- a check has at most one open incident.
+CREATE UNIQUE INDEX IF NOT EXISTS incidents_open_check_id ON incidents (check_id) WHERE resolved_at IS NULL;
+
+ALTER TABLE notification_alerts ADD COLUMN IF NOT EXISTS incident_id BIGINT REFERENCES incidents (id);
+
+-- alerts that were still waiting to be delivered when their incident was acknowledged.
+ALTER TYPE alert_delivery_status ADD VALUE IF NOT EXISTS 'CANCELLED';
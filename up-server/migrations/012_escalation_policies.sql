@@ -0,0 +1,47 @@
+-- an escalation policy notifies its tiers in order. the first tier is notified when an
+-- incident opens, and each following tier is notified if the incident is still neither
+-- acknowledged nor resolved escalate_after_minutes after the previous tier was notified.
+-- checks with an escalation policy alert through it instead of their own notifications.
+CREATE TABLE IF NOT EXISTS escalation_policies (
+    id         BIGSERIAL PRIMARY KEY,
+    account_id BIGINT NOT NULL REFERENCES accounts (id),
+    project_id BIGINT NOT NULL REFERENCES projects (id),
+    uuid       UUID NOT NULL DEFAULT gen_random_uuid(),
+    shortid    TEXT NOT NULL,
+    name       TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    created_by BIGINT NOT NULL REFERENCES users(id),
+    updated_at TIMESTAMP WITHOUT TIME ZONE,
+    updated_by BIGINT REFERENCES users(id),
+    deleted    BOOLEAN NOT NULL DEFAULT false,
+    deleted_at TIMESTAMP WITHOUT TIME ZONE,
+    deleted_by BIGINT REFERENCES users(id),
+
+    CONSTRAINT escalation_policies_unique_uuid UNIQUE (uuid),
+    CONSTRAINT escalation_policies_unique_shortid UNIQUE (shortid)
+);
+
+CREATE TABLE IF NOT EXISTS escalation_policy_tiers (
+    id                     BIGSERIAL PRIMARY KEY,
+    escalation_policy_id   BIGINT NOT NULL REFERENCES escalation_policies (id),
+    position               INTEGER NOT NULL,
+    escalate_after_minutes INTEGER NOT NULL DEFAULT 15,
+
+    CONSTRAINT escalation_policy_tiers_unique_position UNIQUE (escalation_policy_id, position),
+    CONSTRAINT escalation_policy_tiers_valid_escalate_after CHECK (escalate_after_minutes > 0)
+);
+
+CREATE TABLE IF NOT EXISTS escalation_policy_tier_notifications (
+    tier_id         BIGINT NOT NULL REFERENCES escalation_policy_tiers (id) ON DELETE CASCADE,
+    notification_id BIGINT NOT NULL REFERENCES notifications (id),
+
+    PRIMARY KEY (tier_id, notification_id)
+);
+
+ALTER TABLE checks ADD COLUMN IF NOT EXISTS escalation_policy_id BIGINT REFERENCES escalation_policies (id);
+
+-- the last tier notified for an incident, and when.
+ALTER TABLE incidents ADD COLUMN IF NOT EXISTS escalation_tier INTEGER;
+ALTER TABLE incidents ADD COLUMN IF NOT EXISTS escalated_at TIMESTAMP WITHOUT TIME ZONE;
+
+ALTER TABLE notification_alerts ADD COLUMN IF NOT EXISTS escalation_tier INTEGER;
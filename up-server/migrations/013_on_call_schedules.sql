@@ -0,0 +1,55 @@
+-- contact details used when a user is on call.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_number TEXT;
+
+CREATE TYPE rotation_period AS ENUM ('DAILY', 'WEEKLY');
+
+-- an on-call schedule hands off to the next member every rotation period, starting with
+-- the first member at handoff_at. overrides put someone else on call for a while.
+CREATE TABLE IF NOT EXISTS on_call_schedules (
+    id         BIGSERIAL PRIMARY KEY,
+    account_id BIGINT NOT NULL REFERENCES accounts (id),
+    project_id BIGINT NOT NULL REFERENCES projects (id),
+    uuid       UUID NOT NULL DEFAULT gen_random_uuid(),
+    shortid    TEXT NOT NULL,
+    name       TEXT NOT NULL DEFAULT '',
+    rotation   rotation_period NOT NULL,
+    handoff_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    created_by BIGINT NOT NULL REFERENCES users(id),
+    updated_at TIMESTAMP WITHOUT TIME ZONE,
+    updated_by BIGINT REFERENCES users(id),
+    deleted    BOOLEAN NOT NULL DEFAULT false,
+    deleted_at TIMESTAMP WITHOUT TIME ZONE,
+    deleted_by BIGINT REFERENCES users(id),
+
+    CONSTRAINT on_call_schedules_unique_uuid UNIQUE (uuid),
+    CONSTRAINT on_call_schedules_unique_shortid UNIQUE (shortid)
+);
+
+CREATE TABLE IF NOT EXISTS on_call_schedule_members (
+    schedule_id BIGINT NOT NULL REFERENCES on_call_schedules (id),
+    position    INTEGER NOT NULL,
+    user_id     BIGINT NOT NULL REFERENCES users (id),
+
+    PRIMARY KEY (schedule_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS on_call_overrides (
+    id          BIGSERIAL PRIMARY KEY,
+    uuid        UUID NOT NULL DEFAULT gen_random_uuid(),
+    schedule_id BIGINT NOT NULL REFERENCES on_call_schedules (id),
+    user_id     BIGINT NOT NULL REFERENCES users (id),
+    starts_at   TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    ends_at     TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    created_at  TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    created_by  BIGINT NOT NULL REFERENCES users(id),
+
+    CONSTRAINT on_call_overrides_unique_uuid UNIQUE (uuid),
+    CONSTRAINT on_call_overrides_valid_period CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS on_call_overrides_schedule_id ON on_call_overrides (schedule_id, ends_at);
+
+-- notifications that route to an on-call schedule are sent to whoever is on call when
+-- the alert is delivered, instead of their own email address or phone number.
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS on_call_schedule_id BIGINT REFERENCES on_call_schedules (id);
@@ -0,0 +1,22 @@
+CREATE TYPE check_severity AS ENUM ('LOW', 'HIGH');
+
+-- during quiet hours, alerts for low severity checks are held until the quiet hours end.
+ALTER TABLE checks ADD COLUMN IF NOT EXISTS severity check_severity NOT NULL DEFAULT 'HIGH';
+
+-- quiet hours are in the user's time zone (an IANA name like Europe/London), and may
+-- span midnight, e.g. 22:00 to 07:00.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS timezone TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE users ADD COLUMN IF NOT EXISTS quiet_hours_start TIME WITHOUT TIME ZONE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS quiet_hours_end TIME WITHOUT TIME ZONE;
+
+-- per-channel preferences of a user, channels without a row use the defaults.
+CREATE TABLE IF NOT EXISTS user_notification_preferences (
+    user_id           BIGINT NOT NULL REFERENCES users (id),
+    notification_type notification_type NOT NULL,
+    quiet_hours       BOOLEAN NOT NULL DEFAULT true,
+
+    PRIMARY KEY (user_id, notification_type)
+);
+
+-- held alerts are delivered together as a summary once the quiet hours end.
+ALTER TABLE notification_alerts ADD COLUMN IF NOT EXISTS held_at TIMESTAMP WITHOUT TIME ZONE;
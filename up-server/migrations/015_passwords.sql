@@ -0,0 +1,7 @@
+-- Argon2 hash of the user's password in PHC string format, users without one can't
+-- log in with a password.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_hash TEXT;
+
+-- users log in with their email address, so it must identify one user with a password.
+CREATE UNIQUE INDEX IF NOT EXISTS users_unique_password_email ON users (LOWER(email))
+    WHERE password_hash IS NOT NULL AND deleted = false;
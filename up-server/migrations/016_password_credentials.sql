@@ -0,0 +1,19 @@
+-- passwords are hashed with Argon2id into users.password_hash (see 015_passwords.sql).
+
+-- consecutive failed logins, too many lock the user out of password logins until
+-- locked_until.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS failed_logins INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMP WITHOUT TIME ZONE;
+
+-- one-time tokens emailed to users to reset their password, only the SHA-256 hash of
+-- a token is stored so that the table can't be used to reset passwords.
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id         BIGSERIAL PRIMARY KEY,
+    user_id    BIGINT NOT NULL REFERENCES users (id),
+    token_hash TEXT NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    used_at    TIMESTAMP WITHOUT TIME ZONE,
+
+    CONSTRAINT password_reset_tokens_unique_token_hash UNIQUE (token_hash)
+);
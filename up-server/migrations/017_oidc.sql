@@ -0,0 +1,20 @@
+-- identities of users at OpenID Connect providers, the provider's subject is unique
+-- per issuer and is what users are mapped by once linked.
+CREATE TABLE IF NOT EXISTS user_identities (
+    id         BIGSERIAL PRIMARY KEY,
+    user_id    BIGINT NOT NULL REFERENCES users (id),
+    issuer     TEXT NOT NULL,
+    subject    TEXT NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+
+    CONSTRAINT user_identities_unique_issuer_subject UNIQUE (issuer, subject)
+);
+
+-- single sign-on logins from redirecting users to the provider until it redirects them
+-- back, only the SHA-256 hash of the state parameter is stored.
+CREATE TABLE IF NOT EXISTS oidc_logins (
+    state_hash    TEXT PRIMARY KEY,
+    pkce_verifier TEXT NOT NULL,
+    nonce         TEXT NOT NULL,
+    expires_at    TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
@@ -0,0 +1,15 @@
+-- single-use links emailed to users to log in without a password, only the SHA-256
+-- hash of a token is stored. Recent rows also rate limit how often links are sent.
+CREATE TABLE IF NOT EXISTS magic_link_tokens (
+    id         BIGSERIAL PRIMARY KEY,
+    user_id    BIGINT NOT NULL REFERENCES users (id),
+    token_hash TEXT NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    used_at    TIMESTAMP WITHOUT TIME ZONE,
+
+    CONSTRAINT magic_link_tokens_unique_token_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS magic_link_tokens_user_id_created_at
+    ON magic_link_tokens (user_id, created_at);
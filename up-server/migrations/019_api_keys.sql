@@ -0,0 +1,26 @@
+CREATE TYPE api_key_scope AS ENUM ('READ_ONLY', 'MANAGE_CHECKS', 'MANAGE_NOTIFICATIONS');
+
+-- long-lived keys for automation, acting as the user who created them, optionally
+-- restricted to a single project. only the SHA-256 hash of a key is stored, along
+-- with its first characters so users can tell their keys apart.
+CREATE TABLE IF NOT EXISTS api_keys (
+    id           BIGSERIAL PRIMARY KEY,
+    uuid         UUID NOT NULL DEFAULT gen_random_uuid(),
+    shortid      TEXT NOT NULL,
+    user_id      BIGINT NOT NULL REFERENCES users (id),
+    project_id   BIGINT REFERENCES projects (id),
+    name         TEXT NOT NULL DEFAULT '',
+    token_hash   TEXT NOT NULL,
+    token_prefix TEXT NOT NULL,
+    scopes       api_key_scope[] NOT NULL,
+    created_at   TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    expires_at   TIMESTAMP WITHOUT TIME ZONE,
+    last_used_at TIMESTAMP WITHOUT TIME ZONE,
+    revoked_at   TIMESTAMP WITHOUT TIME ZONE,
+
+    CONSTRAINT api_keys_unique_uuid UNIQUE (uuid),
+    CONSTRAINT api_keys_unique_shortid UNIQUE (shortid),
+    CONSTRAINT api_keys_unique_token_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_user_id ON api_keys (user_id);
@@ -0,0 +1,18 @@
+-- refresh tokens exchanged for new JWTs. each use rotates a token to a new one in the
+-- same family, a rotated token being used again means it was stolen, so its whole
+-- family is revoked. only the SHA-256 hash of a token is stored.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id         BIGSERIAL PRIMARY KEY,
+    user_id    BIGINT NOT NULL REFERENCES users (id),
+    family     UUID NOT NULL,
+    token_hash TEXT NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    rotated_at TIMESTAMP WITHOUT TIME ZONE,
+    revoked_at TIMESTAMP WITHOUT TIME ZONE,
+
+    CONSTRAINT refresh_tokens_unique_token_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family ON refresh_tokens (family);
+CREATE INDEX IF NOT EXISTS refresh_tokens_user_id ON refresh_tokens (user_id);
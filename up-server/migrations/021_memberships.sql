@@ -0,0 +1,34 @@
+-- a user has exactly one role per account. the original primary key allowed each role
+-- only once per user across all accounts, keep the most privileged role of any user
+-- with several in the same account.
+DELETE FROM user_roles ur
+    USING user_roles other
+    WHERE ur.user_id = other.user_id
+    AND ur.account_id = other.account_id
+    AND ur.role > other.role;
+
+ALTER TABLE user_roles DROP CONSTRAINT IF EXISTS user_roles_pkey;
+ALTER TABLE user_roles ADD PRIMARY KEY (user_id, account_id);
+
+-- invitations for people to join an account with a role, accepted with the emailed
+-- token. only the SHA-256 hash of the token is stored.
+CREATE TABLE IF NOT EXISTS invitations (
+    id          BIGSERIAL PRIMARY KEY,
+    uuid        UUID NOT NULL DEFAULT gen_random_uuid(),
+    shortid     TEXT NOT NULL,
+    account_id  BIGINT NOT NULL REFERENCES accounts (id),
+    email       TEXT NOT NULL,
+    role        user_role NOT NULL,
+    token_hash  TEXT NOT NULL,
+    created_at  TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    created_by  BIGINT NOT NULL REFERENCES users (id),
+    expires_at  TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    accepted_at TIMESTAMP WITHOUT TIME ZONE,
+    accepted_by BIGINT REFERENCES users (id),
+
+    CONSTRAINT invitations_unique_uuid UNIQUE (uuid),
+    CONSTRAINT invitations_unique_shortid UNIQUE (shortid),
+    CONSTRAINT invitations_unique_token_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS invitations_account_id ON invitations (account_id);
@@ -0,0 +1,17 @@
+-- roles are assigned per project. a user's role in an account only governs the account
+-- itself, its members and projects, except that account administrators also administer
+-- every project in the account they are assigned to.
+ALTER TABLE user_projects ADD COLUMN IF NOT EXISTS role user_role;
+
+-- existing assignments keep the role the user had in the project's account.
+UPDATE user_projects up
+    SET role = ur.role
+    FROM projects p, user_roles ur
+    WHERE p.id = up.project_id
+    AND ur.user_id = up.user_id
+    AND ur.account_id = p.account_id
+    AND up.role IS NULL;
+
+UPDATE user_projects SET role = 'VIEWER' WHERE role IS NULL;
+
+ALTER TABLE user_projects ALTER COLUMN role SET NOT NULL;
@@ -0,0 +1,11 @@
+-- a slot is reserved for every SMS alert before it is sent, with the account row locked,
+-- so concurrent deliveries can't exceed an account's hourly SMS limit between counting
+-- the SMS already sent and sending another. Slots of SMS that failed to send are released.
+CREATE TABLE IF NOT EXISTS sms_reservations (
+    id          BIGSERIAL PRIMARY KEY,
+    account_id  BIGINT NOT NULL REFERENCES accounts (id),
+    reserved_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
+
+CREATE INDEX IF NOT EXISTS sms_reservations_account_id_reserved_at
+    ON sms_reservations (account_id, reserved_at);
@@ -0,0 +1,120 @@
+use up_core::jwt::ACKNOWLEDGEMENT_AUDIENCE;
+use uuid::Uuid;
+
+use crate::{api::v1::ACKNOWLEDGE_URI, keys::KeyStore};
+
+/// Issues and verifies the signed links included in alerts, which acknowledge the
+/// incident an alert was sent for. Links are JWTs for the alert, signed with the
+/// server key and verified against the server's JWKS like any other JWT.
+#[derive(Clone)]
+pub struct AcknowledgementLinks {
+    keys: KeyStore,
+    expiry_hours: i64,
+}
+
+impl AcknowledgementLinks {
+    /// Creates links signed with the current signing key of `keys`. Without a key,
+    /// alerts are sent without acknowledgement links.
+    pub fn new(keys: KeyStore, expiry_hours: i64) -> Self {
+        Self { keys, expiry_hours }
+    }
+
+    /// Returns the acknowledgement URL for an alert, or `None` if links can't be signed.
+    /// Failing to sign a link is logged rather than returned, the alert is still worth
+    /// sending without it.
+    pub fn url(&self, base_url: &url::Url, alert_uuid: &Uuid) -> Option<String> {
+        let generator = self.keys.acknowledgement_generator()?;
+
+        let token = match generator.generate(&alert_uuid.to_string(), self.expiry_hours, None) {
+            Ok(token) => token,
+            Err(e) => {
+                tracing::warn!(
+                    alert_uuid = alert_uuid.to_string(),
+                    "failed to sign acknowledgement link: {}",
+                    e
+                );
+                return None;
+            }
+        };
+
+        match base_url.join(&format!("{}/{}", &ACKNOWLEDGE_URI[1..], token)) {
+            Ok(url) => Some(url.to_string()),
+            Err(e) => {
+                tracing::warn!(
+                    alert_uuid = alert_uuid.to_string(),
+                    "failed to build acknowledgement link: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns the UUID of the alert a link was issued for, if the link is valid and
+    /// has not expired.
+    pub fn verify(&self, token: &str) -> Option<Uuid> {
+        match self
+            .keys
+            .verifier()
+            .verify_for_audience(token, ACKNOWLEDGEMENT_AUDIENCE)
+        {
+            Ok(claims) => claims.subject.and_then(|subject| subject.parse().ok()),
+            Err(e) => {
+                tracing::debug!("rejecting acknowledgement link: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use openssl::rsa::Rsa;
+    use up_core::jwks::Jwks;
+
+    use super::*;
+
+    fn key_store(pem: &[u8], signing_key: Option<&[u8]>) -> KeyStore {
+        KeyStore::from_jwks(&Jwks::from_pem(pem).unwrap().to_string(), signing_key).unwrap()
+    }
+
+    fn generate_pem() -> Vec<u8> {
+        let rsa = Rsa::generate(2048).unwrap();
+        let mut pem = rsa.private_key_to_pem().unwrap();
+        pem.extend(rsa.public_key_to_pem().unwrap());
+        pem
+    }
+
+    #[test]
+    fn link_roundtrip() {
+        let pem = generate_pem();
+        let links = AcknowledgementLinks::new(key_store(&pem, Some(&pem)), 1);
+        let base_url: url::Url = "https://up.example.com/".parse().unwrap();
+        let alert_uuid = Uuid::new_v4();
+
+        let url = links.url(&base_url, &alert_uuid).unwrap();
+        let prefix = format!("https://up.example.com{}/", ACKNOWLEDGE_URI);
+        assert!(url.starts_with(&prefix));
+        assert_eq!(Some(alert_uuid), links.verify(&url[prefix.len()..]));
+    }
+
+    #[test]
+    fn link_signed_by_other_key_is_rejected() {
+        let pem = generate_pem();
+        let links = AcknowledgementLinks::new(key_store(&pem, Some(&pem)), 1);
+        let other_links = AcknowledgementLinks::new(key_store(&pem, Some(&generate_pem())), 1);
+        let base_url: url::Url = "https://up.example.com/".parse().unwrap();
+
+        let url = other_links.url(&base_url, &Uuid::new_v4()).unwrap();
+        let token = url.rsplit('/').next().unwrap();
+        assert_eq!(None, links.verify(token));
+    }
+
+    #[test]
+    fn no_links_without_key() {
+        let links = AcknowledgementLinks::new(key_store(&generate_pem(), None), 1);
+        let base_url: url::Url = "https://up.example.com/".parse().unwrap();
+
+        assert_eq!(None, links.url(&base_url, &Uuid::new_v4()));
+    }
+}
@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use axum::{
     body::{boxed, Bytes},
     handler::Handler,
@@ -15,13 +13,16 @@ use hyper::{
 };
 use miette::{Diagnostic, GraphicalReportHandler, JSONReportHandler, NarratableReportHandler};
 use serde::{Deserialize, Serialize};
-use up_core::jwt::Verifier;
 
 mod json;
 mod ui;
 pub mod v1;
 
-use crate::{api::json::Json, auth, notifier::Notifier, repository::Repository};
+use crate::{
+    acknowledgement::AcknowledgementLinks, api::json::Json, api::v1::accounts::SignupConfig, auth,
+    keys::KeyStore, notifier::Notifier, oidc::OidcProvider, repository::Repository,
+    tokens::TokenIssuer,
+};
 
 // Basic response status.
 #[derive(Serialize, Deserialize, Debug)]
@@ -85,14 +86,26 @@ impl NotFoundResponse {
 
 /// Builds a new router, providing handlers with a [`Repository`]
 /// connected to the specified [`Database`].
-pub fn build(repository: Repository, notifier: Notifier, verifier: Arc<Verifier>) -> Router {
+pub fn build(
+    repository: Repository,
+    notifier: Notifier,
+    acknowledgements: AcknowledgementLinks,
+    tokens: TokenIssuer,
+    oidc: OidcProvider,
+    signup: SignupConfig,
+    keys: KeyStore,
+) -> Router {
     let router = v1::router()
         .route("/", get(ui::index_handler))
         .layer(Extension(notifier))
+        .layer(Extension(acknowledgements))
+        .layer(Extension(tokens))
+        .layer(Extension(oidc))
+        .layer(Extension(signup))
         .layer(middleware::from_fn(error_middleware))
         .layer(middleware::from_fn(auth::auth_middleware))
         .layer(Extension(repository))
-        .layer(Extension(verifier))
+        .layer(Extension(keys))
         .fallback(not_found_handler.into_service());
 
     ui::Asset::register_routes(router)
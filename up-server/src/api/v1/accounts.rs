@@ -0,0 +1,145 @@
+use axum::{extract::Path, Extension};
+use chrono::{DateTime, TimeZone, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use up_core::auth::Role;
+
+use crate::{
+    api::{
+        v1::{
+            auth::{issue_token, Token},
+            ApiError,
+        },
+        GenericResponse, Json,
+    },
+    auth::Identity,
+    repository::{dto, Repository},
+    shortid::ShortId,
+    tokens::TokenIssuer,
+};
+
+/// Whether anyone can sign up, rather than only users invited to an account.
+#[derive(Clone, Copy, Debug)]
+pub struct SignupConfig {
+    pub enabled: bool,
+}
+
+/// Handler for `GET /api/v1/accounts`
+pub async fn read_all(
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<Account>>, ApiError> {
+    let accounts: Vec<Account> = repository
+        .account()
+        .read_all(&identity)
+        .await?
+        .into_iter()
+        .map(|a| a.into())
+        .collect();
+    Ok(accounts.into())
+}
+
+/// Handler for `PATCH /api/v1/accounts/:id`
+pub async fn update(
+    Path(id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<UpdateAccount>,
+) -> Result<Json<Account>, ApiError> {
+    let account: Account = repository
+        .account()
+        .update(&identity, id.as_uuid(), request.0.into())
+        .await?
+        .into();
+    Ok(account.into())
+}
+
+/// Handler for `DELETE /api/v1/accounts/:id`, also deletes the projects, checks and
+/// notifications in the account.
+pub async fn delete(
+    Path(id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository.account().delete(&identity, id.as_uuid()).await?;
+    Ok(Json(GenericResponse::success("deleted")))
+}
+
+/// Handler for `POST /api/v1/auth/signup`, creates a user, an account they administer
+/// and a default project, and logs the user in.
+pub async fn signup(
+    Extension(repository): Extension<Repository>,
+    Extension(tokens): Extension<TokenIssuer>,
+    Extension(config): Extension<SignupConfig>,
+    request: Json<Signup>,
+) -> Result<Json<Token>, ApiError> {
+    if !config.enabled {
+        return Err(ApiError::SignupDisabled);
+    }
+
+    let subject = repository.account().signup(request.0.into()).await?;
+
+    issue_token(&repository, &tokens, &subject).await
+}
+
+/// An API [`Account`] type, along with the role of the user reading it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Account {
+    pub id: ShortId,
+    pub name: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Body for `PATCH /api/v1/accounts/:id`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateAccount {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Body for `POST /api/v1/auth/signup`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Signup {
+    pub email: String,
+    pub password: String,
+    pub account_name: String,
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::Account`] to
+/// API [`Account`].
+impl From<dto::Account> for Account {
+    fn from(account: dto::Account) -> Self {
+        Self {
+            id: account.uuid.into(),
+            name: account.name,
+            role: account.role.into(),
+            created_at: Utc.from_utc_datetime(&account.created_at),
+            updated_at: account.updated_at.map(|dt| Utc.from_utc_datetime(&dt)),
+        }
+    }
+}
+
+/// Conversion from API [`UpdateAccount`] to
+/// repository [`dto::UpdateAccount`].
+impl From<UpdateAccount> for dto::UpdateAccount {
+    fn from(request: UpdateAccount) -> Self {
+        Self { name: request.name }
+    }
+}
+
+/// Conversion from API [`Signup`] to
+/// repository [`dto::Signup`].
+impl From<Signup> for dto::Signup {
+    fn from(request: Signup) -> Self {
+        Self {
+            email: request.email,
+            password: request.password,
+            account_name: request.account_name,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use axum::{extract::Path, Extension};
+use chrono::{DateTime, TimeZone, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use up_core::auth::Scope;
+
+use crate::{
+    api::{v1::ApiError, GenericResponse, Json},
+    auth::Identity,
+    repository::{dto, Repository},
+    shortid::ShortId,
+};
+
+/// Handler for `GET /api/v1/api-keys`
+pub async fn read_all(
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<ApiKey>>, ApiError> {
+    let keys: Vec<ApiKey> = repository
+        .api_key()
+        .read_all(&identity)
+        .await?
+        .into_iter()
+        .map(|k| k.into())
+        .collect();
+    Ok(keys.into())
+}
+
+/// Handler for `POST /api/v1/api-keys`
+pub async fn create(
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<CreateApiKey>,
+) -> Result<Json<CreatedApiKey>, ApiError> {
+    let created: CreatedApiKey = repository
+        .api_key()
+        .create(&identity, request.0.into())
+        .await?
+        .into();
+    Ok(created.into())
+}
+
+/// Handler for `DELETE /api/v1/api-keys/:id`
+pub async fn revoke(
+    Path(key_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository
+        .api_key()
+        .revoke(&identity, key_id.as_uuid())
+        .await?;
+    Ok(Json(GenericResponse::success("revoked")))
+}
+
+/// An API [`ApiKey`] type, without the key itself.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: ShortId,
+    pub name: String,
+    /// The first characters of the key, to tell keys apart.
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<ShortId>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Response for `POST /api/v1/api-keys`, `key` is sent as `Authorization: Bearer <key>`
+/// and can't be read again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// Body for `POST /api/v1/api-keys`. Keys act as the user creating them, restricted
+/// to `project_id` if set, and never expire unless `expires_at` is set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKey {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub scopes: Vec<Scope>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<ShortId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::ApiKey`] to
+/// API [`ApiKey`].
+impl From<dto::ApiKey> for ApiKey {
+    fn from(key: dto::ApiKey) -> Self {
+        Self {
+            id: key.uuid.into(),
+            name: key.name,
+            prefix: key.token_prefix,
+            scopes: key.scopes.into_iter().map(|s| s.into()).collect(),
+            project_id: key.project_uuid.map(|u| u.into()),
+            created_at: Utc.from_utc_datetime(&key.created_at),
+            expires_at: key.expires_at.map(|dt| Utc.from_utc_datetime(&dt)),
+            last_used_at: key.last_used_at.map(|dt| Utc.from_utc_datetime(&dt)),
+        }
+    }
+}
+
+/// Conversion from repository [`dto::CreatedApiKey`] to
+/// API [`CreatedApiKey`].
+impl From<dto::CreatedApiKey> for CreatedApiKey {
+    fn from(created: dto::CreatedApiKey) -> Self {
+        Self {
+            api_key: created.key.into(),
+            key: created.token,
+        }
+    }
+}
+
+/// Conversion from API [`CreateApiKey`] to
+/// repository [`dto::CreateApiKey`].
+impl From<CreateApiKey> for dto::CreateApiKey {
+    fn from(request: CreateApiKey) -> Self {
+        Self {
+            name: request.name,
+            project_uuid: request.project_id.map(|id| id.into_uuid()),
+            scopes: request.scopes.into_iter().map(|s| s.into()).collect(),
+            expires_at: request.expires_at.map(|dt| dt.naive_utc()),
+        }
+    }
+}
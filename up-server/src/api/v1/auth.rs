@@ -0,0 +1,367 @@
+use axum::{extract::Query, response::Redirect, Extension};
+use chrono::{DateTime, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use up_core::jwks::Jwks;
+
+use crate::{
+    api::{v1::ApiError, GenericResponse, Json},
+    auth::Identity,
+    keys::KeyStore,
+    mask,
+    notifier::Notifier,
+    oidc::OidcProvider,
+    repository::{dto::Authentication, Repository, RepositoryError},
+    tokens::TokenIssuer,
+};
+
+/// Handler for `POST /api/v1/auth/login`
+pub async fn login(
+    Extension(repository): Extension<Repository>,
+    Extension(tokens): Extension<TokenIssuer>,
+    request: Json<Login>,
+) -> Result<Json<Token>, ApiError> {
+    let subject = match repository
+        .auth()
+        .authenticate(&request.email, &request.password)
+        .await?
+    {
+        Authentication::Authenticated(subject) => subject,
+        Authentication::Failed => {
+            tracing::trace!(
+                email = mask::email(&request.email),
+                "invalid email or password, rejecting login"
+            );
+            return Err(ApiError::InvalidCredentials);
+        }
+        // Rejected like any other failed login, so the response doesn't reveal
+        // whether an account exists for the email address.
+        Authentication::LockedOut => {
+            tracing::warn!(
+                email = mask::email(&request.email),
+                "user locked out after too many failed logins, rejecting login"
+            );
+            return Err(ApiError::InvalidCredentials);
+        }
+    };
+
+    issue_token(&repository, &tokens, &subject).await
+}
+
+/// Handler for `GET /api/v1/auth/oidc/login`, redirects to the OpenID Connect
+/// provider to log in.
+pub async fn oidc_login(
+    Extension(repository): Extension<Repository>,
+    Extension(oidc): Extension<OidcProvider>,
+) -> Result<Redirect, ApiError> {
+    let login = oidc.start_login().await?;
+    repository
+        .auth()
+        .create_oidc_login(&login.state, &login.pkce_verifier, &login.nonce)
+        .await?;
+    Ok(Redirect::to(login.authorize_url.as_str()))
+}
+
+/// Handler for `GET /api/v1/auth/oidc/callback`, where the OpenID Connect provider
+/// redirects back to once the user has logged in.
+pub async fn oidc_callback(
+    Query(query): Query<OidcCallback>,
+    Extension(repository): Extension<Repository>,
+    Extension(oidc): Extension<OidcProvider>,
+    Extension(tokens): Extension<TokenIssuer>,
+) -> Result<Json<Token>, ApiError> {
+    let (pkce_verifier, nonce) = repository
+        .auth()
+        .take_oidc_login(&query.state)
+        .await?
+        .ok_or_else(|| {
+            RepositoryError::BadArgument(
+                "single sign-on login is invalid or has expired".to_string(),
+            )
+        })?;
+
+    let code = match (query.code, query.error) {
+        (Some(code), None) => code,
+        (_, error) => {
+            return Err(RepositoryError::BadArgument(format!(
+                "single sign-on login failed: {}",
+                error.as_deref().unwrap_or("no authorization code")
+            ))
+            .into())
+        }
+    };
+
+    let identity = oidc.complete_login(&code, &pkce_verifier, &nonce).await?;
+
+    let subject = repository
+        .auth()
+        .find_or_provision_external_user(&identity, oidc.provisioning())
+        .await?
+        .ok_or_else(|| {
+            tracing::trace!(
+                issuer = identity.issuer,
+                email = identity.email.as_deref().map(mask::email),
+                "no user for identity, rejecting login"
+            );
+            ApiError::UnknownIdentity
+        })?;
+
+    issue_token(&repository, &tokens, &subject).await
+}
+
+/// Handler for `PUT /api/v1/auth/password`. Every session of the user ends, so they
+/// log in again with the new password.
+pub async fn set_password(
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<SetPassword>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository
+        .auth()
+        .set_password(
+            &identity,
+            request.current_password.as_deref(),
+            &request.new_password,
+        )
+        .await?;
+    Ok(Json(GenericResponse::success("password set")))
+}
+
+/// Handler for `POST /api/v1/auth/password-reset`. The response is the same whether
+/// or not a user has the email address, so it can't be used to find out who has
+/// an account.
+pub async fn request_password_reset(
+    Extension(repository): Extension<Repository>,
+    Extension(notifier): Extension<Notifier>,
+    request: Json<RequestPasswordReset>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    match repository
+        .auth()
+        .create_password_reset(&request.email)
+        .await?
+    {
+        // Sent in the background, so the response takes as long whether or not a
+        // user has the email address.
+        Some(reset) => {
+            tokio::spawn(async move {
+                if let Err(e) = notifier.send_password_reset(&reset).await {
+                    tracing::error!(
+                        email = mask::email(&reset.email),
+                        "failed to send password reset: {}",
+                        e.to_message()
+                    );
+                }
+            });
+        }
+        None => tracing::trace!(
+            email = mask::email(&request.email),
+            "no user with email address or rate limited, not sending password reset"
+        ),
+    }
+    Ok(Json(GenericResponse::success(
+        "if a user has this email address, a password reset link has been sent to it",
+    )))
+}
+
+/// Handler for `POST /api/v1/auth/password-reset/confirm`
+pub async fn reset_password(
+    Extension(repository): Extension<Repository>,
+    request: Json<ResetPassword>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository
+        .auth()
+        .reset_password(&request.token, &request.new_password)
+        .await?;
+    Ok(Json(GenericResponse::success("password reset")))
+}
+
+/// Handler for `POST /api/v1/auth/magic-link`. Like password resets, the response
+/// is the same whether or not a link has been sent.
+pub async fn request_magic_link(
+    Extension(repository): Extension<Repository>,
+    Extension(notifier): Extension<Notifier>,
+    request: Json<RequestMagicLink>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    match repository.auth().create_magic_link(&request.email).await? {
+        // Sent in the background like password resets.
+        Some(link) => {
+            tokio::spawn(async move {
+                if let Err(e) = notifier.send_magic_link(&link).await {
+                    tracing::error!(
+                        email = mask::email(&link.email),
+                        "failed to send magic link: {}",
+                        e.to_message()
+                    );
+                }
+            });
+        }
+        None => tracing::trace!(
+            email = mask::email(&request.email),
+            "no user with email address or rate limited, not sending magic link"
+        ),
+    }
+    Ok(Json(GenericResponse::success(
+        "if a user has this email address, a link to log in has been sent to it",
+    )))
+}
+
+/// Handler for `POST /api/v1/auth/magic-link/callback`. It's a `POST` rather than the
+/// link itself, so that email scanners following the link don't use it up.
+pub async fn magic_link_callback(
+    Extension(repository): Extension<Repository>,
+    Extension(tokens): Extension<TokenIssuer>,
+    request: Json<MagicLinkCallback>,
+) -> Result<Json<Token>, ApiError> {
+    let subject = repository
+        .auth()
+        .redeem_magic_link(&request.token)
+        .await?
+        .ok_or_else(|| {
+            RepositoryError::BadArgument("sign-in link is invalid or has expired".to_string())
+        })?;
+
+    issue_token(&repository, &tokens, &subject).await
+}
+
+/// Handler for `POST /api/v1/auth/refresh`, exchanges a refresh token for a new JWT
+/// and a new refresh token. Each refresh token can only be used once.
+pub async fn refresh(
+    Extension(repository): Extension<Repository>,
+    Extension(tokens): Extension<TokenIssuer>,
+    request: Json<Refresh>,
+) -> Result<Json<Token>, ApiError> {
+    let refreshed = repository
+        .auth()
+        .rotate_refresh_token(&request.refresh_token)
+        .await?
+        .ok_or(ApiError::InvalidRefreshToken)?;
+
+    let issued = tokens
+        .issue(&refreshed.subject)?
+        .ok_or(ApiError::LoginUnavailable)?;
+
+    Ok(Token {
+        token: issued.token,
+        expires_at: issued.expires_at,
+        refresh_token: refreshed.refresh_token,
+    }
+    .into())
+}
+
+/// Handler for `POST /api/v1/auth/logout`, revokes the refresh token and every token it
+/// was rotated from or to. JWTs already issued remain valid until they expire.
+pub async fn logout(
+    Extension(repository): Extension<Repository>,
+    request: Json<Refresh>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    if !repository
+        .auth()
+        .revoke_refresh_token(&request.refresh_token)
+        .await?
+    {
+        tracing::trace!("refresh token not found, nothing to revoke");
+    }
+    Ok(Json(GenericResponse::success("logged out")))
+}
+
+/// Handler for `POST /api/v1/auth/rotate-subject`, logs the user out everywhere by
+/// invalidating every JWT and refresh token issued to them, including the one used to
+/// make the request.
+pub async fn rotate_subject(
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository.auth().rotate_subject(&identity).await?;
+    Ok(Json(GenericResponse::success(
+        "logged out everywhere, log in again to continue",
+    )))
+}
+
+/// Handler for `GET /.well-known/jwks.json`, publishes the keys JWTs are verified
+/// against so that other services can verify them too.
+pub async fn jwks(Extension(keys): Extension<KeyStore>) -> Json<Jwks> {
+    Json(keys.jwks())
+}
+
+pub(super) async fn issue_token(
+    repository: &Repository,
+    tokens: &TokenIssuer,
+    subject: &str,
+) -> Result<Json<Token>, ApiError> {
+    let issued = tokens.issue(subject)?.ok_or(ApiError::LoginUnavailable)?;
+    let refresh_token = repository.auth().create_refresh_token(subject).await?;
+
+    Ok(Token {
+        token: issued.token,
+        expires_at: issued.expires_at,
+        refresh_token,
+    }
+    .into())
+}
+
+/// The body for `POST /api/v1/auth/login`.
+#[derive(Deserialize, Serialize)]
+pub struct Login {
+    pub email: String,
+    pub password: String,
+}
+
+/// A JWT issued on login, sent as `Authorization: Bearer <token>` until it expires,
+/// along with a refresh token to get a new one with.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Token {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_token: String,
+}
+
+/// The body for `POST /api/v1/auth/refresh` and `POST /api/v1/auth/logout`.
+#[derive(Deserialize, Serialize)]
+pub struct Refresh {
+    pub refresh_token: String,
+}
+
+/// The body for `PUT /api/v1/auth/password`, `current_password` is required if the
+/// user already has a password.
+#[derive(Deserialize, Serialize)]
+pub struct SetPassword {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_password: Option<String>,
+    pub new_password: String,
+}
+
+/// The body for `POST /api/v1/auth/password-reset`.
+#[derive(Deserialize, Serialize)]
+pub struct RequestPasswordReset {
+    pub email: String,
+}
+
+/// The body for `POST /api/v1/auth/password-reset/confirm`, `token` is from the link
+/// emailed to the user.
+#[derive(Deserialize, Serialize)]
+pub struct ResetPassword {
+    pub token: String,
+    pub new_password: String,
+}
+
+/// The body for `POST /api/v1/auth/magic-link`.
+#[derive(Deserialize, Serialize)]
+pub struct RequestMagicLink {
+    pub email: String,
+}
+
+/// The body for `POST /api/v1/auth/magic-link/callback`, `token` is from the link
+/// emailed to the user.
+#[derive(Deserialize, Serialize)]
+pub struct MagicLinkCallback {
+    pub token: String,
+}
+
+/// The query of `GET /api/v1/auth/oidc/callback`, the provider redirects back with
+/// either an authorization `code` or an `error`.
+#[derive(Deserialize)]
+pub struct OidcCallback {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+}
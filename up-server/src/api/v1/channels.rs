@@ -0,0 +1,145 @@
+use axum::{body::Empty, extract::Path, response::IntoResponse, Extension};
+use miette::Result;
+
+use crate::{
+    api::{
+        v1::{
+            notifications::{CreateNotification, Notification, UpdateNotification},
+            ApiError,
+        },
+        Json,
+    },
+    auth::Identity,
+    repository::Repository,
+    shortid::ShortId,
+};
+
+/// Handler for `GET /api/v1/projects/:id/channels/:id`
+pub async fn read_one(
+    Path((project_id, channel_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Notification>, ApiError> {
+    let channel: Notification = repository
+        .channel()
+        .read_one(&identity, project_id.as_uuid(), channel_id.as_uuid())
+        .await?
+        .into();
+    Ok(channel.into())
+}
+
+/// Handler for `GET /api/v1/projects/:id/channels`
+pub async fn read_all(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<Notification>>, ApiError> {
+    let channels: Vec<Notification> = repository
+        .channel()
+        .read_all(&identity, project_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(channels.into())
+}
+
+/// Handler for `POST /api/v1/projects/:id/channels`
+pub async fn create(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<CreateNotification>,
+) -> Result<Json<Notification>, ApiError> {
+    let channel: Notification = repository
+        .channel()
+        .create(&identity, project_id.as_uuid(), request.0.into())
+        .await?
+        .into();
+    Ok(channel.into())
+}
+
+/// Handler for `PATCH /api/v1/projects/:id/channels/:id`
+pub async fn update(
+    Path((project_id, channel_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<UpdateNotification>,
+) -> Result<Json<Notification>, ApiError> {
+    let channel: Notification = repository
+        .channel()
+        .update(
+            &identity,
+            project_id.as_uuid(),
+            channel_id.as_uuid(),
+            request.0.into(),
+        )
+        .await?
+        .into();
+    Ok(channel.into())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/channels/:id`
+pub async fn delete(
+    Path((project_id, channel_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .channel()
+        .delete(&identity, project_id.as_uuid(), channel_id.as_uuid())
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `GET /api/v1/projects/:id/checks/:id/channels`
+pub async fn read_all_for_check(
+    Path((project_id, check_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<Notification>>, ApiError> {
+    let channels: Vec<Notification> = repository
+        .channel()
+        .read_all_for_check(&identity, project_id.as_uuid(), check_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(channels.into())
+}
+
+/// Handler for `PUT /api/v1/projects/:id/checks/:id/channels/:id`
+pub async fn attach(
+    Path((project_id, check_id, channel_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .channel()
+        .attach(
+            &identity,
+            project_id.as_uuid(),
+            check_id.as_uuid(),
+            channel_id.as_uuid(),
+        )
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/checks/:id/channels/:id`
+pub async fn detach(
+    Path((project_id, check_id, channel_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .channel()
+        .detach(
+            &identity,
+            project_id.as_uuid(),
+            check_id.as_uuid(),
+            channel_id.as_uuid(),
+        )
+        .await?;
+    Ok(Empty::new())
+}
@@ -88,6 +88,58 @@ pub async fn delete(
     Ok(Empty::new())
 }
 
+/// Handler for `GET /api/v1/projects/:id/checks/:id/dependencies`
+pub async fn read_dependencies(
+    Path((project_id, check_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<Check>>, ApiError> {
+    let checks: Vec<Check> = repository
+        .check()
+        .read_dependencies(&identity, project_id.as_uuid(), check_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(checks.into())
+}
+
+/// Handler for `PUT /api/v1/projects/:id/checks/:id/dependencies/:id`
+pub async fn add_dependency(
+    Path((project_id, check_id, parent_check_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .check()
+        .add_dependency(
+            &identity,
+            project_id.as_uuid(),
+            check_id.as_uuid(),
+            parent_check_id.as_uuid(),
+        )
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/checks/:id/dependencies/:id`
+pub async fn remove_dependency(
+    Path((project_id, check_id, parent_check_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .check()
+        .remove_dependency(
+            &identity,
+            project_id.as_uuid(),
+            check_id.as_uuid(),
+            parent_check_id.as_uuid(),
+        )
+        .await?;
+    Ok(Empty::new())
+}
+
 // API model types
 
 /// An API [`Check`] type.
@@ -97,6 +149,7 @@ pub struct Check {
     pub name: String,
     pub description: String,
     pub status: CheckStatus,
+    pub severity: CheckSeverity,
     pub schedule_type: ScheduleType,
     pub ping_period: i32,
     pub ping_period_units: PeriodUnits,
@@ -120,6 +173,14 @@ pub enum CheckStatus {
     Created,
 }
 
+/// An API check severity.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CheckSeverity {
+    Low,
+    High,
+}
+
 /// An API check schedule type.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -144,12 +205,16 @@ pub struct CreateCheck {
     pub account_id: ShortId,
     pub project_id: ShortId,
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<CheckSeverity>,
 }
 
 /// Body for `PATCH /api/v1/projects/:id/checks`
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateCheck {
     pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<CheckSeverity>,
 }
 
 // Model conversions
@@ -163,6 +228,7 @@ impl From<dto::Check> for Check {
             name: issue.name,
             description: issue.description,
             status: issue.status.into(),
+            severity: issue.severity.into(),
             schedule_type: issue.schedule_type.into(),
             ping_period: issue.ping_period,
             ping_period_units: issue.ping_period_units.into(),
@@ -188,6 +254,28 @@ impl From<dto::CheckStatus> for CheckStatus {
     }
 }
 
+/// Conversion from repository [`dto::CheckSeverity`] to
+/// API [`CheckSeverity`].
+impl From<dto::CheckSeverity> for CheckSeverity {
+    fn from(severity: dto::CheckSeverity) -> Self {
+        match severity {
+            dto::CheckSeverity::Low => CheckSeverity::Low,
+            dto::CheckSeverity::High => CheckSeverity::High,
+        }
+    }
+}
+
+/// Conversion from API [`CheckSeverity`] to
+/// repository [`dto::CheckSeverity`].
+impl From<CheckSeverity> for dto::CheckSeverity {
+    fn from(severity: CheckSeverity) -> Self {
+        match severity {
+            CheckSeverity::Low => dto::CheckSeverity::Low,
+            CheckSeverity::High => dto::CheckSeverity::High,
+        }
+    }
+}
+
 /// Conversion from repository [`dto::ScheduleType`] to
 /// API [`ScheduleType`].
 impl From<dto::ScheduleType> for ScheduleType {
@@ -211,6 +299,18 @@ impl From<dto::PeriodUnits> for PeriodUnits {
     }
 }
 
+/// Conversion from API [`PeriodUnits`] to
+/// repository [`dto::PeriodUnits`].
+impl From<PeriodUnits> for dto::PeriodUnits {
+    fn from(units: PeriodUnits) -> Self {
+        match units {
+            PeriodUnits::Minutes => dto::PeriodUnits::Minutes,
+            PeriodUnits::Hours => dto::PeriodUnits::Hours,
+            PeriodUnits::Days => dto::PeriodUnits::Days,
+        }
+    }
+}
+
 /// Conversion from API [`CreateCheck`] to
 /// repository [`dto::CreateCheck`].
 impl From<CreateCheck> for dto::CreateCheck {
@@ -218,6 +318,7 @@ impl From<CreateCheck> for dto::CreateCheck {
         Self {
             project_uuid: request.project_id.into_uuid(),
             name: request.name,
+            severity: request.severity.map(|s| s.into()),
         }
     }
 }
@@ -226,6 +327,9 @@ impl From<CreateCheck> for dto::CreateCheck {
 /// repository [`dto::UpdateCheck`].
 impl From<UpdateCheck> for dto::UpdateCheck {
     fn from(request: UpdateCheck) -> Self {
-        Self { name: request.name }
+        Self {
+            name: request.name,
+            severity: request.severity.map(|s| s.into()),
+        }
     }
 }
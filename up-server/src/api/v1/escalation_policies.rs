@@ -0,0 +1,243 @@
+use axum::{body::Empty, extract::Path, response::IntoResponse, Extension};
+use chrono::{DateTime, TimeZone, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{v1::ApiError, Json},
+    auth::Identity,
+    repository::{dto, Repository},
+    shortid::ShortId,
+};
+
+/// Handler for `GET /api/v1/projects/:id/escalation-policies/:id`
+pub async fn read_one(
+    Path((project_id, policy_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<EscalationPolicy>, ApiError> {
+    let policy: EscalationPolicy = repository
+        .escalation_policy()
+        .read_one(&identity, project_id.as_uuid(), policy_id.as_uuid())
+        .await?
+        .into();
+    Ok(policy.into())
+}
+
+/// Handler for `GET /api/v1/projects/:id/escalation-policies`
+pub async fn read_all(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<EscalationPolicy>>, ApiError> {
+    let policies: Vec<EscalationPolicy> = repository
+        .escalation_policy()
+        .read_all(&identity, project_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(policies.into())
+}
+
+/// Handler for `POST /api/v1/projects/:id/escalation-policies`
+pub async fn create(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<CreateEscalationPolicy>,
+) -> Result<Json<EscalationPolicy>, ApiError> {
+    let policy: EscalationPolicy = repository
+        .escalation_policy()
+        .create(&identity, project_id.as_uuid(), request.0.into())
+        .await?
+        .into();
+    Ok(policy.into())
+}
+
+/// Handler for `PATCH /api/v1/projects/:id/escalation-policies/:id`
+pub async fn update(
+    Path((project_id, policy_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<UpdateEscalationPolicy>,
+) -> Result<Json<EscalationPolicy>, ApiError> {
+    let policy: EscalationPolicy = repository
+        .escalation_policy()
+        .update(
+            &identity,
+            project_id.as_uuid(),
+            policy_id.as_uuid(),
+            request.0.into(),
+        )
+        .await?
+        .into();
+    Ok(policy.into())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/escalation-policies/:id`
+pub async fn delete(
+    Path((project_id, policy_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .escalation_policy()
+        .delete(&identity, project_id.as_uuid(), policy_id.as_uuid())
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `GET /api/v1/projects/:id/checks/:id/escalation-policy`
+pub async fn read_for_check(
+    Path((project_id, check_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<EscalationPolicy>, ApiError> {
+    let policy: EscalationPolicy = repository
+        .escalation_policy()
+        .read_for_check(&identity, project_id.as_uuid(), check_id.as_uuid())
+        .await?
+        .into();
+    Ok(policy.into())
+}
+
+/// Handler for `PUT /api/v1/projects/:id/checks/:id/escalation-policy/:id`
+pub async fn attach(
+    Path((project_id, check_id, policy_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .escalation_policy()
+        .attach(
+            &identity,
+            project_id.as_uuid(),
+            check_id.as_uuid(),
+            policy_id.as_uuid(),
+        )
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/checks/:id/escalation-policy`
+pub async fn detach(
+    Path((project_id, check_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .escalation_policy()
+        .detach(&identity, project_id.as_uuid(), check_id.as_uuid())
+        .await?;
+    Ok(Empty::new())
+}
+
+/// An API [`EscalationPolicy`] type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EscalationPolicy {
+    pub id: ShortId,
+    pub name: String,
+    pub tiers: Vec<EscalationTier>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// An API [`EscalationTier`] type. Tiers are notified in order, each one
+/// `escalate_after_minutes` after the previous one if the incident is still
+/// neither acknowledged nor resolved.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EscalationTier {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escalate_after_minutes: Option<i32>,
+    pub notification_ids: Vec<ShortId>,
+}
+
+/// Body for `POST /api/v1/projects/:id/escalation-policies`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateEscalationPolicy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub tiers: Vec<EscalationTier>,
+}
+
+/// Body for `PATCH /api/v1/projects/:id/escalation-policies/:id`, `tiers` replaces
+/// all existing tiers if set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateEscalationPolicy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiers: Option<Vec<EscalationTier>>,
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::EscalationPolicy`] and its tiers to
+/// API [`EscalationPolicy`].
+impl From<(dto::EscalationPolicy, Vec<dto::EscalationTier>)> for EscalationPolicy {
+    fn from((policy, tiers): (dto::EscalationPolicy, Vec<dto::EscalationTier>)) -> Self {
+        Self {
+            id: policy.uuid.into(),
+            name: policy.name,
+            tiers: tiers.into_iter().map(|t| t.into()).collect(),
+            created_at: Utc.from_utc_datetime(&policy.created_at),
+            updated_at: policy.updated_at.map(|dt| Utc.from_utc_datetime(&dt)),
+        }
+    }
+}
+
+/// Conversion from repository [`dto::EscalationTier`] to
+/// API [`EscalationTier`].
+impl From<dto::EscalationTier> for EscalationTier {
+    fn from(tier: dto::EscalationTier) -> Self {
+        Self {
+            escalate_after_minutes: Some(tier.escalate_after_minutes),
+            notification_ids: tier
+                .notification_uuids
+                .into_iter()
+                .map(|u| u.into())
+                .collect(),
+        }
+    }
+}
+
+/// Conversion from API [`EscalationTier`] to
+/// repository [`dto::CreateEscalationTier`].
+impl From<EscalationTier> for dto::CreateEscalationTier {
+    fn from(tier: EscalationTier) -> Self {
+        Self {
+            escalate_after_minutes: tier.escalate_after_minutes,
+            notification_uuids: tier
+                .notification_ids
+                .into_iter()
+                .map(|id| id.into_uuid())
+                .collect(),
+        }
+    }
+}
+
+/// Conversion from API [`CreateEscalationPolicy`] to
+/// repository [`dto::CreateEscalationPolicy`].
+impl From<CreateEscalationPolicy> for dto::CreateEscalationPolicy {
+    fn from(request: CreateEscalationPolicy) -> Self {
+        Self {
+            name: request.name,
+            tiers: request.tiers.into_iter().map(|t| t.into()).collect(),
+        }
+    }
+}
+
+/// Conversion from API [`UpdateEscalationPolicy`] to
+/// repository [`dto::UpdateEscalationPolicy`].
+impl From<UpdateEscalationPolicy> for dto::UpdateEscalationPolicy {
+    fn from(request: UpdateEscalationPolicy) -> Self {
+        Self {
+            name: request.name,
+            tiers: request
+                .tiers
+                .map(|tiers| tiers.into_iter().map(|t| t.into()).collect()),
+        }
+    }
+}
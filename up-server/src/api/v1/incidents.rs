@@ -0,0 +1,73 @@
+use axum::{extract::Path, Extension};
+use chrono::{DateTime, TimeZone, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    acknowledgement::AcknowledgementLinks,
+    api::{v1::ApiError, Json},
+    auth::Identity,
+    repository::{dto, Repository, RepositoryError},
+    shortid::ShortId,
+};
+
+/// Handler for `GET /api/v1/projects/:id/checks/:id/incidents`
+pub async fn read_all(
+    Path((project_id, check_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<Incident>>, ApiError> {
+    let incidents: Vec<Incident> = repository
+        .incident()
+        .read_all(&identity, project_id.as_uuid(), check_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(incidents.into())
+}
+
+/// Handler for `GET /api/v1/acknowledge/:token`, the signed link included in alerts.
+/// The link is its own authorization, so this handler has no [`Identity`].
+pub async fn acknowledge(
+    Path(token): Path<String>,
+    Extension(acknowledgements): Extension<AcknowledgementLinks>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Incident>, ApiError> {
+    let alert_uuid = acknowledgements.verify(&token).ok_or_else(|| {
+        RepositoryError::BadArgument("acknowledgement link is invalid or has expired".to_string())
+    })?;
+    let incident: Incident = repository.incident().acknowledge(&alert_uuid).await?.into();
+    Ok(incident.into())
+}
+
+/// An API [`Incident`] type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Incident {
+    pub id: ShortId,
+    pub started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledged_by: Option<String>,
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::Incident`] to
+/// API [`Incident`].
+impl From<dto::Incident> for Incident {
+    fn from(incident: dto::Incident) -> Self {
+        Self {
+            id: incident.uuid.into(),
+            started_at: Utc.from_utc_datetime(&incident.started_at),
+            resolved_at: incident.resolved_at.map(|dt| Utc.from_utc_datetime(&dt)),
+            acknowledged_at: incident
+                .acknowledged_at
+                .map(|dt| Utc.from_utc_datetime(&dt)),
+            acknowledged_by: incident.acknowledged_by,
+        }
+    }
+}
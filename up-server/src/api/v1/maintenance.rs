@@ -0,0 +1,148 @@
+use axum::{body::Empty, extract::Path, response::IntoResponse, Extension};
+use chrono::{DateTime, TimeZone, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        v1::{checks::PeriodUnits, ApiError},
+        Json,
+    },
+    auth::Identity,
+    repository::{dto, Repository},
+    shortid::ShortId,
+};
+
+/// Handler for `GET /api/v1/projects/:id/maintenance-windows/:id`
+pub async fn read_one(
+    Path((project_id, window_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<MaintenanceWindow>, ApiError> {
+    let window: MaintenanceWindow = repository
+        .maintenance()
+        .read_one(&identity, project_id.as_uuid(), window_id.as_uuid())
+        .await?
+        .into();
+    Ok(window.into())
+}
+
+/// Handler for `GET /api/v1/projects/:id/maintenance-windows`
+pub async fn read_all(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<MaintenanceWindow>>, ApiError> {
+    let windows: Vec<MaintenanceWindow> = repository
+        .maintenance()
+        .read_all(&identity, project_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(windows.into())
+}
+
+/// Handler for `POST /api/v1/projects/:id/maintenance-windows`
+pub async fn create(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<CreateMaintenanceWindow>,
+) -> Result<Json<MaintenanceWindow>, ApiError> {
+    let window: MaintenanceWindow = repository
+        .maintenance()
+        .create(&identity, project_id.as_uuid(), request.0.into())
+        .await?
+        .into();
+    Ok(window.into())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/maintenance-windows/:id`
+pub async fn delete(
+    Path((project_id, window_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .maintenance()
+        .delete(&identity, project_id.as_uuid(), window_id.as_uuid())
+        .await?;
+    Ok(Empty::new())
+}
+
+/// An API [`MaintenanceWindow`] type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub id: ShortId,
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_every: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_every_units: Option<PeriodUnits>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub check_ids: Vec<ShortId>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body for `POST /api/v1/projects/:id/maintenance-windows`. The window applies to the
+/// checks with `tag` if set, otherwise to `check_ids` if set, otherwise to the whole project.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateMaintenanceWindow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_every: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_every_units: Option<PeriodUnits>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub check_ids: Vec<ShortId>,
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::MaintenanceWindow`] to
+/// API [`MaintenanceWindow`].
+impl From<dto::MaintenanceWindow> for MaintenanceWindow {
+    fn from(window: dto::MaintenanceWindow) -> Self {
+        Self {
+            id: window.uuid.into(),
+            name: window.name,
+            starts_at: Utc.from_utc_datetime(&window.starts_at),
+            ends_at: Utc.from_utc_datetime(&window.ends_at),
+            repeat_every: window.repeat_every,
+            repeat_every_units: window.repeat_every_units.map(|u| u.into()),
+            tag: window.tag,
+            check_ids: window.check_uuids.into_iter().map(|u| u.into()).collect(),
+            created_at: Utc.from_utc_datetime(&window.created_at),
+        }
+    }
+}
+
+/// Conversion from API [`CreateMaintenanceWindow`] to
+/// repository [`dto::CreateMaintenanceWindow`].
+impl From<CreateMaintenanceWindow> for dto::CreateMaintenanceWindow {
+    fn from(request: CreateMaintenanceWindow) -> Self {
+        Self {
+            name: request.name,
+            starts_at: request.starts_at.naive_utc(),
+            ends_at: request.ends_at.naive_utc(),
+            repeat_every: request.repeat_every,
+            repeat_every_units: request.repeat_every_units.map(|u| u.into()),
+            tag: request.tag,
+            check_uuids: request
+                .check_ids
+                .into_iter()
+                .map(|id| id.into_uuid())
+                .collect(),
+        }
+    }
+}
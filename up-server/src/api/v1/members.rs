@@ -0,0 +1,248 @@
+use axum::{extract::Path, Extension};
+use chrono::{DateTime, TimeZone, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use up_core::auth::Role;
+
+use crate::{
+    api::{
+        v1::{
+            auth::{issue_token, Token},
+            ApiError,
+        },
+        GenericResponse, Json,
+    },
+    auth::Identity,
+    mask,
+    notifier::Notifier,
+    repository::{dto, Repository, RepositoryError},
+    shortid::ShortId,
+    tokens::TokenIssuer,
+};
+
+/// Handler for `GET /api/v1/accounts/:id/members`
+pub async fn read_all(
+    Path(account_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<Member>>, ApiError> {
+    let members: Vec<Member> = repository
+        .member()
+        .read_all(&identity, account_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|m| m.into())
+        .collect();
+    Ok(members.into())
+}
+
+/// Handler for `POST /api/v1/accounts/:id/invitations`. The invitation is created even
+/// if the email can't be sent, inviting the same email address again replaces it.
+pub async fn invite(
+    Path(account_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    Extension(notifier): Extension<Notifier>,
+    request: Json<CreateInvitation>,
+) -> Result<Json<Invitation>, ApiError> {
+    let invitation = repository
+        .member()
+        .invite(&identity, account_id.as_uuid(), request.0.into())
+        .await?;
+
+    if let Err(e) = notifier.send_invitation(&invitation).await {
+        tracing::error!(
+            email = mask::email(&invitation.email),
+            "failed to send invitation: {}",
+            e.to_message()
+        );
+    }
+
+    let invitation: Invitation = invitation.into();
+    Ok(invitation.into())
+}
+
+/// Handler for `POST /api/v1/invitations/accept`
+pub async fn accept_invitation(
+    Extension(repository): Extension<Repository>,
+    Extension(tokens): Extension<TokenIssuer>,
+    request: Json<AcceptInvitation>,
+) -> Result<Json<Token>, ApiError> {
+    let subject = repository
+        .member()
+        .accept_invitation(&request.token, request.password.as_deref())
+        .await?
+        .ok_or_else(|| {
+            RepositoryError::BadArgument(
+                "invitation is invalid, has expired or has already been accepted".to_string(),
+            )
+        })?;
+
+    issue_token(&repository, &tokens, &subject).await
+}
+
+/// Handler for `PUT /api/v1/accounts/:id/members/:id/role`
+pub async fn update_role(
+    Path((account_id, user_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<UpdateRole>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository
+        .member()
+        .update_role(
+            &identity,
+            account_id.as_uuid(),
+            user_id.as_uuid(),
+            request.role.into(),
+        )
+        .await?;
+    Ok(Json(GenericResponse::success("role changed")))
+}
+
+/// Handler for `DELETE /api/v1/accounts/:id/members/:id`
+pub async fn remove(
+    Path((account_id, user_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository
+        .member()
+        .remove(&identity, account_id.as_uuid(), user_id.as_uuid())
+        .await?;
+    Ok(Json(GenericResponse::success("removed")))
+}
+
+/// Handler for `PUT /api/v1/projects/:id/members/:id`
+pub async fn assign_to_project(
+    Path((project_id, user_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<AssignToProject>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository
+        .member()
+        .assign_to_project(
+            &identity,
+            project_id.as_uuid(),
+            user_id.as_uuid(),
+            request.role.map(|r| r.into()),
+        )
+        .await?;
+    Ok(Json(GenericResponse::success("assigned")))
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/members/:id`
+pub async fn unassign_from_project(
+    Path((project_id, user_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<GenericResponse>, ApiError> {
+    repository
+        .member()
+        .unassign_from_project(&identity, project_id.as_uuid(), user_id.as_uuid())
+        .await?;
+    Ok(Json(GenericResponse::success("unassigned")))
+}
+
+/// An API [`Member`] type, a user in an account.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Member {
+    pub id: ShortId,
+    pub email: String,
+    pub role: Role,
+    pub projects: Vec<MemberProject>,
+}
+
+/// A project a [`Member`] is assigned to, with their role in it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemberProject {
+    pub id: ShortId,
+    pub role: Role,
+}
+
+/// An API [`Invitation`] type, without the token emailed to the invitee.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Invitation {
+    pub id: ShortId,
+    pub email: String,
+    pub role: Role,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Body for `POST /api/v1/accounts/:id/invitations`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateInvitation {
+    pub email: String,
+    pub role: Role,
+}
+
+/// Body for `POST /api/v1/invitations/accept`. `password` is only used if the invitee
+/// has no user yet.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AcceptInvitation {
+    pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// Body for `PUT /api/v1/accounts/:id/members/:id/role`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRole {
+    pub role: Role,
+}
+
+/// Body for `PUT /api/v1/projects/:id/members/:id`. Members get their role in the
+/// account unless `role` is set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssignToProject {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::Member`] to
+/// API [`Member`].
+impl From<dto::Member> for Member {
+    fn from(member: dto::Member) -> Self {
+        Self {
+            id: member.user_uuid.into(),
+            email: member.email,
+            role: member.role.into(),
+            projects: member
+                .project_uuids
+                .into_iter()
+                .zip(member.project_roles)
+                .map(|(uuid, role)| MemberProject {
+                    id: uuid.into(),
+                    role: role.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Conversion from repository [`dto::Invitation`] to
+/// API [`Invitation`].
+impl From<dto::Invitation> for Invitation {
+    fn from(invitation: dto::Invitation) -> Self {
+        Self {
+            id: invitation.uuid.into(),
+            email: invitation.email,
+            role: invitation.role.into(),
+            expires_at: Utc.from_utc_datetime(&invitation.expires_at),
+        }
+    }
+}
+
+/// Conversion from API [`CreateInvitation`] to
+/// repository [`dto::CreateInvitation`].
+impl From<CreateInvitation> for dto::CreateInvitation {
+    fn from(request: CreateInvitation) -> Self {
+        Self {
+            email: request.email,
+            role: request.role.into(),
+        }
+    }
+}
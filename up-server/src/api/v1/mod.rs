@@ -1,19 +1,29 @@
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::{delete, get, patch, post},
+    routing::{delete, get, patch, post, put},
     Extension, Router,
 };
 use miette::Diagnostic;
 use thiserror::Error;
 
-use crate::{api::Json, app::App, auth::Identity, repository::RepositoryError};
+use crate::{api::Json, app::App, auth::Identity, oidc::OidcError, repository::RepositoryError};
 
 use super::{GenericResponse, ReportRenderer, ReportType};
 
+pub mod accounts;
+pub mod api_keys;
+pub mod auth;
+pub mod channels;
 pub mod checks;
+pub mod escalation_policies;
+pub mod incidents;
+pub mod maintenance;
+pub mod members;
 pub mod notifications;
+pub mod on_call_schedules;
 pub mod ping;
+pub mod preferences;
 pub mod projects;
 
 #[derive(Error, Diagnostic, Debug)]
@@ -21,14 +31,92 @@ pub enum ApiError {
     #[error("repository error")]
     #[diagnostic(code(up::error::repository))]
     Repository(#[from] RepositoryError),
+    #[error("invalid email or password")]
+    #[diagnostic(code(up::error::authentication))]
+    InvalidCredentials,
+    #[error("refresh token is invalid, has expired or has been revoked")]
+    #[diagnostic(code(up::error::authentication))]
+    InvalidRefreshToken,
+    #[error("login is not available, no signing key is configured")]
+    #[diagnostic(code(up::error::authentication))]
+    LoginUnavailable,
+    #[error("no user for this identity, ask an administrator to invite you")]
+    #[diagnostic(code(up::error::authentication))]
+    UnknownIdentity,
+    #[error("signup is disabled, ask an administrator to invite you")]
+    #[diagnostic(code(up::error::authentication))]
+    SignupDisabled,
+    #[error("single sign-on failed")]
+    #[diagnostic(code(up::error::oidc))]
+    Oidc(#[from] OidcError),
+    #[error("failed to issue JWT")]
+    #[diagnostic(code(up::error::jwt))]
+    Token(#[from] up_core::Error),
 }
 
 pub const PING_URI: &str = "/api/v1/ping";
+pub const ACKNOWLEDGE_URI: &str = "/api/v1/acknowledge";
+pub const LOGIN_URI: &str = "/api/v1/auth/login";
+pub const PASSWORD_RESET_URI: &str = "/api/v1/auth/password-reset";
+pub const OIDC_URI: &str = "/api/v1/auth/oidc";
+pub const OIDC_CALLBACK_URI: &str = "/api/v1/auth/oidc/callback";
+pub const MAGIC_LINK_URI: &str = "/api/v1/auth/magic-link";
+pub const REFRESH_URI: &str = "/api/v1/auth/refresh";
+pub const LOGOUT_URI: &str = "/api/v1/auth/logout";
+pub const SIGNUP_URI: &str = "/api/v1/auth/signup";
+pub const INVITATION_URI: &str = "/api/v1/invitations/accept";
 pub const HEALTH_URI: &str = "/health";
+pub const JWKS_URI: &str = "/.well-known/jwks.json";
 
 pub fn router() -> Router {
     Router::new()
+        // Authentication
+        .route(LOGIN_URI, post(auth::login))
+        .route("/api/v1/auth/password", put(auth::set_password))
+        .route(PASSWORD_RESET_URI, post(auth::request_password_reset))
+        .route(
+            &format!("{}/confirm", PASSWORD_RESET_URI),
+            post(auth::reset_password),
+        )
+        .route(&format!("{}/login", OIDC_URI), get(auth::oidc_login))
+        .route(OIDC_CALLBACK_URI, get(auth::oidc_callback))
+        .route(MAGIC_LINK_URI, post(auth::request_magic_link))
+        .route(
+            &format!("{}/callback", MAGIC_LINK_URI),
+            post(auth::magic_link_callback),
+        )
+        .route(REFRESH_URI, post(auth::refresh))
+        .route(LOGOUT_URI, post(auth::logout))
+        .route("/api/v1/auth/rotate-subject", post(auth::rotate_subject))
+        .route(SIGNUP_URI, post(accounts::signup))
         .route("/api/v1/identity", get(identity_handler))
+        .route("/api/v1/preferences", get(preferences::read))
+        .route("/api/v1/preferences", put(preferences::update))
+        // API keys
+        .route("/api/v1/api-keys", get(api_keys::read_all))
+        .route("/api/v1/api-keys", post(api_keys::create))
+        .route("/api/v1/api-keys/:id", delete(api_keys::revoke))
+        // Accounts
+        .route("/api/v1/accounts", get(accounts::read_all))
+        .route("/api/v1/accounts/:id", patch(accounts::update))
+        .route("/api/v1/accounts/:id", delete(accounts::delete))
+        // Members
+        .route("/api/v1/accounts/:id/members", get(members::read_all))
+        .route("/api/v1/accounts/:id/invitations", post(members::invite))
+        .route(INVITATION_URI, post(members::accept_invitation))
+        .route(
+            "/api/v1/accounts/:id/members/:id/role",
+            put(members::update_role),
+        )
+        .route("/api/v1/accounts/:id/members/:id", delete(members::remove))
+        .route(
+            "/api/v1/projects/:id/members/:id",
+            put(members::assign_to_project),
+        )
+        .route(
+            "/api/v1/projects/:id/members/:id",
+            delete(members::unassign_from_project),
+        )
         // Projects
         .route("/api/v1/projects/:id", get(projects::read_one))
         .route("/api/v1/projects", get(projects::read_all))
@@ -41,6 +129,60 @@ pub fn router() -> Router {
         .route("/api/v1/projects/:id/checks", post(checks::create))
         .route("/api/v1/projects/:id/checks/:id", patch(checks::update))
         .route("/api/v1/projects/:id/checks/:id", delete(checks::delete))
+        .route(
+            "/api/v1/projects/:id/checks/:id/dependencies",
+            get(checks::read_dependencies),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/dependencies/:id",
+            put(checks::add_dependency),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/dependencies/:id",
+            delete(checks::remove_dependency),
+        )
+        // Escalation policies
+        .route(
+            "/api/v1/projects/:id/escalation-policies/:id",
+            get(escalation_policies::read_one),
+        )
+        .route(
+            "/api/v1/projects/:id/escalation-policies",
+            get(escalation_policies::read_all),
+        )
+        .route(
+            "/api/v1/projects/:id/escalation-policies",
+            post(escalation_policies::create),
+        )
+        .route(
+            "/api/v1/projects/:id/escalation-policies/:id",
+            patch(escalation_policies::update),
+        )
+        .route(
+            "/api/v1/projects/:id/escalation-policies/:id",
+            delete(escalation_policies::delete),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/escalation-policy",
+            get(escalation_policies::read_for_check),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/escalation-policy/:id",
+            put(escalation_policies::attach),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/escalation-policy",
+            delete(escalation_policies::detach),
+        )
+        // Incidents
+        .route(
+            "/api/v1/projects/:id/checks/:id/incidents",
+            get(incidents::read_all),
+        )
+        .route(
+            &format!("{}/:token", ACKNOWLEDGE_URI),
+            get(incidents::acknowledge),
+        )
         // Notifications
         .route(
             "/api/v1/projects/:id/checks/:id/notifications/:id",
@@ -62,8 +204,100 @@ pub fn router() -> Router {
             "/api/v1/projects/:id/checks/:id/notifications/:id",
             delete(notifications::delete),
         )
+        .route(
+            "/api/v1/projects/:id/checks/:id/notifications/:id/alerts",
+            get(notifications::read_alerts),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/notifications/:id/test",
+            post(notifications::test),
+        )
+        // Channels
+        .route("/api/v1/projects/:id/channels/:id", get(channels::read_one))
+        .route("/api/v1/projects/:id/channels", get(channels::read_all))
+        .route("/api/v1/projects/:id/channels", post(channels::create))
+        .route("/api/v1/projects/:id/channels/:id", patch(channels::update))
+        .route(
+            "/api/v1/projects/:id/channels/:id",
+            delete(channels::delete),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/channels",
+            get(channels::read_all_for_check),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/channels/:id",
+            put(channels::attach),
+        )
+        .route(
+            "/api/v1/projects/:id/checks/:id/channels/:id",
+            delete(channels::detach),
+        )
+        // Maintenance windows
+        .route(
+            "/api/v1/projects/:id/maintenance-windows/:id",
+            get(maintenance::read_one),
+        )
+        .route(
+            "/api/v1/projects/:id/maintenance-windows",
+            get(maintenance::read_all),
+        )
+        .route(
+            "/api/v1/projects/:id/maintenance-windows",
+            post(maintenance::create),
+        )
+        .route(
+            "/api/v1/projects/:id/maintenance-windows/:id",
+            delete(maintenance::delete),
+        )
+        // On-call schedules
+        .route(
+            "/api/v1/projects/:id/on-call-schedules/:id",
+            get(on_call_schedules::read_one),
+        )
+        .route(
+            "/api/v1/projects/:id/on-call-schedules",
+            get(on_call_schedules::read_all),
+        )
+        .route(
+            "/api/v1/projects/:id/on-call-schedules",
+            post(on_call_schedules::create),
+        )
+        .route(
+            "/api/v1/projects/:id/on-call-schedules/:id",
+            patch(on_call_schedules::update),
+        )
+        .route(
+            "/api/v1/projects/:id/on-call-schedules/:id",
+            delete(on_call_schedules::delete),
+        )
+        .route(
+            "/api/v1/projects/:id/on-call-schedules/:id/on-call",
+            get(on_call_schedules::read_on_call),
+        )
+        .route(
+            "/api/v1/projects/:id/on-call-schedules/:id/overrides",
+            post(on_call_schedules::create_override),
+        )
+        .route(
+            "/api/v1/projects/:id/on-call-schedules/:id/overrides/:id",
+            delete(on_call_schedules::delete_override),
+        )
+        .route(
+            "/api/v1/projects/:id/channels/:id/on-call-schedule",
+            get(on_call_schedules::read_for_channel),
+        )
+        .route(
+            "/api/v1/projects/:id/channels/:id/on-call-schedule/:id",
+            put(on_call_schedules::attach),
+        )
+        .route(
+            "/api/v1/projects/:id/channels/:id/on-call-schedule",
+            delete(on_call_schedules::detach),
+        )
         // Miscellaneous
         .route(HEALTH_URI, get(health_handler))
+        .route(JWKS_URI, get(auth::jwks))
         .route(&format!("{}/:key", PING_URI), post(ping::ping))
 }
 
@@ -99,6 +333,7 @@ impl IntoResponse for ApiError {
                             format!("{} with ID {} does not exist", entity_type, id),
                         ),
                         RepositoryError::Forbidden => (StatusCode::FORBIDDEN, format!("{}", e)),
+                        RepositoryError::BadArgument(message) => (StatusCode::BAD_REQUEST, message),
                         _ => {
                             let mut messages: Vec<String> =
                                 format!("{}", ReportRenderer(ReportType::Narratable, &e))
@@ -118,6 +353,29 @@ impl IntoResponse for ApiError {
                     }
                 }
             }
+            ApiError::InvalidCredentials => (StatusCode::UNAUTHORIZED, format!("{}", self)),
+            ApiError::InvalidRefreshToken => (StatusCode::UNAUTHORIZED, format!("{}", self)),
+            ApiError::LoginUnavailable => (StatusCode::SERVICE_UNAVAILABLE, format!("{}", self)),
+            ApiError::UnknownIdentity => (StatusCode::FORBIDDEN, format!("{}", self)),
+            ApiError::SignupDisabled => (StatusCode::FORBIDDEN, format!("{}", self)),
+            ApiError::Oidc(e) => match e {
+                OidcError::NotConfigured => (StatusCode::SERVICE_UNAVAILABLE, format!("{}", e)),
+                OidcError::MissingIdToken | OidcError::InvalidIdToken(_) => {
+                    tracing::debug!("rejecting ID token: {:?}", e);
+                    (StatusCode::UNAUTHORIZED, format!("{}", e))
+                }
+                OidcError::DiscoveryFailed(_) | OidcError::CodeExchangeFailed(_) => {
+                    tracing::error!("single sign-on failed: {:?}", e);
+                    (StatusCode::BAD_GATEWAY, format!("{}", e))
+                }
+            },
+            ApiError::Token(e) => {
+                tracing::error!("failed to issue JWT: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "failed to issue JWT".to_string(),
+                )
+            }
         };
 
         let body = if details.is_empty() {
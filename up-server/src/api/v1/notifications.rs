@@ -4,8 +4,12 @@ use miette::Result;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::{v1::ApiError, Json},
+    api::{
+        v1::{checks::CheckStatus, ApiError},
+        Json,
+    },
     auth::Identity,
+    notifier::Notifier,
     repository::{dto, Repository},
     shortid::ShortId,
 };
@@ -104,6 +108,62 @@ pub async fn delete(
     Ok(Empty::new())
 }
 
+/// Handler for `GET /api/v1/projects/:id/checks/:id/notifications/:id/alerts`
+pub async fn read_alerts(
+    Path((project_id, check_id, notification_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<Alert>>, ApiError> {
+    let alerts: Vec<Alert> = repository
+        .notification()
+        .read_alerts(
+            &identity,
+            project_id.as_uuid(),
+            check_id.as_uuid(),
+            notification_id.as_uuid(),
+        )
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(alerts.into())
+}
+
+/// Handler for `POST /api/v1/projects/:id/checks/:id/notifications/:id/test`
+pub async fn test(
+    Path((project_id, check_id, notification_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    Extension(notifier): Extension<Notifier>,
+) -> Result<Json<TestResult>, ApiError> {
+    let alert = repository
+        .notification()
+        .read_test_alert(
+            &identity,
+            project_id.as_uuid(),
+            check_id.as_uuid(),
+            notification_id.as_uuid(),
+        )
+        .await?;
+
+    let result = match notifier.send_alert(&alert).await {
+        Ok(delivery) => TestResult {
+            delivered: true,
+            http_status: delivery.http_status,
+            provider_message_id: delivery.provider_message_id,
+            error: None,
+        },
+        Err(e) => TestResult {
+            delivered: false,
+            http_status: e.http_status(),
+            provider_message_id: None,
+            error: Some(e.to_message()),
+        },
+    };
+
+    Ok(result.into())
+}
+
 /// An API [`Notification`] type.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Notification {
@@ -115,7 +175,13 @@ pub struct Notification {
     pub email: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
     pub max_retries: i32,
+    pub retry_backoff_seconds: i32,
+    pub retry_backoff_max_seconds: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_window_seconds: Option<i32>,
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<DateTime<Utc>>,
@@ -126,6 +192,59 @@ pub struct Notification {
 pub enum NotificationType {
     Email,
     Webhook,
+    Sms,
+}
+
+/// An API [`Alert`] type, an alert sent for a notification.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: ShortId,
+    pub check_id: ShortId,
+    pub check_status: CheckStatus,
+    pub delivery_status: DeliveryStatus,
+    pub retries_remaining: i32,
+    /// Index of the escalation policy tier the alert was sent to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escalation_tier: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    pub attempts: Vec<AlertAttempt>,
+}
+
+/// An API [`AlertAttempt`] type, a single attempt at delivering an alert.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlertAttempt {
+    pub delivery_status: DeliveryStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// Response for `POST /api/v1/projects/:id/checks/:id/notifications/:id/test`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestResult {
+    pub delivered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeliveryStatus {
+    Queued,
+    Running,
+    Delivered,
+    Failed,
+    Cancelled,
 }
 
 /// Body for `POST /api/v1/notifications`.
@@ -140,7 +259,16 @@ pub struct CreateNotification {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_retries: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_seconds: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_max_seconds: Option<i32>,
+    /// Seconds to collect alerts for before sending them as one digest, 0 disables digests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_window_seconds: Option<i32>,
 }
 
 /// Body for `PUT /api/v1/notifications`.
@@ -155,7 +283,16 @@ pub struct UpdateNotification {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_retries: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_seconds: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_max_seconds: Option<i32>,
+    /// Seconds to collect alerts for before sending them as one digest, 0 disables digests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_window_seconds: Option<i32>,
 }
 
 // Notification model conversions
@@ -170,13 +307,63 @@ impl From<dto::Notification> for Notification {
             notification_type: notification.notification_type.into(),
             email: notification.email,
             url: notification.url,
+            phone_number: notification.phone_number,
             max_retries: notification.max_retries,
+            retry_backoff_seconds: notification.retry_backoff_seconds,
+            retry_backoff_max_seconds: notification.retry_backoff_max_seconds,
+            digest_window_seconds: notification.digest_window_seconds,
             created_at: Utc.from_utc_datetime(&notification.created_at),
             updated_at: notification.updated_at.map(|d| Utc.from_utc_datetime(&d)),
         }
     }
 }
 
+/// Conversion from repository [`dto::Alert`] and its [`dto::AlertAttempt`]s to
+/// API [`Alert`].
+impl From<(dto::Alert, Vec<dto::AlertAttempt>)> for Alert {
+    fn from((alert, attempts): (dto::Alert, Vec<dto::AlertAttempt>)) -> Self {
+        Self {
+            id: alert.uuid.into(),
+            check_id: alert.check_uuid.into(),
+            check_status: alert.check_status.into(),
+            delivery_status: alert.delivery_status.into(),
+            retries_remaining: alert.retries_remaining,
+            escalation_tier: alert.escalation_tier,
+            created_at: Utc.from_utc_datetime(&alert.created_at),
+            finished_at: alert.finished_at.map(|d| Utc.from_utc_datetime(&d)),
+            attempts: attempts.into_iter().map(|a| a.into()).collect(),
+        }
+    }
+}
+
+/// Conversion from repository [`dto::AlertAttempt`] to
+/// API [`AlertAttempt`].
+impl From<dto::AlertAttempt> for AlertAttempt {
+    fn from(attempt: dto::AlertAttempt) -> Self {
+        Self {
+            delivery_status: attempt.delivery_status.into(),
+            http_status: attempt.http_status,
+            provider_message_id: attempt.provider_message_id,
+            error: attempt.error,
+            attempted_at: Utc.from_utc_datetime(&attempt.attempted_at),
+        }
+    }
+}
+
+/// Conversion from repository [`dto::DeliveryStatus`] to
+/// API [`DeliveryStatus`].
+impl From<dto::DeliveryStatus> for DeliveryStatus {
+    fn from(status: dto::DeliveryStatus) -> Self {
+        match status {
+            dto::DeliveryStatus::Queued => DeliveryStatus::Queued,
+            dto::DeliveryStatus::Running => DeliveryStatus::Running,
+            dto::DeliveryStatus::Delivered => DeliveryStatus::Delivered,
+            dto::DeliveryStatus::Failed => DeliveryStatus::Failed,
+            dto::DeliveryStatus::Cancelled => DeliveryStatus::Cancelled,
+        }
+    }
+}
+
 /// Conversion from repository [`dto::NotificationType`] to
 /// API [`NotificationType`].
 impl From<dto::NotificationType> for NotificationType {
@@ -184,6 +371,7 @@ impl From<dto::NotificationType> for NotificationType {
         match notification_type {
             dto::NotificationType::Email => NotificationType::Email,
             dto::NotificationType::Webhook => NotificationType::Webhook,
+            dto::NotificationType::Sms => NotificationType::Sms,
         }
     }
 }
@@ -193,6 +381,7 @@ impl From<NotificationType> for dto::NotificationType {
         match notification_type {
             NotificationType::Email => dto::NotificationType::Email,
             NotificationType::Webhook => dto::NotificationType::Webhook,
+            NotificationType::Sms => dto::NotificationType::Sms,
         }
     }
 }
@@ -204,7 +393,11 @@ impl From<CreateNotification> for dto::CreateNotification {
             name: request.name,
             email: request.email,
             url: request.url,
+            phone_number: request.phone_number,
             max_retries: request.max_retries,
+            retry_backoff_seconds: request.retry_backoff_seconds,
+            retry_backoff_max_seconds: request.retry_backoff_max_seconds,
+            digest_window_seconds: request.digest_window_seconds,
         }
     }
 }
@@ -216,7 +409,11 @@ impl From<UpdateNotification> for dto::UpdateNotification {
             notification_type: request.notification_type.map(|t| t.into()),
             email: request.email,
             url: request.url,
+            phone_number: request.phone_number,
             max_retries: request.max_retries,
+            retry_backoff_seconds: request.retry_backoff_seconds,
+            retry_backoff_max_seconds: request.retry_backoff_max_seconds,
+            digest_window_seconds: request.digest_window_seconds,
         }
     }
 }
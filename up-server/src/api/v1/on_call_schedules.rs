@@ -0,0 +1,381 @@
+use axum::{body::Empty, extract::Path, response::IntoResponse, Extension};
+use chrono::{DateTime, TimeZone, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{v1::ApiError, Json},
+    auth::Identity,
+    repository::{dto, Repository},
+    shortid::ShortId,
+};
+
+/// Handler for `GET /api/v1/projects/:id/on-call-schedules/:id`
+pub async fn read_one(
+    Path((project_id, schedule_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<OnCallSchedule>, ApiError> {
+    let (schedule, overrides) = repository
+        .on_call_schedule()
+        .read_one(&identity, project_id.as_uuid(), schedule_id.as_uuid())
+        .await?;
+    let mut schedule: OnCallSchedule = schedule.into();
+    schedule.overrides = overrides.into_iter().map(|o| o.into()).collect();
+    Ok(schedule.into())
+}
+
+/// Handler for `GET /api/v1/projects/:id/on-call-schedules`
+pub async fn read_all(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Vec<OnCallSchedule>>, ApiError> {
+    let schedules: Vec<OnCallSchedule> = repository
+        .on_call_schedule()
+        .read_all(&identity, project_id.as_uuid())
+        .await?
+        .into_iter()
+        .map(|i| i.into())
+        .collect();
+    Ok(schedules.into())
+}
+
+/// Handler for `POST /api/v1/projects/:id/on-call-schedules`
+pub async fn create(
+    Path(project_id): Path<ShortId>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<CreateOnCallSchedule>,
+) -> Result<Json<OnCallSchedule>, ApiError> {
+    let schedule: OnCallSchedule = repository
+        .on_call_schedule()
+        .create(&identity, project_id.as_uuid(), request.0.into())
+        .await?
+        .into();
+    Ok(schedule.into())
+}
+
+/// Handler for `PATCH /api/v1/projects/:id/on-call-schedules/:id`
+pub async fn update(
+    Path((project_id, schedule_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<UpdateOnCallSchedule>,
+) -> Result<Json<OnCallSchedule>, ApiError> {
+    let schedule: OnCallSchedule = repository
+        .on_call_schedule()
+        .update(
+            &identity,
+            project_id.as_uuid(),
+            schedule_id.as_uuid(),
+            request.0.into(),
+        )
+        .await?
+        .into();
+    Ok(schedule.into())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/on-call-schedules/:id`
+pub async fn delete(
+    Path((project_id, schedule_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .on_call_schedule()
+        .delete(&identity, project_id.as_uuid(), schedule_id.as_uuid())
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `GET /api/v1/projects/:id/on-call-schedules/:id/on-call`, responds
+/// with `null` if nobody is on call.
+pub async fn read_on_call(
+    Path((project_id, schedule_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<Option<OnCallUser>>, ApiError> {
+    let user: Option<OnCallUser> = repository
+        .on_call_schedule()
+        .read_on_call(&identity, project_id.as_uuid(), schedule_id.as_uuid())
+        .await?
+        .map(|u| u.into());
+    Ok(user.into())
+}
+
+/// Handler for `POST /api/v1/projects/:id/on-call-schedules/:id/overrides`
+pub async fn create_override(
+    Path((project_id, schedule_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<CreateOnCallOverride>,
+) -> Result<Json<OnCallOverride>, ApiError> {
+    let on_call_override: OnCallOverride = repository
+        .on_call_schedule()
+        .create_override(
+            &identity,
+            project_id.as_uuid(),
+            schedule_id.as_uuid(),
+            request.0.into(),
+        )
+        .await?
+        .into();
+    Ok(on_call_override.into())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/on-call-schedules/:id/overrides/:id`
+pub async fn delete_override(
+    Path((project_id, schedule_id, override_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .on_call_schedule()
+        .delete_override(
+            &identity,
+            project_id.as_uuid(),
+            schedule_id.as_uuid(),
+            override_id.as_uuid(),
+        )
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `GET /api/v1/projects/:id/channels/:id/on-call-schedule`
+pub async fn read_for_channel(
+    Path((project_id, channel_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<OnCallSchedule>, ApiError> {
+    let schedule: OnCallSchedule = repository
+        .on_call_schedule()
+        .read_for_channel(&identity, project_id.as_uuid(), channel_id.as_uuid())
+        .await?
+        .into();
+    Ok(schedule.into())
+}
+
+/// Handler for `PUT /api/v1/projects/:id/channels/:id/on-call-schedule/:id`
+pub async fn attach(
+    Path((project_id, channel_id, schedule_id)): Path<(ShortId, ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .on_call_schedule()
+        .attach(
+            &identity,
+            project_id.as_uuid(),
+            channel_id.as_uuid(),
+            schedule_id.as_uuid(),
+        )
+        .await?;
+    Ok(Empty::new())
+}
+
+/// Handler for `DELETE /api/v1/projects/:id/channels/:id/on-call-schedule`
+pub async fn detach(
+    Path((project_id, channel_id)): Path<(ShortId, ShortId)>,
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository
+        .on_call_schedule()
+        .detach(&identity, project_id.as_uuid(), channel_id.as_uuid())
+        .await?;
+    Ok(Empty::new())
+}
+
+/// An API [`OnCallSchedule`] type. The first of `member_ids` is on call from
+/// `handoff_at`, and each `rotation` period the next member takes over.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnCallSchedule {
+    pub id: ShortId,
+    pub name: String,
+    pub rotation: RotationPeriod,
+    pub handoff_at: DateTime<Utc>,
+    pub member_ids: Vec<ShortId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<OnCallOverride>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// An API on-call rotation period type.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RotationPeriod {
+    Daily,
+    Weekly,
+}
+
+/// An API [`OnCallOverride`] type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnCallOverride {
+    pub id: ShortId,
+    pub user_id: ShortId,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An API [`OnCallUser`] type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnCallUser {
+    pub user_id: ShortId,
+    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    /// Whether the user is on call because of an override rather than the rotation.
+    pub overridden: bool,
+}
+
+/// Body for `POST /api/v1/projects/:id/on-call-schedules`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateOnCallSchedule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub rotation: RotationPeriod,
+    pub handoff_at: DateTime<Utc>,
+    pub member_ids: Vec<ShortId>,
+}
+
+/// Body for `PATCH /api/v1/projects/:id/on-call-schedules/:id`, `member_ids` replaces
+/// the existing rotation if set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateOnCallSchedule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<RotationPeriod>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handoff_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_ids: Option<Vec<ShortId>>,
+}
+
+/// Body for `POST /api/v1/projects/:id/on-call-schedules/:id/overrides`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateOnCallOverride {
+    pub user_id: ShortId,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::OnCallSchedule`] to
+/// API [`OnCallSchedule`].
+impl From<dto::OnCallSchedule> for OnCallSchedule {
+    fn from(schedule: dto::OnCallSchedule) -> Self {
+        Self {
+            id: schedule.uuid.into(),
+            name: schedule.name,
+            rotation: schedule.rotation.into(),
+            handoff_at: Utc.from_utc_datetime(&schedule.handoff_at),
+            member_ids: schedule
+                .member_uuids
+                .into_iter()
+                .map(|u| u.into())
+                .collect(),
+            overrides: Vec::new(),
+            created_at: Utc.from_utc_datetime(&schedule.created_at),
+            updated_at: schedule.updated_at.map(|dt| Utc.from_utc_datetime(&dt)),
+        }
+    }
+}
+
+/// Conversion from repository [`dto::RotationPeriod`] to
+/// API [`RotationPeriod`].
+impl From<dto::RotationPeriod> for RotationPeriod {
+    fn from(rotation: dto::RotationPeriod) -> Self {
+        match rotation {
+            dto::RotationPeriod::Daily => RotationPeriod::Daily,
+            dto::RotationPeriod::Weekly => RotationPeriod::Weekly,
+        }
+    }
+}
+
+/// Conversion from API [`RotationPeriod`] to
+/// repository [`dto::RotationPeriod`].
+impl From<RotationPeriod> for dto::RotationPeriod {
+    fn from(rotation: RotationPeriod) -> Self {
+        match rotation {
+            RotationPeriod::Daily => dto::RotationPeriod::Daily,
+            RotationPeriod::Weekly => dto::RotationPeriod::Weekly,
+        }
+    }
+}
+
+/// Conversion from repository [`dto::OnCallOverride`] to
+/// API [`OnCallOverride`].
+impl From<dto::OnCallOverride> for OnCallOverride {
+    fn from(on_call_override: dto::OnCallOverride) -> Self {
+        Self {
+            id: on_call_override.uuid.into(),
+            user_id: on_call_override.user_uuid.into(),
+            starts_at: Utc.from_utc_datetime(&on_call_override.starts_at),
+            ends_at: Utc.from_utc_datetime(&on_call_override.ends_at),
+            created_at: Utc.from_utc_datetime(&on_call_override.created_at),
+        }
+    }
+}
+
+/// Conversion from repository [`dto::OnCallUser`] to
+/// API [`OnCallUser`].
+impl From<dto::OnCallUser> for OnCallUser {
+    fn from(user: dto::OnCallUser) -> Self {
+        Self {
+            user_id: user.uuid.into(),
+            email: user.email,
+            phone_number: user.phone_number,
+            overridden: user.overridden,
+        }
+    }
+}
+
+/// Conversion from API [`CreateOnCallSchedule`] to
+/// repository [`dto::CreateOnCallSchedule`].
+impl From<CreateOnCallSchedule> for dto::CreateOnCallSchedule {
+    fn from(request: CreateOnCallSchedule) -> Self {
+        Self {
+            name: request.name,
+            rotation: request.rotation.into(),
+            handoff_at: request.handoff_at.naive_utc(),
+            member_uuids: request
+                .member_ids
+                .into_iter()
+                .map(|id| id.into_uuid())
+                .collect(),
+        }
+    }
+}
+
+/// Conversion from API [`UpdateOnCallSchedule`] to
+/// repository [`dto::UpdateOnCallSchedule`].
+impl From<UpdateOnCallSchedule> for dto::UpdateOnCallSchedule {
+    fn from(request: UpdateOnCallSchedule) -> Self {
+        Self {
+            name: request.name,
+            rotation: request.rotation.map(|r| r.into()),
+            handoff_at: request.handoff_at.map(|dt| dt.naive_utc()),
+            member_uuids: request
+                .member_ids
+                .map(|ids| ids.into_iter().map(|id| id.into_uuid()).collect()),
+        }
+    }
+}
+
+/// Conversion from API [`CreateOnCallOverride`] to
+/// repository [`dto::CreateOnCallOverride`].
+impl From<CreateOnCallOverride> for dto::CreateOnCallOverride {
+    fn from(request: CreateOnCallOverride) -> Self {
+        Self {
+            user_uuid: request.user_id.into_uuid(),
+            starts_at: request.starts_at.naive_utc(),
+            ends_at: request.ends_at.naive_utc(),
+        }
+    }
+}
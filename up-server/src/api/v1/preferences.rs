@@ -0,0 +1,106 @@
+use axum::Extension;
+use chrono::NaiveTime;
+use miette::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        v1::{notifications::NotificationType, ApiError},
+        Json,
+    },
+    auth::Identity,
+    repository::{dto, Repository},
+};
+
+/// Handler for `GET /api/v1/preferences`
+pub async fn read(
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+) -> Result<Json<NotificationPreferences>, ApiError> {
+    let preferences: NotificationPreferences =
+        repository.preferences().read(&identity).await?.into();
+    Ok(preferences.into())
+}
+
+/// Handler for `PUT /api/v1/preferences`
+pub async fn update(
+    Extension(identity): Extension<Identity>,
+    Extension(repository): Extension<Repository>,
+    request: Json<NotificationPreferences>,
+) -> Result<Json<NotificationPreferences>, ApiError> {
+    let preferences: NotificationPreferences = repository
+        .preferences()
+        .update(&identity, request.0.into())
+        .await?
+        .into();
+    Ok(preferences.into())
+}
+
+/// An API [`NotificationPreferences`] type, also the body for `PUT /api/v1/preferences`.
+/// During quiet hours in `timezone`, alerts for low severity checks are held and
+/// delivered together when the quiet hours end.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quiet_hours_start: Option<NaiveTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quiet_hours_end: Option<NaiveTime>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<ChannelPreference>,
+}
+
+/// An API [`ChannelPreference`] type, channel types without one hold alerts during
+/// quiet hours.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChannelPreference {
+    pub notification_type: NotificationType,
+    pub quiet_hours: bool,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+// Model conversions
+
+/// Conversion from repository [`dto::NotificationPreferences`] to
+/// API [`NotificationPreferences`].
+impl From<dto::NotificationPreferences> for NotificationPreferences {
+    fn from(preferences: dto::NotificationPreferences) -> Self {
+        Self {
+            timezone: preferences.timezone,
+            quiet_hours_start: preferences.quiet_hours_start,
+            quiet_hours_end: preferences.quiet_hours_end,
+            channels: preferences
+                .channels
+                .into_iter()
+                .map(|c| ChannelPreference {
+                    notification_type: c.notification_type.into(),
+                    quiet_hours: c.quiet_hours,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Conversion from API [`NotificationPreferences`] to
+/// repository [`dto::NotificationPreferences`].
+impl From<NotificationPreferences> for dto::NotificationPreferences {
+    fn from(request: NotificationPreferences) -> Self {
+        Self {
+            timezone: request.timezone,
+            quiet_hours_start: request.quiet_hours_start,
+            quiet_hours_end: request.quiet_hours_end,
+            channels: request
+                .channels
+                .into_iter()
+                .map(|c| dto::ChannelPreference {
+                    notification_type: c.notification_type.into(),
+                    quiet_hours: c.quiet_hours,
+                })
+                .collect(),
+        }
+    }
+}
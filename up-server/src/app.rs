@@ -1,16 +1,29 @@
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
 
 use argh::FromArgs;
+use camino::Utf8PathBuf;
 use dotenv::dotenv;
 use miette::{Diagnostic, IntoDiagnostic, Result};
 use thiserror::Error;
 use tracing_subscriber::EnvFilter;
-use up_core::jwt::{self, DEFAULT_AUDIENCE, DEFAULT_ISSUER};
-use up_core::JWKS_ENV;
-
-use crate::{api, database, integrations, jobs, notifier::Notifier, repository::Repository};
+use up_core::{JWKS_ENV, SERVER_CERTIFICATE_ENV};
+
+use crate::{
+    acknowledgement::AcknowledgementLinks,
+    api::{self, v1::accounts::SignupConfig},
+    database, integrations, jobs,
+    keys::{KeySource, KeyStore, SigningKeySource},
+    notifier::{Branding, Notifier},
+    oidc::{OidcConfig, OidcProvider},
+    repository::{
+        dto::{Provisioning, UserRole},
+        Repository,
+    },
+    shortid::ShortId,
+    templates::Templates,
+    tokens::TokenIssuer,
+};
 
 static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
 
@@ -69,20 +82,84 @@ impl App {
                 .ok();
         }
 
-        let jwks = env_or_error(JWKS_ENV, "JWT verification")?;
-
-        let jwt_verifier = Arc::new(
-            jwt::Verifier::new_from_jwks(&jwks, Some(DEFAULT_ISSUER), Some(DEFAULT_AUDIENCE))
-                .map_err(AppError::ConfigurationError)?,
-        );
-
-        let key_ids = jwt_verifier.key_ids();
+        let key_source = match (
+            self.args.jwks_file.clone().or_else(default_jwks_file),
+            self.args.jwks_url.clone().or_else(default_jwks_url),
+        ) {
+            (Some(path), _) => KeySource::File(path),
+            (None, Some(url)) => KeySource::Url(url),
+            (None, None) => KeySource::Static(env_or_error(JWKS_ENV, "JWT verification")?),
+        };
+        let signing_key_source = match (
+            self.args
+                .signing_key_file
+                .clone()
+                .or_else(default_signing_key_file),
+            std::env::var(SERVER_CERTIFICATE_ENV).ok(),
+        ) {
+            (Some(path), _) => Some(SigningKeySource::File(path)),
+            (None, Some(pem)) => Some(SigningKeySource::Static(pem.into_bytes())),
+            (None, None) => {
+                tracing::debug!(
+                    "{} is not set, alerts will not include acknowledgement links and users can't log in",
+                    SERVER_CERTIFICATE_ENV
+                );
+                None
+            }
+        };
+        let keys = KeyStore::load(key_source, signing_key_source).await?;
+
+        let key_ids = keys.key_ids();
         tracing::debug!(
             "using {} key(s) to verify JWTs: {}",
             key_ids.len(),
             key_ids.join(", ")
         );
 
+        let acknowledgements =
+            AcknowledgementLinks::new(keys.clone(), self.args.acknowledgement_link_expiry_hours);
+        let tokens = TokenIssuer::new(keys.clone(), self.args.jwt_expiry_minutes);
+
+        let oidc_config = match (
+            self.args
+                .oidc_issuer_url
+                .clone()
+                .or_else(default_oidc_issuer_url),
+            self.args
+                .oidc_client_id
+                .clone()
+                .or_else(default_oidc_client_id),
+        ) {
+            (Some(issuer_url), Some(client_id)) => Some(OidcConfig {
+                issuer_url,
+                client_id,
+                client_secret: self
+                    .args
+                    .oidc_client_secret
+                    .clone()
+                    .or_else(default_oidc_client_secret),
+                provisioning: self
+                    .args
+                    .oidc_default_account
+                    .or_else(default_oidc_default_account)
+                    .map(|account| Provisioning {
+                        account_uuid: account.into_uuid(),
+                        role: self
+                            .args
+                            .oidc_default_role
+                            .or_else(default_oidc_default_role)
+                            .unwrap_or(UserRole::Viewer),
+                    }),
+            }),
+            _ => {
+                tracing::debug!(
+                    "OIDC_ISSUER_URL and OIDC_CLIENT_ID are not set, single sign-on is disabled"
+                );
+                None
+            }
+        };
+        let oidc = OidcProvider::new(oidc_config, &self.args.base_url).into_diagnostic()?;
+
         let database = database::connect(
             &self.args.database_url,
             1,
@@ -94,22 +171,79 @@ impl App {
 
         let repository = Repository::new(database.clone());
         let postmark_client = integrations::postmark::PostmarkClient::new()?;
-        let notifier = Notifier::new(repository.clone(), postmark_client);
+        let twilio_client = match integrations::twilio::TwilioClient::new() {
+            Ok(client) => Some(client),
+            Err(e) => {
+                tracing::debug!("{}, SMS alerts will not be sent", e);
+                None
+            }
+        };
+        let templates_dir = self
+            .args
+            .templates_dir
+            .clone()
+            .or_else(default_templates_dir);
+        let templates = Templates::new(templates_dir.as_deref())?;
+        let branding = Branding {
+            email_from: self.args.email_from.clone(),
+            email_reply_to: self
+                .args
+                .email_reply_to
+                .clone()
+                .or_else(default_email_reply_to),
+            product_name: self.args.product_name.clone(),
+            base_url: self.args.base_url.clone(),
+        };
+        let notifier = Notifier::new(
+            repository.clone(),
+            postmark_client,
+            twilio_client,
+            templates,
+            branding,
+            self.args.sms_rate_limit_per_hour,
+            acknowledgements.clone(),
+        );
 
         let mut enqueue_alerts_job: Option<jobs::EnqueueAlerts> = None;
         let mut send_alerts_job: Option<jobs::SendAlerts> = None;
+        let mut escalate_incidents_job: Option<jobs::EscalateIncidents> = None;
+        let mut reload_keys_job: Option<jobs::ReloadKeys> = None;
+
+        if keys.is_reloadable() {
+            let interval = match self.args.jwks_reload_interval {
+                0 => None,
+                seconds => Some(std::time::Duration::from_secs(seconds)),
+            };
+            reload_keys_job = Some(jobs::ReloadKeys::with_key_store(keys.clone(), interval));
+        } else {
+            tracing::debug!("JWKS is set at startup, keys will not be reloaded");
+        }
 
         if !self.args.disable_background_jobs {
             enqueue_alerts_job = Some(jobs::EnqueueAlerts::with_repository(repository.clone()));
             send_alerts_job = Some(jobs::SendAlerts::with_repository(
                 repository.clone(),
                 notifier.clone(),
+                self.args.alert_concurrency,
+                std::time::Duration::from_secs(self.args.alert_claim_timeout),
             ));
+            escalate_incidents_job =
+                Some(jobs::EscalateIncidents::with_repository(repository.clone()));
         } else {
             tracing::debug!("background jobs disabled, alerts will not be sent");
         }
 
-        let router = api::build(repository, notifier, jwt_verifier);
+        let router = api::build(
+            repository,
+            notifier,
+            acknowledgements,
+            tokens,
+            oidc,
+            SignupConfig {
+                enabled: self.args.allow_signup || default_allow_signup(),
+            },
+            keys,
+        );
 
         tracing::debug!(
             ip = self.args.listen_address.ip().to_string().as_str(),
@@ -122,9 +256,14 @@ impl App {
             "server started"
         );
 
+        if let Some(reload_keys_job) = reload_keys_job.as_mut() {
+            reload_keys_job.spawn().await;
+        }
+
         if !self.args.disable_background_jobs {
             enqueue_alerts_job.as_mut().unwrap().spawn().await;
             send_alerts_job.as_mut().unwrap().spawn().await;
+            escalate_incidents_job.as_mut().unwrap().spawn().await;
         }
 
         let server = axum::Server::bind(&self.args.listen_address)
@@ -133,6 +272,8 @@ impl App {
         let graceful = server.with_graceful_shutdown(shutdown_signal(
             enqueue_alerts_job.as_mut(),
             send_alerts_job.as_mut(),
+            escalate_incidents_job.as_mut(),
+            reload_keys_job.as_mut(),
         ));
         graceful.await.into_diagnostic()?;
 
@@ -145,6 +286,8 @@ impl App {
 async fn shutdown_signal(
     enqueue_alerts_job: Option<&mut jobs::EnqueueAlerts>,
     send_alerts_job: Option<&mut jobs::SendAlerts>,
+    escalate_incidents_job: Option<&mut jobs::EscalateIncidents>,
+    reload_keys_job: Option<&mut jobs::ReloadKeys>,
 ) {
     tokio::signal::ctrl_c()
         .await
@@ -157,6 +300,12 @@ async fn shutdown_signal(
     if let Some(send_alerts_job) = send_alerts_job {
         send_alerts_job.stop().await;
     }
+    if let Some(escalate_incidents_job) = escalate_incidents_job {
+        escalate_incidents_job.stop().await;
+    }
+    if let Some(reload_keys_job) = reload_keys_job {
+        reload_keys_job.stop().await;
+    }
 }
 
 #[derive(FromArgs)]
@@ -174,6 +323,66 @@ pub struct Args {
     /// the maximum number of connections in the PostgreSQL connection pool (default: 20, or DATABASE_MAX_CONNECTIONS environment variable)
     #[argh(option, default = "default_database_max_connections()")]
     pub database_max_connections: u32,
+    /// the public URL of the server, used for links in notifications (default: http://localhost:8080/, or BASE_URL environment variable)
+    #[argh(option, default = "default_base_url()")]
+    pub base_url: url::Url,
+    /// the sender address of notification emails (default: up.io <no-reply@sector42.io>, or EMAIL_FROM environment variable)
+    #[argh(option, default = "default_email_from()")]
+    pub email_from: String,
+    /// the reply-to address of notification emails (default: none, or EMAIL_REPLY_TO environment variable)
+    #[argh(option)]
+    pub email_reply_to: Option<String>,
+    /// the product name shown in notifications (default: up.io, or PRODUCT_NAME environment variable)
+    #[argh(option, default = "default_product_name()")]
+    pub product_name: String,
+    /// directory containing notification templates that override the built-in ones (default: none, or TEMPLATES_DIR environment variable)
+    #[argh(option)]
+    pub templates_dir: Option<Utf8PathBuf>,
+    /// the maximum number of SMS alerts sent per account per hour (default: 10, or SMS_RATE_LIMIT_PER_HOUR environment variable)
+    #[argh(option, default = "default_sms_rate_limit_per_hour()")]
+    pub sms_rate_limit_per_hour: u32,
+    /// the maximum number of alerts delivered at the same time (default: 4, or ALERT_CONCURRENCY environment variable)
+    #[argh(option, default = "default_alert_concurrency()")]
+    pub alert_concurrency: usize,
+    /// seconds after which an alert that is still being delivered is assumed lost and delivered again (default: 300, or ALERT_CLAIM_TIMEOUT environment variable)
+    #[argh(option, default = "default_alert_claim_timeout()")]
+    pub alert_claim_timeout: u64,
+    /// hours for which acknowledgement links in alerts are valid (default: 72, or ACKNOWLEDGEMENT_LINK_EXPIRY_HOURS environment variable)
+    #[argh(option, default = "default_acknowledgement_link_expiry_hours()")]
+    pub acknowledgement_link_expiry_hours: i64,
+    /// minutes for which JWTs issued when users log in are valid, refresh tokens are used to get new ones (default: 15, or JWT_EXPIRY_MINUTES environment variable)
+    #[argh(option, default = "default_jwt_expiry_minutes()")]
+    pub jwt_expiry_minutes: i64,
+    /// the issuer URL of the OpenID Connect provider users can log in with (default: none, or OIDC_ISSUER_URL environment variable)
+    #[argh(option)]
+    pub oidc_issuer_url: Option<String>,
+    /// the client ID of the server at the OpenID Connect provider (default: none, or OIDC_CLIENT_ID environment variable)
+    #[argh(option)]
+    pub oidc_client_id: Option<String>,
+    /// the client secret of the server at the OpenID Connect provider (default: none, or OIDC_CLIENT_SECRET environment variable)
+    #[argh(option)]
+    pub oidc_client_secret: Option<String>,
+    /// the ID of the account users logging in with the OpenID Connect provider for the first time are added to (default: none, users are not provisioned, or OIDC_DEFAULT_ACCOUNT environment variable)
+    #[argh(option)]
+    pub oidc_default_account: Option<ShortId>,
+    /// the role of provisioned users, ADMINISTRATOR, MEMBER or VIEWER (default: VIEWER, or OIDC_DEFAULT_ROLE environment variable)
+    #[argh(option)]
+    pub oidc_default_role: Option<UserRole>,
+    /// file to read the JWKS that JWTs are verified against from, reloaded on SIGHUP and every --jwks-reload-interval seconds (default: none, or JWKS_FILE environment variable)
+    #[argh(option)]
+    pub jwks_file: Option<Utf8PathBuf>,
+    /// URL to fetch the JWKS that JWTs are verified against from if there is no --jwks-file, reloaded like it (default: none, the JWKS environment variable is used, or JWKS_URL environment variable)
+    #[argh(option)]
+    pub jwks_url: Option<url::Url>,
+    /// file to read the private key that JWTs are signed with from, in PEM format, reloaded along with the JWKS (default: none, the SERVER_CERTIFICATE environment variable is used, or SERVER_CERTIFICATE_FILE environment variable)
+    #[argh(option)]
+    pub signing_key_file: Option<Utf8PathBuf>,
+    /// seconds between reloads of the JWKS from --jwks-file or --jwks-url and of the signing key from --signing-key-file, 0 to only reload on SIGHUP (default: 300, or JWKS_RELOAD_INTERVAL environment variable)
+    #[argh(option, default = "default_jwks_reload_interval()")]
+    pub jwks_reload_interval: u64,
+    /// allow anyone to sign up, creating a user, an account and a project, rather than only invited users (or ALLOW_SIGNUP=true environment variable)
+    #[argh(switch)]
+    pub allow_signup: bool,
     /// use JSON for log messages
     #[argh(switch)]
     pub json: bool,
@@ -188,6 +397,26 @@ impl Default for Args {
             listen_address: SocketAddr::from(([127, 0, 0, 1], default_listen_port())),
             database_url: default_database_url(),
             database_max_connections: default_database_max_connections(),
+            base_url: default_base_url(),
+            email_from: default_email_from(),
+            email_reply_to: default_email_reply_to(),
+            product_name: default_product_name(),
+            templates_dir: default_templates_dir(),
+            sms_rate_limit_per_hour: default_sms_rate_limit_per_hour(),
+            alert_concurrency: default_alert_concurrency(),
+            alert_claim_timeout: default_alert_claim_timeout(),
+            acknowledgement_link_expiry_hours: default_acknowledgement_link_expiry_hours(),
+            jwt_expiry_minutes: default_jwt_expiry_minutes(),
+            oidc_issuer_url: default_oidc_issuer_url(),
+            oidc_client_id: default_oidc_client_id(),
+            oidc_client_secret: default_oidc_client_secret(),
+            oidc_default_account: default_oidc_default_account(),
+            oidc_default_role: default_oidc_default_role(),
+            jwks_file: default_jwks_file(),
+            jwks_url: default_jwks_url(),
+            signing_key_file: default_signing_key_file(),
+            jwks_reload_interval: default_jwks_reload_interval(),
+            allow_signup: default_allow_signup(),
             json: false,
             disable_background_jobs: false,
         }
@@ -232,6 +461,173 @@ fn default_database_max_connections() -> u32 {
     }
 }
 
+const DEFAULT_BASE_URL: &str = "http://localhost:8080/";
+
+fn default_base_url() -> url::Url {
+    if let Ok(value) = std::env::var("BASE_URL") {
+        if let Ok(url) = value.parse() {
+            return url;
+        }
+        tracing::warn!(url = value, "ignoring malformed BASE_URL");
+    }
+    DEFAULT_BASE_URL.parse().unwrap()
+}
+
+const DEFAULT_EMAIL_FROM: &str = "up.io <no-reply@sector42.io>";
+
+fn default_email_from() -> String {
+    if let Ok(value) = std::env::var("EMAIL_FROM") {
+        value
+    } else {
+        DEFAULT_EMAIL_FROM.to_string()
+    }
+}
+
+fn default_email_reply_to() -> Option<String> {
+    std::env::var("EMAIL_REPLY_TO").ok()
+}
+
+const DEFAULT_PRODUCT_NAME: &str = "up.io";
+
+fn default_product_name() -> String {
+    if let Ok(value) = std::env::var("PRODUCT_NAME") {
+        value
+    } else {
+        DEFAULT_PRODUCT_NAME.to_string()
+    }
+}
+
+fn default_templates_dir() -> Option<Utf8PathBuf> {
+    std::env::var("TEMPLATES_DIR").ok().map(Utf8PathBuf::from)
+}
+
+const DEFAULT_SMS_RATE_LIMIT_PER_HOUR: u32 = 10;
+
+fn default_sms_rate_limit_per_hour() -> u32 {
+    if let Ok(value) = std::env::var("SMS_RATE_LIMIT_PER_HOUR") {
+        value
+            .parse()
+            .ok()
+            .unwrap_or(DEFAULT_SMS_RATE_LIMIT_PER_HOUR)
+    } else {
+        DEFAULT_SMS_RATE_LIMIT_PER_HOUR
+    }
+}
+
+const DEFAULT_ALERT_CONCURRENCY: usize = 4;
+
+fn default_alert_concurrency() -> usize {
+    if let Ok(value) = std::env::var("ALERT_CONCURRENCY") {
+        value.parse().ok().unwrap_or(DEFAULT_ALERT_CONCURRENCY)
+    } else {
+        DEFAULT_ALERT_CONCURRENCY
+    }
+}
+
+const DEFAULT_ALERT_CLAIM_TIMEOUT: u64 = 300;
+
+fn default_alert_claim_timeout() -> u64 {
+    if let Ok(value) = std::env::var("ALERT_CLAIM_TIMEOUT") {
+        value.parse().ok().unwrap_or(DEFAULT_ALERT_CLAIM_TIMEOUT)
+    } else {
+        DEFAULT_ALERT_CLAIM_TIMEOUT
+    }
+}
+
+const DEFAULT_ACKNOWLEDGEMENT_LINK_EXPIRY_HOURS: i64 = 72;
+
+fn default_acknowledgement_link_expiry_hours() -> i64 {
+    if let Ok(value) = std::env::var("ACKNOWLEDGEMENT_LINK_EXPIRY_HOURS") {
+        value
+            .parse()
+            .ok()
+            .unwrap_or(DEFAULT_ACKNOWLEDGEMENT_LINK_EXPIRY_HOURS)
+    } else {
+        DEFAULT_ACKNOWLEDGEMENT_LINK_EXPIRY_HOURS
+    }
+}
+
+const DEFAULT_JWT_EXPIRY_MINUTES: i64 = 15;
+
+fn default_jwt_expiry_minutes() -> i64 {
+    if let Ok(value) = std::env::var("JWT_EXPIRY_MINUTES") {
+        value.parse().ok().unwrap_or(DEFAULT_JWT_EXPIRY_MINUTES)
+    } else {
+        DEFAULT_JWT_EXPIRY_MINUTES
+    }
+}
+
+fn default_oidc_issuer_url() -> Option<String> {
+    std::env::var("OIDC_ISSUER_URL").ok()
+}
+
+fn default_oidc_client_id() -> Option<String> {
+    std::env::var("OIDC_CLIENT_ID").ok()
+}
+
+fn default_oidc_client_secret() -> Option<String> {
+    std::env::var("OIDC_CLIENT_SECRET").ok()
+}
+
+fn default_oidc_default_account() -> Option<ShortId> {
+    let value = std::env::var("OIDC_DEFAULT_ACCOUNT").ok()?;
+    match value.parse() {
+        Ok(account) => Some(account),
+        Err(_) => {
+            tracing::warn!(account = value, "ignoring malformed OIDC_DEFAULT_ACCOUNT");
+            None
+        }
+    }
+}
+
+fn default_oidc_default_role() -> Option<UserRole> {
+    let value = std::env::var("OIDC_DEFAULT_ROLE").ok()?;
+    match value.parse() {
+        Ok(role) => Some(role),
+        Err(e) => {
+            tracing::warn!("ignoring OIDC_DEFAULT_ROLE: {}", e);
+            None
+        }
+    }
+}
+
+fn default_jwks_file() -> Option<Utf8PathBuf> {
+    std::env::var("JWKS_FILE").ok().map(Utf8PathBuf::from)
+}
+
+fn default_signing_key_file() -> Option<Utf8PathBuf> {
+    std::env::var("SERVER_CERTIFICATE_FILE")
+        .ok()
+        .map(Utf8PathBuf::from)
+}
+
+fn default_jwks_url() -> Option<url::Url> {
+    let value = std::env::var("JWKS_URL").ok()?;
+    match value.parse() {
+        Ok(url) => Some(url),
+        Err(_) => {
+            tracing::warn!(url = value, "ignoring malformed JWKS_URL");
+            None
+        }
+    }
+}
+
+const DEFAULT_JWKS_RELOAD_INTERVAL: u64 = 300;
+
+fn default_jwks_reload_interval() -> u64 {
+    if let Ok(value) = std::env::var("JWKS_RELOAD_INTERVAL") {
+        value.parse().ok().unwrap_or(DEFAULT_JWKS_RELOAD_INTERVAL)
+    } else {
+        DEFAULT_JWKS_RELOAD_INTERVAL
+    }
+}
+
+fn default_allow_signup() -> bool {
+    std::env::var("ALLOW_SIGNUP")
+        .map(|value| value == "true")
+        .unwrap_or(false)
+}
+
 fn env_or_error(name: &str, purpose: &str) -> Result<String, AppError> {
     if let Ok(value) = std::env::var(name) {
         Ok(value)
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::sync::Arc;
 
 use axum::{
     http::{header, Request, StatusCode},
@@ -7,21 +6,41 @@ use axum::{
     response::Response,
 };
 use serde::{Deserialize, Serialize};
-use up_core::{auth::Role, jwt};
+use up_core::{
+    auth::{Role, Scope},
+    jwt,
+};
 use uuid::Uuid;
 
 use crate::{
-    api::v1::{HEALTH_URI, PING_URI},
+    api::v1::{
+        ACKNOWLEDGE_URI, HEALTH_URI, INVITATION_URI, JWKS_URI, LOGIN_URI, LOGOUT_URI,
+        MAGIC_LINK_URI, OIDC_URI, PASSWORD_RESET_URI, PING_URI, REFRESH_URI, SIGNUP_URI,
+    },
+    keys::KeyStore,
     mask,
     repository::{
         self,
-        dto::{User, UserRole},
-        RepositoryError,
+        dto::{ApiKey, ApiKeyScope, User, UserRole},
+        RepositoryError, API_KEY_PREFIX,
     },
     shortid::ShortId,
 };
 
-const SKIP_AUTH_URIS: &[&str] = &[PING_URI, HEALTH_URI];
+const SKIP_AUTH_URIS: &[&str] = &[
+    PING_URI,
+    HEALTH_URI,
+    JWKS_URI,
+    ACKNOWLEDGE_URI,
+    LOGIN_URI,
+    PASSWORD_RESET_URI,
+    OIDC_URI,
+    MAGIC_LINK_URI,
+    INVITATION_URI,
+    SIGNUP_URI,
+    REFRESH_URI,
+    LOGOUT_URI,
+];
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Identity {
@@ -36,17 +55,58 @@ pub struct Identity {
     pub email: String,
     #[serde(skip_serializing)]
     pub roles: HashMap<i64, Vec<Role>>,
+    #[serde(skip_serializing)]
+    pub project_roles: HashMap<i64, Vec<Role>>,
+    /// The API key the request was authenticated with, `None` for JWTs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<ApiKeyGrant>,
+}
+
+/// What the API key a request was authenticated with grants, on top of the roles of
+/// the user it belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKeyGrant {
+    pub id: ShortId,
+    pub scopes: Vec<Scope>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<ShortId>,
 }
 
 const ENTITY_PROJECT: &str = "project";
 
 impl Identity {
-    pub fn is_administrator_in_account(&self, uuid: &Uuid) -> bool {
-        self.has_role_in_account(uuid, Role::Administrator)
+    /// Creates the identity of a user authenticated with an API key, restricted to the
+    /// key's project if it has one. API keys never act as administrators.
+    pub fn with_api_key(user: User, key: ApiKey) -> Self {
+        let mut identity: Identity = user.into();
+
+        if let Some(project_uuid) = key.project_uuid {
+            identity.project_ids.retain(|uuid, _| *uuid == project_uuid);
+        }
+
+        for roles in identity
+            .roles
+            .values_mut()
+            .chain(identity.project_roles.values_mut())
+        {
+            if roles.contains(&Role::Administrator) {
+                roles.retain(|r| *r != Role::Administrator);
+                if !roles.contains(&Role::Member) {
+                    roles.push(Role::Member);
+                }
+            }
+        }
+
+        identity.api_key = Some(ApiKeyGrant {
+            id: key.uuid.into(),
+            scopes: key.scopes.into_iter().map(|s| s.into()).collect(),
+            project_id: key.project_uuid.map(|u| u.into()),
+        });
+        identity
     }
 
-    pub fn is_member_in_account_with_id(&self, id: i64) -> bool {
-        self.has_role_in_account_with_id(id, Role::Member)
+    pub fn is_administrator_in_account(&self, uuid: &Uuid) -> bool {
+        self.has_role_in_account(uuid, Role::Administrator)
     }
 
     pub fn is_administrator_in_account_with_id(&self, id: i64) -> bool {
@@ -67,6 +127,56 @@ impl Identity {
             .unwrap_or(false)
     }
 
+    pub fn is_member_in_project_with_id(&self, id: i64) -> bool {
+        self.has_role_in_project_with_id(id, Role::Member)
+    }
+
+    pub fn is_administrator_in_project_with_id(&self, id: i64) -> bool {
+        self.has_role_in_project_with_id(id, Role::Administrator)
+    }
+
+    /// Whether the identity has a role in a project, account administrators are
+    /// administrators in every project in the account they are assigned to.
+    pub fn has_role_in_project_with_id(&self, id: i64, role: Role) -> bool {
+        self.project_roles
+            .get(&id)
+            .map(|r| self.has_equivalent_role(r, role))
+            .unwrap_or(false)
+    }
+
+    /// Whether checks and maintenance windows can be changed in a project.
+    pub fn can_manage_checks_in_project_with_id(&self, id: i64) -> bool {
+        self.is_member_in_project_with_id(id) && self.has_scope(Scope::ManageChecks)
+    }
+
+    /// Whether notifications, channels, escalation policies and on-call schedules can
+    /// be changed in a project.
+    pub fn can_manage_notifications_in_project_with_id(&self, id: i64) -> bool {
+        self.is_member_in_project_with_id(id) && self.has_scope(Scope::ManageNotifications)
+    }
+
+    /// Whether the identity was granted a scope, always the case unless it was
+    /// authenticated with an API key.
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.api_key
+            .as_ref()
+            .map(|k| k.scopes.contains(&scope))
+            .unwrap_or(true)
+    }
+
+    /// Rejects API keys for what only users themselves can do, e.g. creating more keys.
+    pub fn ensure_not_api_key(&self) -> Result<(), RepositoryError> {
+        if let Some(api_key) = &self.api_key {
+            tracing::trace!(
+                user_uuid = self.user_uuid.to_string(),
+                api_key_id = api_key.id.to_string(),
+                "API key not allowed, rejecting API call"
+            );
+            return Err(RepositoryError::Forbidden);
+        }
+        Ok(())
+    }
+
     pub fn is_assigned_to_account(&self, uuid: &Uuid) -> bool {
         self.account_ids.contains_key(uuid)
     }
@@ -131,6 +241,36 @@ impl From<UserRole> for Role {
     }
 }
 
+impl From<Role> for UserRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Administrator => UserRole::Administrator,
+            Role::Member => UserRole::Member,
+            Role::Viewer => UserRole::Viewer,
+        }
+    }
+}
+
+impl From<ApiKeyScope> for Scope {
+    fn from(scope: ApiKeyScope) -> Self {
+        match scope {
+            ApiKeyScope::ReadOnly => Scope::ReadOnly,
+            ApiKeyScope::ManageChecks => Scope::ManageChecks,
+            ApiKeyScope::ManageNotifications => Scope::ManageNotifications,
+        }
+    }
+}
+
+impl From<Scope> for ApiKeyScope {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::ReadOnly => ApiKeyScope::ReadOnly,
+            Scope::ManageChecks => ApiKeyScope::ManageChecks,
+            Scope::ManageNotifications => ApiKeyScope::ManageNotifications,
+        }
+    }
+}
+
 impl From<User> for Identity {
     fn from(u: User) -> Self {
         Self {
@@ -140,6 +280,8 @@ impl From<User> for Identity {
             project_ids: to_uuid_and_id_map(u.project_ids),
             email: u.email,
             roles: to_role_and_id_map(u.roles),
+            project_roles: to_role_and_id_map(u.project_roles),
+            api_key: None,
         }
     }
 }
@@ -148,10 +290,10 @@ fn to_role_and_id_map(items: Vec<String>) -> HashMap<i64, Vec<Role>> {
     let mut map = HashMap::new();
     for item in items {
         let parsed: Vec<_> = item.split('|').collect();
-        let account_id: i64 = parsed[1].parse().unwrap();
+        let id: i64 = parsed[1].parse().unwrap();
         let user_role: UserRole = parsed[0].parse().unwrap();
         let role: Role = user_role.into();
-        let roles = map.entry(account_id).or_insert_with(Vec::new);
+        let roles = map.entry(id).or_insert_with(Vec::new);
         if !roles.contains(&role) {
             roles.push(role);
         }
@@ -188,7 +330,7 @@ pub async fn auth_middleware<B>(
         .and_then(|header| header.to_str().ok());
 
     let repository = req.extensions().get::<repository::Repository>().unwrap();
-    let jwt_verifier = req.extensions().get::<Arc<jwt::Verifier>>().unwrap();
+    let keys = req.extensions().get::<KeyStore>().unwrap();
 
     let auth_header = if let Some(auth_header) = auth_header {
         auth_header
@@ -202,46 +344,80 @@ pub async fn auth_middleware<B>(
         return Err(StatusCode::UNAUTHORIZED);
     }
 
-    let claims = match jwt_verifier.verify(&auth_header[7..]) {
+    let token = &auth_header[7..];
+    let identity = if token.starts_with(API_KEY_PREFIX) {
+        identify_by_api_key(repository, token).await
+    } else {
+        identify_by_jwt(repository, &keys.verifier(), token).await
+    };
+
+    let identity = identity.ok_or(StatusCode::UNAUTHORIZED)?;
+    tracing::trace!(
+        user_uuid = identity.user_uuid.to_string(),
+        email = mask::email(&identity.email),
+        account_uuids = format!("{:?}", identity.account_ids.keys().collect::<Vec<_>>()),
+        account_ids = format!("{:?}", identity.account_ids.values().collect::<Vec<_>>()),
+        project_uuids = format!("{:?}", identity.project_ids.keys().collect::<Vec<_>>()),
+        project_ids = format!("{:?}", identity.project_ids.values().collect::<Vec<_>>()),
+        roles = format!("{:?}", identity.roles),
+        api_key = format!("{:?}", identity.api_key),
+        "user authorized"
+    );
+    req.extensions_mut().insert(identity);
+    Ok(next.run(req).await)
+}
+
+async fn identify_by_jwt(
+    repository: &repository::Repository,
+    jwt_verifier: &jwt::Verifier,
+    token: &str,
+) -> Option<Identity> {
+    let claims = match jwt_verifier.verify(token) {
         Ok(claims) => claims,
         Err(e) => {
             tracing::trace!("failed to verify user JWT: {:?}", e);
-            return Err(StatusCode::UNAUTHORIZED);
+            return None;
         }
     };
 
-    if let Some(subject) = claims.subject {
-        match repository.auth().find_user_by_subject(&subject).await {
-            Ok(Some(user)) => {
-                let identity: Identity = user.into();
-                tracing::trace!(
-                    user_uuid = identity.user_uuid.to_string(),
-                    email = mask::email(&identity.email),
-                    account_uuids =
-                        format!("{:?}", identity.account_ids.keys().collect::<Vec<_>>()),
-                    account_ids =
-                        format!("{:?}", identity.account_ids.values().collect::<Vec<_>>()),
-                    project_uuids =
-                        format!("{:?}", identity.project_ids.keys().collect::<Vec<_>>()),
-                    project_ids =
-                        format!("{:?}", identity.project_ids.values().collect::<Vec<_>>()),
-                    roles = format!("{:?}", identity.roles),
-                    "user authorized"
-                );
-                req.extensions_mut().insert(identity);
-                Ok(next.run(req).await)
-            }
-            Ok(None) => {
-                tracing::trace!(subject = subject, "user not found in repository");
-                Err(StatusCode::UNAUTHORIZED)
-            }
-            Err(e) => {
-                tracing::trace!("failed to authorize user: {:?}", e);
-                Err(StatusCode::UNAUTHORIZED)
-            }
-        }
+    let subject = if let Some(subject) = claims.subject {
+        subject
     } else {
         tracing::trace!("JWT has no subject claim");
-        Err(StatusCode::UNAUTHORIZED)
+        return None;
+    };
+
+    find_user(repository, &subject).await.map(|u| u.into())
+}
+
+async fn identify_by_api_key(repository: &repository::Repository, token: &str) -> Option<Identity> {
+    let key_user = match repository.api_key().authenticate(token).await {
+        Ok(Some(key_user)) => key_user,
+        Ok(None) => {
+            tracing::trace!("API key not found, expired or revoked");
+            return None;
+        }
+        Err(e) => {
+            tracing::trace!("failed to authorize API key: {:?}", e);
+            return None;
+        }
+    };
+
+    find_user(repository, &key_user.subject)
+        .await
+        .map(|u| Identity::with_api_key(u, key_user.key))
+}
+
+async fn find_user(repository: &repository::Repository, subject: &str) -> Option<User> {
+    match repository.auth().find_user_by_subject(subject).await {
+        Ok(Some(user)) => Some(user),
+        Ok(None) => {
+            tracing::trace!(subject = subject, "user not found in repository");
+            None
+        }
+        Err(e) => {
+            tracing::trace!("failed to authorize user: {:?}", e);
+            None
+        }
     }
 }
@@ -0,0 +1,71 @@
+use chrono::Duration;
+use rand::Rng;
+
+/// Exponential backoff with jitter for retrying alert deliveries.
+///
+/// The delay before retry `n` (starting at 1) is `base * 2^(n - 1)`, capped at `max`,
+/// and then randomized to somewhere between half of it and all of it, so that alerts
+/// that failed together during a provider outage are not all retried at the same moment.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    base_seconds: i64,
+    max_seconds: i64,
+}
+
+impl Backoff {
+    pub fn new(base_seconds: i32, max_seconds: i32) -> Self {
+        let base_seconds = (base_seconds as i64).max(1);
+        Self {
+            base_seconds,
+            max_seconds: (max_seconds as i64).max(base_seconds),
+        }
+    }
+
+    /// The delay before the given retry, without jitter.
+    pub fn ceiling(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(32);
+        let seconds = self
+            .base_seconds
+            .saturating_mul(1i64 << exponent)
+            .min(self.max_seconds);
+        Duration::seconds(seconds)
+    }
+
+    /// The delay before the given retry, with jitter applied.
+    pub fn delay<R: Rng>(&self, retry: u32, rng: &mut R) -> Duration {
+        let ceiling = self.ceiling(retry).num_milliseconds();
+        Duration::milliseconds(rng.gen_range((ceiling / 2)..=ceiling))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_until_capped() {
+        let backoff = Backoff::new(30, 300);
+
+        assert_eq!(Duration::seconds(30), backoff.ceiling(1));
+        assert_eq!(Duration::seconds(60), backoff.ceiling(2));
+        assert_eq!(Duration::seconds(120), backoff.ceiling(3));
+        assert_eq!(Duration::seconds(240), backoff.ceiling(4));
+        assert_eq!(Duration::seconds(300), backoff.ceiling(5));
+        assert_eq!(Duration::seconds(300), backoff.ceiling(100));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let backoff = Backoff::new(30, 300);
+        let mut rng = rand::thread_rng();
+
+        for retry in 1..10 {
+            let ceiling = backoff.ceiling(retry);
+            for _ in 0..100 {
+                let delay = backoff.delay(retry, &mut rng);
+                assert!(delay <= ceiling);
+                assert!(delay >= ceiling / 2);
+            }
+        }
+    }
+}
@@ -1 +1,3 @@
 pub mod postmark;
+pub mod twilio;
+pub mod webhook;
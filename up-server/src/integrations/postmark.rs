@@ -48,12 +48,6 @@ impl PostmarkClient {
     pub fn new() -> Result<Self> {
         let token =
             std::env::var(POSTMARK_API_TOKEN_ENV).map_err(|_| PostmarkError::MissingToken)?;
-        let client = reqwest::Client::builder()
-            .build()
-            .map_err(PostmarkError::ClientBuildError)?;
-        let api_base_url: url::Url = POSTMARK_API_BASE_URL
-            .parse()
-            .map_err(PostmarkError::UrlParsingError)?;
 
         if token == POSTMARK_API_TEST_TOKEN {
             tracing::warn!(
@@ -61,14 +55,25 @@ impl PostmarkClient {
             );
         }
 
+        Self::with_token(&token, POSTMARK_API_BASE_URL)
+    }
+
+    pub fn with_token(token: &str, api_base_url: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(PostmarkError::ClientBuildError)?;
+        let api_base_url: url::Url = api_base_url
+            .parse()
+            .map_err(PostmarkError::UrlParsingError)?;
+
         Ok(Self {
-            token,
+            token: token.to_string(),
             api_base_url,
             client,
         })
     }
 
-    pub async fn send_email(&self, request: &SendEmailRequest) -> Result<()> {
+    pub async fn send_email(&self, request: &SendEmailRequest) -> Result<SendEmailResponse> {
         let req = self
             .client
             .request(
@@ -99,8 +104,9 @@ impl PostmarkClient {
             ));
         }
 
-        let api_response: SendEmailResponse = serde_json::from_slice(&response_body_bytes)
+        let mut api_response: SendEmailResponse = serde_json::from_slice(&response_body_bytes)
             .map_err(PostmarkError::ResponseParseError)?;
+        api_response.http_status = Some(status.as_u16());
 
         if api_response.error_code == 0 {
             if tracing::event_enabled!(Level::TRACE) {
@@ -114,7 +120,7 @@ impl PostmarkClient {
                 let subject = request.subject.as_deref().unwrap_or("");
                 tracing::info!(emails = emails, subject = subject, "emails sent");
             }
-            Ok(())
+            Ok(api_response)
         } else {
             Err(PostmarkError::ApiError(
                 api_response.error_code,
@@ -134,6 +140,8 @@ pub struct SendEmailRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cc: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub subject: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tag: Option<String>,
@@ -151,14 +159,56 @@ pub struct SendEmailResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub submitted_at: Option<DateTime<Utc>>,
     pub to: Option<String>,
+    /// HTTP status of the API response, not part of the response body.
+    #[serde(skip)]
+    pub http_status: Option<u16>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "BodyParts", into = "BodyParts")]
 pub enum Body {
-    #[serde(rename = "TextBody")]
     Text(String),
-    #[serde(rename = "HtmlBody")]
     Html(String),
+    HtmlAndText { html: String, text: String },
+}
+
+/// Wire representation of [`Body`], Postmark sends a multi-part email if both are present.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BodyParts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html_body: Option<String>,
+}
+
+impl From<BodyParts> for Body {
+    fn from(parts: BodyParts) -> Self {
+        match (parts.html_body, parts.text_body) {
+            (Some(html), Some(text)) => Body::HtmlAndText { html, text },
+            (Some(html), None) => Body::Html(html),
+            (None, text) => Body::Text(text.unwrap_or_default()),
+        }
+    }
+}
+
+impl From<Body> for BodyParts {
+    fn from(body: Body) -> Self {
+        match body {
+            Body::Text(text) => BodyParts {
+                text_body: Some(text),
+                html_body: None,
+            },
+            Body::Html(html) => BodyParts {
+                text_body: None,
+                html_body: Some(html),
+            },
+            Body::HtmlAndText { html, text } => BodyParts {
+                text_body: Some(text),
+                html_body: Some(html),
+            },
+        }
+    }
 }
 
 impl Default for Body {
@@ -166,3 +216,51 @@ impl Default for Body {
         Body::Text("".into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn multi_part_body_serializes_both_parts() {
+        let request = SendEmailRequest {
+            from: "from@example.com".to_string(),
+            to: "to@example.com".to_string(),
+            body: Body::HtmlAndText {
+                html: "<p>hello</p>".to_string(),
+                text: "hello".to_string(),
+            },
+            ..SendEmailRequest::default()
+        };
+
+        assert_eq!(
+            json!({
+                "From": "from@example.com",
+                "To": "to@example.com",
+                "HtmlBody": "<p>hello</p>",
+                "TextBody": "hello",
+            }),
+            serde_json::to_value(&request).unwrap()
+        );
+    }
+
+    #[test]
+    fn text_body_serializes_text_part_only() {
+        let request = SendEmailRequest {
+            from: "from@example.com".to_string(),
+            to: "to@example.com".to_string(),
+            body: Body::Text("hello".to_string()),
+            ..SendEmailRequest::default()
+        };
+
+        assert_eq!(
+            json!({
+                "From": "from@example.com",
+                "To": "to@example.com",
+                "TextBody": "hello",
+            }),
+            serde_json::to_value(&request).unwrap()
+        );
+    }
+}
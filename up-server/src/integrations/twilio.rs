@@ -0,0 +1,165 @@
+use miette::Diagnostic;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::Level;
+
+use crate::mask;
+
+pub type Result<T> = miette::Result<T, TwilioError>;
+
+const TWILIO_ACCOUNT_SID_ENV: &str = "TWILIO_ACCOUNT_SID";
+const TWILIO_AUTH_TOKEN_ENV: &str = "TWILIO_AUTH_TOKEN";
+const TWILIO_FROM_NUMBER_ENV: &str = "TWILIO_FROM_NUMBER";
+const TWILIO_API_BASE_URL_ENV: &str = "TWILIO_API_BASE_URL";
+const TWILIO_API_BASE_URL: &str = "https://api.twilio.com";
+
+/// Client for the Twilio Programmable Messaging API, or any API compatible with it.
+#[derive(Clone)]
+pub struct TwilioClient {
+    account_sid: String,
+    auth_token: String,
+    from_number: String,
+    api_base_url: url::Url,
+    client: reqwest::Client,
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum TwilioError {
+    #[error("expected Twilio credentials in TWILIO_ACCOUNT_SID, TWILIO_AUTH_TOKEN and TWILIO_FROM_NUMBER environment variables")]
+    #[diagnostic(code(up::config::invalid))]
+    MissingCredentials,
+    #[error("failed to create HTTP client: {0}")]
+    ClientBuildError(reqwest::Error),
+    #[error("failed to create HTTP request: {0}")]
+    RequestBuildError(reqwest::Error),
+    #[error("failed to execute HTTP request: {0}")]
+    RequestError(reqwest::Error),
+    #[error("failed to parse API response: {0}")]
+    ResponseParseError(serde_json::Error),
+    #[error("failed to parse API URL: {0}")]
+    UrlParsingError(url::ParseError),
+    #[error("failed to send SMS using Twilio: {1} ({0})")]
+    ApiError(i32, String),
+    #[error("HTTP error sending SMS using Twilio: {1} ({0})")]
+    ApiHttpError(StatusCode, String),
+}
+
+impl TwilioClient {
+    pub fn new() -> Result<Self> {
+        let account_sid =
+            std::env::var(TWILIO_ACCOUNT_SID_ENV).map_err(|_| TwilioError::MissingCredentials)?;
+        let auth_token =
+            std::env::var(TWILIO_AUTH_TOKEN_ENV).map_err(|_| TwilioError::MissingCredentials)?;
+        let from_number =
+            std::env::var(TWILIO_FROM_NUMBER_ENV).map_err(|_| TwilioError::MissingCredentials)?;
+        let api_base_url = std::env::var(TWILIO_API_BASE_URL_ENV)
+            .unwrap_or_else(|_| TWILIO_API_BASE_URL.to_string());
+
+        Self::with_credentials(&account_sid, &auth_token, &from_number, &api_base_url)
+    }
+
+    pub fn with_credentials(
+        account_sid: &str,
+        auth_token: &str,
+        from_number: &str,
+        api_base_url: &str,
+    ) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(TwilioError::ClientBuildError)?;
+        let api_base_url: url::Url = api_base_url.parse().map_err(TwilioError::UrlParsingError)?;
+
+        Ok(Self {
+            account_sid: account_sid.to_string(),
+            auth_token: auth_token.to_string(),
+            from_number: from_number.to_string(),
+            api_base_url,
+            client,
+        })
+    }
+
+    pub async fn send_sms(&self, to: &str, body: &str) -> Result<SendSmsResponse> {
+        let request = SendSmsRequest {
+            to: to.to_string(),
+            from: self.from_number.clone(),
+            body: body.to_string(),
+        };
+
+        let req = self
+            .client
+            .request(
+                Method::POST,
+                self.api_base_url
+                    .join(&format!(
+                        "/2010-04-01/Accounts/{}/Messages.json",
+                        self.account_sid
+                    ))
+                    .map_err(TwilioError::UrlParsingError)?,
+            )
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&request)
+            .build()
+            .map_err(TwilioError::RequestBuildError)?;
+
+        let resp = self
+            .client
+            .execute(req)
+            .await
+            .map_err(TwilioError::RequestError)?;
+
+        let status = resp.status();
+
+        let response_body_bytes = resp.bytes().await.map_err(TwilioError::RequestError)?;
+
+        if !status.is_success() {
+            return Err(TwilioError::ApiHttpError(
+                status,
+                String::from_utf8_lossy(&response_body_bytes).to_string(),
+            ));
+        }
+
+        let mut api_response: SendSmsResponse = serde_json::from_slice(&response_body_bytes)
+            .map_err(TwilioError::ResponseParseError)?;
+        api_response.http_status = Some(status.as_u16());
+
+        if let Some(error_code) = api_response.error_code {
+            return Err(TwilioError::ApiError(
+                error_code,
+                api_response.error_message.unwrap_or_default(),
+            ));
+        }
+
+        if tracing::event_enabled!(Level::TRACE) {
+            tracing::info!(
+                to = mask::phone_number(to),
+                sid = api_response.sid,
+                "sms sent"
+            );
+        }
+
+        Ok(api_response)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SendSmsRequest {
+    pub to: String,
+    pub from: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendSmsResponse {
+    pub sid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+    /// HTTP status of the API response, not part of the response body.
+    #[serde(skip)]
+    pub http_status: Option<u16>,
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use miette::Diagnostic;
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use thiserror::Error;
+
+pub type Result<T> = miette::Result<T, WebhookError>;
+
+/// How long a webhook has to respond, so a slow endpoint can't hold up alert delivery.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client calling the webhooks that users configure to receive alerts.
+#[derive(Clone)]
+pub struct WebhookClient {
+    client: reqwest::Client,
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum WebhookError {
+    #[error("failed to create HTTP request: {0}")]
+    RequestBuildError(reqwest::Error),
+    #[error("failed to execute HTTP request: {0}")]
+    RequestError(reqwest::Error),
+    #[error("HTTP error calling webhook: {1} ({0})")]
+    ApiHttpError(StatusCode, String),
+}
+
+impl Default for WebhookClient {
+    /// Creates a client, panicking if the TLS backend can't be initialized like
+    /// [`reqwest::Client::new`] does.
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .expect("failed to create webhook HTTP client");
+
+        Self { client }
+    }
+}
+
+impl WebhookClient {
+    /// Posts `body` as JSON to `url`, returning the HTTP status of the response.
+    pub async fn post<T: Serialize>(&self, url: &str, body: &T) -> Result<u16> {
+        let req = self
+            .client
+            .request(Method::POST, url)
+            .json(body)
+            .build()
+            .map_err(WebhookError::RequestBuildError)?;
+
+        let resp = self
+            .client
+            .execute(req)
+            .await
+            .map_err(WebhookError::RequestError)?;
+
+        let status = resp.status();
+
+        if !status.is_success() {
+            let response_body_bytes = resp.bytes().await.map_err(WebhookError::RequestError)?;
+            return Err(WebhookError::ApiHttpError(
+                status,
+                String::from_utf8_lossy(&response_body_bytes).to_string(),
+            ));
+        }
+
+        Ok(status.as_u16())
+    }
+}
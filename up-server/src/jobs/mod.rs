@@ -1,5 +1,9 @@
 mod enqueue_alerts;
+mod escalate_incidents;
+mod reload_keys;
 mod send_alerts;
 
 pub use enqueue_alerts::EnqueueAlerts;
+pub use escalate_incidents::EscalateIncidents;
+pub use reload_keys::ReloadKeys;
 pub use send_alerts::SendAlerts;
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::oneshot,
+    task::JoinHandle,
+    time::{self, Instant},
+};
+
+use crate::keys::KeyStore;
+
+pub struct ReloadKeys {
+    keys: KeyStore,
+    interval: Option<Duration>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl ReloadKeys {
+    /// Creates a job reloading keys on SIGHUP and, unless `interval` is `None`, every
+    /// `interval`.
+    pub fn with_key_store(keys: KeyStore, interval: Option<Duration>) -> Self {
+        Self {
+            keys,
+            interval,
+            shutdown_tx: None,
+            join_handle: None,
+        }
+    }
+
+    pub async fn spawn(&mut self) {
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+        let mut hangup = signal(SignalKind::hangup()).expect("failed to handle SIGHUP signal");
+        let reload_on_interval = self.interval.is_some();
+        let period = self.interval.unwrap_or(Duration::from_secs(1));
+        let mut reload_interval = time::interval_at(Instant::now() + period, period);
+        let keys = self.keys.clone();
+
+        self.shutdown_tx = Some(shutdown_tx);
+        self.join_handle = Some(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = reload_interval.tick(), if reload_on_interval => {
+                        reload_keys(&keys).await
+                    },
+                    _ = hangup.recv() => {
+                        tracing::info!("SIGHUP received, reloading keys");
+                        reload_keys(&keys).await
+                    },
+                    _msg = &mut shutdown_rx => {
+                        break;
+                    }
+                }
+            }
+        }));
+    }
+
+    pub async fn stop(&mut self) {
+        if let Some(handle) = self.join_handle.take() {
+            if let Some(tx) = self.shutdown_tx.take() {
+                if tx.send(()).is_err() {
+                    tracing::error!("failed to send ReloadKeys job shutdown signal");
+                }
+            }
+            if let Err(e) = handle.await {
+                tracing::error!("failed to wait for ReloadKeys job to terminate: {}", e);
+            }
+        }
+
+        tracing::debug!("finished ReloadKeys job");
+    }
+}
+
+async fn reload_keys(keys: &KeyStore) {
+    if let Err(e) = keys.reload().await {
+        tracing::error!("failed to reload keys, keeping current keys: {:?}", e);
+    }
+}
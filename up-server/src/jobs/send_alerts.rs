@@ -1,23 +1,46 @@
-use std::time::Duration;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use tokio::{sync::oneshot, task::JoinHandle, time};
+use chrono::Utc;
+use tokio::{
+    sync::{oneshot, Semaphore},
+    task::JoinHandle,
+    time,
+};
 
-use crate::{notifier::Notifier, repository::Repository};
+use crate::{
+    notifier::Notifier,
+    repository::{
+        dto::{CheckSeverity, NotificationAlert},
+        Repository,
+    },
+};
 
 const POLL_INTERVAL: u64 = 5;
+const BATCH_SIZE: i64 = 10;
 
 pub struct SendAlerts {
     repository: Repository,
     notifier: Notifier,
+    concurrency: usize,
+    claim_timeout: Duration,
     shutdown_tx: Option<oneshot::Sender<()>>,
     join_handle: Option<JoinHandle<()>>,
 }
 
 impl SendAlerts {
-    pub fn with_repository(repository: Repository, notifier: Notifier) -> Self {
+    /// Creates the job, delivering up to `concurrency` alerts at a time. Alerts claimed
+    /// for longer than `claim_timeout` without finishing are claimed again.
+    pub fn with_repository(
+        repository: Repository,
+        notifier: Notifier,
+        concurrency: usize,
+        claim_timeout: Duration,
+    ) -> Self {
         Self {
             repository,
             notifier,
+            concurrency: concurrency.max(1),
+            claim_timeout,
             shutdown_tx: None,
             join_handle: None,
         }
@@ -28,13 +51,16 @@ impl SendAlerts {
         let mut poll_interval = time::interval(Duration::from_secs(POLL_INTERVAL));
         let repository = self.repository.clone();
         let notifier = self.notifier.clone();
+        let concurrency = self.concurrency;
+        let claim_timeout = chrono::Duration::from_std(self.claim_timeout)
+            .unwrap_or_else(|_| chrono::Duration::max_value());
 
         self.shutdown_tx = Some(shutdown_tx);
         self.join_handle = Some(tokio::spawn(async move {
             loop {
                 tokio::select! {
                     _ = poll_interval.tick() => {
-                        send_alerts(&repository, &notifier).await
+                        send_alerts(&repository, &notifier, concurrency, claim_timeout).await
                     },
                     _msg = &mut shutdown_rx => {
                         break;
@@ -60,17 +86,165 @@ impl SendAlerts {
     }
 }
 
-async fn send_alerts(repository: &Repository, notifier: &Notifier) {
-    match repository.notification().send_alert_batch(notifier).await {
-        Ok(delivered_alerts) => {
-            for alert in delivered_alerts {
+async fn send_alerts(
+    repository: &Repository,
+    notifier: &Notifier,
+    concurrency: usize,
+    claim_timeout: chrono::Duration,
+) {
+    let stale_before = Utc::now().naive_utc() - claim_timeout;
+    let alerts = match repository
+        .notification()
+        .claim_alert_batch(BATCH_SIZE, stale_before)
+        .await
+    {
+        Ok(alerts) => alerts,
+        Err(e) => {
+            tracing::error!("failed to claim alert batch: {:?}", e);
+            return;
+        }
+    };
+
+    let alerts = hold_during_quiet_hours(repository, alerts).await;
+
+    // Each message is delivered in a task of its own, so a delivery that panics only
+    // loses its own alerts, which are retried once their claims are stale.
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut deliveries = Vec::new();
+    for alerts in group_digests(alerts) {
+        let permit = permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("alert delivery semaphore closed");
+        let repository = repository.clone();
+        let notifier = notifier.clone();
+        deliveries.push(tokio::spawn(async move {
+            send_alerts_message(&repository, &notifier, alerts).await;
+            drop(permit);
+        }));
+    }
+
+    for delivery in deliveries {
+        if let Err(e) = delivery.await {
+            tracing::error!(
+                "alert delivery failed, its alerts will be retried once their claims are stale: {}",
+                e
+            );
+        }
+    }
+}
+
+/// Holds alerts for low severity checks whose recipient is in their quiet hours until the
+/// quiet hours end, and returns the alerts to deliver now.
+async fn hold_during_quiet_hours(
+    repository: &Repository,
+    alerts: Vec<NotificationAlert>,
+) -> Vec<NotificationAlert> {
+    let now = Utc::now();
+    let mut deliver = Vec::with_capacity(alerts.len());
+
+    for alert in alerts {
+        if alert.check_severity != CheckSeverity::Low {
+            deliver.push(alert);
+            continue;
+        }
+
+        let held_until = match repository
+            .preferences()
+            .read_quiet_hours_for_alert(&alert)
+            .await
+        {
+            Ok(quiet_hours) => quiet_hours.and_then(|q| q.end_after(now)),
+            Err(e) => {
+                tracing::error!(
+                    alert_id = alert.id,
+                    "failed to read quiet hours of alert recipient, delivering it anyway: {:?}",
+                    e
+                );
+                None
+            }
+        };
+
+        match held_until {
+            Some(until) => {
+                if let Err(e) = repository
+                    .notification()
+                    .hold_alert(&alert, until.naive_utc())
+                    .await
+                {
+                    tracing::error!(
+                        alert_id = alert.id,
+                        "failed to hold alert, it will be retried once its claim is stale: {:?}",
+                        e
+                    );
+                }
+            }
+            None => deliver.push(alert),
+        }
+    }
+
+    deliver
+}
+
+/// Groups alerts of notifications with a digest window, and alerts held during quiet
+/// hours, so that each group is sent as one message. Every other alert is sent on its own.
+fn group_digests(alerts: Vec<NotificationAlert>) -> Vec<Vec<NotificationAlert>> {
+    let mut messages: Vec<Vec<NotificationAlert>> = Vec::new();
+    let mut digests: HashMap<i64, usize> = HashMap::new();
+
+    for alert in alerts {
+        if alert.digest_window_seconds.is_none() && !alert.held {
+            messages.push(vec![alert]);
+            continue;
+        }
+        match digests.get(&alert.notification_id) {
+            Some(&index) => messages[index].push(alert),
+            None => {
+                digests.insert(alert.notification_id, messages.len());
+                messages.push(vec![alert]);
+            }
+        }
+    }
+
+    messages
+}
+
+async fn send_alerts_message(
+    repository: &Repository,
+    notifier: &Notifier,
+    alerts: Vec<NotificationAlert>,
+) {
+    let result = notifier.send_digest(&alerts).await;
+
+    for alert in alerts.iter() {
+        let recorded = match &result {
+            Ok(delivery) => {
                 tracing::debug!(
                     check_uuid = alert.check_uuid.to_string(),
                     alert_type = alert.notification_type.to_string(),
                     "alert delivered successfully",
                 );
+                repository
+                    .notification()
+                    .record_alert_delivered(alert, delivery)
+                    .await
             }
+            Err(e) => {
+                tracing::error!("failed to send alert: {:?}", e);
+                repository
+                    .notification()
+                    .record_alert_failed(alert, e)
+                    .await
+            }
+        };
+
+        if let Err(e) = recorded {
+            tracing::error!(
+                alert_id = alert.id,
+                "failed to record alert delivery outcome, it will be retried once its claim is stale: {:?}",
+                e
+            );
         }
-        Err(e) => tracing::error!("failed to send alert batch: {:?}", e),
     }
 }
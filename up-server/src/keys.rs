@@ -0,0 +1,390 @@
+use std::sync::{Arc, RwLock};
+
+use camino::Utf8PathBuf;
+use miette::Diagnostic;
+use thiserror::Error;
+use up_core::{
+    jwks::Jwks,
+    jwt::{self, ACKNOWLEDGEMENT_AUDIENCE, DEFAULT_AUDIENCE, DEFAULT_ISSUER},
+};
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum KeyError {
+    #[error("failed to read JWKS from {path}")]
+    #[diagnostic(code(up::error::keys))]
+    ReadFailed {
+        path: Utf8PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to fetch JWKS from {url}")]
+    #[diagnostic(code(up::error::keys))]
+    FetchFailed {
+        url: url::Url,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("JWKS is invalid")]
+    #[diagnostic(code(up::error::keys))]
+    Invalid(#[from] up_core::Error),
+    #[error("JWKS has no keys, no JWT could be verified")]
+    #[diagnostic(code(up::error::keys))]
+    NoKeys,
+    #[error("signing key {0} is not in JWKS, JWTs signed with it could not be verified")]
+    #[diagnostic(code(up::error::keys))]
+    SigningKeyNotInJwks(String),
+}
+
+/// Where the JWKS that JWTs are verified against is read from.
+#[derive(Clone, Debug)]
+pub enum KeySource {
+    /// A JWKS fixed at startup, e.g. from the `JWKS` environment variable.
+    Static(String),
+    File(Utf8PathBuf),
+    Url(url::Url),
+}
+
+/// Where the private key that JWTs are signed with is read from, in PEM format.
+#[derive(Clone, Debug)]
+pub enum SigningKeySource {
+    /// A key fixed at startup, e.g. from the `SERVER_CERTIFICATE` environment variable.
+    Static(Vec<u8>),
+    File(Utf8PathBuf),
+}
+
+/// The keys in use at one point in time, replaced as a whole on reload so that JWTs
+/// are never signed with a key that the JWKS they are verified against lacks.
+struct Keys {
+    verifier: Arc<jwt::Verifier>,
+    token_generator: Option<Arc<jwt::Generator>>,
+    acknowledgement_generator: Option<Arc<jwt::Generator>>,
+}
+
+/// The public keys JWTs are verified against and the private key they are signed
+/// with, which can be reloaded from their sources while the server is running.
+/// Rotating a key means publishing the new key alongside the old one until JWTs signed
+/// with the old key have expired, which a JWKS with several keys supports as JWTs are
+/// verified against the key matching their key ID.
+#[derive(Clone)]
+pub struct KeyStore {
+    source: Arc<KeySource>,
+    signing_key_source: Arc<Option<SigningKeySource>>,
+    keys: Arc<RwLock<Arc<Keys>>>,
+    client: reqwest::Client,
+}
+
+impl KeyStore {
+    /// Creates a store that reads keys from `source`, and the key to sign JWTs with from
+    /// `signing_key_source` if any, failing if they can't be read rather than starting
+    /// a server no one can log in to.
+    pub async fn load(
+        source: KeySource,
+        signing_key_source: Option<SigningKeySource>,
+    ) -> Result<Self, KeyError> {
+        let client = reqwest::Client::new();
+        let keys = read_keys(&source, signing_key_source.as_ref(), &client).await?;
+        warn_if_signing_key_not_in_jwks(&keys);
+
+        Ok(Self {
+            source: Arc::new(source),
+            signing_key_source: Arc::new(signing_key_source),
+            keys: Arc::new(RwLock::new(Arc::new(keys))),
+            client,
+        })
+    }
+
+    /// Creates a store with keys that never change.
+    pub fn from_jwks(jwks: &str, signing_key: Option<&[u8]>) -> Result<Self, KeyError> {
+        let keys = parse_keys(jwks, signing_key)?;
+        warn_if_signing_key_not_in_jwks(&keys);
+
+        Ok(Self {
+            source: Arc::new(KeySource::Static(jwks.to_string())),
+            signing_key_source: Arc::new(
+                signing_key.map(|pem| SigningKeySource::Static(pem.to_vec())),
+            ),
+            keys: Arc::new(RwLock::new(Arc::new(keys))),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Whether reloading can change the keys, i.e. they are not fixed at startup.
+    pub fn is_reloadable(&self) -> bool {
+        !matches!(*self.source, KeySource::Static(_))
+            || matches!(*self.signing_key_source, Some(SigningKeySource::File(_)))
+    }
+
+    fn current(&self) -> Arc<Keys> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// Returns the verifier for the current keys. Reloading doesn't affect verifiers
+    /// already returned, so a JWT is verified against the same keys throughout.
+    pub fn verifier(&self) -> Arc<jwt::Verifier> {
+        self.current().verifier.clone()
+    }
+
+    /// Returns the generator for the JWTs users log in with, or `None` if there is no
+    /// key to sign them with.
+    pub fn token_generator(&self) -> Option<Arc<jwt::Generator>> {
+        self.current().token_generator.clone()
+    }
+
+    /// Returns the generator for the JWTs in acknowledgement links, or `None` if there
+    /// is no key to sign them with.
+    pub fn acknowledgement_generator(&self) -> Option<Arc<jwt::Generator>> {
+        self.current().acknowledgement_generator.clone()
+    }
+
+    /// Returns the current public keys.
+    pub fn jwks(&self) -> Jwks {
+        self.verifier().jwks().clone()
+    }
+
+    pub fn key_ids(&self) -> Vec<String> {
+        self.verifier()
+            .key_ids()
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Reads the keys from their sources again, replacing the current keys only if the
+    /// new ones are valid and the signing key is in the JWKS, so a botched rotation
+    /// doesn't lock everyone out.
+    pub async fn reload(&self) -> Result<(), KeyError> {
+        let keys = read_keys(
+            &self.source,
+            self.signing_key_source.as_ref().as_ref(),
+            &self.client,
+        )
+        .await?;
+        if let Some(key_id) = signing_key_not_in_jwks(&keys) {
+            return Err(KeyError::SigningKeyNotInJwks(key_id.to_string()));
+        }
+
+        let key_ids = keys.verifier.key_ids().join(", ");
+        let signing_key_id = keys
+            .token_generator
+            .as_ref()
+            .map(|generator| generator.key_id().to_string());
+        *self.keys.write().unwrap() = Arc::new(keys);
+
+        tracing::debug!(
+            key_ids = key_ids,
+            signing_key_id = signing_key_id,
+            "reloaded keys to sign and verify JWTs"
+        );
+
+        Ok(())
+    }
+}
+
+/// The ID of the signing key, if there is one and the JWKS lacks it.
+fn signing_key_not_in_jwks(keys: &Keys) -> Option<&str> {
+    let key_id = keys.token_generator.as_ref()?.key_id();
+    if keys.verifier.key_ids().contains(&key_id) {
+        None
+    } else {
+        Some(key_id)
+    }
+}
+
+/// At startup there are no previous keys to keep, so a signing key missing from the
+/// JWKS is only warned about.
+fn warn_if_signing_key_not_in_jwks(keys: &Keys) {
+    if let Some(key_id) = signing_key_not_in_jwks(keys) {
+        tracing::warn!(
+            key_id = key_id,
+            "signing key is not in JWKS, issued JWTs and acknowledgement links will be rejected"
+        );
+    }
+}
+
+async fn read_keys(
+    source: &KeySource,
+    signing_key_source: Option<&SigningKeySource>,
+    client: &reqwest::Client,
+) -> Result<Keys, KeyError> {
+    let jwks = read_jwks(source, client).await?;
+    let signing_key = match signing_key_source {
+        None => None,
+        Some(SigningKeySource::Static(pem)) => Some(pem.clone()),
+        Some(SigningKeySource::File(path)) => Some(tokio::fs::read(path).await.map_err(
+            |source| KeyError::ReadFailed {
+                path: path.clone(),
+                source,
+            },
+        )?),
+    };
+
+    parse_keys(&jwks, signing_key.as_deref())
+}
+
+async fn read_jwks(source: &KeySource, client: &reqwest::Client) -> Result<String, KeyError> {
+    let jwks = match source {
+        KeySource::Static(jwks) => jwks.clone(),
+        KeySource::File(path) => {
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|source| KeyError::ReadFailed {
+                    path: path.clone(),
+                    source,
+                })?
+        }
+        KeySource::Url(url) => {
+            fetch_jwks(client, url)
+                .await
+                .map_err(|source| KeyError::FetchFailed {
+                    url: url.clone(),
+                    source,
+                })?
+        }
+    };
+
+    Ok(jwks)
+}
+
+async fn fetch_jwks(client: &reqwest::Client, url: &url::Url) -> Result<String, reqwest::Error> {
+    client
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await
+}
+
+fn parse_keys(jwks: &str, signing_key: Option<&[u8]>) -> Result<Keys, KeyError> {
+    let verifier =
+        jwt::Verifier::new_from_jwks(jwks, Some(DEFAULT_ISSUER), Some(DEFAULT_AUDIENCE))?;
+    if verifier.jwks().is_empty() {
+        return Err(KeyError::NoKeys);
+    }
+
+    let generator = |audience| {
+        signing_key
+            .map(|pem| jwt::Generator::new_from_pem(pem, DEFAULT_ISSUER, audience))
+            .transpose()
+            .map(|generator| generator.map(Arc::new))
+    };
+
+    Ok(Keys {
+        verifier: Arc::new(verifier),
+        token_generator: generator(DEFAULT_AUDIENCE)?,
+        acknowledgement_generator: generator(ACKNOWLEDGEMENT_AUDIENCE)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use openssl::rsa::Rsa;
+
+    use super::*;
+    use crate::tokens::TokenIssuer;
+
+    fn generate_pem() -> Vec<u8> {
+        let rsa = Rsa::generate(2048).unwrap();
+        let mut pem = rsa.private_key_to_pem().unwrap();
+        pem.extend(rsa.public_key_to_pem().unwrap());
+        pem
+    }
+
+    fn generate_jwks() -> Jwks {
+        Jwks::from_pem(&generate_pem()).unwrap()
+    }
+
+    fn temp_path(extension: &str) -> Utf8PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("jwks-{}.{}", uuid::Uuid::new_v4(), extension));
+        Utf8PathBuf::from_path_buf(path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reload_picks_up_rotated_keys() {
+        let path = temp_path("json");
+        let key_path = temp_path("pem");
+        let old_pem = generate_pem();
+        let old_jwks = Jwks::from_pem(&old_pem).unwrap();
+        std::fs::write(&path, old_jwks.to_string()).unwrap();
+        std::fs::write(&key_path, &old_pem).unwrap();
+        let keys = KeyStore::load(
+            KeySource::File(path.clone()),
+            Some(SigningKeySource::File(key_path.clone())),
+        )
+        .await
+        .unwrap();
+        let tokens = TokenIssuer::new(keys.clone(), 15);
+        let verifier = keys.verifier();
+
+        let new_pem = generate_pem();
+        let new_jwks = Jwks::from_pem(&new_pem).unwrap();
+        std::fs::write(&path, new_jwks.to_string()).unwrap();
+        std::fs::write(&key_path, &new_pem).unwrap();
+        keys.reload().await.unwrap();
+
+        assert_eq!(new_jwks.key_ids(), keys.verifier().key_ids());
+        assert_eq!(old_jwks.key_ids(), verifier.key_ids());
+        assert_eq!(
+            new_jwks.key_ids(),
+            vec![keys.acknowledgement_generator().unwrap().key_id()]
+        );
+
+        // Tokens issued after the reload carry the new key ID.
+        let token = tokens.issue("subject").unwrap().unwrap().token;
+        assert!(keys.verifier().verify(&token).is_ok());
+        assert!(verifier.verify(&token).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&key_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_reload_keeps_keys() {
+        let path = temp_path("json");
+        let jwks = generate_jwks();
+        std::fs::write(&path, jwks.to_string()).unwrap();
+        let keys = KeyStore::load(KeySource::File(path.clone()), None)
+            .await
+            .unwrap();
+
+        for invalid in ["{\"keys\":[]}", "not JSON"] {
+            std::fs::write(&path, invalid).unwrap();
+            assert!(keys.reload().await.is_err());
+            assert_eq!(jwks.key_ids(), keys.verifier().key_ids());
+        }
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(keys.reload().await.is_err());
+        assert_eq!(jwks.key_ids(), keys.verifier().key_ids());
+    }
+
+    #[tokio::test]
+    async fn signing_key_missing_from_jwks_is_not_reloaded() {
+        let path = temp_path("json");
+        let key_path = temp_path("pem");
+        let pem = generate_pem();
+        let jwks = Jwks::from_pem(&pem).unwrap();
+        std::fs::write(&path, jwks.to_string()).unwrap();
+        std::fs::write(&key_path, &pem).unwrap();
+        let keys = KeyStore::load(
+            KeySource::File(path.clone()),
+            Some(SigningKeySource::File(key_path.clone())),
+        )
+        .await
+        .unwrap();
+
+        std::fs::write(&key_path, generate_pem()).unwrap();
+        assert!(matches!(
+            keys.reload().await,
+            Err(KeyError::SigningKeyNotInJwks(_))
+        ));
+        assert_eq!(
+            jwks.key_ids(),
+            vec![keys.token_generator().unwrap().key_id()]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&key_path).unwrap();
+    }
+}
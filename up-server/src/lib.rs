@@ -1,10 +1,18 @@
+pub mod acknowledgement;
 pub mod api;
 pub mod app;
 pub mod auth;
+pub mod backoff;
 pub mod database;
 pub mod integrations;
 pub mod jobs;
+pub mod keys;
 pub mod mask;
 pub mod notifier;
+pub mod oidc;
+pub mod password;
+pub mod quiet_hours;
 pub mod repository;
 pub mod shortid;
+pub mod templates;
+pub mod tokens;
@@ -11,6 +11,16 @@ pub fn ping_key(key: &str) -> String {
     }
 }
 
+/// Mask phone number suitable for use in logs.
+pub fn phone_number(number: &str) -> String {
+    const DEFAULT_MASK: &str = "********";
+    if number.len() < 6 {
+        DEFAULT_MASK.to_string()
+    } else {
+        format!("{}{}", DEFAULT_MASK, &number[(number.len() - 2)..])
+    }
+}
+
 /// Mask email address suitable for use in logs.
 pub fn email(address: &str) -> String {
     const DEFAULT_MASK: &str = "****@******";
@@ -55,6 +65,14 @@ mod test {
         assert_eq!("bbbb************", ping_key("bbbba"));
     }
 
+    #[test]
+    fn phone_number_masking() {
+        assert_eq!("********", phone_number(""));
+        assert_eq!("********", phone_number("12345"));
+        assert_eq!("********56", phone_number("123456"));
+        assert_eq!("********67", phone_number("+15551234567"));
+    }
+
     #[test]
     fn email_masking() {
         assert_eq!("x****@******zz", email("x@y.zz"));
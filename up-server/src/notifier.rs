@@ -1,17 +1,186 @@
 #![allow(dead_code)]
 
 use crate::integrations::postmark::{Body, PostmarkClient, PostmarkError, SendEmailRequest};
+use crate::integrations::twilio::{TwilioClient, TwilioError};
+use crate::integrations::webhook::{WebhookClient, WebhookError};
 use chrono::{TimeZone, Utc};
 use miette::Diagnostic;
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::repository::dto::NotificationType;
-use crate::repository::{dto::NotificationAlert, Repository};
+use crate::acknowledgement::AcknowledgementLinks;
+use crate::mask;
+use crate::repository::dto::{
+    CheckStatus, Invitation, MagicLink, NotificationType, OnCallUser, PasswordReset, PeriodUnits,
+    ScheduleType,
+};
+use crate::repository::{dto::NotificationAlert, Repository, RepositoryError};
+use crate::shortid::ShortId;
+use crate::templates::{
+    TemplateError, Templates, ALERT_HTML_TEMPLATE, ALERT_SUBJECT_TEMPLATE, ALERT_TEXT_TEMPLATE,
+    DIGEST_HTML_TEMPLATE, DIGEST_SUBJECT_TEMPLATE, DIGEST_TEXT_TEMPLATE, INVITATION_HTML_TEMPLATE,
+    INVITATION_SUBJECT_TEMPLATE, INVITATION_TEXT_TEMPLATE, MAGIC_LINK_HTML_TEMPLATE,
+    MAGIC_LINK_SUBJECT_TEMPLATE, MAGIC_LINK_TEXT_TEMPLATE, PASSWORD_RESET_HTML_TEMPLATE,
+    PASSWORD_RESET_SUBJECT_TEMPLATE, PASSWORD_RESET_TEXT_TEMPLATE,
+};
 
 #[derive(Clone)]
 pub struct Notifier {
     repository: Repository,
     postmark_client: PostmarkClient,
+    twilio_client: Option<TwilioClient>,
+    webhook_client: WebhookClient,
+    templates: Templates,
+    branding: Branding,
+    sms_rate_limit_per_hour: u32,
+    acknowledgements: AcknowledgementLinks,
+}
+
+/// Sender address and branding used in notifications. The server-wide values
+/// can be overridden per account.
+#[derive(Clone, Debug)]
+pub struct Branding {
+    pub email_from: String,
+    pub email_reply_to: Option<String>,
+    pub product_name: String,
+    pub base_url: url::Url,
+}
+
+impl Branding {
+    /// Returns the branding to use for an alert, applying any overrides configured
+    /// for the account the alert belongs to.
+    pub fn for_alert(&self, alert: &NotificationAlert) -> Branding {
+        let base_url = alert
+            .account_base_url
+            .as_deref()
+            .and_then(|url| match url.parse() {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    tracing::warn!(
+                        account_id = alert.account_id,
+                        url = url,
+                        "ignoring malformed account base URL: {}",
+                        e
+                    );
+                    None
+                }
+            })
+            .unwrap_or_else(|| self.base_url.clone());
+
+        Branding {
+            email_from: alert
+                .account_email_from
+                .clone()
+                .unwrap_or_else(|| self.email_from.clone()),
+            email_reply_to: alert
+                .account_email_reply_to
+                .clone()
+                .or_else(|| self.email_reply_to.clone()),
+            product_name: alert
+                .account_product_name
+                .clone()
+                .unwrap_or_else(|| self.product_name.clone()),
+            base_url,
+        }
+    }
+}
+
+/// Data available to alert templates.
+#[derive(Serialize)]
+struct AlertTemplateData<'a> {
+    test: bool,
+    product_name: &'a str,
+    name: &'a str,
+    status: String,
+    project_name: &'a str,
+    check_name: &'a str,
+    last_ping_at: Option<String>,
+    schedule: String,
+    grace_period: String,
+    check_url: String,
+    acknowledge_url: Option<String>,
+}
+
+/// Body posted to webhooks for each alert.
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    test: bool,
+    name: &'a str,
+    status: String,
+    project_id: ShortId,
+    project_name: &'a str,
+    check_id: ShortId,
+    check_name: &'a str,
+    last_ping_at: Option<String>,
+    check_url: String,
+    acknowledge_url: Option<String>,
+}
+
+/// Body posted to webhooks for several alerts of the same notification at once.
+#[derive(Serialize)]
+struct WebhookDigestPayload<'a> {
+    name: &'a str,
+    status: String,
+    count: usize,
+    alerts: Vec<WebhookPayload<'a>>,
+}
+
+/// Data available to digest templates.
+#[derive(Serialize)]
+struct DigestTemplateData<'a> {
+    product_name: &'a str,
+    status: String,
+    mixed: bool,
+    held: bool,
+    count: usize,
+    alerts: Vec<DigestAlertTemplateData<'a>>,
+}
+
+/// Data available for each alert in a digest.
+#[derive(Serialize)]
+struct DigestAlertTemplateData<'a> {
+    status: String,
+    project_name: &'a str,
+    check_name: &'a str,
+    last_ping_at: Option<String>,
+    schedule: String,
+    grace_period: String,
+    check_url: String,
+    acknowledge_url: Option<String>,
+}
+
+/// Data available to password reset templates.
+#[derive(Serialize)]
+struct PasswordResetTemplateData<'a> {
+    product_name: &'a str,
+    reset_url: String,
+    expiry_minutes: i32,
+}
+
+/// Data available to magic link templates.
+#[derive(Serialize)]
+struct MagicLinkTemplateData<'a> {
+    product_name: &'a str,
+    login_url: String,
+    expiry_minutes: i32,
+}
+
+/// Data available to invitation templates.
+#[derive(Serialize)]
+struct InvitationTemplateData<'a> {
+    product_name: &'a str,
+    account_name: &'a str,
+    inviter_email: &'a str,
+    role: String,
+    accept_url: String,
+    expiry_days: i32,
+}
+
+/// What the provider reported for a successfully delivered alert.
+#[derive(Debug, Default)]
+pub struct Delivery {
+    pub http_status: Option<u16>,
+    pub provider_message_id: Option<String>,
 }
 
 type Result<T> = miette::Result<T, NotifierError>;
@@ -21,48 +190,313 @@ pub enum NotifierError {
     #[error("failed to send email notification")]
     #[diagnostic(code(up::error::notification::email))]
     EmailSendError(#[from] PostmarkError),
+    #[error("failed to send SMS notification")]
+    #[diagnostic(code(up::error::notification::sms))]
+    SmsSendError(#[from] TwilioError),
+    #[error("failed to call webhook")]
+    #[diagnostic(code(up::error::notification::webhook))]
+    WebhookError(#[from] WebhookError),
+    #[error("SMS notifications are not configured on this server")]
+    #[diagnostic(code(up::error::notification::sms))]
+    SmsNotConfigured,
+    #[error("SMS rate limit of {0} message(s) per hour exceeded for account")]
+    #[diagnostic(code(up::error::notification::sms))]
+    SmsRateLimitExceeded(u32),
+    #[error("nobody is on call to receive the alert")]
+    #[diagnostic(code(up::error::notification::on_call))]
+    NobodyOnCall,
+    #[error("the user on call has no phone number")]
+    #[diagnostic(code(up::error::notification::on_call))]
+    OnCallPhoneNumberMissing,
+    #[error("the notification has no {0} to send the alert to")]
+    #[diagnostic(code(up::error::notification::destination))]
+    DestinationMissing(&'static str),
+    #[error("failed to render notification template")]
+    #[diagnostic(code(up::error::notification::template))]
+    TemplateError(#[from] TemplateError),
+    #[error("failed to build check URL: {0}")]
+    #[diagnostic(code(up::error::notification::url))]
+    UrlError(#[from] url::ParseError),
+    #[error("failed to query repository")]
+    #[diagnostic(code(up::error::notification::repository))]
+    RepositoryError(#[from] RepositoryError),
+}
+
+impl NotifierError {
+    /// The HTTP status returned by the provider, if the provider rejected the request.
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            Self::EmailSendError(PostmarkError::ApiHttpError(status, _)) => Some(status.as_u16()),
+            Self::SmsSendError(TwilioError::ApiHttpError(status, _)) => Some(status.as_u16()),
+            Self::WebhookError(WebhookError::ApiHttpError(status, _)) => Some(status.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// The error and all of its causes, suitable for storing in the delivery log.
+    pub fn to_message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(e) = source {
+            message.push_str(": ");
+            message.push_str(&e.to_string());
+            source = e.source();
+        }
+        message
+    }
 }
 
 impl Notifier {
-    pub fn new(repository: Repository, postmark_client: PostmarkClient) -> Self {
+    pub fn new(
+        repository: Repository,
+        postmark_client: PostmarkClient,
+        twilio_client: Option<TwilioClient>,
+        templates: Templates,
+        branding: Branding,
+        sms_rate_limit_per_hour: u32,
+        acknowledgements: AcknowledgementLinks,
+    ) -> Self {
         Self {
             repository,
             postmark_client,
+            twilio_client,
+            webhook_client: WebhookClient::default(),
+            templates,
+            branding,
+            sms_rate_limit_per_hour,
+            acknowledgements,
         }
     }
 
-    pub async fn send_alert(&self, alert: &NotificationAlert) -> Result<()> {
+    pub async fn send_alert(&self, alert: &NotificationAlert) -> Result<Delivery> {
         match alert.notification_type {
             NotificationType::Email => self.send_alert_email(alert).await,
             NotificationType::Webhook => self.call_alert_webhook(alert).await,
+            NotificationType::Sms => self.send_alert_sms(alert).await,
         }
     }
 
-    async fn call_alert_webhook(&self, alert: &NotificationAlert) -> Result<()> {
+    /// Sends a single message for several alerts of the same notification.
+    pub async fn send_digest(&self, alerts: &[NotificationAlert]) -> Result<Delivery> {
+        let first = match alerts {
+            [] => return Ok(Delivery::default()),
+            [alert] => return self.send_alert(alert).await,
+            [first, ..] => first,
+        };
+
+        match first.notification_type {
+            NotificationType::Email => self.send_digest_email(alerts).await,
+            NotificationType::Webhook => self.call_digest_webhook(alerts).await,
+            NotificationType::Sms => self.send_digest_sms(alerts).await,
+        }
+    }
+
+    /// Emails a user the link to reset their password with. Password resets are not
+    /// for an account, so they use the server-wide branding.
+    pub async fn send_password_reset(&self, reset: &PasswordReset) -> Result<Delivery> {
+        let mut reset_url = self.branding.base_url.join("reset-password")?;
+        reset_url
+            .query_pairs_mut()
+            .append_pair("token", &reset.token);
+
+        tracing::debug!(email = mask::email(&reset.email), "sending password reset");
+
+        let data = PasswordResetTemplateData {
+            product_name: &self.branding.product_name,
+            reset_url: reset_url.to_string(),
+            expiry_minutes: reset.expiry_minutes,
+        };
+
+        self.send_user_email(
+            &reset.email,
+            PASSWORD_RESET_SUBJECT_TEMPLATE,
+            PASSWORD_RESET_TEXT_TEMPLATE,
+            PASSWORD_RESET_HTML_TEMPLATE,
+            &data,
+        )
+        .await
+    }
+
+    /// Emails a user a link to log in with. Like password resets, magic links are not
+    /// for an account, so they use the server-wide branding.
+    pub async fn send_magic_link(&self, link: &MagicLink) -> Result<Delivery> {
+        let mut login_url = self.branding.base_url.join("magic-link")?;
+        login_url
+            .query_pairs_mut()
+            .append_pair("token", &link.token);
+
+        tracing::debug!(email = mask::email(&link.email), "sending magic link");
+
+        let data = MagicLinkTemplateData {
+            product_name: &self.branding.product_name,
+            login_url: login_url.to_string(),
+            expiry_minutes: link.expiry_minutes,
+        };
+
+        self.send_user_email(
+            &link.email,
+            MAGIC_LINK_SUBJECT_TEMPLATE,
+            MAGIC_LINK_TEXT_TEMPLATE,
+            MAGIC_LINK_HTML_TEMPLATE,
+            &data,
+        )
+        .await
+    }
+
+    /// Emails someone invited to an account the link to accept the invitation with.
+    /// Invitees may not have a user yet, so they get the server-wide branding.
+    pub async fn send_invitation(&self, invitation: &Invitation) -> Result<Delivery> {
+        let mut accept_url = self.branding.base_url.join("accept-invite")?;
+        accept_url
+            .query_pairs_mut()
+            .append_pair("token", &invitation.token);
+
+        tracing::debug!(email = mask::email(&invitation.email), "sending invitation");
+
+        let data = InvitationTemplateData {
+            product_name: &self.branding.product_name,
+            account_name: &invitation.account_name,
+            inviter_email: &invitation.invited_by,
+            role: format!("{:?}", invitation.role).to_lowercase(),
+            accept_url: accept_url.to_string(),
+            expiry_days: invitation.expiry_days,
+        };
+
+        self.send_user_email(
+            &invitation.email,
+            INVITATION_SUBJECT_TEMPLATE,
+            INVITATION_TEXT_TEMPLATE,
+            INVITATION_HTML_TEMPLATE,
+            &data,
+        )
+        .await
+    }
+
+    /// Renders and sends an email to a user with the server-wide branding.
+    async fn send_user_email<T: Serialize>(
+        &self,
+        to: &str,
+        subject_template: &str,
+        text_template: &str,
+        html_template: &str,
+        data: &T,
+    ) -> Result<Delivery> {
+        let rendered =
+            self.templates
+                .render_email(subject_template, text_template, html_template, data)?;
+
+        let email = SendEmailRequest {
+            from: self.branding.email_from.clone(),
+            to: to.to_string(),
+            reply_to: self.branding.email_reply_to.clone(),
+            subject: Some(rendered.subject),
+            body: Body::HtmlAndText {
+                html: rendered.html,
+                text: rendered.text,
+            },
+            ..SendEmailRequest::default()
+        };
+
+        let response = self.postmark_client.send_email(&email).await?;
+
+        Ok(Delivery {
+            http_status: response.http_status,
+            provider_message_id: response.message_id,
+        })
+    }
+
+    async fn call_alert_webhook(&self, alert: &NotificationAlert) -> Result<Delivery> {
         let last_ping_at = alert
             .last_ping_at
             .map(|dt| Utc.from_utc_datetime(&dt))
             .map(|dt| dt.to_string())
             .unwrap_or_else(String::new);
-        let webhook_url = alert.url.as_deref().unwrap();
+        let webhook_url = alert
+            .url
+            .as_deref()
+            .ok_or(NotifierError::DestinationMissing("URL"))?;
+        let branding = self.branding.for_alert(alert);
+        let payload = self.webhook_payload(&branding, alert)?;
 
         tracing::debug!(
             check_uuid = alert.check_uuid.to_string(),
             last_ping_at = last_ping_at,
             url = webhook_url,
+            acknowledge_url = payload.acknowledge_url,
             "sending alert",
         );
 
-        Ok(())
+        let http_status = self.webhook_client.post(webhook_url, &payload).await?;
+
+        Ok(Delivery {
+            http_status: Some(http_status),
+            provider_message_id: None,
+        })
+    }
+
+    async fn call_digest_webhook(&self, alerts: &[NotificationAlert]) -> Result<Delivery> {
+        let first = &alerts[0];
+        let webhook_url = first
+            .url
+            .as_deref()
+            .ok_or(NotifierError::DestinationMissing("URL"))?;
+
+        tracing::debug!(
+            count = alerts.len(),
+            url = webhook_url,
+            "sending alert digest",
+        );
+
+        let branding = self.branding.for_alert(first);
+        let (status, _) = digest_status(alerts);
+
+        let payload = WebhookDigestPayload {
+            name: &first.name,
+            status: status.to_string(),
+            count: alerts.len(),
+            alerts: alerts
+                .iter()
+                .map(|alert| self.webhook_payload(&branding, alert))
+                .collect::<Result<_>>()?,
+        };
+
+        let http_status = self.webhook_client.post(webhook_url, &payload).await?;
+
+        Ok(Delivery {
+            http_status: Some(http_status),
+            provider_message_id: None,
+        })
     }
 
-    async fn send_alert_email(&self, alert: &NotificationAlert) -> Result<()> {
+    fn webhook_payload<'a>(
+        &self,
+        branding: &Branding,
+        alert: &'a NotificationAlert,
+    ) -> Result<WebhookPayload<'a>> {
+        Ok(WebhookPayload {
+            test: alert.test,
+            name: &alert.name,
+            status: alert.check_status.to_string(),
+            project_id: ShortId::from_uuid(&alert.project_uuid),
+            project_name: &alert.project_name,
+            check_id: ShortId::from_uuid(&alert.check_uuid),
+            check_name: &alert.check_name,
+            last_ping_at: alert
+                .last_ping_at
+                .map(|dt| Utc.from_utc_datetime(&dt).to_string()),
+            check_url: check_url(&branding.base_url, alert)?.to_string(),
+            acknowledge_url: self.acknowledge_url(branding, alert),
+        })
+    }
+
+    async fn send_alert_email(&self, alert: &NotificationAlert) -> Result<Delivery> {
         let last_ping_at = alert
             .last_ping_at
             .map(|dt| Utc.from_utc_datetime(&dt))
             .map(|dt| dt.to_string())
             .unwrap_or_else(String::new);
-        let alert_email = alert.email.as_deref().unwrap();
+        let alert_email = self.recipient_email(alert).await?;
+        let alert_email = alert_email.as_str();
 
         tracing::debug!(
             check_uuid = alert.check_uuid.to_string(),
@@ -71,16 +505,290 @@ impl Notifier {
             "sending alert",
         );
 
+        let branding = self.branding.for_alert(alert);
+
+        let data = AlertTemplateData {
+            test: alert.test,
+            product_name: &branding.product_name,
+            name: &alert.name,
+            status: alert.check_status.to_string(),
+            project_name: &alert.project_name,
+            check_name: &alert.check_name,
+            last_ping_at: alert
+                .last_ping_at
+                .map(|dt| Utc.from_utc_datetime(&dt).to_string()),
+            schedule: format_schedule(alert),
+            grace_period: format_period(alert.grace_period, &alert.grace_period_units),
+            check_url: check_url(&branding.base_url, alert)?.to_string(),
+            acknowledge_url: self.acknowledge_url(&branding, alert),
+        };
+
+        let rendered = self.templates.render_email(
+            ALERT_SUBJECT_TEMPLATE,
+            ALERT_TEXT_TEMPLATE,
+            ALERT_HTML_TEMPLATE,
+            &data,
+        )?;
+
+        let email = SendEmailRequest {
+            from: branding.email_from.clone(),
+            to: alert_email.to_string(),
+            reply_to: branding.email_reply_to.clone(),
+            subject: Some(rendered.subject),
+            body: Body::HtmlAndText {
+                html: rendered.html,
+                text: rendered.text,
+            },
+            ..SendEmailRequest::default()
+        };
+
+        let response = self.postmark_client.send_email(&email).await?;
+
+        Ok(Delivery {
+            http_status: response.http_status,
+            provider_message_id: response.message_id,
+        })
+    }
+
+    async fn send_digest_email(&self, alerts: &[NotificationAlert]) -> Result<Delivery> {
+        let first = &alerts[0];
+        let alert_email = self.recipient_email(first).await?;
+        let alert_email = alert_email.as_str();
+
+        tracing::debug!(
+            count = alerts.len(),
+            email = alert_email,
+            "sending alert digest",
+        );
+
+        let branding = self.branding.for_alert(first);
+        let (status, mixed) = digest_status(alerts);
+
+        let data = DigestTemplateData {
+            product_name: &branding.product_name,
+            status: status.to_string(),
+            mixed,
+            held: alerts.iter().any(|a| a.held),
+            count: alerts.len(),
+            alerts: alerts
+                .iter()
+                .map(|alert| {
+                    Ok(DigestAlertTemplateData {
+                        status: alert.check_status.to_string(),
+                        project_name: &alert.project_name,
+                        check_name: &alert.check_name,
+                        last_ping_at: alert
+                            .last_ping_at
+                            .map(|dt| Utc.from_utc_datetime(&dt).to_string()),
+                        schedule: format_schedule(alert),
+                        grace_period: format_period(alert.grace_period, &alert.grace_period_units),
+                        check_url: check_url(&branding.base_url, alert)?.to_string(),
+                        acknowledge_url: self.acknowledge_url(&branding, alert),
+                    })
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        let rendered = self.templates.render_email(
+            DIGEST_SUBJECT_TEMPLATE,
+            DIGEST_TEXT_TEMPLATE,
+            DIGEST_HTML_TEMPLATE,
+            &data,
+        )?;
+
         let email = SendEmailRequest {
-            from: "up.io <no-reply@sector42.io>".to_string(),
+            from: branding.email_from.clone(),
             to: alert_email.to_string(),
-            subject: Some(format!("[DOWN] {}", alert.name)),
-            body: Body::Text(String::from("Sent by up.io")),
+            reply_to: branding.email_reply_to.clone(),
+            subject: Some(rendered.subject),
+            body: Body::HtmlAndText {
+                html: rendered.html,
+                text: rendered.text,
+            },
             ..SendEmailRequest::default()
         };
 
-        self.postmark_client.send_email(&email).await?;
+        let response = self.postmark_client.send_email(&email).await?;
+
+        Ok(Delivery {
+            http_status: response.http_status,
+            provider_message_id: response.message_id,
+        })
+    }
+
+    /// The email address to send an alert to, which is that of whoever is on call if
+    /// the notification routes to an on-call schedule.
+    async fn recipient_email(&self, alert: &NotificationAlert) -> Result<String> {
+        match alert.on_call_schedule_id {
+            Some(schedule_id) => Ok(self.on_call_user(schedule_id).await?.email),
+            None => alert
+                .email
+                .clone()
+                .ok_or(NotifierError::DestinationMissing("email address")),
+        }
+    }
+
+    /// The phone number to send an alert to, which is that of whoever is on call if
+    /// the notification routes to an on-call schedule.
+    async fn recipient_phone_number(&self, alert: &NotificationAlert) -> Result<String> {
+        match alert.on_call_schedule_id {
+            Some(schedule_id) => self
+                .on_call_user(schedule_id)
+                .await?
+                .phone_number
+                .ok_or(NotifierError::OnCallPhoneNumberMissing),
+            None => alert
+                .phone_number
+                .clone()
+                .ok_or(NotifierError::DestinationMissing("phone number")),
+        }
+    }
+
+    /// Resolved when the alert is delivered rather than when it is enqueued, so
+    /// handoffs and overrides apply to alerts that are retried or batched in a digest.
+    async fn on_call_user(&self, schedule_id: i64) -> Result<OnCallUser> {
+        let user = self
+            .repository
+            .on_call_schedule()
+            .read_on_call_by_id(schedule_id)
+            .await?
+            .ok_or(NotifierError::NobodyOnCall)?;
+
+        tracing::debug!(
+            schedule_id = schedule_id,
+            user_uuid = user.uuid.to_string(),
+            overridden = user.overridden,
+            "resolved on-call user",
+        );
+
+        Ok(user)
+    }
+
+    /// Test alerts are not for an incident, so they have nothing to acknowledge.
+    fn acknowledge_url(&self, branding: &Branding, alert: &NotificationAlert) -> Option<String> {
+        if alert.test {
+            return None;
+        }
+        self.acknowledgements.url(&branding.base_url, &alert.uuid)
+    }
+
+    async fn send_digest_sms(&self, alerts: &[NotificationAlert]) -> Result<Delivery> {
+        let (status, mixed) = digest_status(alerts);
+        let names: Vec<String> = alerts
+            .iter()
+            .map(|a| {
+                if mixed {
+                    format!("{} ({})", a.check_name, a.check_status.to_string())
+                } else {
+                    a.check_name.clone()
+                }
+            })
+            .collect();
+        let body = format!(
+            "[{}] {} checks: {}",
+            status.to_string(),
+            alerts.len(),
+            names.join(", ")
+        );
+        self.send_sms(&alerts[0], &body).await
+    }
+
+    async fn send_alert_sms(&self, alert: &NotificationAlert) -> Result<Delivery> {
+        let body = if alert.test {
+            format!("[TEST] [DOWN] {}", alert.name)
+        } else {
+            format!("[DOWN] {}", alert.name)
+        };
+
+        self.send_sms(alert, &body).await
+    }
+
+    async fn send_sms(&self, alert: &NotificationAlert, body: &str) -> Result<Delivery> {
+        let twilio_client = self
+            .twilio_client
+            .as_ref()
+            .ok_or(NotifierError::SmsNotConfigured)?;
+        let phone_number = self.recipient_phone_number(alert).await?;
+        let phone_number = phone_number.as_str();
+
+        let reservation_id = self
+            .repository
+            .notification()
+            .reserve_sms(alert.account_id, self.sms_rate_limit_per_hour)
+            .await?
+            .ok_or_else(|| {
+                tracing::warn!(
+                    account_id = alert.account_id,
+                    "SMS rate limit exceeded, not sending alert"
+                );
+                NotifierError::SmsRateLimitExceeded(self.sms_rate_limit_per_hour)
+            })?;
+
+        tracing::debug!(
+            check_uuid = alert.check_uuid.to_string(),
+            phone_number = mask::phone_number(phone_number),
+            "sending alert",
+        );
+
+        let response = match twilio_client.send_sms(phone_number, body).await {
+            Ok(response) => response,
+            Err(e) => {
+                if let Err(e) = self
+                    .repository
+                    .notification()
+                    .release_sms(reservation_id)
+                    .await
+                {
+                    tracing::error!("failed to release SMS reservation: {:?}", e);
+                }
+                return Err(e.into());
+            }
+        };
+
+        Ok(Delivery {
+            http_status: response.http_status,
+            provider_message_id: Some(response.sid),
+        })
+    }
+}
+
+fn check_url(base_url: &url::Url, alert: &NotificationAlert) -> Result<url::Url> {
+    Ok(base_url.join(&format!("checks/{}", ShortId::from_uuid(&alert.check_uuid)))?)
+}
+
+/// The status a digest is labelled with, which is that of all its alerts unless they
+/// differ, in which case it is labelled DOWN as at least one check needs attention.
+fn digest_status(alerts: &[NotificationAlert]) -> (CheckStatus, bool) {
+    let status = alerts[0].check_status;
+    if alerts.iter().all(|a| a.check_status == status) {
+        (status, false)
+    } else {
+        (CheckStatus::Down, true)
+    }
+}
+
+fn format_schedule(alert: &NotificationAlert) -> String {
+    match (
+        &alert.schedule_type,
+        &alert.ping_cron_expression,
+        alert.ping_period,
+        &alert.ping_period_units,
+    ) {
+        (ScheduleType::Cron, Some(expression), _, _) => format!("cron '{}'", expression),
+        (_, _, Some(period), Some(units)) => format!("every {}", format_period(period, units)),
+        _ => String::from("unknown"),
+    }
+}
 
-        Ok(())
+fn format_period(value: i32, units: &PeriodUnits) -> String {
+    let unit = match units {
+        PeriodUnits::Minutes => "minute",
+        PeriodUnits::Hours => "hour",
+        PeriodUnits::Days => "day",
+    };
+    if value == 1 {
+        format!("{} {}", value, unit)
+    } else {
+        format!("{} {}s", value, unit)
     }
 }
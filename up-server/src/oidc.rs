@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use miette::Diagnostic;
+use openidconnect::{
+    core::{CoreAuthenticationFlow, CoreClient, CoreErrorResponseType, CoreProviderMetadata},
+    reqwest::async_http_client,
+    AuthorizationCode, ClaimsVerificationError, ClientId, ClientSecret, CsrfToken, DiscoveryError,
+    IssuerUrl, Nonce, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, RequestTokenError, Scope,
+    StandardErrorResponse, TokenResponse,
+};
+use thiserror::Error;
+
+use crate::{
+    api::v1::OIDC_CALLBACK_URI,
+    repository::dto::{ExternalIdentity, Provisioning},
+};
+
+type HttpClientError = openidconnect::reqwest::Error<reqwest::Error>;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum OidcError {
+    #[error("single sign-on is not configured on this server")]
+    #[diagnostic(code(up::error::oidc))]
+    NotConfigured,
+    #[error("failed to discover OpenID Connect provider")]
+    #[diagnostic(code(up::error::oidc))]
+    DiscoveryFailed(#[source] DiscoveryError<HttpClientError>),
+    #[error("failed to exchange authorization code with OpenID Connect provider")]
+    #[diagnostic(code(up::error::oidc))]
+    CodeExchangeFailed(
+        #[source] RequestTokenError<HttpClientError, StandardErrorResponse<CoreErrorResponseType>>,
+    ),
+    #[error("OpenID Connect provider did not return an ID token")]
+    #[diagnostic(code(up::error::oidc))]
+    MissingIdToken,
+    #[error("ID token from OpenID Connect provider is invalid")]
+    #[diagnostic(code(up::error::oidc))]
+    InvalidIdToken(#[from] ClaimsVerificationError),
+}
+
+/// Configuration of the OpenID Connect provider users log in with.
+pub struct OidcConfig {
+    /// Exactly as the provider's discovery document has it, e.g. without a trailing
+    /// slash.
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Where users who have no user are provisioned, if anywhere.
+    pub provisioning: Option<Provisioning>,
+}
+
+/// A login redirected to the provider, kept until the provider redirects back.
+pub struct OidcLogin {
+    pub authorize_url: url::Url,
+    pub state: String,
+    pub pkce_verifier: String,
+    pub nonce: String,
+}
+
+/// Logs users in with an OpenID Connect provider using the authorization code flow
+/// with PKCE. The provider is discovered for every login, so that rotated provider
+/// keys are picked up.
+#[derive(Clone)]
+pub struct OidcProvider {
+    config: Option<Arc<OidcConfig>>,
+    issuer_url: Option<IssuerUrl>,
+    redirect_url: RedirectUrl,
+}
+
+impl OidcProvider {
+    /// Creates a provider that redirects back to `base_url`. Without a configuration,
+    /// single sign-on is not available.
+    pub fn new(config: Option<OidcConfig>, base_url: &url::Url) -> Result<Self, url::ParseError> {
+        let issuer_url = config
+            .as_ref()
+            .map(|c| IssuerUrl::new(c.issuer_url.clone()))
+            .transpose()?;
+        let redirect_url = RedirectUrl::from_url(base_url.join(&OIDC_CALLBACK_URI[1..])?);
+        Ok(Self {
+            config: config.map(Arc::new),
+            issuer_url,
+            redirect_url,
+        })
+    }
+
+    /// Where users who have no user are provisioned, if anywhere.
+    pub fn provisioning(&self) -> Option<&Provisioning> {
+        self.config.as_ref().and_then(|c| c.provisioning.as_ref())
+    }
+
+    /// Starts a login, returning the provider URL to redirect the user to along with
+    /// what is needed to complete the login once the provider redirects back.
+    pub async fn start_login(&self) -> Result<OidcLogin, OidcError> {
+        let client = self.client().await?;
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+
+        let (authorize_url, state, nonce) = client
+            .authorize_url(
+                CoreAuthenticationFlow::AuthorizationCode,
+                CsrfToken::new_random,
+                Nonce::new_random,
+            )
+            .add_scope(Scope::new("email".to_string()))
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+
+        Ok(OidcLogin {
+            authorize_url,
+            state: state.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+            nonce: nonce.secret().clone(),
+        })
+    }
+
+    /// Completes a login by exchanging the authorization code the provider redirected
+    /// back with for an ID token, returning who the token identifies once it has been
+    /// validated against the provider's JWKS.
+    pub async fn complete_login(
+        &self,
+        code: &str,
+        pkce_verifier: &str,
+        nonce: &str,
+    ) -> Result<ExternalIdentity, OidcError> {
+        let client = self.client().await?;
+
+        let response = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier.to_string()))
+            .request_async(async_http_client)
+            .await
+            .map_err(OidcError::CodeExchangeFailed)?;
+
+        let id_token = response.id_token().ok_or(OidcError::MissingIdToken)?;
+        let claims =
+            id_token.claims(&client.id_token_verifier(), &Nonce::new(nonce.to_string()))?;
+
+        Ok(ExternalIdentity {
+            issuer: claims.issuer().to_string(),
+            subject: claims.subject().to_string(),
+            email: claims.email().map(|e| e.to_string()),
+            email_verified: claims.email_verified().unwrap_or(false),
+        })
+    }
+
+    async fn client(&self) -> Result<CoreClient, OidcError> {
+        let (config, issuer_url) = match (&self.config, &self.issuer_url) {
+            (Some(config), Some(issuer_url)) => (config, issuer_url),
+            _ => return Err(OidcError::NotConfigured),
+        };
+
+        let metadata = CoreProviderMetadata::discover_async(issuer_url.clone(), async_http_client)
+            .await
+            .map_err(OidcError::DiscoveryFailed)?;
+
+        Ok(CoreClient::from_provider_metadata(
+            metadata,
+            ClientId::new(config.client_id.clone()),
+            config.client_secret.clone().map(ClientSecret::new),
+        )
+        .set_redirect_uri(self.redirect_url.clone()))
+    }
+}
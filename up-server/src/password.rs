@@ -0,0 +1,112 @@
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Hash verified against when a user has no password, so that failed logins take
+    /// as long whether or not the user exists.
+    static ref DUMMY_HASH: String = hash("not a password").expect("failed to hash dummy password");
+}
+
+const MIN_LENGTH: usize = 12;
+const MAX_LENGTH: usize = 128;
+
+/// Checks that a new password for the user with `email` is acceptable, returning the
+/// reason it is not otherwise.
+pub fn check_policy(password: &str, email: &str) -> Result<(), String> {
+    let length = password.chars().count();
+    if length < MIN_LENGTH {
+        return Err(format!(
+            "passwords must be at least {} characters long",
+            MIN_LENGTH
+        ));
+    }
+    if length > MAX_LENGTH {
+        return Err(format!(
+            "passwords must be at most {} characters long",
+            MAX_LENGTH
+        ));
+    }
+
+    let local_part = email.split('@').next().unwrap_or(email);
+    if password.eq_ignore_ascii_case(email) || password.eq_ignore_ascii_case(local_part) {
+        return Err("passwords must not be the email address".to_string());
+    }
+
+    let mut chars = password.chars();
+    let first = chars.next();
+    if chars.all(|c| Some(c) == first) {
+        return Err("passwords must not repeat a single character".to_string());
+    }
+
+    Ok(())
+}
+
+/// Hashes a password with Argon2id and a random salt, returning the hash in PHC
+/// string format.
+pub fn hash(password: &str) -> Result<String, password_hash::Error> {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+/// Verifies a password against a hash in PHC string format. Malformed hashes never
+/// verify.
+pub fn verify(password: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(e) => {
+            tracing::warn!("malformed password hash: {}", e);
+            false
+        }
+    }
+}
+
+/// Verifies a password against the hash of a user, or against a dummy hash if there is
+/// no user, taking about as long either way.
+pub fn verify_or_dummy(password: &str, hash: Option<&str>) -> bool {
+    match hash {
+        Some(hash) => verify(password, hash),
+        None => {
+            verify(password, &DUMMY_HASH);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn correct_password_verifies() {
+        let hash = hash("correct horse battery staple").unwrap();
+
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify("correct horse battery staple", &hash));
+        assert!(!verify("correct horse battery stapler", &hash));
+    }
+
+    #[test]
+    fn password_policy() {
+        let email = "jane.doe.example@example.com";
+
+        assert!(check_policy("correct horse battery staple", email).is_ok());
+        assert!(check_policy("too short", email).is_err());
+        assert!(check_policy(&"x".repeat(129), email).is_err());
+        assert!(check_policy("aaaaaaaaaaaaaaaa", email).is_err());
+        assert!(check_policy("Jane.Doe.Example@example.com", email).is_err());
+        assert!(check_policy("jane.doe.example", email).is_err());
+    }
+
+    #[test]
+    fn malformed_hash_does_not_verify() {
+        assert!(!verify("password", "password"));
+        assert!(!verify_or_dummy("not a password", None));
+    }
+}
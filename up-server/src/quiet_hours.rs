@@ -0,0 +1,118 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// A user's quiet hours, during which alerts for low severity checks are held.
+///
+/// Quiet hours recur daily in the user's time zone from `start` up to `end`, and span
+/// midnight if `end` is before `start`. Equal `start` and `end` means no quiet hours.
+#[derive(Clone, Copy, Debug)]
+pub struct QuietHours {
+    timezone: Tz,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn new(timezone: Tz, start: NaiveTime, end: NaiveTime) -> Self {
+        Self {
+            timezone,
+            start,
+            end,
+        }
+    }
+
+    /// When the quiet hours `now` falls within end, or `None` if it is not within them.
+    pub fn end_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local = now.with_timezone(&self.timezone).naive_local();
+        let time = local.time();
+
+        let quiet = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        };
+        if !quiet {
+            return None;
+        }
+
+        let mut date = local.date();
+        if time >= self.end {
+            date += Duration::days(1);
+        }
+        let end = date.and_time(self.end);
+
+        let end = match self.timezone.from_local_datetime(&end) {
+            LocalResult::Single(end) | LocalResult::Ambiguous(end, _) => end,
+            // The clocks went forward over the end, so it ends once they have.
+            LocalResult::None => self
+                .timezone
+                .from_local_datetime(&(end + Duration::hours(1)))
+                .earliest()?,
+        };
+
+        Some(end.with_timezone(&Utc))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn quiet_hours_within_a_day() {
+        let quiet_hours = QuietHours::new(chrono_tz::UTC, time("09:00"), time("17:00"));
+
+        assert_eq!(None, quiet_hours.end_after(utc("2022-08-01T08:59:59Z")));
+        assert_eq!(
+            Some(utc("2022-08-01T17:00:00Z")),
+            quiet_hours.end_after(utc("2022-08-01T09:00:00Z"))
+        );
+        assert_eq!(None, quiet_hours.end_after(utc("2022-08-01T17:00:00Z")));
+    }
+
+    #[test]
+    fn quiet_hours_spanning_midnight_in_time_zone() {
+        let quiet_hours =
+            QuietHours::new(chrono_tz::America::New_York, time("22:00"), time("07:00"));
+
+        // 23:00 and 02:00 EDT, both end at 07:00 EDT the next morning.
+        assert_eq!(
+            Some(utc("2022-08-01T11:00:00Z")),
+            quiet_hours.end_after(utc("2022-08-01T03:00:00Z"))
+        );
+        assert_eq!(
+            Some(utc("2022-08-01T11:00:00Z")),
+            quiet_hours.end_after(utc("2022-08-01T06:00:00Z"))
+        );
+        // 08:00 and 21:59 EDT.
+        assert_eq!(None, quiet_hours.end_after(utc("2022-08-01T12:00:00Z")));
+        assert_eq!(None, quiet_hours.end_after(utc("2022-08-02T01:59:00Z")));
+    }
+
+    #[test]
+    fn quiet_hours_ending_when_clocks_go_forward() {
+        let quiet_hours =
+            QuietHours::new(chrono_tz::America::New_York, time("22:00"), time("02:30"));
+
+        // 02:30 does not exist on 2022-03-13, clocks go from 02:00 EST to 03:00 EDT.
+        assert_eq!(
+            Some(utc("2022-03-13T07:30:00Z")),
+            quiet_hours.end_after(utc("2022-03-13T05:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn equal_start_and_end_is_never_quiet() {
+        let quiet_hours = QuietHours::new(chrono_tz::UTC, time("09:00"), time("09:00"));
+
+        assert_eq!(None, quiet_hours.end_after(utc("2022-08-01T09:00:00Z")));
+    }
+}
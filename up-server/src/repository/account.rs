@@ -0,0 +1,356 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{
+        auth::{add_user_to_account, create_user, update_password, UserRole},
+        ensure_administrator_in_account,
+        project::ENTITY_ACCOUNT,
+        RepositoryError, Result,
+    },
+    shortid::ShortId,
+};
+
+/// The name of the project created along with an account on signup.
+const DEFAULT_PROJECT_NAME: &str = "Default";
+
+#[derive(sqlx::FromRow)]
+pub struct Account {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub name: String,
+    pub role: UserRole,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+pub struct UpdateAccount {
+    pub name: Option<String>,
+}
+
+pub struct Signup {
+    pub email: String,
+    pub password: String,
+    pub account_name: String,
+}
+
+#[derive(Clone)]
+pub struct AccountRepository {
+    database: Database,
+}
+
+impl AccountRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Reads the accounts the user making the request is a member of, along with their
+    /// role in each.
+    pub async fn read_all(&self, identity: &Identity) -> Result<Vec<Account>> {
+        let mut conn = self.database.connection().await?;
+
+        tracing::trace!("reading accounts");
+
+        let sql = r"
+            SELECT
+                a.id,
+                a.uuid,
+                a.name,
+                ur.role,
+                a.created_at,
+                a.updated_at
+            FROM
+                accounts a
+                INNER JOIN
+                user_roles ur ON ur.account_id = a.id
+            WHERE
+                ur.user_id = $1
+                AND
+                a.id = ANY($2)
+                AND
+                a.deleted = false
+            ORDER BY
+                a.name ASC
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(identity.user_id)
+            .bind(identity.account_ids())
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    pub async fn update(
+        &self,
+        identity: &Identity,
+        uuid: &Uuid,
+        request: UpdateAccount,
+    ) -> Result<Account> {
+        let account_id = ensure_administrator_in_account(identity, uuid)?;
+
+        if request.name.as_deref().map(str::trim) == Some("") {
+            return Err(RepositoryError::BadArgument(
+                "account name can't be empty".to_string(),
+            ));
+        }
+
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            UPDATE
+                accounts a
+            SET
+                name = COALESCE($2, a.name),
+                updated_at = NOW() AT TIME ZONE 'UTC',
+                updated_by = $3
+            FROM
+                user_roles ur
+            WHERE
+                a.id = $1
+                AND
+                a.deleted = false
+                AND
+                ur.account_id = a.id
+                AND
+                ur.user_id = $3
+            RETURNING
+                a.id,
+                a.uuid,
+                a.name,
+                ur.role,
+                a.created_at,
+                a.updated_at
+        ";
+
+        let account = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(request.name.as_deref().map(str::trim))
+            .bind(identity.user_id)
+            .fetch_optional(&mut conn)
+            .await?
+            .ok_or_else(|| RepositoryError::NotFound {
+                entity_type: ENTITY_ACCOUNT.to_string(),
+                id: ShortId::from_uuid(uuid).to_string(),
+            })?;
+
+        tracing::trace!(uuid = uuid.to_string(), "account updated");
+
+        Ok(account)
+    }
+
+    /// Deletes an account along with its projects, checks and notifications.
+    pub async fn delete(&self, identity: &Identity, uuid: &Uuid) -> Result<bool> {
+        let account_id = ensure_administrator_in_account(identity, uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        for sql in [
+            r"
+                UPDATE notifications
+                SET
+                    deleted = true,
+                    deleted_at = NOW() AT TIME ZONE 'UTC',
+                    deleted_by = $2
+                WHERE
+                    account_id = $1
+                    AND
+                    deleted = false
+            ",
+            r"
+                UPDATE checks
+                SET
+                    deleted = true,
+                    deleted_at = NOW() AT TIME ZONE 'UTC',
+                    deleted_by = $2
+                WHERE
+                    account_id = $1
+                    AND
+                    deleted = false
+            ",
+            r"
+                UPDATE projects
+                SET
+                    deleted = true,
+                    deleted_at = NOW() AT TIME ZONE 'UTC',
+                    deleted_by = $2
+                WHERE
+                    account_id = $1
+                    AND
+                    deleted = false
+            ",
+        ] {
+            sqlx::query(sql)
+                .bind(account_id)
+                .bind(identity.user_id)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        let sql = r"
+            UPDATE accounts
+            SET
+                deleted = true,
+                deleted_at = NOW() AT TIME ZONE 'UTC',
+                deleted_by = $2
+            WHERE
+                id = $1
+                AND
+                deleted = false
+        ";
+
+        let deleted = sqlx::query(sql)
+            .bind(account_id)
+            .bind(identity.user_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        tx.commit().await?;
+
+        if deleted {
+            tracing::trace!(uuid = uuid.to_string(), "account deleted");
+        } else {
+            tracing::trace!(uuid = uuid.to_string(), "no such account, nothing deleted");
+        }
+
+        Ok(deleted)
+    }
+
+    /// Creates a user with a password, an account they administer and a default project
+    /// in it, returning the subject of the user.
+    ///
+    /// [`signup`] not called by APIs with an identity, whether signup is allowed at all
+    /// is up to the caller.
+    pub async fn signup(&self, request: Signup) -> Result<String> {
+        let email = request.email.trim();
+        let account_name = request.account_name.trim();
+        if !email.contains('@') {
+            return Err(RepositoryError::BadArgument(format!(
+                "{} is not a valid email address",
+                email
+            )));
+        }
+        if account_name.is_empty() {
+            return Err(RepositoryError::BadArgument(
+                "account name can't be empty".to_string(),
+            ));
+        }
+
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            SELECT EXISTS (
+                SELECT 1
+                FROM users
+                WHERE LOWER(email) = LOWER($1) AND deleted = false
+            )
+        ";
+
+        let exists: bool = sqlx::query_scalar(sql)
+            .bind(email)
+            .fetch_one(&mut tx)
+            .await?;
+        if exists {
+            return Err(RepositoryError::BadArgument(
+                "a user with this email address already exists, log in instead".to_string(),
+            ));
+        }
+
+        let (user_id, user_uuid, subject) = create_user(&mut tx, email).await?;
+        update_password(&mut tx, user_id, email, &request.password).await?;
+
+        let account_id = insert_account(&mut tx, account_name, user_id).await?;
+        add_user_to_account(&mut tx, user_id, account_id, UserRole::Administrator).await?;
+        insert_default_project(&mut tx, account_id, user_id).await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(user_uuid = user_uuid.to_string(), "user signed up");
+
+        Ok(subject)
+    }
+}
+
+async fn insert_account(conn: &mut DbConnection, name: &str, created_by: i64) -> Result<i64> {
+    let uuid = Uuid::new_v4();
+    let short_id: ShortId = uuid.into();
+
+    let sql = r"
+        INSERT INTO accounts (
+            uuid,
+            shortid,
+            name,
+            created_by
+        ) VALUES (
+            $1,
+            $2,
+            $3,
+            $4
+        ) RETURNING id
+    ";
+
+    Ok(sqlx::query_scalar(sql)
+        .bind(uuid)
+        .bind(short_id.to_string())
+        .bind(name)
+        .bind(created_by)
+        .fetch_one(conn)
+        .await?)
+}
+
+async fn insert_default_project(
+    conn: &mut DbConnection,
+    account_id: i64,
+    created_by: i64,
+) -> Result<()> {
+    let uuid = Uuid::new_v4();
+    let short_id: ShortId = uuid.into();
+
+    let sql = r"
+        INSERT INTO projects (
+            account_id,
+            uuid,
+            shortid,
+            name,
+            created_by
+        ) VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        ) RETURNING id
+    ";
+
+    let project_id: i64 = sqlx::query_scalar(sql)
+        .bind(account_id)
+        .bind(uuid)
+        .bind(short_id.to_string())
+        .bind(DEFAULT_PROJECT_NAME)
+        .bind(created_by)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let sql = r"
+        INSERT INTO user_projects (
+            user_id,
+            project_id,
+            role
+        ) VALUES (
+            $1,
+            $2,
+            'ADMINISTRATOR'
+        )
+    ";
+
+    sqlx::query(sql)
+        .bind(created_by)
+        .bind(project_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
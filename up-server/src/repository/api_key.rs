@@ -0,0 +1,338 @@
+use chrono::NaiveDateTime;
+use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{RepositoryError, Result},
+    shortid::ShortId,
+    tokens,
+};
+
+/// Prefix of API keys, telling them apart from JWTs in `Authorization` headers.
+pub const API_KEY_PREFIX: &str = "up_";
+/// How many leading characters of a key are kept, so users can tell their keys apart.
+const TOKEN_PREFIX_LENGTH: usize = 10;
+
+const ENTITY_API_KEY: &str = "API key";
+
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "api_key_scope", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ApiKeyScope {
+    ReadOnly,
+    ManageChecks,
+    ManageNotifications,
+}
+
+impl PgHasArrayType for ApiKeyScope {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_api_key_scope")
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub project_uuid: Option<Uuid>,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+pub struct CreateApiKey {
+    pub name: Option<String>,
+    pub project_uuid: Option<Uuid>,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// A newly created API key, `token` is only ever returned here.
+pub struct CreatedApiKey {
+    pub key: ApiKey,
+    pub token: String,
+}
+
+/// An API key used to authenticate, along with the subject of the user it belongs to.
+#[derive(sqlx::FromRow)]
+pub struct ApiKeyUser {
+    pub subject: String,
+    #[sqlx(flatten)]
+    pub key: ApiKey,
+}
+
+#[derive(Clone)]
+pub struct ApiKeyRepository {
+    database: Database,
+}
+
+impl ApiKeyRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Reads the API keys of the user making the request that have not been revoked,
+    /// including expired ones.
+    pub async fn read_all(&self, identity: &Identity) -> Result<Vec<ApiKey>> {
+        identity.ensure_not_api_key()?;
+
+        let mut conn = self.database.connection().await?;
+
+        tracing::trace!(user_id = identity.user_id, "reading API keys");
+
+        let sql = r"
+            SELECT
+                k.id,
+                k.uuid,
+                p.uuid AS project_uuid,
+                k.name,
+                k.token_prefix,
+                k.scopes,
+                k.created_at,
+                k.expires_at,
+                k.last_used_at
+            FROM
+                api_keys k
+                LEFT JOIN
+                projects p ON p.id = k.project_id
+            WHERE
+                k.user_id = $1
+                AND
+                k.revoked_at IS NULL
+            ORDER BY
+                k.created_at ASC
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(identity.user_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    /// Creates an API key acting as the user making the request, restricted to a
+    /// project if `project_uuid` is set.
+    pub async fn create(
+        &self,
+        identity: &Identity,
+        request: CreateApiKey,
+    ) -> Result<CreatedApiKey> {
+        identity.ensure_not_api_key()?;
+
+        if request.scopes.is_empty() {
+            return Err(RepositoryError::BadArgument(
+                "API keys need at least one scope".to_string(),
+            ));
+        }
+        if request.scopes.contains(&ApiKeyScope::ReadOnly) && request.scopes.len() > 1 {
+            return Err(RepositoryError::BadArgument(
+                "read-only API keys can't have other scopes".to_string(),
+            ));
+        }
+
+        let project_id = request
+            .project_uuid
+            .as_ref()
+            .map(|uuid| identity.get_project_id(uuid))
+            .transpose()?;
+
+        let mut tx = self.database.transaction().await?;
+
+        if let Some(expires_at) = request.expires_at {
+            let (expired,): (bool,) = sqlx::query_as("SELECT $1 <= NOW() AT TIME ZONE 'UTC'")
+                .bind(expires_at)
+                .fetch_one(&mut tx)
+                .await?;
+            if expired {
+                return Err(RepositoryError::BadArgument(
+                    "API keys must expire in the future".to_string(),
+                ));
+            }
+        }
+
+        let sql = r"
+            INSERT INTO api_keys (
+                uuid,
+                shortid,
+                user_id,
+                project_id,
+                name,
+                token_hash,
+                token_prefix,
+                scopes,
+                expires_at
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8,
+                $9
+            )
+        ";
+
+        let uuid = Uuid::new_v4();
+        let short_id: ShortId = uuid.into();
+        let token = format!("{}{}", API_KEY_PREFIX, tokens::generate_secret());
+
+        sqlx::query(sql)
+            .bind(uuid)
+            .bind(short_id.to_string())
+            .bind(identity.user_id)
+            .bind(project_id)
+            .bind(request.name.as_deref().unwrap_or(""))
+            .bind(tokens::hash_secret(&token))
+            .bind(&token[..TOKEN_PREFIX_LENGTH])
+            .bind(&request.scopes)
+            .bind(request.expires_at)
+            .execute(&mut tx)
+            .await?;
+
+        let key = read_key(&mut tx, &uuid, identity.user_id).await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            user_id = identity.user_id,
+            uuid = uuid.to_string(),
+            scopes = format!("{:?}", key.scopes),
+            "API key created"
+        );
+
+        Ok(CreatedApiKey { key, token })
+    }
+
+    /// Revokes an API key of the user making the request, it can't be used from then on.
+    pub async fn revoke(&self, identity: &Identity, uuid: &Uuid) -> Result<()> {
+        identity.ensure_not_api_key()?;
+
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            UPDATE
+                api_keys
+            SET
+                revoked_at = NOW() AT TIME ZONE 'UTC'
+            WHERE
+                uuid = $1
+                AND
+                user_id = $2
+                AND
+                revoked_at IS NULL
+        ";
+
+        let revoked = sqlx::query(sql)
+            .bind(uuid)
+            .bind(identity.user_id)
+            .execute(&mut conn)
+            .await?
+            .rows_affected()
+            > 0;
+
+        if !revoked {
+            return Err(RepositoryError::NotFound {
+                entity_type: ENTITY_API_KEY.to_string(),
+                id: ShortId::from_uuid(uuid).to_string(),
+            });
+        }
+
+        tracing::trace!(uuid = uuid.to_string(), "API key revoked");
+
+        Ok(())
+    }
+
+    /// Finds the API key with a token that has neither expired nor been revoked, and
+    /// records that it was used.
+    ///
+    /// [`authenticate`] not called by APIs, so no access checks needed.
+    pub async fn authenticate(&self, token: &str) -> Result<Option<ApiKeyUser>> {
+        let mut conn = self.database.connection().await?;
+
+        // Keys restricted to a project that has since been deleted must not fall back
+        // to all of the user's projects, so they are rejected.
+        let sql = r"
+            UPDATE
+                api_keys k
+            SET
+                last_used_at = NOW() AT TIME ZONE 'UTC'
+            FROM
+                users u
+            WHERE
+                k.token_hash = $1
+                AND
+                k.revoked_at IS NULL
+                AND
+                (k.expires_at IS NULL OR k.expires_at > NOW() AT TIME ZONE 'UTC')
+                AND
+                u.id = k.user_id
+                AND
+                u.deleted = false
+                AND
+                (
+                    k.project_id IS NULL
+                    OR
+                    EXISTS (
+                        SELECT 1
+                        FROM projects p
+                        WHERE p.id = k.project_id AND p.deleted = false
+                    )
+                )
+            RETURNING
+                u.subject,
+                k.id,
+                k.uuid,
+                (SELECT p.uuid FROM projects p WHERE p.id = k.project_id) AS project_uuid,
+                k.name,
+                k.token_prefix,
+                k.scopes,
+                k.created_at,
+                k.expires_at,
+                k.last_used_at
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(tokens::hash_secret(token))
+            .fetch_optional(&mut conn)
+            .await?)
+    }
+}
+
+async fn read_key(conn: &mut DbConnection, uuid: &Uuid, user_id: i64) -> Result<ApiKey> {
+    let sql = r"
+            SELECT
+                k.id,
+                k.uuid,
+                p.uuid AS project_uuid,
+                k.name,
+                k.token_prefix,
+                k.scopes,
+                k.created_at,
+                k.expires_at,
+                k.last_used_at
+            FROM
+                api_keys k
+                LEFT JOIN
+                projects p ON p.id = k.project_id
+            WHERE
+                k.uuid = $1
+                AND
+                k.user_id = $2
+        ";
+
+    let key: Option<ApiKey> = sqlx::query_as(sql)
+        .bind(uuid)
+        .bind(user_id)
+        .fetch_optional(conn)
+        .await?;
+
+    key.ok_or_else(|| RepositoryError::NotFound {
+        entity_type: ENTITY_API_KEY.to_string(),
+        id: ShortId::from_uuid(uuid).to_string(),
+    })
+}
@@ -1,7 +1,35 @@
+use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
 use std::str::FromStr;
+use ulid::Ulid;
 use uuid::Uuid;
 
-use crate::{database::Database, repository::Result};
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    password,
+    repository::{project::ENTITY_ACCOUNT, RepositoryError, Result},
+    shortid::ShortId,
+    tokens,
+};
+
+pub(super) const ENTITY_USER: &str = "user";
+
+/// Consecutive failed logins after which a user is locked out.
+const MAX_FAILED_LOGINS: i32 = 5;
+/// How long a user is locked out for after too many failed logins.
+const LOCKOUT_MINUTES: i32 = 15;
+/// How long a password reset token can be used for.
+const PASSWORD_RESET_EXPIRY_MINUTES: i32 = 60;
+/// The maximum number of password resets sent to an email address per hour.
+const PASSWORD_RESET_RATE_LIMIT_PER_HOUR: i64 = 5;
+/// How long users have to log in at the OpenID Connect provider.
+const OIDC_LOGIN_EXPIRY_MINUTES: i32 = 10;
+/// How long a magic link can be used for.
+const MAGIC_LINK_EXPIRY_MINUTES: i32 = 15;
+/// The maximum number of magic links sent to an email address per hour.
+const MAGIC_LINK_RATE_LIMIT_PER_HOUR: i64 = 5;
+/// How long a refresh token can be used for, each use rotates it to a new one.
+const REFRESH_TOKEN_EXPIRY_DAYS: i32 = 30;
 
 #[derive(sqlx::FromRow)]
 pub struct User {
@@ -11,9 +39,57 @@ pub struct User {
     pub account_ids: Vec<String>,
     pub project_ids: Vec<String>,
     pub roles: Vec<String>,
+    pub project_roles: Vec<String>,
+}
+
+/// The outcome of authenticating a user with an email address and password.
+pub enum Authentication {
+    /// The user with this subject has this email address and password.
+    Authenticated(String),
+    /// There is no user with this email address and password.
+    Failed,
+    /// The user is locked out after too many failed logins.
+    LockedOut,
+}
+
+/// A one-time token to reset the password of the user with `email`.
+pub struct PasswordReset {
+    pub email: String,
+    pub token: String,
+    pub expiry_minutes: i32,
+}
+
+/// A single-use link to log in as the user with `email`.
+pub struct MagicLink {
+    pub email: String,
+    pub token: String,
+    pub expiry_minutes: i32,
+}
+
+/// A refresh token rotated to a new one for the user with `subject`.
+pub struct RefreshedToken {
+    pub subject: String,
+    pub refresh_token: String,
+}
+
+/// Who an OpenID Connect provider says a user is.
+pub struct ExternalIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    /// Only verified email addresses are used to link existing users.
+    pub email_verified: bool,
 }
 
-#[derive(sqlx::Type)]
+/// The account and role users who log in with an OpenID Connect provider are given if
+/// they have no user yet.
+#[derive(Clone, Debug)]
+pub struct Provisioning {
+    pub account_uuid: Uuid,
+    pub role: UserRole,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "user_role", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum UserRole {
     Administrator,
@@ -21,6 +97,12 @@ pub enum UserRole {
     Viewer,
 }
 
+impl PgHasArrayType for UserRole {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_user_role")
+    }
+}
+
 impl FromStr for UserRole {
     type Err = String;
 
@@ -56,7 +138,7 @@ impl AuthRepository {
                     SELECT DISTINCT a.uuid || '|' || a.id
                     FROM user_accounts ua
                     INNER JOIN accounts a ON a.id = ua.account_id
-                    WHERE ua.user_id = users.id
+                    WHERE ua.user_id = users.id AND a.deleted = false
                 ) AS account_ids,
                 ARRAY(
                     SELECT DISTINCT p.uuid || '|' || p.id
@@ -68,7 +150,16 @@ impl AuthRepository {
                     SELECT DISTINCT ur.role || '|' || ur.account_id
                     FROM user_roles ur
                     WHERE ur.user_id = users.id
-                ) AS roles
+                ) AS roles,
+                ARRAY(
+                    SELECT
+                        CASE WHEN ur.role = 'ADMINISTRATOR' THEN ur.role ELSE up.role END
+                        || '|' || up.project_id
+                    FROM user_projects up
+                    INNER JOIN projects p ON p.id = up.project_id
+                    INNER JOIN user_roles ur ON ur.user_id = up.user_id AND ur.account_id = p.account_id
+                    WHERE up.user_id = users.id
+                ) AS project_roles
             FROM
                 users
             WHERE
@@ -84,4 +175,952 @@ impl AuthRepository {
 
         Ok(user)
     }
+
+    /// Authenticates a user by email address and password. Too many consecutive
+    /// failures lock the user out for a while, during which even the right password
+    /// is rejected.
+    pub async fn authenticate(&self, email: &str, password: &str) -> Result<Authentication> {
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            SELECT
+                id,
+                subject,
+                password_hash,
+                COALESCE(locked_until > NOW() AT TIME ZONE 'UTC', false) AS locked
+            FROM
+                users
+            WHERE
+                LOWER(email) = LOWER($1)
+                AND
+                password_hash IS NOT NULL
+                AND
+                deleted = false
+        ";
+
+        let user: Option<(i64, String, String, bool)> = sqlx::query_as(sql)
+            .bind(email)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        let (user_id, subject, hash) = match user {
+            Some((_, _, hash, true)) => {
+                // Verify anyway, so a locked out user takes as long to reject as
+                // anyone else.
+                verify_password(password, Some(hash)).await?;
+                return Ok(Authentication::LockedOut);
+            }
+            Some((user_id, subject, hash, false)) => (Some(user_id), Some(subject), Some(hash)),
+            None => (None, None, None),
+        };
+
+        if !verify_password(password, hash).await? {
+            if let Some(user_id) = user_id {
+                let sql = r"
+                    UPDATE
+                        users
+                    SET
+                        failed_logins = CASE
+                            WHEN failed_logins + 1 >= $2 THEN 0
+                            ELSE failed_logins + 1
+                        END,
+                        locked_until = CASE
+                            WHEN failed_logins + 1 >= $2 THEN NOW() AT TIME ZONE 'UTC' + $3 * INTERVAL '1 minute'
+                            ELSE locked_until
+                        END
+                    WHERE
+                        id = $1
+                    RETURNING
+                        locked_until > NOW() AT TIME ZONE 'UTC'
+                ";
+
+                let locked: Option<bool> = sqlx::query_scalar(sql)
+                    .bind(user_id)
+                    .bind(MAX_FAILED_LOGINS)
+                    .bind(LOCKOUT_MINUTES)
+                    .fetch_one(&mut conn)
+                    .await?;
+                if locked.unwrap_or(false) {
+                    tracing::debug!(
+                        user_id = user_id,
+                        "too many failed logins, locking user out"
+                    );
+                }
+            }
+            return Ok(Authentication::Failed);
+        }
+
+        let sql = r"
+            UPDATE
+                users
+            SET
+                failed_logins = 0,
+                locked_until = NULL
+            WHERE
+                id = $1
+        ";
+
+        sqlx::query(sql).bind(user_id).execute(&mut conn).await?;
+
+        Ok(subject.map_or(Authentication::Failed, Authentication::Authenticated))
+    }
+
+    /// Sets the password of the user making the request. Users who already have a
+    /// password need to provide it to change it. Every session of the user ends,
+    /// including the one making the request, so they have to log in again.
+    pub async fn set_password(
+        &self,
+        identity: &Identity,
+        current_password: Option<&str>,
+        new_password: &str,
+    ) -> Result<()> {
+        identity.ensure_not_api_key()?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            SELECT
+                email,
+                password_hash
+            FROM
+                users
+            WHERE
+                id = $1
+            FOR UPDATE
+        ";
+
+        let (email, hash): (String, Option<String>) = sqlx::query_as(sql)
+            .bind(identity.user_id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        if hash.is_some() {
+            let current_password = current_password.ok_or_else(|| {
+                RepositoryError::BadArgument("the current password is required".to_string())
+            })?;
+            if !verify_password(current_password, hash).await? {
+                return Err(RepositoryError::BadArgument(
+                    "the current password is incorrect".to_string(),
+                ));
+            }
+        }
+
+        update_password(&mut tx, identity.user_id, &email, new_password).await?;
+        end_sessions(&mut tx, identity.user_id).await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(user_id = identity.user_id, "password set");
+
+        Ok(())
+    }
+
+    /// Creates a one-time token to reset the password of the user with an email
+    /// address, or `None` if there is no such user or too many resets have been sent
+    /// to the email address in the last hour. Users without a password can set one
+    /// this way.
+    ///
+    /// [`create_password_reset`] not called with an identity, but the token is only
+    /// sent to the user's email address, so no access checks needed.
+    pub async fn create_password_reset(&self, email: &str) -> Result<Option<PasswordReset>> {
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            SELECT
+                u.id,
+                u.email,
+                (
+                    SELECT COUNT(*)
+                    FROM password_reset_tokens t
+                    WHERE t.user_id = u.id
+                    AND t.created_at > NOW() AT TIME ZONE 'UTC' - INTERVAL '1 hour'
+                ) AS sent
+            FROM
+                users u
+            WHERE
+                LOWER(u.email) = LOWER($1)
+                AND
+                u.deleted = false
+            ORDER BY
+                u.password_hash IS NULL ASC,
+                u.id ASC
+            LIMIT 1
+        ";
+
+        let user: Option<(i64, String, i64)> = sqlx::query_as(sql)
+            .bind(email)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        let (user_id, email) = match user {
+            Some((user_id, _, sent)) if sent >= PASSWORD_RESET_RATE_LIMIT_PER_HOUR => {
+                tracing::debug!(
+                    user_id = user_id,
+                    "password reset rate limit of {} per hour exceeded",
+                    PASSWORD_RESET_RATE_LIMIT_PER_HOUR
+                );
+                return Ok(None);
+            }
+            Some((user_id, email, _)) => (user_id, email),
+            None => return Ok(None),
+        };
+
+        let token = tokens::generate_secret();
+
+        let sql = r"
+            INSERT INTO password_reset_tokens (
+                user_id,
+                token_hash,
+                expires_at
+            ) VALUES (
+                $1,
+                $2,
+                NOW() AT TIME ZONE 'UTC' + $3 * INTERVAL '1 minute'
+            )
+        ";
+
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(tokens::hash_secret(&token))
+            .bind(PASSWORD_RESET_EXPIRY_MINUTES)
+            .execute(&mut conn)
+            .await?;
+
+        tracing::debug!(user_id = user_id, "password reset created");
+
+        Ok(Some(PasswordReset {
+            email,
+            token,
+            expiry_minutes: PASSWORD_RESET_EXPIRY_MINUTES,
+        }))
+    }
+
+    /// Resets the password of the user a password reset token was created for, lifts
+    /// any lockout and ends every session of the user. Neither the token nor any other
+    /// reset token of the user can be used again.
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<()> {
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            UPDATE
+                password_reset_tokens t
+            SET
+                used_at = NOW() AT TIME ZONE 'UTC'
+            FROM
+                users u
+            WHERE
+                t.token_hash = $1
+                AND
+                t.used_at IS NULL
+                AND
+                t.expires_at > NOW() AT TIME ZONE 'UTC'
+                AND
+                u.id = t.user_id
+                AND
+                u.deleted = false
+            RETURNING
+                u.id,
+                u.email
+        ";
+
+        let user: Option<(i64, String)> = sqlx::query_as(sql)
+            .bind(tokens::hash_secret(token))
+            .fetch_optional(&mut tx)
+            .await?;
+
+        let (user_id, email) = user.ok_or_else(|| {
+            RepositoryError::BadArgument(
+                "password reset token is invalid or has expired".to_string(),
+            )
+        })?;
+
+        update_password(&mut tx, user_id, &email, new_password).await?;
+        end_sessions(&mut tx, user_id).await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(user_id = user_id, "password reset");
+
+        Ok(())
+    }
+
+    /// Creates a single-use token to log in as the user with an email address, or
+    /// `None` if there is no such user or too many links have been sent to the email
+    /// address in the last hour.
+    ///
+    /// [`create_magic_link`] not called with an identity, but the token is only sent
+    /// to the user's email address, so no access checks needed.
+    pub async fn create_magic_link(&self, email: &str) -> Result<Option<MagicLink>> {
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            SELECT
+                u.id,
+                u.email,
+                (
+                    SELECT COUNT(*)
+                    FROM magic_link_tokens t
+                    WHERE t.user_id = u.id
+                    AND t.created_at > NOW() AT TIME ZONE 'UTC' - INTERVAL '1 hour'
+                ) AS sent
+            FROM
+                users u
+            WHERE
+                LOWER(u.email) = LOWER($1)
+                AND
+                u.deleted = false
+            ORDER BY
+                u.id ASC
+            LIMIT 1
+        ";
+
+        let user: Option<(i64, String, i64)> = sqlx::query_as(sql)
+            .bind(email)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        let (user_id, email) = match user {
+            Some((user_id, _, sent)) if sent >= MAGIC_LINK_RATE_LIMIT_PER_HOUR => {
+                tracing::debug!(
+                    user_id = user_id,
+                    "magic link rate limit of {} per hour exceeded",
+                    MAGIC_LINK_RATE_LIMIT_PER_HOUR
+                );
+                return Ok(None);
+            }
+            Some((user_id, email, _)) => (user_id, email),
+            None => return Ok(None),
+        };
+
+        let token = tokens::generate_secret();
+
+        let sql = r"
+            INSERT INTO magic_link_tokens (
+                user_id,
+                token_hash,
+                expires_at
+            ) VALUES (
+                $1,
+                $2,
+                NOW() AT TIME ZONE 'UTC' + $3 * INTERVAL '1 minute'
+            )
+        ";
+
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(tokens::hash_secret(&token))
+            .bind(MAGIC_LINK_EXPIRY_MINUTES)
+            .execute(&mut conn)
+            .await?;
+
+        tracing::debug!(user_id = user_id, "magic link created");
+
+        Ok(Some(MagicLink {
+            email,
+            token,
+            expiry_minutes: MAGIC_LINK_EXPIRY_MINUTES,
+        }))
+    }
+
+    /// Returns the subject of the user a magic link was created for, or `None` if
+    /// the link is invalid, has expired or has been used already.
+    pub async fn redeem_magic_link(&self, token: &str) -> Result<Option<String>> {
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            UPDATE
+                magic_link_tokens t
+            SET
+                used_at = NOW() AT TIME ZONE 'UTC'
+            FROM
+                users u
+            WHERE
+                t.token_hash = $1
+                AND
+                t.used_at IS NULL
+                AND
+                t.expires_at > NOW() AT TIME ZONE 'UTC'
+                AND
+                u.id = t.user_id
+                AND
+                u.deleted = false
+            RETURNING
+                u.subject
+        ";
+
+        let subject: Option<String> = sqlx::query_scalar(sql)
+            .bind(tokens::hash_secret(token))
+            .fetch_optional(&mut conn)
+            .await?;
+
+        Ok(subject)
+    }
+
+    /// Creates a refresh token for a user who has just logged in, starting a new family
+    /// of tokens.
+    ///
+    /// [`create_refresh_token`] called by APIs once users have logged in, so no access
+    /// checks needed.
+    pub async fn create_refresh_token(&self, subject: &str) -> Result<String> {
+        let mut conn = self.database.connection().await?;
+
+        let user_id: Option<i64> =
+            sqlx::query_scalar("SELECT id FROM users WHERE subject = $1 AND deleted = false")
+                .bind(subject)
+                .fetch_optional(&mut conn)
+                .await?;
+
+        let user_id = user_id.ok_or_else(|| RepositoryError::NotFound {
+            entity_type: ENTITY_USER.to_string(),
+            id: subject.to_string(),
+        })?;
+
+        insert_refresh_token(&mut conn, user_id, &Uuid::new_v4()).await
+    }
+
+    /// Rotates a refresh token to a new one, or returns `None` if the token is invalid,
+    /// has expired or has been revoked. A token that has already been rotated revokes
+    /// its whole family, since only a stolen copy would be used again.
+    pub async fn rotate_refresh_token(&self, token: &str) -> Result<Option<RefreshedToken>> {
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            SELECT
+                t.id,
+                t.user_id,
+                t.family,
+                u.subject,
+                t.rotated_at IS NOT NULL AS rotated,
+                (
+                    t.revoked_at IS NULL
+                    AND
+                    t.expires_at > NOW() AT TIME ZONE 'UTC'
+                    AND
+                    u.deleted = false
+                ) AS valid
+            FROM
+                refresh_tokens t
+                INNER JOIN
+                users u ON u.id = t.user_id
+            WHERE
+                t.token_hash = $1
+            FOR UPDATE OF t
+        ";
+
+        let row: Option<(i64, i64, Uuid, String, bool, bool)> = sqlx::query_as(sql)
+            .bind(tokens::hash_secret(token))
+            .fetch_optional(&mut tx)
+            .await?;
+
+        let (id, user_id, family, subject) = match row {
+            Some((_, _, _, _, _, false)) | None => return Ok(None),
+            Some((_, user_id, family, _, true, true)) => {
+                revoke_refresh_token_family(&mut tx, &family).await?;
+                tx.commit().await?;
+                tracing::warn!(
+                    user_id = user_id,
+                    "rotated refresh token used again, revoked its family"
+                );
+                return Ok(None);
+            }
+            Some((id, user_id, family, subject, false, true)) => (id, user_id, family, subject),
+        };
+
+        sqlx::query(
+            "UPDATE refresh_tokens SET rotated_at = NOW() AT TIME ZONE 'UTC' WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+
+        let refresh_token = insert_refresh_token(&mut tx, user_id, &family).await?;
+
+        tx.commit().await?;
+
+        Ok(Some(RefreshedToken {
+            subject,
+            refresh_token,
+        }))
+    }
+
+    /// Revokes the family of a refresh token, logging out the session it belongs to.
+    /// Returns whether the token was found.
+    pub async fn revoke_refresh_token(&self, token: &str) -> Result<bool> {
+        let mut tx = self.database.transaction().await?;
+
+        let family: Option<Uuid> =
+            sqlx::query_scalar("SELECT family FROM refresh_tokens WHERE token_hash = $1")
+                .bind(tokens::hash_secret(token))
+                .fetch_optional(&mut tx)
+                .await?;
+
+        if let Some(family) = &family {
+            revoke_refresh_token_family(&mut tx, family).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(family.is_some())
+    }
+
+    /// Gives the user making the request a new subject and revokes all their refresh
+    /// tokens, so that every JWT issued to them stops working immediately.
+    pub async fn rotate_subject(&self, identity: &Identity) -> Result<()> {
+        identity.ensure_not_api_key()?;
+
+        let mut tx = self.database.transaction().await?;
+
+        end_sessions(&mut tx, identity.user_id).await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(
+            user_uuid = identity.user_uuid.to_string(),
+            "subject rotated, all sessions revoked"
+        );
+
+        Ok(())
+    }
+
+    /// Keeps what is needed to complete a single sign-on login once the provider
+    /// redirects the user back with `state`.
+    pub async fn create_oidc_login(
+        &self,
+        state: &str,
+        pkce_verifier: &str,
+        nonce: &str,
+    ) -> Result<()> {
+        let mut conn = self.database.connection().await?;
+
+        sqlx::query("DELETE FROM oidc_logins WHERE expires_at < NOW() AT TIME ZONE 'UTC'")
+            .execute(&mut conn)
+            .await?;
+
+        let sql = r"
+            INSERT INTO oidc_logins (
+                state_hash,
+                pkce_verifier,
+                nonce,
+                expires_at
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                NOW() AT TIME ZONE 'UTC' + $4 * INTERVAL '1 minute'
+            )
+        ";
+
+        sqlx::query(sql)
+            .bind(tokens::hash_secret(state))
+            .bind(pkce_verifier)
+            .bind(nonce)
+            .bind(OIDC_LOGIN_EXPIRY_MINUTES)
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the PKCE verifier and nonce of the single sign-on login with `state`,
+    /// or `None` if there is no such login or it has expired. A login can only be
+    /// completed once.
+    pub async fn take_oidc_login(&self, state: &str) -> Result<Option<(String, String)>> {
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            DELETE FROM
+                oidc_logins
+            WHERE
+                state_hash = $1
+            RETURNING
+                pkce_verifier,
+                nonce,
+                expires_at > NOW() AT TIME ZONE 'UTC'
+        ";
+
+        let login: Option<(String, String, bool)> = sqlx::query_as(sql)
+            .bind(tokens::hash_secret(state))
+            .fetch_optional(&mut conn)
+            .await?;
+
+        Ok(login
+            .filter(|(_, _, valid)| *valid)
+            .map(|(pkce_verifier, nonce, _)| (pkce_verifier, nonce)))
+    }
+
+    /// Returns the subject of the user with an identity at an OpenID Connect provider.
+    /// Users who logged in with the provider before are found by the provider's
+    /// subject, otherwise the user with the same verified email address is linked to
+    /// the identity. If there is no such user either, one is provisioned if
+    /// `provisioning` is given, otherwise `None` is returned.
+    ///
+    /// [`find_or_provision_external_user`] not called by APIs with an identity, the
+    /// provider has authenticated the user, so no access checks needed.
+    pub async fn find_or_provision_external_user(
+        &self,
+        identity: &ExternalIdentity,
+        provisioning: Option<&Provisioning>,
+    ) -> Result<Option<String>> {
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            SELECT
+                u.subject
+            FROM
+                user_identities i
+                INNER JOIN
+                users u ON u.id = i.user_id
+            WHERE
+                i.issuer = $1
+                AND
+                i.subject = $2
+                AND
+                u.deleted = false
+        ";
+
+        let subject: Option<String> = sqlx::query_scalar(sql)
+            .bind(&identity.issuer)
+            .bind(&identity.subject)
+            .fetch_optional(&mut tx)
+            .await?;
+
+        if subject.is_some() {
+            return Ok(subject);
+        }
+
+        let email = match &identity.email {
+            Some(email) => email,
+            None => {
+                tracing::debug!(
+                    issuer = identity.issuer,
+                    "identity has no email address, can't link or provision a user"
+                );
+                return Ok(None);
+            }
+        };
+
+        let user: Option<(i64, String)> = if identity.email_verified {
+            let sql = r"
+                SELECT
+                    id,
+                    subject
+                FROM
+                    users
+                WHERE
+                    LOWER(email) = LOWER($1)
+                    AND
+                    deleted = false
+                ORDER BY
+                    id ASC
+                LIMIT 1
+            ";
+
+            sqlx::query_as(sql)
+                .bind(email)
+                .fetch_optional(&mut tx)
+                .await?
+        } else {
+            None
+        };
+
+        let (user_id, subject) = match (user, provisioning) {
+            (Some(user), _) => user,
+            (None, Some(provisioning)) => provision_user(&mut tx, email, provisioning).await?,
+            (None, None) => return Ok(None),
+        };
+
+        let sql = r"
+            INSERT INTO user_identities (
+                user_id,
+                issuer,
+                subject
+            ) VALUES (
+                $1,
+                $2,
+                $3
+            )
+        ";
+
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(&identity.issuer)
+            .bind(&identity.subject)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(
+            user_id = user_id,
+            issuer = identity.issuer,
+            "linked identity to user"
+        );
+
+        Ok(Some(subject))
+    }
+}
+
+async fn insert_refresh_token(
+    conn: &mut DbConnection,
+    user_id: i64,
+    family: &Uuid,
+) -> Result<String> {
+    let sql = r"
+            INSERT INTO refresh_tokens (
+                user_id,
+                family,
+                token_hash,
+                expires_at
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                NOW() AT TIME ZONE 'UTC' + $4 * INTERVAL '1 day'
+            )
+        ";
+
+    let token = tokens::generate_secret();
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(family)
+        .bind(tokens::hash_secret(&token))
+        .bind(REFRESH_TOKEN_EXPIRY_DAYS)
+        .execute(conn)
+        .await?;
+
+    Ok(token)
+}
+
+async fn revoke_refresh_token_family(conn: &mut DbConnection, family: &Uuid) -> Result<()> {
+    let sql = r"
+            UPDATE
+                refresh_tokens
+            SET
+                revoked_at = NOW() AT TIME ZONE 'UTC'
+            WHERE
+                family = $1
+                AND
+                revoked_at IS NULL
+        ";
+
+    sqlx::query(sql).bind(family).execute(conn).await?;
+
+    Ok(())
+}
+
+/// Creates a user in the account and with the role of `provisioning`, returning its
+/// ID and subject.
+async fn provision_user(
+    conn: &mut DbConnection,
+    email: &str,
+    provisioning: &Provisioning,
+) -> Result<(i64, String)> {
+    let sql = r"
+        SELECT
+            id
+        FROM
+            accounts
+        WHERE
+            uuid = $1
+            AND
+            deleted = false
+    ";
+
+    let account_id: i64 = sqlx::query_scalar(sql)
+        .bind(provisioning.account_uuid)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| RepositoryError::NotFound {
+            entity_type: ENTITY_ACCOUNT.to_string(),
+            id: ShortId::from_uuid(&provisioning.account_uuid).to_string(),
+        })?;
+
+    let (user_id, uuid, subject) = create_user(&mut *conn, email).await?;
+    add_user_to_account(&mut *conn, user_id, account_id, provisioning.role).await?;
+
+    tracing::debug!(
+        user_uuid = uuid.to_string(),
+        account_uuid = provisioning.account_uuid.to_string(),
+        "provisioned user"
+    );
+
+    Ok((user_id, subject))
+}
+
+/// Creates a user without a password, returning its ID, UUID and subject.
+pub(super) async fn create_user(
+    conn: &mut DbConnection,
+    email: &str,
+) -> Result<(i64, Uuid, String)> {
+    let uuid = Uuid::new_v4();
+    let short_id: ShortId = uuid.into();
+    let subject = Ulid::new().to_string();
+
+    let sql = r"
+        INSERT INTO users (
+            uuid,
+            shortid,
+            subject,
+            email
+        ) VALUES (
+            $1,
+            $2,
+            $3,
+            $4
+        ) RETURNING id
+    ";
+
+    let user_id: i64 = sqlx::query_scalar(sql)
+        .bind(uuid)
+        .bind(short_id.to_string())
+        .bind(&subject)
+        .bind(email)
+        .fetch_one(conn)
+        .await?;
+
+    Ok((user_id, uuid, subject))
+}
+
+/// Makes a user a member of an account with a role, unless they already are.
+pub(super) async fn add_user_to_account(
+    conn: &mut DbConnection,
+    user_id: i64,
+    account_id: i64,
+    role: UserRole,
+) -> Result<()> {
+    let sql = r"
+        INSERT INTO user_accounts (
+            user_id,
+            account_id
+        ) VALUES (
+            $1,
+            $2
+        )
+        ON CONFLICT DO NOTHING
+    ";
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(account_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let sql = r"
+        INSERT INTO user_roles (
+            user_id,
+            account_id,
+            role
+        ) VALUES (
+            $1,
+            $2,
+            $3
+        )
+        ON CONFLICT DO NOTHING
+    ";
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(account_id)
+        .bind(role)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Replaces the password of a user, lifting any lockout and invalidating outstanding
+/// password reset tokens.
+pub(super) async fn update_password(
+    conn: &mut DbConnection,
+    user_id: i64,
+    email: &str,
+    new_password: &str,
+) -> Result<()> {
+    password::check_policy(new_password, email).map_err(RepositoryError::BadArgument)?;
+
+    // Hashing is deliberately slow, keep it off the async runtime.
+    let new_password = new_password.to_string();
+    let hash = tokio::task::spawn_blocking(move || password::hash(&new_password)).await??;
+
+    let sql = r"
+        UPDATE
+            users
+        SET
+            password_hash = $2,
+            failed_logins = 0,
+            locked_until = NULL,
+            updated_at = NOW() AT TIME ZONE 'UTC',
+            updated_by = $1
+        WHERE
+            id = $1
+    ";
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(hash)
+        .execute(&mut *conn)
+        .await?;
+
+    let sql = r"
+        UPDATE
+            password_reset_tokens
+        SET
+            used_at = NOW() AT TIME ZONE 'UTC'
+        WHERE
+            user_id = $1
+            AND
+            used_at IS NULL
+    ";
+
+    sqlx::query(sql).bind(user_id).execute(conn).await?;
+
+    Ok(())
+}
+
+/// Ends every session of a user by giving them a new subject, which their JWTs no
+/// longer match, and revoking their refresh tokens.
+async fn end_sessions(conn: &mut DbConnection, user_id: i64) -> Result<()> {
+    let sql = r"
+        UPDATE
+            users
+        SET
+            subject = $2,
+            updated_at = NOW() AT TIME ZONE 'UTC',
+            updated_by = $1
+        WHERE
+            id = $1
+    ";
+
+    sqlx::query(sql)
+        .bind(user_id)
+        .bind(Ulid::new().to_string())
+        .execute(&mut *conn)
+        .await?;
+
+    let sql = r"
+        UPDATE
+            refresh_tokens
+        SET
+            revoked_at = NOW() AT TIME ZONE 'UTC'
+        WHERE
+            user_id = $1
+            AND
+            revoked_at IS NULL
+    ";
+
+    sqlx::query(sql).bind(user_id).execute(conn).await?;
+
+    Ok(())
+}
+
+/// Verifies a password against a hash, or against a dummy hash if there is none so
+/// that it takes as long either way.
+async fn verify_password(password: &str, hash: Option<String>) -> Result<bool> {
+    // Verifying is deliberately slow, keep it off the async runtime.
+    let password = password.to_string();
+    Ok(
+        tokio::task::spawn_blocking(move || password::verify_or_dummy(&password, hash.as_deref()))
+            .await?,
+    )
 }
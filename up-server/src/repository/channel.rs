@@ -0,0 +1,538 @@
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{
+        get_check_account_id, get_project_account_id,
+        notification::{ensure_destination, CreateNotification, Notification, UpdateNotification},
+        RepositoryError, Result,
+    },
+    shortid::ShortId,
+};
+
+pub(super) const ENTITY_CHANNEL: &str = "channel";
+
+/// Channels are notifications that belong to a project instead of a single check,
+/// and are attached to checks explicitly.
+#[derive(Clone)]
+pub struct ChannelRepository {
+    database: Database,
+}
+
+impl ChannelRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn read_one(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<Notification> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "reading channel"
+        );
+
+        let sql = r"
+            SELECT
+                *
+            FROM
+                notifications
+            WHERE
+                uuid = $1
+                AND
+                check_id IS NULL
+                AND
+                account_id = $2
+                AND
+                project_id = $3
+                AND
+                deleted = false
+        ";
+
+        let channel: Option<Notification> = sqlx::query_as(sql)
+            .bind(uuid)
+            .bind(account_id)
+            .bind(project_id)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        channel.ok_or_else(|| RepositoryError::NotFound {
+            entity_type: ENTITY_CHANNEL.to_string(),
+            id: ShortId::from_uuid(uuid).to_string(),
+        })
+    }
+
+    pub async fn read_all(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+    ) -> Result<Vec<Notification>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(project_uuid = project_uuid.to_string(), "reading channels");
+
+        let sql = r"
+            SELECT
+                *
+            FROM
+                notifications
+            WHERE
+                check_id IS NULL
+                AND
+                account_id = $1
+                AND
+                project_id = $2
+                AND
+                deleted = false
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    pub async fn read_all_for_check(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+    ) -> Result<Vec<Notification>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (check_id, account_id) =
+            get_check_account_id(&mut conn, check_uuid, project_id, &identity.account_ids())
+                .await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            check_uuid = check_uuid.to_string(),
+            "reading channels attached to check"
+        );
+
+        let sql = r"
+            SELECT
+                n.*
+            FROM
+                notifications n
+                INNER JOIN
+                check_notifications cn ON cn.notification_id = n.id
+            WHERE
+                cn.check_id = $1
+                AND
+                n.check_id IS NULL
+                AND
+                n.account_id = $2
+                AND
+                n.project_id = $3
+                AND
+                n.deleted = false
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(check_id)
+            .bind(account_id)
+            .bind(project_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    pub async fn create(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        request: CreateNotification,
+    ) -> Result<Notification> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        ensure_destination(
+            &request.notification_type,
+            request.email.as_deref(),
+            request.url.as_deref(),
+            request.phone_number.as_deref(),
+        )?;
+
+        let sql = r"
+            INSERT INTO notifications (
+                account_id,
+                project_id,
+                uuid,
+                shortid,
+                name,
+                notification_type,
+                email,
+                url,
+                phone_number,
+                max_retries,
+                retry_backoff_seconds,
+                retry_backoff_max_seconds,
+                digest_window_seconds,
+                created_by
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8,
+                $9,
+                COALESCE($10, 5),
+                COALESCE($11, 30),
+                COALESCE($12, 3600),
+                NULLIF($13, 0),
+                $14
+            )
+            RETURNING *
+        ";
+
+        let uuid = Uuid::new_v4();
+        let short_id: ShortId = uuid.into();
+
+        let channel: Notification = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(short_id.to_string())
+            .bind(request.name.as_deref().unwrap_or(""))
+            .bind(&request.notification_type)
+            .bind(&request.email)
+            .bind(&request.url)
+            .bind(&request.phone_number)
+            .bind(request.max_retries)
+            .bind(request.retry_backoff_seconds)
+            .bind(request.retry_backoff_max_seconds)
+            .bind(request.digest_window_seconds)
+            .bind(identity.user_id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = channel.uuid.to_string(),
+            name = request.name,
+            "channel created"
+        );
+
+        Ok(channel)
+    }
+
+    pub async fn update(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+        request: UpdateNotification,
+    ) -> Result<Notification> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            UPDATE
+                notifications
+            SET
+                name = COALESCE($4, name),
+                email = COALESCE($5, email),
+                url = COALESCE($6, url),
+                phone_number = COALESCE($7, phone_number),
+                max_retries = COALESCE($8, max_retries),
+                retry_backoff_seconds = COALESCE($9, retry_backoff_seconds),
+                retry_backoff_max_seconds = COALESCE($10, retry_backoff_max_seconds),
+                digest_window_seconds = CASE
+                    WHEN $11::INTEGER IS NULL THEN digest_window_seconds
+                    ELSE NULLIF($11, 0)
+                END,
+                updated_at = NOW() AT TIME ZONE 'UTC',
+                updated_by = $12
+            WHERE
+                check_id IS NULL
+                AND
+                account_id = $1
+                AND
+                project_id = $2
+                AND
+                uuid = $3
+                AND
+                deleted = false
+            RETURNING *
+        ";
+
+        let channel: Option<Notification> = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(&request.name)
+            .bind(&request.email)
+            .bind(&request.url)
+            .bind(&request.phone_number)
+            .bind(request.max_retries)
+            .bind(request.retry_backoff_seconds)
+            .bind(request.retry_backoff_max_seconds)
+            .bind(request.digest_window_seconds)
+            .bind(identity.user_id)
+            .fetch_optional(&mut tx)
+            .await?;
+
+        let channel = channel.ok_or_else(|| RepositoryError::NotFound {
+            entity_type: ENTITY_CHANNEL.to_string(),
+            id: ShortId::from_uuid(uuid).to_string(),
+        })?;
+
+        ensure_destination(
+            &channel.notification_type,
+            channel.email.as_deref(),
+            channel.url.as_deref(),
+            channel.phone_number.as_deref(),
+        )?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "channel updated"
+        );
+
+        Ok(channel)
+    }
+
+    pub async fn delete(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "deleting channel"
+        );
+
+        let sql = r"
+            UPDATE
+                notifications
+            SET
+                deleted = true,
+                deleted_at = NOW() AT TIME ZONE 'UTC',
+                deleted_by = $4
+            WHERE
+                check_id IS NULL
+                AND
+                account_id = $1
+                AND
+                project_id = $2
+                AND
+                uuid = $3
+        ";
+
+        let deleted = sqlx::query(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(identity.user_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        tx.commit().await?;
+
+        if deleted {
+            tracing::trace!(uuid = uuid.to_string(), "channel deleted");
+        }
+
+        Ok(deleted)
+    }
+
+    pub async fn attach(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<()> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (check_id, account_id) =
+            get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let channel_id = get_channel_id(&mut tx, uuid, project_id, account_id).await?;
+
+        let sql = r"
+            INSERT INTO check_notifications (
+                check_id,
+                notification_id,
+                created_by
+            ) VALUES (
+                $1,
+                $2,
+                $3
+            )
+            ON CONFLICT DO NOTHING
+        ";
+
+        sqlx::query(sql)
+            .bind(check_id)
+            .bind(channel_id)
+            .bind(identity.user_id)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            check_uuid = check_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "channel attached to check"
+        );
+
+        Ok(())
+    }
+
+    pub async fn detach(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (check_id, account_id) =
+            get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let channel_id = get_channel_id(&mut tx, uuid, project_id, account_id).await?;
+
+        let sql = r"
+            DELETE FROM
+                check_notifications
+            WHERE
+                check_id = $1
+                AND
+                notification_id = $2
+        ";
+
+        let detached = sqlx::query(sql)
+            .bind(check_id)
+            .bind(channel_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        tx.commit().await?;
+
+        if detached {
+            tracing::trace!(
+                check_uuid = check_uuid.to_string(),
+                uuid = uuid.to_string(),
+                "channel detached from check"
+            );
+        }
+
+        Ok(detached)
+    }
+}
+
+pub(super) async fn get_channel_id(
+    conn: &mut DbConnection,
+    uuid: &Uuid,
+    project_id: i64,
+    account_id: i64,
+) -> Result<i64> {
+    let sql = r"
+            SELECT
+                id
+            FROM
+                notifications
+            WHERE
+                uuid = $1
+                AND
+                check_id IS NULL
+                AND
+                project_id = $2
+                AND
+                account_id = $3
+                AND
+                deleted = false
+            LIMIT 1
+        ";
+
+    let id: Option<(i64,)> = sqlx::query_as(sql)
+        .bind(uuid)
+        .bind(project_id)
+        .bind(account_id)
+        .fetch_optional(conn)
+        .await?;
+
+    id.map(|id| id.0).ok_or(RepositoryError::NotFound {
+        entity_type: ENTITY_CHANNEL.to_string(),
+        id: ShortId::from_uuid(uuid).to_string(),
+    })
+}
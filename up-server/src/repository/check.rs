@@ -4,7 +4,13 @@ use uuid::Uuid;
 use crate::{
     auth::Identity,
     database::Database,
-    repository::{get_check_account_id, get_project_account_id, RepositoryError, Result},
+    repository::{
+        get_check_account_id, get_project_account_id,
+        incident::{open_incident, resolve_incident},
+        maintenance::is_check_in_maintenance,
+        notification::enqueue_alert,
+        RepositoryError, Result,
+    },
     shortid::ShortId,
 };
 
@@ -20,6 +26,7 @@ pub struct Check {
     pub name: String,
     pub description: String,
     pub status: CheckStatus,
+    pub severity: CheckSeverity,
     pub schedule_type: ScheduleType,
     pub ping_period: i32,
     pub ping_period_units: PeriodUnits,
@@ -31,14 +38,14 @@ pub struct Check {
     pub updated_at: Option<NaiveDateTime>,
 }
 
-#[derive(sqlx::Type)]
+#[derive(sqlx::Type, Debug)]
 #[sqlx(type_name = "schedule_type", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ScheduleType {
     Simple,
     Cron,
 }
 
-#[derive(sqlx::Type, Copy, Clone)]
+#[derive(sqlx::Type, Copy, Clone, Debug, PartialEq, Eq)]
 #[sqlx(type_name = "check_status", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum CheckStatus {
     Up,
@@ -56,7 +63,16 @@ impl ToString for CheckStatus {
     }
 }
 
-#[derive(sqlx::Type)]
+/// How urgent alerts for a check are, alerts for low severity checks are held during
+/// their recipient's quiet hours.
+#[derive(sqlx::Type, Copy, Clone, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "check_severity", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CheckSeverity {
+    Low,
+    High,
+}
+
+#[derive(sqlx::Type, Debug)]
 #[sqlx(type_name = "period_units", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PeriodUnits {
     Hours,
@@ -69,6 +85,7 @@ pub enum PeriodUnits {
 pub enum NotificationType {
     Email,
     Webhook,
+    Sms,
 }
 
 impl ToString for NotificationType {
@@ -76,6 +93,7 @@ impl ToString for NotificationType {
         match self {
             NotificationType::Email => "EMAIL".to_string(),
             NotificationType::Webhook => "WEBHOOK".to_string(),
+            NotificationType::Sms => "SMS".to_string(),
         }
     }
 }
@@ -83,10 +101,12 @@ impl ToString for NotificationType {
 pub struct CreateCheck {
     pub project_uuid: Uuid,
     pub name: String,
+    pub severity: Option<CheckSeverity>,
 }
 
 pub struct UpdateCheck {
     pub name: Option<String>,
+    pub severity: Option<CheckSeverity>,
 }
 
 #[derive(Clone)]
@@ -189,7 +209,7 @@ impl CheckRepository {
         let (project_id, account_id) =
             get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
 
-        if !identity.is_member_in_account_with_id(account_id) {
+        if !identity.can_manage_checks_in_project_with_id(project_id) {
             return Err(RepositoryError::Forbidden);
         }
 
@@ -211,6 +231,7 @@ impl CheckRepository {
                 shortid,
                 ping_key,
                 name,
+                severity,
                 created_by
             ) VALUES (
                 $1,
@@ -219,6 +240,7 @@ impl CheckRepository {
                 $4,
                 $5,
                 $6,
+                COALESCE($8, 'HIGH'::check_severity),
                 $7
             ) RETURNING *
         ";
@@ -231,6 +253,7 @@ impl CheckRepository {
             .bind(ping_key.to_string())
             .bind(&request.name)
             .bind(identity.user_id)
+            .bind(request.severity)
             .fetch_one(&mut tx)
             .await?;
 
@@ -259,7 +282,7 @@ impl CheckRepository {
         let (project_id, account_id) =
             get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
 
-        if !identity.is_member_in_account_with_id(account_id) {
+        if !identity.can_manage_checks_in_project_with_id(project_id) {
             return Err(RepositoryError::Forbidden);
         }
 
@@ -268,6 +291,7 @@ impl CheckRepository {
                 checks
             SET
                 name = COALESCE($4,name),
+                severity = COALESCE($6,severity),
                 updated_at = NOW() AT TIME ZONE 'UTC',
                 updated_by = $5
             WHERE
@@ -287,6 +311,7 @@ impl CheckRepository {
             .bind(account_id)
             .bind(&request.name)
             .bind(identity.user_id)
+            .bind(request.severity)
             .fetch_optional(&mut tx)
             .await?;
 
@@ -317,7 +342,7 @@ impl CheckRepository {
         let (project_id, account_id) =
             get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
 
-        if !identity.is_member_in_account_with_id(account_id) {
+        if !identity.can_manage_checks_in_project_with_id(project_id) {
             return Err(RepositoryError::Forbidden);
         }
 
@@ -371,17 +396,219 @@ impl CheckRepository {
                 AND
                 deleted = false
             RETURNING
+                id,
                 uuid
         ";
 
-        let check_uuid: Option<(Uuid,)> = sqlx::query_as(sql)
+        let check: Option<(i64, Uuid)> = sqlx::query_as(sql)
             .bind(key)
             .fetch_optional(&mut tx)
             .await?;
 
+        if let Some((check_id, _)) = check {
+            resolve_incident(&mut tx, check_id).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(check.map(|(_, uuid)| uuid))
+    }
+
+    /// Reads the checks that a check directly depends on.
+    pub async fn read_dependencies(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+    ) -> Result<Vec<Check>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (check_id, account_id) =
+            get_check_account_id(&mut conn, check_uuid, project_id, &identity.account_ids())
+                .await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            check_uuid = check_uuid.to_string(),
+            "reading check dependencies"
+        );
+
+        let sql = r"
+            SELECT
+                c.*
+            FROM
+                checks c
+                INNER JOIN
+                check_dependencies d ON d.parent_check_id = c.id
+            WHERE
+                d.check_id = $1
+                AND
+                c.account_id = $2
+                AND
+                c.deleted = false
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(check_id)
+            .bind(account_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    /// Makes a check depend on another check in the same project, rejecting dependencies
+    /// that would form a cycle.
+    pub async fn add_dependency(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+        parent_check_uuid: &Uuid,
+    ) -> Result<()> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (check_id, _) =
+            get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
+        let (parent_check_id, _) = get_check_account_id(
+            &mut tx,
+            parent_check_uuid,
+            project_id,
+            &identity.account_ids(),
+        )
+        .await?;
+
+        if !identity.can_manage_checks_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        // Serialize changes to the dependencies of a project by locking its row, so that
+        // two concurrent changes can't form a cycle that neither of them would on its own.
+        sqlx::query("SELECT id FROM projects WHERE id = $1 FOR UPDATE")
+            .bind(project_id)
+            .execute(&mut tx)
+            .await?;
+
+        let sql = r"
+            WITH RECURSIVE ancestors (id) AS (
+                SELECT
+                    $1::BIGINT
+                UNION
+                SELECT
+                    d.parent_check_id
+                FROM
+                    check_dependencies d
+                    INNER JOIN
+                    ancestors a ON d.check_id = a.id
+            )
+            SELECT EXISTS (
+                SELECT 1 FROM ancestors WHERE id = $2
+            )
+        ";
+
+        let (cycle,): (bool,) = sqlx::query_as(sql)
+            .bind(parent_check_id)
+            .bind(check_id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        if cycle {
+            return Err(RepositoryError::BadArgument(format!(
+                "check {} already depends on check {}, dependencies can't form a cycle",
+                ShortId::from_uuid(parent_check_uuid),
+                ShortId::from_uuid(check_uuid)
+            )));
+        }
+
+        let sql = r"
+            INSERT INTO check_dependencies (
+                check_id,
+                parent_check_id,
+                created_by
+            ) VALUES (
+                $1,
+                $2,
+                $3
+            )
+            ON CONFLICT DO NOTHING
+        ";
+
+        sqlx::query(sql)
+            .bind(check_id)
+            .bind(parent_check_id)
+            .bind(identity.user_id)
+            .execute(&mut tx)
+            .await?;
+
         tx.commit().await?;
 
-        Ok(check_uuid.map(|id| id.0))
+        tracing::trace!(
+            check_uuid = check_uuid.to_string(),
+            parent_check_uuid = parent_check_uuid.to_string(),
+            "check dependency added"
+        );
+
+        Ok(())
+    }
+
+    pub async fn remove_dependency(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+        parent_check_uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (check_id, _) =
+            get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
+        let (parent_check_id, _) = get_check_account_id(
+            &mut tx,
+            parent_check_uuid,
+            project_id,
+            &identity.account_ids(),
+        )
+        .await?;
+
+        if !identity.can_manage_checks_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            DELETE FROM
+                check_dependencies
+            WHERE
+                check_id = $1
+                AND
+                parent_check_id = $2
+        ";
+
+        let removed = sqlx::query(sql)
+            .bind(check_id)
+            .bind(parent_check_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        tx.commit().await?;
+
+        if removed {
+            tracing::trace!(
+                check_uuid = check_uuid.to_string(),
+                parent_check_uuid = parent_check_uuid.to_string(),
+                "check dependency removed"
+            );
+        }
+
+        Ok(removed)
     }
 
     /// [`enqueue_alerts_for_overdue_pings`] not called by APIs, so no access checks needed.
@@ -441,8 +668,10 @@ impl CheckRepository {
         let overdue_pings: Vec<(i64, Uuid, CheckStatus, String, NaiveDateTime)> =
             sqlx::query_as(overdue_ping_sql).fetch_all(&mut tx).await?;
 
+        let overdue_check_ids: Vec<i64> = overdue_pings.iter().map(|p| p.0).collect();
+
         for ping_details in overdue_pings {
-            let (check_id, check_uuid, check_status, check_name, last_ping_at) = ping_details;
+            let (check_id, check_uuid, _, check_name, last_ping_at) = ping_details;
 
             let sql = r"
                 UPDATE
@@ -472,6 +701,93 @@ impl CheckRepository {
                 });
             }
 
+            let (incident_id, acknowledged) = open_incident(&mut tx, check_id).await?;
+
+            if acknowledged {
+                tracing::debug!(
+                    check_uuid = check_uuid.to_string(),
+                    name = check_name,
+                    "incident is acknowledged, not enqueuing alerts"
+                );
+                continue;
+            }
+
+            if is_check_in_maintenance(&mut tx, check_id).await? {
+                tracing::debug!(
+                    check_uuid = check_uuid.to_string(),
+                    name = check_name,
+                    "check is in a maintenance window, not enqueuing alerts"
+                );
+                continue;
+            }
+
+            // Checks that are overdue in this same pass count as down, so the outcome does
+            // not depend on the order in which the checks are processed.
+            let sql = r"
+                WITH RECURSIVE ancestors (id) AS (
+                    SELECT
+                        parent_check_id
+                    FROM
+                        check_dependencies
+                    WHERE
+                        check_id = $1
+                    UNION
+                    SELECT
+                        d.parent_check_id
+                    FROM
+                        check_dependencies d
+                        INNER JOIN
+                        ancestors a ON d.check_id = a.id
+                )
+                SELECT EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        ancestors a
+                        INNER JOIN
+                        checks c ON c.id = a.id
+                    WHERE
+                        c.deleted = false
+                        AND
+                        (c.status = 'DOWN' OR c.id = ANY($2))
+                )
+            ";
+
+            let (parent_down,): (bool,) = sqlx::query_as(sql)
+                .bind(check_id)
+                .bind(&overdue_check_ids)
+                .fetch_one(&mut tx)
+                .await?;
+
+            if parent_down {
+                tracing::debug!(
+                    check_uuid = check_uuid.to_string(),
+                    name = check_name,
+                    "check depends on a check that is down, not enqueuing alerts"
+                );
+                continue;
+            }
+
+            let sql = r"
+                SELECT
+                    p.id
+                FROM
+                    checks c
+                    LEFT JOIN
+                    escalation_policies p ON p.id = c.escalation_policy_id AND p.deleted = false
+                WHERE
+                    c.id = $1
+            ";
+
+            let (escalation_policy_id,): (Option<i64>,) = sqlx::query_as(sql)
+                .bind(check_id)
+                .fetch_one(&mut tx)
+                .await?;
+
+            // Checks with an escalation policy alert its first tier, later tiers are
+            // alerted by [`EscalationPolicyRepository::escalate_incidents`].
+            let escalation_tier = escalation_policy_id.map(|_| 0);
+
             let sql = r"
                 SELECT
                     id,
@@ -482,12 +798,36 @@ impl CheckRepository {
                 FROM
                     notifications
                 WHERE
-                    check_id = $1
+                    (
+                        (
+                            $3::BIGINT IS NULL
+                            AND
+                            (
+                                check_id = $1
+                                OR
+                                id IN (
+                                    SELECT notification_id
+                                    FROM check_notifications
+                                    WHERE check_id = $1
+                                )
+                            )
+                        )
+                        OR
+                        id IN (
+                            SELECT tn.notification_id
+                            FROM escalation_policy_tiers t
+                            INNER JOIN escalation_policy_tier_notifications tn ON tn.tier_id = t.id
+                            WHERE t.escalation_policy_id = $3 AND t.position = 0
+                        )
+                    )
+                    AND deleted = false
                     AND NOT EXISTS (
                         SELECT 1
                         FROM notification_alerts a
                         WHERE
                             a.notification_id = notifications.id
+                            AND
+                            a.incident_id = $2
                     )
             ";
 
@@ -500,29 +840,24 @@ impl CheckRepository {
                 i32,
             )> = sqlx::query_as(sql)
                 .bind(check_id)
+                .bind(incident_id)
+                .bind(escalation_policy_id)
                 .fetch_all(&mut tx)
                 .await?;
 
             for (notification_id, notification_type, email, url, retries_remaining) in
                 notifications_to_alert
             {
-                let sql = r"
-                INSERT INTO notification_alerts (
+                enqueue_alert(
+                    &mut tx,
                     notification_id,
-                    check_status,
-                    retries_remaining
-                ) VALUES (
-                    $1,
-                    $2,
-                    $3
-                );
-                ";
-                sqlx::query(sql)
-                    .bind(notification_id)
-                    .bind(check_status)
-                    .bind(retries_remaining)
-                    .execute(&mut tx)
-                    .await?;
+                    check_id,
+                    incident_id,
+                    CheckStatus::Down,
+                    retries_remaining,
+                    escalation_tier,
+                )
+                .await?;
 
                 tracing::debug!(
                     check_uuid = check_uuid.to_string(),
@@ -534,6 +869,22 @@ impl CheckRepository {
                     "enqueuing alert"
                 );
             }
+
+            if escalation_tier.is_some() {
+                let sql = r"
+                    UPDATE
+                        incidents
+                    SET
+                        escalation_tier = 0,
+                        escalated_at = NOW() AT TIME ZONE 'UTC'
+                    WHERE
+                        id = $1
+                        AND
+                        escalation_tier IS NULL
+                ";
+
+                sqlx::query(sql).bind(incident_id).execute(&mut tx).await?;
+            }
         }
 
         tx.commit().await?;
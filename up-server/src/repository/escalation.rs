@@ -0,0 +1,748 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{
+        check::CheckStatus,
+        get_check_account_id, get_project_account_id,
+        maintenance::is_check_in_maintenance,
+        notification::{enqueue_alert, ENTITY_NOTIFICATION},
+        RepositoryError, Result,
+    },
+    shortid::ShortId,
+};
+
+const ENTITY_ESCALATION_POLICY: &str = "escalation policy";
+
+#[derive(sqlx::FromRow)]
+pub struct EscalationPolicy {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct EscalationTier {
+    pub escalation_policy_id: i64,
+    pub position: i32,
+    pub escalate_after_minutes: i32,
+    pub notification_uuids: Vec<Uuid>,
+}
+
+pub struct CreateEscalationPolicy {
+    pub name: Option<String>,
+    pub tiers: Vec<CreateEscalationTier>,
+}
+
+pub struct UpdateEscalationPolicy {
+    pub name: Option<String>,
+    pub tiers: Option<Vec<CreateEscalationTier>>,
+}
+
+pub struct CreateEscalationTier {
+    pub escalate_after_minutes: Option<i32>,
+    pub notification_uuids: Vec<Uuid>,
+}
+
+#[derive(Clone)]
+pub struct EscalationPolicyRepository {
+    database: Database,
+}
+
+impl EscalationPolicyRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn read_one(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<(EscalationPolicy, Vec<EscalationTier>)> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "reading escalation policy"
+        );
+
+        let policy = read_policy(&mut conn, uuid, project_id, account_id).await?;
+        let tiers = read_tiers(&mut conn, &[policy.id]).await?;
+
+        Ok((policy, tiers))
+    }
+
+    pub async fn read_all(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+    ) -> Result<Vec<(EscalationPolicy, Vec<EscalationTier>)>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            "reading escalation policies"
+        );
+
+        let sql = r"
+            SELECT
+                *
+            FROM
+                escalation_policies
+            WHERE
+                project_id = $1
+                AND
+                account_id = $2
+                AND
+                deleted = false
+            ORDER BY
+                created_at ASC
+        ";
+
+        let policies: Vec<EscalationPolicy> = sqlx::query_as(sql)
+            .bind(project_id)
+            .bind(account_id)
+            .fetch_all(&mut conn)
+            .await?;
+
+        let policy_ids: Vec<i64> = policies.iter().map(|p| p.id).collect();
+        let mut tiers = read_tiers(&mut conn, &policy_ids).await?;
+
+        Ok(policies
+            .into_iter()
+            .map(|policy| {
+                let (policy_tiers, rest): (Vec<_>, Vec<_>) = tiers
+                    .drain(..)
+                    .partition(|t| t.escalation_policy_id == policy.id);
+                tiers = rest;
+                (policy, policy_tiers)
+            })
+            .collect())
+    }
+
+    pub async fn create(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        request: CreateEscalationPolicy,
+    ) -> Result<(EscalationPolicy, Vec<EscalationTier>)> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            INSERT INTO escalation_policies (
+                account_id,
+                project_id,
+                uuid,
+                shortid,
+                name,
+                created_by
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6
+            )
+            RETURNING *
+        ";
+
+        let uuid = Uuid::new_v4();
+        let short_id: ShortId = uuid.into();
+
+        let policy: EscalationPolicy = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(short_id.to_string())
+            .bind(request.name.as_deref().unwrap_or(""))
+            .bind(identity.user_id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        insert_tiers(&mut tx, policy.id, project_id, account_id, &request.tiers).await?;
+        let tiers = read_tiers(&mut tx, &[policy.id]).await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            name = request.name,
+            "escalation policy created"
+        );
+
+        Ok((policy, tiers))
+    }
+
+    /// Updates an escalation policy. If `tiers` are given, they replace all existing
+    /// tiers. Incidents that are already escalating continue from the tier position
+    /// they have reached.
+    pub async fn update(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+        request: UpdateEscalationPolicy,
+    ) -> Result<(EscalationPolicy, Vec<EscalationTier>)> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            UPDATE
+                escalation_policies
+            SET
+                name = COALESCE($4, name),
+                updated_at = NOW() AT TIME ZONE 'UTC',
+                updated_by = $5
+            WHERE
+                account_id = $1
+                AND
+                project_id = $2
+                AND
+                uuid = $3
+                AND
+                deleted = false
+            RETURNING *
+        ";
+
+        let policy: EscalationPolicy = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(&request.name)
+            .bind(identity.user_id)
+            .fetch_optional(&mut tx)
+            .await?
+            .ok_or_else(|| RepositoryError::NotFound {
+                entity_type: ENTITY_ESCALATION_POLICY.to_string(),
+                id: ShortId::from_uuid(uuid).to_string(),
+            })?;
+
+        if let Some(tiers) = &request.tiers {
+            sqlx::query("DELETE FROM escalation_policy_tiers WHERE escalation_policy_id = $1")
+                .bind(policy.id)
+                .execute(&mut tx)
+                .await?;
+            insert_tiers(&mut tx, policy.id, project_id, account_id, tiers).await?;
+        }
+
+        let tiers = read_tiers(&mut tx, &[policy.id]).await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "escalation policy updated"
+        );
+
+        Ok((policy, tiers))
+    }
+
+    /// Deletes an escalation policy, checks that used it alert through their own
+    /// notifications again.
+    pub async fn delete(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "deleting escalation policy"
+        );
+
+        let sql = r"
+            UPDATE
+                escalation_policies
+            SET
+                deleted = true,
+                deleted_at = NOW() AT TIME ZONE 'UTC',
+                deleted_by = $4
+            WHERE
+                project_id = $1
+                AND
+                account_id = $2
+                AND
+                uuid = $3
+                AND
+                deleted = false
+            RETURNING id
+        ";
+
+        let deleted: Option<(i64,)> = sqlx::query_as(sql)
+            .bind(project_id)
+            .bind(account_id)
+            .bind(uuid)
+            .bind(identity.user_id)
+            .fetch_optional(&mut tx)
+            .await?;
+
+        if let Some((id,)) = deleted {
+            sqlx::query(
+                "UPDATE checks SET escalation_policy_id = NULL WHERE escalation_policy_id = $1",
+            )
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        if deleted.is_some() {
+            tracing::trace!(uuid = uuid.to_string(), "escalation policy deleted");
+        }
+
+        Ok(deleted.is_some())
+    }
+
+    /// Reads the escalation policy a check alerts through.
+    pub async fn read_for_check(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+    ) -> Result<(EscalationPolicy, Vec<EscalationTier>)> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (check_id, _) =
+            get_check_account_id(&mut conn, check_uuid, project_id, &identity.account_ids())
+                .await?;
+
+        let sql = r"
+            SELECT
+                p.*
+            FROM
+                escalation_policies p
+                INNER JOIN
+                checks c ON c.escalation_policy_id = p.id
+            WHERE
+                c.id = $1
+                AND
+                p.deleted = false
+        ";
+
+        let policy: EscalationPolicy = sqlx::query_as(sql)
+            .bind(check_id)
+            .fetch_optional(&mut conn)
+            .await?
+            .ok_or_else(|| RepositoryError::NotFound {
+                entity_type: ENTITY_ESCALATION_POLICY.to_string(),
+                id: ShortId::from_uuid(check_uuid).to_string(),
+            })?;
+        let tiers = read_tiers(&mut conn, &[policy.id]).await?;
+
+        Ok((policy, tiers))
+    }
+
+    /// Makes a check alert through an escalation policy, replacing any policy it used
+    /// before.
+    pub async fn attach(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<()> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (check_id, account_id) =
+            get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let policy = read_policy(&mut tx, uuid, project_id, account_id).await?;
+
+        sqlx::query("UPDATE checks SET escalation_policy_id = $2 WHERE id = $1")
+            .bind(check_id)
+            .bind(policy.id)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            check_uuid = check_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "escalation policy attached to check"
+        );
+
+        Ok(())
+    }
+
+    /// Makes a check alert through its own notifications again.
+    pub async fn detach(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (check_id, _) =
+            get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            UPDATE
+                checks
+            SET
+                escalation_policy_id = NULL
+            WHERE
+                id = $1
+                AND
+                escalation_policy_id IS NOT NULL
+        ";
+
+        let detached = sqlx::query(sql)
+            .bind(check_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        tx.commit().await?;
+
+        if detached {
+            tracing::trace!(
+                check_uuid = check_uuid.to_string(),
+                "escalation policy detached from check"
+            );
+        }
+
+        Ok(detached)
+    }
+
+    /// Notifies the next tier of every incident that is still neither acknowledged nor
+    /// resolved once its current tier has had `escalate_after_minutes` to respond.
+    ///
+    /// [`escalate_incidents`] not called by APIs, so no access checks needed.
+    pub async fn escalate_incidents(&self) -> Result<()> {
+        let mut tx = self.database.transaction().await?;
+
+        tracing::trace!("checking for incidents to escalate");
+
+        let sql = r"
+            SELECT
+                i.id,
+                c.id,
+                c.uuid,
+                c.name,
+                next.id,
+                next.position
+            FROM
+                incidents i
+                INNER JOIN
+                checks c ON c.id = i.check_id AND c.deleted = false
+                INNER JOIN
+                escalation_policies p ON p.id = c.escalation_policy_id AND p.deleted = false
+                INNER JOIN
+                escalation_policy_tiers current
+                    ON current.escalation_policy_id = p.id AND current.position = i.escalation_tier
+                INNER JOIN
+                escalation_policy_tiers next
+                    ON next.escalation_policy_id = p.id AND next.position = i.escalation_tier + 1
+            WHERE
+                i.resolved_at IS NULL
+                AND
+                i.acknowledged_at IS NULL
+                AND
+                i.escalated_at + current.escalate_after_minutes * INTERVAL '1 minute'
+                    <= NOW() AT TIME ZONE 'UTC'
+            FOR UPDATE OF i SKIP LOCKED
+        ";
+
+        #[allow(clippy::type_complexity)]
+        let escalations: Vec<(i64, i64, Uuid, String, i64, i32)> =
+            sqlx::query_as(sql).fetch_all(&mut tx).await?;
+
+        for (incident_id, check_id, check_uuid, check_name, tier_id, position) in escalations {
+            if is_check_in_maintenance(&mut tx, check_id).await? {
+                tracing::debug!(
+                    check_uuid = check_uuid.to_string(),
+                    name = check_name,
+                    "check is in a maintenance window, not escalating incident"
+                );
+                continue;
+            }
+
+            let sql = r"
+                SELECT
+                    n.id,
+                    n.max_retries
+                FROM
+                    notifications n
+                    INNER JOIN
+                    escalation_policy_tier_notifications tn ON tn.notification_id = n.id
+                WHERE
+                    tn.tier_id = $1
+                    AND
+                    n.deleted = false
+            ";
+
+            let notifications: Vec<(i64, i32)> =
+                sqlx::query_as(sql).bind(tier_id).fetch_all(&mut tx).await?;
+
+            for (notification_id, retries_remaining) in notifications {
+                enqueue_alert(
+                    &mut tx,
+                    notification_id,
+                    check_id,
+                    incident_id,
+                    CheckStatus::Down,
+                    retries_remaining,
+                    Some(position),
+                )
+                .await?;
+            }
+
+            let sql = r"
+                UPDATE
+                    incidents
+                SET
+                    escalation_tier = $2,
+                    escalated_at = NOW() AT TIME ZONE 'UTC'
+                WHERE
+                    id = $1
+            ";
+
+            sqlx::query(sql)
+                .bind(incident_id)
+                .bind(position)
+                .execute(&mut tx)
+                .await?;
+
+            tracing::debug!(
+                check_uuid = check_uuid.to_string(),
+                name = check_name,
+                tier = position,
+                "escalating incident"
+            );
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+async fn read_policy(
+    conn: &mut DbConnection,
+    uuid: &Uuid,
+    project_id: i64,
+    account_id: i64,
+) -> Result<EscalationPolicy> {
+    let sql = r"
+            SELECT
+                *
+            FROM
+                escalation_policies
+            WHERE
+                uuid = $1
+                AND
+                project_id = $2
+                AND
+                account_id = $3
+                AND
+                deleted = false
+        ";
+
+    let policy: Option<EscalationPolicy> = sqlx::query_as(sql)
+        .bind(uuid)
+        .bind(project_id)
+        .bind(account_id)
+        .fetch_optional(conn)
+        .await?;
+
+    policy.ok_or_else(|| RepositoryError::NotFound {
+        entity_type: ENTITY_ESCALATION_POLICY.to_string(),
+        id: ShortId::from_uuid(uuid).to_string(),
+    })
+}
+
+async fn read_tiers(conn: &mut DbConnection, policy_ids: &[i64]) -> Result<Vec<EscalationTier>> {
+    let sql = r"
+            SELECT
+                t.escalation_policy_id,
+                t.position,
+                t.escalate_after_minutes,
+                ARRAY(
+                    SELECT n.uuid
+                    FROM escalation_policy_tier_notifications tn
+                    INNER JOIN notifications n ON n.id = tn.notification_id
+                    WHERE tn.tier_id = t.id AND n.deleted = false
+                ) AS notification_uuids
+            FROM
+                escalation_policy_tiers t
+            WHERE
+                t.escalation_policy_id = ANY($1)
+            ORDER BY
+                t.escalation_policy_id ASC,
+                t.position ASC
+        ";
+
+    Ok(sqlx::query_as(sql).bind(policy_ids).fetch_all(conn).await?)
+}
+
+async fn insert_tiers(
+    conn: &mut DbConnection,
+    policy_id: i64,
+    project_id: i64,
+    account_id: i64,
+    tiers: &[CreateEscalationTier],
+) -> Result<()> {
+    if tiers.is_empty() {
+        return Err(RepositoryError::BadArgument(
+            "an escalation policy needs at least one tier".to_string(),
+        ));
+    }
+
+    for (position, tier) in tiers.iter().enumerate() {
+        if matches!(tier.escalate_after_minutes, Some(minutes) if minutes <= 0) {
+            return Err(RepositoryError::BadArgument(format!(
+                "escalation tier {} must escalate after a positive number of minutes",
+                position + 1
+            )));
+        }
+
+        if tier.notification_uuids.is_empty() {
+            return Err(RepositoryError::BadArgument(format!(
+                "escalation tier {} has no notifications",
+                position + 1
+            )));
+        }
+
+        let sql = r"
+            SELECT
+                id,
+                uuid
+            FROM
+                notifications
+            WHERE
+                uuid = ANY($1)
+                AND
+                project_id = $2
+                AND
+                account_id = $3
+                AND
+                deleted = false
+        ";
+
+        let notifications: Vec<(i64, Uuid)> = sqlx::query_as(sql)
+            .bind(&tier.notification_uuids)
+            .bind(project_id)
+            .bind(account_id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        if let Some(missing) = tier
+            .notification_uuids
+            .iter()
+            .find(|uuid| !notifications.iter().any(|(_, u)| u == *uuid))
+        {
+            return Err(RepositoryError::NotFound {
+                entity_type: ENTITY_NOTIFICATION.to_string(),
+                id: ShortId::from_uuid(missing).to_string(),
+            });
+        }
+
+        let sql = r"
+            INSERT INTO escalation_policy_tiers (
+                escalation_policy_id,
+                position,
+                escalate_after_minutes
+            ) VALUES (
+                $1,
+                $2,
+                COALESCE($3, 15)
+            )
+            RETURNING id
+        ";
+
+        let (tier_id,): (i64,) = sqlx::query_as(sql)
+            .bind(policy_id)
+            .bind(position as i32)
+            .bind(tier.escalate_after_minutes)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        let sql = r"
+            INSERT INTO escalation_policy_tier_notifications (
+                tier_id,
+                notification_id
+            )
+            SELECT
+                $1,
+                UNNEST($2::BIGINT[])
+        ";
+
+        let notification_ids: Vec<i64> = notifications.iter().map(|(id, _)| *id).collect();
+        sqlx::query(sql)
+            .bind(tier_id)
+            .bind(&notification_ids)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
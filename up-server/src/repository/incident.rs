@@ -0,0 +1,235 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{
+        get_check_account_id, on_call::read_on_call_user_by_schedule_id, RepositoryError, Result,
+    },
+    shortid::ShortId,
+};
+
+const ENTITY_INCIDENT: &str = "incident";
+
+#[derive(sqlx::FromRow)]
+pub struct Incident {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub check_id: i64,
+    pub started_at: NaiveDateTime,
+    pub resolved_at: Option<NaiveDateTime>,
+    pub acknowledged_at: Option<NaiveDateTime>,
+    pub acknowledged_by: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct IncidentRepository {
+    database: Database,
+}
+
+impl IncidentRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn read_all(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+    ) -> Result<Vec<Incident>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (check_id, _) =
+            get_check_account_id(&mut conn, check_uuid, project_id, &identity.account_ids())
+                .await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            check_uuid = check_uuid.to_string(),
+            "reading incidents"
+        );
+
+        let sql = r"
+            SELECT
+                *
+            FROM
+                incidents
+            WHERE
+                check_id = $1
+            ORDER BY
+                started_at DESC
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(check_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    /// Acknowledges the incident an alert was sent for, on behalf of the recipient of
+    /// the alert. Alerts for the incident that are still waiting to be delivered are
+    /// cancelled, and no further alerts are enqueued for it.
+    ///
+    /// The recipient is recorded as whoever was on call when the alert was sent if the
+    /// notification routes to an on-call schedule, otherwise as the email address or
+    /// phone number of the notification. Webhook URLs can be secret, so webhook
+    /// notifications are recorded by name.
+    ///
+    /// [`acknowledge`] is called by the API without an identity, so callers must have
+    /// verified that the request is authorized for this alert, e.g. by a signed link.
+    pub async fn acknowledge(&self, alert_uuid: &Uuid) -> Result<Incident> {
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            SELECT
+                i.id,
+                n.on_call_schedule_id,
+                COALESCE(a.finished_at, NOW() AT TIME ZONE 'UTC') AS alerted_at,
+                COALESCE(n.email, n.phone_number, n.name) AS recipient
+            FROM
+                notification_alerts a
+                INNER JOIN
+                incidents i ON i.id = a.incident_id
+                INNER JOIN
+                notifications n ON n.id = a.notification_id
+            WHERE
+                a.uuid = $1
+            FOR UPDATE OF i
+        ";
+
+        let (id, on_call_schedule_id, alerted_at, recipient): (
+            i64,
+            Option<i64>,
+            NaiveDateTime,
+            String,
+        ) = sqlx::query_as(sql)
+            .bind(alert_uuid)
+            .fetch_optional(&mut tx)
+            .await?
+            .ok_or_else(|| RepositoryError::NotFound {
+                entity_type: ENTITY_INCIDENT.to_string(),
+                id: ShortId::from_uuid(alert_uuid).to_string(),
+            })?;
+
+        let recipient = match on_call_schedule_id {
+            Some(schedule_id) => read_on_call_user_by_schedule_id(&mut tx, schedule_id, alerted_at)
+                .await?
+                .map(|user| user.email)
+                .unwrap_or(recipient),
+            None => recipient,
+        };
+
+        let sql = r"
+            UPDATE
+                incidents
+            SET
+                acknowledged_at = NOW() AT TIME ZONE 'UTC',
+                acknowledged_by = $2
+            WHERE
+                id = $1
+                AND
+                acknowledged_at IS NULL
+                AND
+                resolved_at IS NULL
+        ";
+
+        let acknowledged = sqlx::query(sql)
+            .bind(id)
+            .bind(&recipient)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        if acknowledged {
+            let sql = r"
+                UPDATE
+                    notification_alerts
+                SET
+                    delivery_status = 'CANCELLED',
+                    finished_at = NOW() AT TIME ZONE 'UTC'
+                WHERE
+                    incident_id = $1
+                    AND
+                    (
+                        delivery_status = 'QUEUED'
+                        OR
+                        (delivery_status = 'FAILED' AND retries_remaining > 0)
+                    )
+            ";
+
+            let cancelled = sqlx::query(sql)
+                .bind(id)
+                .execute(&mut tx)
+                .await?
+                .rows_affected();
+
+            tracing::debug!(
+                alert_uuid = alert_uuid.to_string(),
+                cancelled_alerts = cancelled,
+                "incident acknowledged"
+            );
+        }
+
+        let incident: Incident = sqlx::query_as("SELECT * FROM incidents WHERE id = $1")
+            .bind(id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(incident)
+    }
+}
+
+/// Returns the open incident of a check, opening one if there is none, and whether it
+/// has been acknowledged.
+pub(super) async fn open_incident(conn: &mut DbConnection, check_id: i64) -> Result<(i64, bool)> {
+    let sql = r"
+        INSERT INTO incidents (
+            check_id
+        ) VALUES (
+            $1
+        )
+        ON CONFLICT (check_id) WHERE resolved_at IS NULL DO NOTHING
+    ";
+
+    sqlx::query(sql).bind(check_id).execute(&mut *conn).await?;
+
+    let sql = r"
+        SELECT
+            id,
+            acknowledged_at IS NOT NULL
+        FROM
+            incidents
+        WHERE
+            check_id = $1
+            AND
+            resolved_at IS NULL
+    ";
+
+    Ok(sqlx::query_as(sql).bind(check_id).fetch_one(conn).await?)
+}
+
+/// Resolves the open incident of a check, if it has one.
+pub(super) async fn resolve_incident(conn: &mut DbConnection, check_id: i64) -> Result<()> {
+    let sql = r"
+        UPDATE
+            incidents
+        SET
+            resolved_at = NOW() AT TIME ZONE 'UTC'
+        WHERE
+            check_id = $1
+            AND
+            resolved_at IS NULL
+    ";
+
+    sqlx::query(sql).bind(check_id).execute(conn).await?;
+
+    Ok(())
+}
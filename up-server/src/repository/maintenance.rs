@@ -0,0 +1,478 @@
+use chrono::{Duration, NaiveDateTime};
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{
+        check::{PeriodUnits, ENTITY_CHECK},
+        get_project_account_id, RepositoryError, Result,
+    },
+    shortid::ShortId,
+};
+
+const ENTITY_MAINTENANCE_WINDOW: &str = "maintenance window";
+
+#[derive(sqlx::FromRow)]
+pub struct MaintenanceWindow {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub name: String,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: NaiveDateTime,
+    pub repeat_every: Option<i32>,
+    pub repeat_every_units: Option<PeriodUnits>,
+    pub tag: Option<String>,
+    pub check_uuids: Vec<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct CreateMaintenanceWindow {
+    pub name: Option<String>,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: NaiveDateTime,
+    pub repeat_every: Option<i32>,
+    pub repeat_every_units: Option<PeriodUnits>,
+    pub tag: Option<String>,
+    pub check_uuids: Vec<Uuid>,
+}
+
+#[derive(Clone)]
+pub struct MaintenanceRepository {
+    database: Database,
+}
+
+impl MaintenanceRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn read_one(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<MaintenanceWindow> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "reading maintenance window"
+        );
+
+        read_window(&mut conn, uuid, project_id, account_id).await
+    }
+
+    pub async fn read_all(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+    ) -> Result<Vec<MaintenanceWindow>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            "reading maintenance windows"
+        );
+
+        let sql = r"
+            SELECT
+                w.*,
+                ARRAY(
+                    SELECT c.uuid
+                    FROM maintenance_window_checks mc
+                    INNER JOIN checks c ON c.id = mc.check_id
+                    WHERE mc.maintenance_window_id = w.id
+                ) AS check_uuids
+            FROM
+                maintenance_windows w
+            WHERE
+                w.project_id = $1
+                AND
+                w.account_id = $2
+                AND
+                w.deleted = false
+            ORDER BY
+                w.starts_at ASC
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(project_id)
+            .bind(account_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    pub async fn create(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        request: CreateMaintenanceWindow,
+    ) -> Result<MaintenanceWindow> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        ensure_valid_window(
+            request.starts_at,
+            request.ends_at,
+            request.repeat_every,
+            request.repeat_every_units.as_ref(),
+        )?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_checks_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            SELECT
+                id,
+                uuid
+            FROM
+                checks
+            WHERE
+                uuid = ANY($1)
+                AND
+                project_id = $2
+                AND
+                account_id = $3
+                AND
+                deleted = false
+        ";
+
+        let checks: Vec<(i64, Uuid)> = sqlx::query_as(sql)
+            .bind(&request.check_uuids)
+            .bind(project_id)
+            .bind(account_id)
+            .fetch_all(&mut tx)
+            .await?;
+
+        if let Some(missing) = request
+            .check_uuids
+            .iter()
+            .find(|uuid| !checks.iter().any(|(_, u)| u == *uuid))
+        {
+            return Err(RepositoryError::NotFound {
+                entity_type: ENTITY_CHECK.to_string(),
+                id: ShortId::from_uuid(missing).to_string(),
+            });
+        }
+
+        let sql = r"
+            INSERT INTO maintenance_windows (
+                account_id,
+                project_id,
+                uuid,
+                shortid,
+                name,
+                starts_at,
+                ends_at,
+                repeat_every,
+                repeat_every_units,
+                tag,
+                created_by
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8,
+                $9,
+                $10,
+                $11
+            )
+            RETURNING id
+        ";
+
+        let uuid = Uuid::new_v4();
+        let short_id: ShortId = uuid.into();
+
+        let (id,): (i64,) = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(short_id.to_string())
+            .bind(request.name.as_deref().unwrap_or(""))
+            .bind(request.starts_at)
+            .bind(request.ends_at)
+            .bind(request.repeat_every)
+            .bind(&request.repeat_every_units)
+            .bind(&request.tag)
+            .bind(identity.user_id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        let sql = r"
+            INSERT INTO maintenance_window_checks (
+                maintenance_window_id,
+                check_id
+            )
+            SELECT
+                $1,
+                UNNEST($2::BIGINT[])
+        ";
+
+        let check_ids: Vec<i64> = checks.iter().map(|(id, _)| *id).collect();
+        sqlx::query(sql)
+            .bind(id)
+            .bind(&check_ids)
+            .execute(&mut tx)
+            .await?;
+
+        let window = read_window(&mut tx, &uuid, project_id, account_id).await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            name = request.name,
+            "maintenance window created"
+        );
+
+        Ok(window)
+    }
+
+    /// Cancels a maintenance window, alerts are no longer suppressed by it.
+    pub async fn delete(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_checks_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "cancelling maintenance window"
+        );
+
+        let sql = r"
+            UPDATE
+                maintenance_windows
+            SET
+                deleted = true,
+                deleted_at = NOW() AT TIME ZONE 'UTC',
+                deleted_by = $4
+            WHERE
+                project_id = $1
+                AND
+                account_id = $2
+                AND
+                uuid = $3
+                AND
+                deleted = false
+        ";
+
+        let deleted = sqlx::query(sql)
+            .bind(project_id)
+            .bind(account_id)
+            .bind(uuid)
+            .bind(identity.user_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        tx.commit().await?;
+
+        if deleted {
+            tracing::trace!(uuid = uuid.to_string(), "maintenance window cancelled");
+        }
+
+        Ok(deleted)
+    }
+}
+
+/// Checks that a window ends after it starts and, if it recurs, that it ends before it
+/// starts again, as overlapping occurrences would never let the checks out of maintenance.
+fn ensure_valid_window(
+    starts_at: NaiveDateTime,
+    ends_at: NaiveDateTime,
+    repeat_every: Option<i32>,
+    repeat_every_units: Option<&PeriodUnits>,
+) -> Result<()> {
+    if ends_at <= starts_at {
+        return Err(RepositoryError::BadArgument(
+            "a maintenance window must end after it starts".to_string(),
+        ));
+    }
+
+    match (repeat_every, repeat_every_units) {
+        (None, None) => Ok(()),
+        (Some(repeat_every), Some(units)) => {
+            if repeat_every <= 0 {
+                return Err(RepositoryError::BadArgument(
+                    "a maintenance window must repeat after a positive period".to_string(),
+                ));
+            }
+            let period = match units {
+                PeriodUnits::Minutes => Duration::minutes(repeat_every.into()),
+                PeriodUnits::Hours => Duration::hours(repeat_every.into()),
+                PeriodUnits::Days => Duration::days(repeat_every.into()),
+            };
+            if ends_at - starts_at >= period {
+                return Err(RepositoryError::BadArgument(
+                    "a recurring maintenance window must be shorter than the period it repeats after"
+                        .to_string(),
+                ));
+            }
+            Ok(())
+        }
+        _ => Err(RepositoryError::BadArgument(
+            "repeat_every and repeat_every_units must be set together".to_string(),
+        )),
+    }
+}
+
+async fn read_window(
+    conn: &mut DbConnection,
+    uuid: &Uuid,
+    project_id: i64,
+    account_id: i64,
+) -> Result<MaintenanceWindow> {
+    let sql = r"
+            SELECT
+                w.*,
+                ARRAY(
+                    SELECT c.uuid
+                    FROM maintenance_window_checks mc
+                    INNER JOIN checks c ON c.id = mc.check_id
+                    WHERE mc.maintenance_window_id = w.id
+                ) AS check_uuids
+            FROM
+                maintenance_windows w
+            WHERE
+                w.uuid = $1
+                AND
+                w.project_id = $2
+                AND
+                w.account_id = $3
+                AND
+                w.deleted = false
+        ";
+
+    let window: Option<MaintenanceWindow> = sqlx::query_as(sql)
+        .bind(uuid)
+        .bind(project_id)
+        .bind(account_id)
+        .fetch_optional(conn)
+        .await?;
+
+    window.ok_or_else(|| RepositoryError::NotFound {
+        entity_type: ENTITY_MAINTENANCE_WINDOW.to_string(),
+        id: ShortId::from_uuid(uuid).to_string(),
+    })
+}
+
+/// Whether a maintenance window covering the check is currently active.
+pub(super) async fn is_check_in_maintenance(
+    conn: &mut DbConnection,
+    check_id: i64,
+) -> Result<bool> {
+    let sql = r"
+            SELECT EXISTS (
+                SELECT
+                    1
+                FROM
+                    maintenance_windows w
+                    INNER JOIN
+                    checks c ON c.id = $1 AND c.project_id = w.project_id
+                WHERE
+                    w.deleted = false
+                    AND
+                    w.starts_at <= NOW() AT TIME ZONE 'UTC'
+                    AND
+                    (
+                        (
+                            w.repeat_every IS NULL
+                            AND
+                            NOW() AT TIME ZONE 'UTC' < w.ends_at
+                        )
+                        OR
+                        (
+                            w.repeat_every IS NOT NULL
+                            AND
+                            MOD(
+                                EXTRACT(EPOCH FROM (NOW() AT TIME ZONE 'UTC' - w.starts_at))::BIGINT,
+                                (w.repeat_every * CASE w.repeat_every_units
+                                    WHEN 'MINUTES' THEN 60
+                                    WHEN 'HOURS' THEN 3600
+                                    WHEN 'DAYS' THEN 86400
+                                    END)::BIGINT
+                            ) < EXTRACT(EPOCH FROM (w.ends_at - w.starts_at))
+                        )
+                    )
+                    AND
+                    (
+                        (
+                            w.tag IS NOT NULL
+                            AND
+                            EXISTS (
+                                SELECT 1
+                                FROM check_tags ct
+                                INNER JOIN tags t ON t.id = ct.tag_id
+                                WHERE
+                                    ct.check_id = c.id
+                                    AND
+                                    t.account_id = w.account_id
+                                    AND
+                                    t.name = w.tag
+                            )
+                        )
+                        OR
+                        (
+                            w.tag IS NULL
+                            AND
+                            (
+                                EXISTS (
+                                    SELECT 1
+                                    FROM maintenance_window_checks mc
+                                    WHERE
+                                        mc.maintenance_window_id = w.id
+                                        AND
+                                        mc.check_id = c.id
+                                )
+                                OR
+                                NOT EXISTS (
+                                    SELECT 1
+                                    FROM maintenance_window_checks mc
+                                    WHERE mc.maintenance_window_id = w.id
+                                )
+                            )
+                        )
+                    )
+            )
+        ";
+
+    let (in_maintenance,): (bool,) = sqlx::query_as(sql).bind(check_id).fetch_one(conn).await?;
+
+    Ok(in_maintenance)
+}
@@ -0,0 +1,606 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{
+        auth::{add_user_to_account, create_user, update_password, UserRole, ENTITY_USER},
+        ensure_administrator_in_account, get_project_account_id, RepositoryError, Result,
+    },
+    shortid::ShortId,
+    tokens,
+};
+
+/// How long an invitation can be accepted for.
+const INVITATION_EXPIRY_DAYS: i32 = 7;
+
+#[derive(sqlx::FromRow)]
+pub struct Member {
+    pub user_uuid: Uuid,
+    pub email: String,
+    pub role: UserRole,
+    /// The projects the member is assigned to, along with their role in each at the
+    /// same index of `project_roles`.
+    pub project_uuids: Vec<Uuid>,
+    pub project_roles: Vec<UserRole>,
+}
+
+pub struct CreateInvitation {
+    pub email: String,
+    pub role: UserRole,
+}
+
+/// A newly created invitation, `token` is only ever emailed to the invitee.
+pub struct Invitation {
+    pub uuid: Uuid,
+    pub email: String,
+    pub role: UserRole,
+    pub account_name: String,
+    pub invited_by: String,
+    pub token: String,
+    pub expires_at: NaiveDateTime,
+    pub expiry_days: i32,
+}
+
+#[derive(Clone)]
+pub struct MemberRepository {
+    database: Database,
+}
+
+impl MemberRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Reads the members of an account along with their role and the projects in the
+    /// account they are assigned to.
+    pub async fn read_all(&self, identity: &Identity, account_uuid: &Uuid) -> Result<Vec<Member>> {
+        let account_id = ensure_administrator_in_account(identity, account_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        tracing::trace!(
+            account_uuid = account_uuid.to_string(),
+            "reading account members"
+        );
+
+        let sql = r"
+            SELECT
+                u.uuid AS user_uuid,
+                u.email,
+                ur.role,
+                ARRAY(
+                    SELECT p.uuid
+                    FROM user_projects up
+                    INNER JOIN projects p ON p.id = up.project_id
+                    WHERE up.user_id = u.id AND p.account_id = ua.account_id AND p.deleted = false
+                    ORDER BY p.name, p.id
+                ) AS project_uuids,
+                ARRAY(
+                    SELECT up.role
+                    FROM user_projects up
+                    INNER JOIN projects p ON p.id = up.project_id
+                    WHERE up.user_id = u.id AND p.account_id = ua.account_id AND p.deleted = false
+                    ORDER BY p.name, p.id
+                ) AS project_roles
+            FROM
+                user_accounts ua
+                INNER JOIN
+                users u ON u.id = ua.user_id
+                INNER JOIN
+                user_roles ur ON ur.user_id = ua.user_id AND ur.account_id = ua.account_id
+            WHERE
+                ua.account_id = $1
+                AND
+                u.deleted = false
+            ORDER BY
+                u.email ASC
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(account_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    /// Invites someone to join an account with a role, replacing any invitation for
+    /// the same email address that has not been accepted yet.
+    pub async fn invite(
+        &self,
+        identity: &Identity,
+        account_uuid: &Uuid,
+        request: CreateInvitation,
+    ) -> Result<Invitation> {
+        let account_id = ensure_administrator_in_account(identity, account_uuid)?;
+
+        let email = request.email.trim();
+        if !email.contains('@') {
+            return Err(RepositoryError::BadArgument(format!(
+                "{} is not a valid email address",
+                email
+            )));
+        }
+
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            SELECT EXISTS (
+                SELECT 1
+                FROM user_accounts ua
+                INNER JOIN users u ON u.id = ua.user_id
+                WHERE ua.account_id = $1 AND LOWER(u.email) = LOWER($2) AND u.deleted = false
+            )
+        ";
+
+        let is_member: bool = sqlx::query_scalar(sql)
+            .bind(account_id)
+            .bind(email)
+            .fetch_one(&mut tx)
+            .await?;
+        if is_member {
+            return Err(RepositoryError::BadArgument(format!(
+                "{} is already a member of the account",
+                email
+            )));
+        }
+
+        let sql = r"
+            DELETE FROM
+                invitations
+            WHERE
+                account_id = $1
+                AND
+                LOWER(email) = LOWER($2)
+                AND
+                accepted_at IS NULL
+        ";
+
+        sqlx::query(sql)
+            .bind(account_id)
+            .bind(email)
+            .execute(&mut tx)
+            .await?;
+
+        let sql = r"
+            INSERT INTO invitations (
+                uuid,
+                shortid,
+                account_id,
+                email,
+                role,
+                token_hash,
+                created_by,
+                expires_at
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                NOW() AT TIME ZONE 'UTC' + $8 * INTERVAL '1 day'
+            ) RETURNING
+                expires_at,
+                (SELECT name FROM accounts WHERE id = $3)
+        ";
+
+        let uuid = Uuid::new_v4();
+        let short_id: ShortId = uuid.into();
+        let token = tokens::generate_secret();
+
+        let (expires_at, account_name): (NaiveDateTime, String) = sqlx::query_as(sql)
+            .bind(uuid)
+            .bind(short_id.to_string())
+            .bind(account_id)
+            .bind(email)
+            .bind(request.role)
+            .bind(tokens::hash_secret(&token))
+            .bind(identity.user_id)
+            .bind(INVITATION_EXPIRY_DAYS)
+            .fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(
+            account_uuid = account_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "invitation created"
+        );
+
+        Ok(Invitation {
+            uuid,
+            email: email.to_string(),
+            role: request.role,
+            account_name,
+            invited_by: identity.email.clone(),
+            token,
+            expires_at,
+            expiry_days: INVITATION_EXPIRY_DAYS,
+        })
+    }
+
+    /// Accepts an invitation, creating a user for the invited email address if there
+    /// is none, and returns the subject of the user or `None` if the invitation is
+    /// invalid, has expired or has already been accepted. `password` is only set for
+    /// new users, existing users keep logging in the way they did.
+    ///
+    /// [`accept_invitation`] not called with an identity, but the token is only sent
+    /// to the invited email address, so no access checks needed.
+    pub async fn accept_invitation(
+        &self,
+        token: &str,
+        password: Option<&str>,
+    ) -> Result<Option<String>> {
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            SELECT
+                i.id,
+                i.account_id,
+                i.email,
+                i.role
+            FROM
+                invitations i
+                INNER JOIN
+                accounts a ON a.id = i.account_id
+            WHERE
+                i.token_hash = $1
+                AND
+                i.accepted_at IS NULL
+                AND
+                i.expires_at > NOW() AT TIME ZONE 'UTC'
+                AND
+                a.deleted = false
+            FOR UPDATE OF i
+        ";
+
+        let invitation: Option<(i64, i64, String, UserRole)> = sqlx::query_as(sql)
+            .bind(tokens::hash_secret(token))
+            .fetch_optional(&mut tx)
+            .await?;
+
+        let (invitation_id, account_id, email, role) = match invitation {
+            Some(invitation) => invitation,
+            None => return Ok(None),
+        };
+
+        let sql = r"
+            SELECT
+                id,
+                subject
+            FROM
+                users
+            WHERE
+                LOWER(email) = LOWER($1)
+                AND
+                deleted = false
+        ";
+
+        let user: Option<(i64, String)> = sqlx::query_as(sql)
+            .bind(&email)
+            .fetch_optional(&mut tx)
+            .await?;
+
+        let (user_id, subject) = match user {
+            Some(user) => user,
+            None => {
+                let (user_id, _, subject) = create_user(&mut tx, &email).await?;
+                if let Some(password) = password {
+                    update_password(&mut tx, user_id, &email, password).await?;
+                }
+                (user_id, subject)
+            }
+        };
+
+        add_user_to_account(&mut tx, user_id, account_id, role).await?;
+
+        let sql = r"
+            UPDATE
+                invitations
+            SET
+                accepted_at = NOW() AT TIME ZONE 'UTC',
+                accepted_by = $2
+            WHERE
+                id = $1
+        ";
+
+        sqlx::query(sql)
+            .bind(invitation_id)
+            .bind(user_id)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(
+            user_id = user_id,
+            account_id = account_id,
+            "invitation accepted"
+        );
+
+        Ok(Some(subject))
+    }
+
+    /// Changes the role of a member of an account. Accounts always keep at least one
+    /// administrator.
+    pub async fn update_role(
+        &self,
+        identity: &Identity,
+        account_uuid: &Uuid,
+        user_uuid: &Uuid,
+        role: UserRole,
+    ) -> Result<()> {
+        let account_id = ensure_administrator_in_account(identity, account_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (user_id, current_role) = get_member(&mut tx, account_id, user_uuid).await?;
+        if current_role == UserRole::Administrator && role != UserRole::Administrator {
+            ensure_other_administrator(&mut tx, account_id, user_id).await?;
+        }
+
+        let sql = r"
+            UPDATE
+                user_roles
+            SET
+                role = $3
+            WHERE
+                user_id = $1
+                AND
+                account_id = $2
+        ";
+
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(account_id)
+            .bind(role)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::debug!(
+            account_uuid = account_uuid.to_string(),
+            user_uuid = user_uuid.to_string(),
+            role = format!("{:?}", role),
+            "member role changed"
+        );
+
+        Ok(())
+    }
+
+    /// Removes a member from an account and the projects in it. Accounts always keep at
+    /// least one administrator.
+    pub async fn remove(
+        &self,
+        identity: &Identity,
+        account_uuid: &Uuid,
+        user_uuid: &Uuid,
+    ) -> Result<()> {
+        let account_id = ensure_administrator_in_account(identity, account_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (user_id, role) = get_member(&mut tx, account_id, user_uuid).await?;
+        if role == UserRole::Administrator {
+            ensure_other_administrator(&mut tx, account_id, user_id).await?;
+        }
+
+        let sql = r"
+            DELETE FROM
+                user_projects up
+            USING
+                projects p
+            WHERE
+                p.id = up.project_id
+                AND
+                up.user_id = $1
+                AND
+                p.account_id = $2
+        ";
+
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(account_id)
+            .execute(&mut tx)
+            .await?;
+
+        for sql in [
+            "DELETE FROM user_roles WHERE user_id = $1 AND account_id = $2",
+            "DELETE FROM user_accounts WHERE user_id = $1 AND account_id = $2",
+        ] {
+            sqlx::query(sql)
+                .bind(user_id)
+                .bind(account_id)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::debug!(
+            account_uuid = account_uuid.to_string(),
+            user_uuid = user_uuid.to_string(),
+            "member removed"
+        );
+
+        Ok(())
+    }
+
+    /// Assigns a member of the account a project belongs to to the project, with their
+    /// role in the account unless `role` is set. Assigning a member again changes their
+    /// role in the project.
+    pub async fn assign_to_project(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        user_uuid: &Uuid,
+        role: Option<UserRole>,
+    ) -> Result<()> {
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+        ensure_project_administrator(identity, project_id, account_id)?;
+
+        let (user_id, account_role) = get_member(&mut conn, account_id, user_uuid).await?;
+        let role = role.unwrap_or(account_role);
+
+        let sql = r"
+            INSERT INTO user_projects (
+                user_id,
+                project_id,
+                role
+            ) VALUES (
+                $1,
+                $2,
+                $3
+            )
+            ON CONFLICT (user_id, project_id) DO UPDATE SET
+                role = EXCLUDED.role
+        ";
+
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(project_id)
+            .bind(role)
+            .execute(&mut conn)
+            .await?;
+
+        tracing::debug!(
+            project_uuid = project_uuid.to_string(),
+            user_uuid = user_uuid.to_string(),
+            role = format!("{:?}", role),
+            "member assigned to project"
+        );
+
+        Ok(())
+    }
+
+    /// Unassigns a member of the account a project belongs to from the project.
+    pub async fn unassign_from_project(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        user_uuid: &Uuid,
+    ) -> Result<()> {
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+        ensure_project_administrator(identity, project_id, account_id)?;
+
+        let (user_id, _) = get_member(&mut conn, account_id, user_uuid).await?;
+
+        let sql = r"
+            DELETE FROM
+                user_projects
+            WHERE
+                user_id = $1
+                AND
+                project_id = $2
+        ";
+
+        sqlx::query(sql)
+            .bind(user_id)
+            .bind(project_id)
+            .execute(&mut conn)
+            .await?;
+
+        tracing::debug!(
+            project_uuid = project_uuid.to_string(),
+            user_uuid = user_uuid.to_string(),
+            "member unassigned from project"
+        );
+
+        Ok(())
+    }
+}
+
+/// Fails unless the user making the request administers a project, either as an
+/// administrator of the project or of its account.
+fn ensure_project_administrator(
+    identity: &Identity,
+    project_id: i64,
+    account_id: i64,
+) -> Result<()> {
+    if identity.is_administrator_in_account_with_id(account_id)
+        || identity.is_administrator_in_project_with_id(project_id)
+    {
+        Ok(())
+    } else {
+        Err(RepositoryError::Forbidden)
+    }
+}
+
+/// Returns the ID and role of a member of an account, locking their role.
+async fn get_member(
+    conn: &mut DbConnection,
+    account_id: i64,
+    user_uuid: &Uuid,
+) -> Result<(i64, UserRole)> {
+    let sql = r"
+        SELECT
+            u.id,
+            ur.role
+        FROM
+            users u
+            INNER JOIN
+            user_roles ur ON ur.user_id = u.id
+        WHERE
+            u.uuid = $1
+            AND
+            ur.account_id = $2
+            AND
+            u.deleted = false
+        FOR UPDATE OF ur
+    ";
+
+    let member: Option<(i64, UserRole)> = sqlx::query_as(sql)
+        .bind(user_uuid)
+        .bind(account_id)
+        .fetch_optional(conn)
+        .await?;
+
+    member.ok_or_else(|| RepositoryError::NotFound {
+        entity_type: ENTITY_USER.to_string(),
+        id: ShortId::from_uuid(user_uuid).to_string(),
+    })
+}
+
+/// Fails unless an account has an administrator other than `user_id`, so that it can't
+/// be left without one.
+async fn ensure_other_administrator(
+    conn: &mut DbConnection,
+    account_id: i64,
+    user_id: i64,
+) -> Result<()> {
+    // Locking every administrator's role keeps concurrent changes from both passing.
+    let sql = r"
+        SELECT
+            user_id
+        FROM
+            user_roles
+        WHERE
+            account_id = $1
+            AND
+            role = 'ADMINISTRATOR'
+        FOR UPDATE
+    ";
+
+    let administrators: Vec<i64> = sqlx::query_scalar(sql)
+        .bind(account_id)
+        .fetch_all(conn)
+        .await?;
+
+    if administrators.iter().all(|id| *id == user_id) {
+        return Err(RepositoryError::BadArgument(
+            "an account needs at least one administrator".to_string(),
+        ));
+    }
+
+    Ok(())
+}
@@ -4,28 +4,67 @@ use miette::Diagnostic;
 use thiserror::Error;
 use uuid::Uuid;
 
+mod account;
+mod api_key;
 mod auth;
+mod channel;
 mod check;
+mod escalation;
+mod incident;
+mod maintenance;
+mod member;
 mod notification;
+mod on_call;
+mod preferences;
 mod project;
 
 pub mod dto {
-    pub use super::auth::{User, UserRole};
+    pub use super::account::{Account, Signup, UpdateAccount};
+    pub use super::api_key::{ApiKey, ApiKeyScope, ApiKeyUser, CreateApiKey, CreatedApiKey};
+    pub use super::auth::{
+        Authentication, ExternalIdentity, MagicLink, PasswordReset, Provisioning, RefreshedToken,
+        User, UserRole,
+    };
     pub use super::check::{
-        Check, CheckStatus, CreateCheck, PeriodUnits, ScheduleType, UpdateCheck,
+        Check, CheckSeverity, CheckStatus, CreateCheck, PeriodUnits, ScheduleType, UpdateCheck,
+    };
+    pub use super::escalation::{
+        CreateEscalationPolicy, CreateEscalationTier, EscalationPolicy, EscalationTier,
+        UpdateEscalationPolicy,
     };
+    pub use super::incident::Incident;
+    pub use super::maintenance::{CreateMaintenanceWindow, MaintenanceWindow};
+    pub use super::member::{CreateInvitation, Invitation, Member};
     pub use super::notification::{
-        CreateNotification, Notification, NotificationAlert, NotificationType, UpdateNotification,
+        Alert, AlertAttempt, CreateNotification, DeliveryStatus, Notification, NotificationAlert,
+        NotificationType, UpdateNotification,
     };
+    pub use super::on_call::{
+        CreateOnCallOverride, CreateOnCallSchedule, OnCallOverride, OnCallSchedule, OnCallUser,
+        RotationPeriod, UpdateOnCallSchedule,
+    };
+    pub use super::preferences::{ChannelPreference, NotificationPreferences};
     pub use super::project::{CreateProject, Project, UpdateProject};
 }
 
+pub use api_key::API_KEY_PREFIX;
+
+use account::AccountRepository;
+use api_key::ApiKeyRepository;
 use auth::AuthRepository;
+use channel::ChannelRepository;
 use check::CheckRepository;
+use escalation::EscalationPolicyRepository;
+use incident::IncidentRepository;
+use maintenance::MaintenanceRepository;
+use member::MemberRepository;
 use notification::NotificationRepository;
+use on_call::OnCallScheduleRepository;
+use preferences::PreferencesRepository;
 use project::ProjectRepository;
 
 use crate::{
+    auth::Identity,
     database::{Database, DbConnection},
     repository::{
         check::ENTITY_CHECK,
@@ -39,9 +78,18 @@ type Result<T> = miette::Result<T, RepositoryError>;
 #[derive(Clone)]
 pub struct Repository {
     auth: AuthRepository,
+    account: AccountRepository,
+    api_key: ApiKeyRepository,
     check: CheckRepository,
     project: ProjectRepository,
     notification: NotificationRepository,
+    channel: ChannelRepository,
+    maintenance: MaintenanceRepository,
+    member: MemberRepository,
+    incident: IncidentRepository,
+    escalation_policy: EscalationPolicyRepository,
+    on_call_schedule: OnCallScheduleRepository,
+    preferences: PreferencesRepository,
 }
 
 #[derive(Error, Diagnostic, Debug)]
@@ -52,12 +100,18 @@ pub enum RepositoryError {
     #[error("permission denied")]
     #[diagnostic(code(up::error::permission))]
     Forbidden,
+    #[error("{0}")]
+    #[diagnostic(code(up::error::bad_argument))]
+    BadArgument(String),
     #[error("SQL query failed")]
     #[diagnostic(code(up::error::sql))]
     SqlQueryFailed(#[from] sqlx::Error),
     #[error("failed to execute background task")]
     #[diagnostic(code(up::error::background_task))]
     BackgroundTaskFailed(#[from] tokio::task::JoinError),
+    #[error("failed to hash password")]
+    #[diagnostic(code(up::error::password))]
+    PasswordHashFailed(#[from] argon2::password_hash::Error),
 }
 
 impl RepositoryError {
@@ -80,14 +134,32 @@ impl RepositoryError {
 impl Repository {
     pub fn new(database: Database) -> Self {
         let auth = AuthRepository::new(database.clone());
+        let account = AccountRepository::new(database.clone());
+        let api_key = ApiKeyRepository::new(database.clone());
         let project = ProjectRepository::new(database.clone());
         let check = CheckRepository::new(database.clone());
-        let notification = NotificationRepository::new(database);
+        let notification = NotificationRepository::new(database.clone());
+        let channel = ChannelRepository::new(database.clone());
+        let maintenance = MaintenanceRepository::new(database.clone());
+        let member = MemberRepository::new(database.clone());
+        let incident = IncidentRepository::new(database.clone());
+        let escalation_policy = EscalationPolicyRepository::new(database.clone());
+        let on_call_schedule = OnCallScheduleRepository::new(database.clone());
+        let preferences = PreferencesRepository::new(database);
         Self {
             auth,
+            account,
+            api_key,
             check,
             project,
             notification,
+            channel,
+            maintenance,
+            member,
+            incident,
+            escalation_policy,
+            on_call_schedule,
+            preferences,
         }
     }
 
@@ -95,6 +167,14 @@ impl Repository {
         &self.auth
     }
 
+    pub fn account(&self) -> &AccountRepository {
+        &self.account
+    }
+
+    pub fn api_key(&self) -> &ApiKeyRepository {
+        &self.api_key
+    }
+
     pub fn check(&self) -> &CheckRepository {
         &self.check
     }
@@ -106,6 +186,57 @@ impl Repository {
     pub fn notification(&self) -> &NotificationRepository {
         &self.notification
     }
+
+    pub fn channel(&self) -> &ChannelRepository {
+        &self.channel
+    }
+
+    pub fn maintenance(&self) -> &MaintenanceRepository {
+        &self.maintenance
+    }
+
+    pub fn member(&self) -> &MemberRepository {
+        &self.member
+    }
+
+    pub fn incident(&self) -> &IncidentRepository {
+        &self.incident
+    }
+
+    pub fn escalation_policy(&self) -> &EscalationPolicyRepository {
+        &self.escalation_policy
+    }
+
+    pub fn on_call_schedule(&self) -> &OnCallScheduleRepository {
+        &self.on_call_schedule
+    }
+
+    pub fn preferences(&self) -> &PreferencesRepository {
+        &self.preferences
+    }
+}
+
+/// Returns the ID of an account the user making the request administers. Accounts the
+/// user is not a member of are reported as not existing.
+fn ensure_administrator_in_account(identity: &Identity, account_uuid: &Uuid) -> Result<i64> {
+    let account_id = identity
+        .account_ids
+        .get(account_uuid)
+        .copied()
+        .ok_or_else(|| RepositoryError::NotFound {
+            entity_type: ENTITY_ACCOUNT.to_string(),
+            id: ShortId::from_uuid(account_uuid).to_string(),
+        })?;
+
+    if !identity.is_administrator_in_account(account_uuid) {
+        tracing::trace!(
+            account_uuid = account_uuid.to_string(),
+            "user not an administrator in account, aborting"
+        );
+        return Err(RepositoryError::Forbidden);
+    }
+
+    Ok(account_id)
 }
 
 async fn get_project_account_id(
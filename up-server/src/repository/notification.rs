@@ -1,17 +1,19 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use sqlx::Row;
 use uuid::Uuid;
 
+use crate::repository::check::{CheckSeverity, CheckStatus, PeriodUnits, ScheduleType};
 use crate::repository::get_check_account_id;
 use crate::{
     auth::Identity,
-    database::Database,
-    notifier::Notifier,
+    backoff::Backoff,
+    database::{Database, DbConnection},
+    notifier::{Delivery, NotifierError},
     repository::{RepositoryError, Result},
     shortid::ShortId,
 };
 
-const ENTITY_NOTIFICATION: &str = "notification";
+pub(super) const ENTITY_NOTIFICATION: &str = "notification";
 
 #[derive(sqlx::FromRow)]
 pub struct Notification {
@@ -21,7 +23,11 @@ pub struct Notification {
     pub notification_type: NotificationType,
     pub email: Option<String>,
     pub url: Option<String>,
+    pub phone_number: Option<String>,
     pub max_retries: i32,
+    pub retry_backoff_seconds: i32,
+    pub retry_backoff_max_seconds: i32,
+    pub digest_window_seconds: Option<i32>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
 }
@@ -31,7 +37,11 @@ pub struct CreateNotification {
     pub name: Option<String>,
     pub email: Option<String>,
     pub url: Option<String>,
+    pub phone_number: Option<String>,
     pub max_retries: Option<i32>,
+    pub retry_backoff_seconds: Option<i32>,
+    pub retry_backoff_max_seconds: Option<i32>,
+    pub digest_window_seconds: Option<i32>,
 }
 
 pub struct UpdateNotification {
@@ -39,20 +49,54 @@ pub struct UpdateNotification {
     pub notification_type: Option<NotificationType>,
     pub email: Option<String>,
     pub url: Option<String>,
+    pub phone_number: Option<String>,
     pub max_retries: Option<i32>,
+    pub retry_backoff_seconds: Option<i32>,
+    pub retry_backoff_max_seconds: Option<i32>,
+    pub digest_window_seconds: Option<i32>,
 }
 
 #[derive(sqlx::FromRow, Debug)]
 pub struct NotificationAlert {
     pub id: i64,
+    pub uuid: Uuid,
+    pub notification_id: i64,
+    pub account_id: i64,
+    pub project_uuid: Uuid,
+    pub project_name: String,
     pub check_uuid: Uuid,
+    pub check_name: String,
+    pub check_status: CheckStatus,
+    pub check_severity: CheckSeverity,
     pub notification_type: NotificationType,
     pub name: String,
     pub email: Option<String>,
     pub url: Option<String>,
+    pub phone_number: Option<String>,
+    /// Set if the alert goes to whoever is on call instead of `email` or `phone_number`.
+    pub on_call_schedule_id: Option<i64>,
+    pub account_email_from: Option<String>,
+    pub account_email_reply_to: Option<String>,
+    pub account_product_name: Option<String>,
+    pub account_base_url: Option<String>,
     pub retries_remaining: i32,
     pub max_retries: i32,
+    pub retry_backoff_seconds: i32,
+    pub retry_backoff_max_seconds: i32,
+    pub digest_window_seconds: Option<i32>,
+    pub schedule_type: ScheduleType,
+    pub ping_period: Option<i32>,
+    pub ping_period_units: Option<PeriodUnits>,
+    pub ping_cron_expression: Option<String>,
+    pub grace_period: i32,
+    pub grace_period_units: PeriodUnits,
     pub last_ping_at: Option<NaiveDateTime>,
+    pub delivery_status: DeliveryStatus,
+    pub claimed_at: Option<NaiveDateTime>,
+    /// Whether the alert was held during its recipient's quiet hours.
+    pub held: bool,
+    /// Whether this is a test alert requested by a user rather than a real one.
+    pub test: bool,
 }
 
 #[derive(sqlx::Type, Debug)]
@@ -60,6 +104,7 @@ pub struct NotificationAlert {
 pub enum NotificationType {
     Email,
     Webhook,
+    Sms,
 }
 
 impl ToString for NotificationType {
@@ -67,10 +112,47 @@ impl ToString for NotificationType {
         match self {
             Self::Email => "EMAIL".to_string(),
             Self::Webhook => "WEBHOOK".to_string(),
+            Self::Sms => "SMS".to_string(),
         }
     }
 }
 
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(
+    type_name = "alert_delivery_status",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum DeliveryStatus {
+    Queued,
+    Running,
+    Delivered,
+    Failed,
+    Cancelled,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct Alert {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub check_uuid: Uuid,
+    pub check_status: CheckStatus,
+    pub delivery_status: DeliveryStatus,
+    pub retries_remaining: i32,
+    pub escalation_tier: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct AlertAttempt {
+    pub alert_id: i64,
+    pub delivery_status: DeliveryStatus,
+    pub http_status: Option<i32>,
+    pub provider_message_id: Option<String>,
+    pub error: Option<String>,
+    pub attempted_at: NaiveDateTime,
+}
+
 #[derive(Clone)]
 pub struct NotificationRepository {
     database: Database,
@@ -218,10 +300,17 @@ impl NotificationRepository {
         let (check_id, account_id) =
             get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
 
-        if !identity.is_member_in_account_with_id(account_id) {
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
             return Err(RepositoryError::Forbidden);
         }
 
+        ensure_destination(
+            &request.notification_type,
+            request.email.as_deref(),
+            request.url.as_deref(),
+            request.phone_number.as_deref(),
+        )?;
+
         let sql = r"
             INSERT INTO notifications (
                 check_id,
@@ -233,7 +322,11 @@ impl NotificationRepository {
                 notification_type,
                 email,
                 url,
+                phone_number,
                 max_retries,
+                retry_backoff_seconds,
+                retry_backoff_max_seconds,
+                digest_window_seconds,
                 created_by
             ) VALUES (
                 $1,
@@ -246,7 +339,11 @@ impl NotificationRepository {
                 $8,
                 $9,
                 $10,
-                $11
+                $11,
+                COALESCE($12, 30),
+                COALESCE($13, 3600),
+                NULLIF($14, 0),
+                $15
             )
             RETURNING *
         ";
@@ -264,7 +361,11 @@ impl NotificationRepository {
             .bind(&request.notification_type)
             .bind(&request.email)
             .bind(&request.url)
+            .bind(&request.phone_number)
             .bind(&request.max_retries)
+            .bind(request.retry_backoff_seconds)
+            .bind(request.retry_backoff_max_seconds)
+            .bind(request.digest_window_seconds)
             .bind(identity.user_id)
             .fetch_one(&mut tx)
             .await?;
@@ -297,7 +398,7 @@ impl NotificationRepository {
         let (check_id, account_id) =
             get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
 
-        if !identity.is_member_in_account_with_id(account_id) {
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
             return Err(RepositoryError::Forbidden);
         }
 
@@ -308,9 +409,16 @@ impl NotificationRepository {
                 name = COALESCE($5, name),
                 email = COALESCE($6, email),
                 url = COALESCE($7, url),
-                max_retries = COALESCE($8, max_retries),
+                phone_number = COALESCE($8, phone_number),
+                max_retries = COALESCE($9, max_retries),
+                retry_backoff_seconds = COALESCE($10, retry_backoff_seconds),
+                retry_backoff_max_seconds = COALESCE($11, retry_backoff_max_seconds),
+                digest_window_seconds = CASE
+                    WHEN $12::INTEGER IS NULL THEN digest_window_seconds
+                    ELSE NULLIF($12, 0)
+                END,
                 updated_at = NOW() AT TIME ZONE 'UTC',
-                updated_by = $9
+                updated_by = $13
             WHERE
                 check_id = $1
                 AND
@@ -332,17 +440,26 @@ impl NotificationRepository {
             .bind(&request.name)
             .bind(&request.email)
             .bind(&request.url)
+            .bind(&request.phone_number)
             .bind(&request.max_retries)
+            .bind(request.retry_backoff_seconds)
+            .bind(request.retry_backoff_max_seconds)
+            .bind(request.digest_window_seconds)
             .bind(identity.user_id)
             .fetch_optional(&mut tx)
             .await?;
 
-        if notification.is_none() {
-            return Err(RepositoryError::NotFound {
-                entity_type: ENTITY_NOTIFICATION.to_string(),
-                id: ShortId::from_uuid(uuid).to_string(),
-            });
-        }
+        let notification = notification.ok_or_else(|| RepositoryError::NotFound {
+            entity_type: ENTITY_NOTIFICATION.to_string(),
+            id: ShortId::from_uuid(uuid).to_string(),
+        })?;
+
+        ensure_destination(
+            &notification.notification_type,
+            notification.email.as_deref(),
+            notification.url.as_deref(),
+            notification.phone_number.as_deref(),
+        )?;
 
         tx.commit().await?;
 
@@ -352,7 +469,7 @@ impl NotificationRepository {
             "notification updated"
         );
 
-        Ok(notification.unwrap())
+        Ok(notification)
     }
 
     pub async fn delete(
@@ -370,7 +487,7 @@ impl NotificationRepository {
         let (check_id, account_id) =
             get_check_account_id(&mut tx, check_uuid, project_id, &identity.account_ids()).await?;
 
-        if !identity.is_member_in_account_with_id(account_id) {
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
             return Err(RepositoryError::Forbidden);
         }
 
@@ -417,119 +534,781 @@ impl NotificationRepository {
         Ok(deleted)
     }
 
-    pub async fn send_alert_batch(&self, notifier: &Notifier) -> Result<Vec<NotificationAlert>> {
+    /// Reads the alerts sent for a notification, most recent first, with every delivery
+    /// attempt made for each of them.
+    pub async fn read_alerts(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<Vec<(Alert, Vec<AlertAttempt>)>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (check_id, account_id) =
+            get_check_account_id(&mut conn, check_uuid, project_id, &identity.account_ids())
+                .await?;
+
+        tracing::trace!(
+            check_uuid = check_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "reading notification alerts"
+        );
+
+        let sql = r"
+            SELECT
+                id
+            FROM
+                notifications
+            WHERE
+                uuid = $1
+                AND
+                account_id = $2
+                AND
+                project_id = $3
+                AND
+                deleted = false
+        ";
+
+        let notification_id: Option<(i64,)> = sqlx::query_as(sql)
+            .bind(uuid)
+            .bind(account_id)
+            .bind(project_id)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        let notification_id = match notification_id {
+            Some((id,)) => id,
+            None => {
+                return Err(RepositoryError::NotFound {
+                    entity_type: ENTITY_NOTIFICATION.to_string(),
+                    id: ShortId::from_uuid(uuid).to_string(),
+                })
+            }
+        };
+
+        let sql = r"
+            SELECT
+                a.id,
+                a.uuid,
+                c.uuid AS check_uuid,
+                a.check_status,
+                a.delivery_status,
+                a.retries_remaining,
+                a.escalation_tier,
+                a.created_at,
+                a.finished_at
+            FROM
+                notification_alerts a
+                INNER JOIN
+                checks c ON c.id = a.check_id
+            WHERE
+                a.notification_id = $1
+                AND
+                a.check_id = $2
+            ORDER BY
+                a.created_at DESC
+        ";
+
+        let alerts: Vec<Alert> = sqlx::query_as(sql)
+            .bind(notification_id)
+            .bind(check_id)
+            .fetch_all(&mut conn)
+            .await?;
+
+        let sql = r"
+            SELECT
+                alert_id,
+                delivery_status,
+                http_status,
+                provider_message_id,
+                error,
+                attempted_at
+            FROM
+                notification_alert_attempts
+            WHERE
+                alert_id = ANY($1)
+            ORDER BY
+                attempted_at ASC
+        ";
+
+        let alert_ids: Vec<i64> = alerts.iter().map(|a| a.id).collect();
+        let mut attempts: Vec<AlertAttempt> = sqlx::query_as(sql)
+            .bind(&alert_ids)
+            .fetch_all(&mut conn)
+            .await?;
+
+        Ok(alerts
+            .into_iter()
+            .map(|alert| {
+                let (alert_attempts, remaining) =
+                    attempts.drain(..).partition(|a| a.alert_id == alert.id);
+                attempts = remaining;
+                (alert, alert_attempts)
+            })
+            .collect())
+    }
+
+    /// Builds an alert for a notification as if its check had just gone down, without
+    /// queueing it, so that it can be sent immediately to test the notification.
+    pub async fn read_test_alert(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        check_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<NotificationAlert> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+        let project_id = identity.get_project_id(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (check_id, account_id) =
+            get_check_account_id(&mut conn, check_uuid, project_id, &identity.account_ids())
+                .await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        tracing::trace!(
+            check_uuid = check_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "reading notification for test alert"
+        );
+
+        let sql = r"
+            SELECT
+                0::BIGINT AS id,
+                gen_random_uuid() AS uuid,
+                n.id AS notification_id,
+                n.max_retries AS retries_remaining,
+                'DOWN'::check_status AS check_status,
+                'RUNNING'::alert_delivery_status AS delivery_status,
+                NULL::TIMESTAMP AS claimed_at,
+                n.account_id,
+                n.notification_type,
+                n.email,
+                n.url,
+                n.phone_number,
+                n.on_call_schedule_id,
+                n.max_retries,
+                n.retry_backoff_seconds,
+                n.retry_backoff_max_seconds,
+                n.digest_window_seconds,
+                acc.email_from as account_email_from,
+                acc.email_reply_to as account_email_reply_to,
+                acc.product_name as account_product_name,
+                acc.base_url as account_base_url,
+                p.uuid as project_uuid,
+                p.name as project_name,
+                c.uuid as check_uuid,
+                c.name as check_name,
+                (CASE LTRIM(RTRIM(n.name))
+                WHEN '' THEN c.name
+                ELSE n.name
+                END) AS name,
+                c.schedule_type,
+                c.ping_period,
+                c.ping_period_units,
+                c.ping_cron_expression,
+                c.grace_period,
+                c.grace_period_units,
+                c.last_ping_at,
+                c.severity AS check_severity,
+                false AS held,
+                true AS test
+            FROM
+                notifications n
+                INNER JOIN
+                checks c ON c.id = $1
+                INNER JOIN
+                projects p ON p.id = c.project_id
+                INNER JOIN
+                accounts acc ON acc.id = n.account_id
+            WHERE
+                n.uuid = $2
+                AND
+                n.account_id = $3
+                AND
+                n.project_id = $4
+                AND
+                (
+                    n.check_id = $1
+                    OR
+                    n.id IN (
+                        SELECT notification_id
+                        FROM check_notifications
+                        WHERE check_id = $1
+                    )
+                )
+                AND
+                n.deleted = false
+        ";
+
+        let alert: Option<NotificationAlert> = sqlx::query_as(sql)
+            .bind(check_id)
+            .bind(uuid)
+            .bind(account_id)
+            .bind(project_id)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        alert.ok_or_else(|| RepositoryError::NotFound {
+            entity_type: ENTITY_NOTIFICATION.to_string(),
+            id: ShortId::from_uuid(uuid).to_string(),
+        })
+    }
+
+    /// Reserves one of the SMS an account may send per hour, returning the ID of the
+    /// reservation, or `None` if the account has used up its limit. The account is
+    /// locked while reserving, so concurrent deliveries can't both take the last SMS.
+    ///
+    /// [`reserve_sms`] not called by APIs, so no access checks needed.
+    pub async fn reserve_sms(&self, account_id: i64, limit_per_hour: u32) -> Result<Option<i64>> {
+        let mut tx = self.database.transaction().await?;
+
+        sqlx::query("SELECT id FROM accounts WHERE id = $1 FOR UPDATE")
+            .bind(account_id)
+            .execute(&mut tx)
+            .await?;
+
+        let sql = r"
+            DELETE FROM
+                sms_reservations
+            WHERE
+                account_id = $1
+                AND
+                reserved_at < NOW() AT TIME ZONE 'UTC' - INTERVAL '1 hour'
+        ";
+
+        sqlx::query(sql).bind(account_id).execute(&mut tx).await?;
+
+        let sql = r"
+            INSERT INTO sms_reservations (
+                account_id
+            )
+            SELECT
+                $1
+            WHERE
+                (
+                    SELECT
+                        COUNT(*)
+                    FROM
+                        sms_reservations
+                    WHERE
+                        account_id = $1
+                ) < $2
+            RETURNING id
+        ";
+
+        let reservation_id: Option<i64> = sqlx::query_scalar(sql)
+            .bind(account_id)
+            .bind(i64::from(limit_per_hour))
+            .fetch_optional(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(reservation_id)
+    }
+
+    /// Releases a reservation made by [`reserve_sms`] for an SMS that wasn't sent, so it
+    /// doesn't count towards the account's limit.
+    ///
+    /// [`release_sms`] not called by APIs, so no access checks needed.
+    pub async fn release_sms(&self, reservation_id: i64) -> Result<()> {
+        let mut conn = self.database.connection().await?;
+
+        sqlx::query("DELETE FROM sms_reservations WHERE id = $1")
+            .bind(reservation_id)
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Claims a batch of alerts that are due for delivery by marking them as `RUNNING`.
+    ///
+    /// Alerts that have been `RUNNING` since before `stale_before` are assumed to belong
+    /// to a delivery that never finished, e.g. because the server was stopped, and are
+    /// claimed again.
+    ///
+    /// [`claim_alert_batch`] not called by APIs, so no access checks needed.
+    pub async fn claim_alert_batch(
+        &self,
+        limit: i64,
+        stale_before: NaiveDateTime,
+    ) -> Result<Vec<NotificationAlert>> {
         let mut tx = self.database.transaction().await?;
 
         let sql = r"
             SELECT
                 a.id,
+                a.uuid,
+                a.notification_id,
                 a.retries_remaining,
+                a.check_status,
+                a.delivery_status,
+                a.claimed_at,
+                n.account_id,
                 n.notification_type,
                 n.email,
                 n.url,
+                n.phone_number,
+                n.on_call_schedule_id,
                 n.max_retries,
+                n.retry_backoff_seconds,
+                n.retry_backoff_max_seconds,
+                n.digest_window_seconds,
+                acc.email_from as account_email_from,
+                acc.email_reply_to as account_email_reply_to,
+                acc.product_name as account_product_name,
+                acc.base_url as account_base_url,
+                p.uuid as project_uuid,
+                p.name as project_name,
                 c.uuid as check_uuid,
+                c.name as check_name,
                 (CASE LTRIM(RTRIM(n.name))
                 WHEN '' THEN c.name
                 ELSE n.name
                 END) AS name,
-                c.last_ping_at
+                c.schedule_type,
+                c.ping_period,
+                c.ping_period_units,
+                c.ping_cron_expression,
+                c.grace_period,
+                c.grace_period_units,
+                c.last_ping_at,
+                c.severity AS check_severity,
+                a.held_at IS NOT NULL AS held,
+                false AS test
             FROM
                 notification_alerts a
                 INNER JOIN
                 notifications n ON n.id = a.notification_id AND n.deleted = false
                 INNER JOIN
-                checks c ON c.id = n.check_id AND c.deleted = false
+                checks c ON c.id = a.check_id AND c.deleted = false
+                INNER JOIN
+                projects p ON p.id = c.project_id
+                INNER JOIN
+                accounts acc ON acc.id = n.account_id
             WHERE
-                delivery_status = 'QUEUED'
-                OR
-                (delivery_status = 'FAILED' AND retries_remaining > 0)
+                (
+                    (
+                        (
+                            a.delivery_status = 'QUEUED'
+                            OR
+                            (a.delivery_status = 'FAILED' AND a.retries_remaining > 0)
+                        )
+                        AND
+                        a.next_attempt_at <= NOW() AT TIME ZONE 'UTC'
+                    )
+                    OR
+                    (
+                        a.delivery_status = 'RUNNING'
+                        AND
+                        a.claimed_at < $1
+                    )
+                )
+                AND
+                (
+                    $3::BIGINT[] IS NULL
+                    OR
+                    (a.notification_id = ANY($3) AND a.id <> ALL($4))
+                )
             ORDER BY
-                a.created_at ASC
-            LIMIT 10
-            FOR UPDATE SKIP LOCKED
+                a.next_attempt_at ASC
+            LIMIT $2
+            FOR UPDATE OF a SKIP LOCKED
             ";
 
-        let alerts: Vec<NotificationAlert> = sqlx::query_as(sql).fetch_all(&mut tx).await?;
-        let mut sent_alerts = Vec::new();
-        let mut failed_alerts = Vec::new();
-
-        for alert in alerts {
-            match notifier.send_alert(&alert).await {
-                Ok(_) => sent_alerts.push(alert),
-                Err(e) => {
-                    tracing::error!("failed to send alert: {:?}", e);
-                    failed_alerts.push(alert)
-                }
-            }
+        let mut alerts: Vec<NotificationAlert> = sqlx::query_as(sql)
+            .bind(stale_before)
+            .bind(limit)
+            .bind(None::<Vec<i64>>)
+            .bind(None::<Vec<i64>>)
+            .fetch_all(&mut tx)
+            .await?;
+
+        if alerts.is_empty() {
+            return Ok(alerts);
         }
 
-        for alert in sent_alerts.iter() {
-            // TODO: Include confirmation from server, e.g. Message ID or HTTP status?
-            let sql = r"
-            UPDATE notification_alerts
-            SET delivery_status = 'DELIVERED', finished_at = NOW() AT TIME ZONE 'UTC'
-            WHERE id = $1
-            ";
+        // A digest should contain the alerts that are due for its notification, not just
+        // the ones that happened to fit in this batch. The same goes for alerts released
+        // together at the end of quiet hours. At most `limit` more alerts are claimed for
+        // them to keep the batch bounded, any left over are sent in a later digest.
+        let mut digest_notification_ids: Vec<i64> = alerts
+            .iter()
+            .filter(|a| a.digest_window_seconds.is_some() || a.held)
+            .map(|a| a.notification_id)
+            .collect();
+        digest_notification_ids.sort_unstable();
+        digest_notification_ids.dedup();
+
+        if !digest_notification_ids.is_empty() {
+            let claimed_ids: Vec<i64> = alerts.iter().map(|a| a.id).collect();
+            let digest_alerts: Vec<NotificationAlert> = sqlx::query_as(sql)
+                .bind(stale_before)
+                .bind(limit)
+                .bind(Some(&digest_notification_ids))
+                .bind(Some(&claimed_ids))
+                .fetch_all(&mut tx)
+                .await?;
+            alerts.extend(digest_alerts);
+        }
 
-            let result = sqlx::query(sql).bind(alert.id).execute(&mut tx).await?;
-            if result.rows_affected() != 1 {
+        for alert in alerts.iter() {
+            if alert.delivery_status == DeliveryStatus::Running {
                 tracing::warn!(
                     alert_id = alert.id,
-                    "alert delivered successfully, but failed to update status, duplicate will be sent later",
+                    claimed_at = alert.claimed_at.map(|dt| dt.to_string()),
+                    "reclaiming alert with stale delivery claim"
                 );
-            } else {
-                tracing::debug!(alert_id = alert.id, "alert delivered successfully");
             }
         }
 
-        for alert in failed_alerts {
-            // TODO: Include confirmation from server, e.g. Message ID or HTTP status?
-            let sql = if alert.retries_remaining <= 0 {
-                r"
-                    UPDATE notification_alerts
-                    SET
-                        delivery_status = 'FAILED',
-                        retries_remaining = 0,
-                        finished_at = NOW() AT TIME ZONE 'UTC'
-                    WHERE
-                        id = $1
-                    RETURNING
-                        retries_remaining
-                "
-            } else {
-                r"
-                    UPDATE notification_alerts
-                    SET
-                        delivery_status = 'FAILED',
-                        retries_remaining = retries_remaining - 1,
-                        finished_at = NOW() AT TIME ZONE 'UTC'
-                    WHERE
-                        id = $1
-                    RETURNING
-                        retries_remaining
-                "
-            };
-
-            let row = sqlx::query(sql).bind(alert.id).fetch_one(&mut tx).await?;
-            let retries_remaining: i32 = row.get("retries_remaining");
-
-            if retries_remaining > 0 {
-                tracing::debug!(
-                    retries_remaining = retries_remaining,
-                    alert_id = alert.id,
-                    "will retry sending alert"
-                );
-            } else {
-                tracing::debug!(
-                    alert_id = alert.id,
-                    "exceeded max_retries, giving up sending alert"
-                );
-            }
+        let sql = r"
+            UPDATE notification_alerts
+            SET
+                delivery_status = 'RUNNING',
+                claimed_at = $2
+            WHERE
+                id = ANY($1)
+        ";
+
+        let claimed_at = Utc::now().naive_utc();
+        let alert_ids: Vec<i64> = alerts.iter().map(|a| a.id).collect();
+
+        sqlx::query(sql)
+            .bind(&alert_ids)
+            .bind(claimed_at)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        for alert in alerts.iter_mut() {
+            alert.delivery_status = DeliveryStatus::Running;
+            alert.claimed_at = Some(claimed_at);
         }
 
+        Ok(alerts)
+    }
+
+    /// Records the successful delivery of an alert claimed with [`claim_alert_batch`].
+    ///
+    /// [`record_alert_delivered`] not called by APIs, so no access checks needed.
+    pub async fn record_alert_delivered(
+        &self,
+        alert: &NotificationAlert,
+        delivery: &Delivery,
+    ) -> Result<()> {
+        let mut tx = self.database.transaction().await?;
+
+        record_attempt(
+            &mut tx,
+            alert.id,
+            DeliveryStatus::Delivered,
+            delivery.http_status,
+            delivery.provider_message_id.as_deref(),
+            None,
+        )
+        .await?;
+
+        let sql = r"
+            UPDATE notification_alerts
+            SET
+                delivery_status = 'DELIVERED',
+                finished_at = NOW() AT TIME ZONE 'UTC'
+            WHERE
+                id = $1
+                AND
+                delivery_status = 'RUNNING'
+                AND
+                claimed_at = $2
+        ";
+
+        let result = sqlx::query(sql)
+            .bind(alert.id)
+            .bind(alert.claimed_at)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        if result.rows_affected() != 1 {
+            tracing::warn!(
+                alert_id = alert.id,
+                "alert delivered successfully, but it was reclaimed by another delivery, duplicate may be sent",
+            );
+        } else {
+            tracing::debug!(alert_id = alert.id, "alert delivered successfully");
+        }
+
+        Ok(())
+    }
+
+    /// Records a failed delivery of an alert claimed with [`claim_alert_batch`], scheduling
+    /// the next attempt if there are retries remaining.
+    ///
+    /// [`record_alert_failed`] not called by APIs, so no access checks needed.
+    pub async fn record_alert_failed(
+        &self,
+        alert: &NotificationAlert,
+        error: &NotifierError,
+    ) -> Result<()> {
+        let retry = (alert.max_retries - alert.retries_remaining + 1).max(1) as u32;
+        let delay = Backoff::new(alert.retry_backoff_seconds, alert.retry_backoff_max_seconds)
+            .delay(retry, &mut rand::thread_rng());
+        let next_attempt_at = Utc::now().naive_utc() + delay;
+
+        let mut tx = self.database.transaction().await?;
+
+        record_attempt(
+            &mut tx,
+            alert.id,
+            DeliveryStatus::Failed,
+            error.http_status(),
+            None,
+            Some(&error.to_message()),
+        )
+        .await?;
+
+        let sql = r"
+            UPDATE notification_alerts
+            SET
+                delivery_status = 'FAILED',
+                retries_remaining = GREATEST(retries_remaining - 1, 0),
+                next_attempt_at = $3,
+                finished_at = NOW() AT TIME ZONE 'UTC'
+            WHERE
+                id = $1
+                AND
+                delivery_status = 'RUNNING'
+                AND
+                claimed_at = $2
+            RETURNING
+                retries_remaining
+        ";
+
+        let row = sqlx::query(sql)
+            .bind(alert.id)
+            .bind(alert.claimed_at)
+            .bind(next_attempt_at)
+            .fetch_optional(&mut tx)
+            .await?;
+
         tx.commit().await?;
 
-        Ok(sent_alerts)
+        match row.map(|row| row.get::<i32, _>("retries_remaining")) {
+            None => tracing::warn!(
+                alert_id = alert.id,
+                "alert delivery failed, but it was reclaimed by another delivery"
+            ),
+            Some(retries_remaining) if retries_remaining > 0 => tracing::debug!(
+                retries_remaining = retries_remaining,
+                alert_id = alert.id,
+                next_attempt_at = next_attempt_at.to_string(),
+                "will retry sending alert"
+            ),
+            Some(_) => tracing::debug!(
+                alert_id = alert.id,
+                "exceeded max_retries, giving up sending alert"
+            ),
+        }
+
+        Ok(())
     }
+
+    /// Puts an alert claimed with [`claim_alert_batch`] back in the queue until `until`,
+    /// without counting it as a delivery attempt. Held alerts of a notification are
+    /// delivered together once they are due.
+    ///
+    /// [`hold_alert`] not called by APIs, so no access checks needed.
+    pub async fn hold_alert(&self, alert: &NotificationAlert, until: NaiveDateTime) -> Result<()> {
+        let mut conn = self.database.connection().await?;
+
+        let sql = r"
+            UPDATE notification_alerts
+            SET
+                delivery_status = 'QUEUED',
+                claimed_at = NULL,
+                next_attempt_at = $3,
+                held_at = COALESCE(held_at, NOW() AT TIME ZONE 'UTC')
+            WHERE
+                id = $1
+                AND
+                delivery_status = 'RUNNING'
+                AND
+                claimed_at = $2
+        ";
+
+        let result = sqlx::query(sql)
+            .bind(alert.id)
+            .bind(alert.claimed_at)
+            .bind(until)
+            .execute(&mut conn)
+            .await?;
+
+        if result.rows_affected() != 1 {
+            tracing::warn!(
+                alert_id = alert.id,
+                "alert not held, it was reclaimed by another delivery"
+            );
+        } else {
+            tracing::debug!(
+                alert_id = alert.id,
+                until = until.to_string(),
+                "holding alert during quiet hours"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Ensures a notification has somewhere to send alerts for its type, so that a missing
+/// email address, URL or phone number is rejected when the notification is saved rather
+/// than failing every alert.
+pub(super) fn ensure_destination(
+    notification_type: &NotificationType,
+    email: Option<&str>,
+    url: Option<&str>,
+    phone_number: Option<&str>,
+) -> Result<()> {
+    let (destination, field) = match notification_type {
+        NotificationType::Email => (email, "an email address"),
+        NotificationType::Webhook => (url, "a URL"),
+        NotificationType::Sms => (phone_number, "a phone number"),
+    };
+
+    match destination.map(str::trim) {
+        Some(destination) if !destination.is_empty() => {
+            if matches!(notification_type, NotificationType::Webhook)
+                && url::Url::parse(destination).is_err()
+            {
+                return Err(RepositoryError::BadArgument(format!(
+                    "{} is not a valid URL",
+                    destination
+                )));
+            }
+            Ok(())
+        }
+        _ => Err(RepositoryError::BadArgument(format!(
+            "{} notifications need {}",
+            notification_type.to_string(),
+            field
+        ))),
+    }
+}
+
+/// Queues an alert for a notification. Alerts for a notification with a digest window
+/// are held until the window closes, joining any alerts already waiting for the same
+/// digest. Alerts held during quiet hours are not a digest window, so new alerts don't
+/// wait for them.
+pub(super) async fn enqueue_alert(
+    conn: &mut DbConnection,
+    notification_id: i64,
+    check_id: i64,
+    incident_id: i64,
+    check_status: CheckStatus,
+    retries_remaining: i32,
+    escalation_tier: Option<i32>,
+) -> Result<()> {
+    let sql = r"
+        INSERT INTO notification_alerts (
+            notification_id,
+            check_id,
+            incident_id,
+            check_status,
+            retries_remaining,
+            escalation_tier,
+            next_attempt_at
+        ) VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6,
+            (
+                SELECT
+                    CASE
+                    WHEN n.digest_window_seconds IS NULL THEN NOW() AT TIME ZONE 'UTC'
+                    ELSE COALESCE(
+                        (
+                            SELECT MAX(q.next_attempt_at)
+                            FROM notification_alerts q
+                            WHERE
+                                q.notification_id = n.id
+                                AND
+                                q.delivery_status = 'QUEUED'
+                                AND
+                                q.held_at IS NULL
+                                AND
+                                q.next_attempt_at > NOW() AT TIME ZONE 'UTC'
+                        ),
+                        NOW() AT TIME ZONE 'UTC' + n.digest_window_seconds * INTERVAL '1 second'
+                    )
+                    END
+                FROM
+                    notifications n
+                WHERE
+                    n.id = $1
+            )
+        )
+    ";
+
+    sqlx::query(sql)
+        .bind(notification_id)
+        .bind(check_id)
+        .bind(incident_id)
+        .bind(check_status)
+        .bind(retries_remaining)
+        .bind(escalation_tier)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn record_attempt(
+    conn: &mut DbConnection,
+    alert_id: i64,
+    delivery_status: DeliveryStatus,
+    http_status: Option<u16>,
+    provider_message_id: Option<&str>,
+    error: Option<&str>,
+) -> Result<()> {
+    let sql = r"
+        INSERT INTO notification_alert_attempts (
+            alert_id,
+            delivery_status,
+            http_status,
+            provider_message_id,
+            error
+        ) VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        )
+    ";
+
+    sqlx::query(sql)
+        .bind(alert_id)
+        .bind(delivery_status)
+        .bind(http_status.map(i32::from))
+        .bind(provider_message_id)
+        .bind(error)
+        .execute(conn)
+        .await?;
+
+    Ok(())
 }
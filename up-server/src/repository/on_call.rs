@@ -0,0 +1,1028 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    repository::{
+        auth::ENTITY_USER,
+        channel::{get_channel_id, ENTITY_CHANNEL},
+        get_project_account_id, RepositoryError, Result,
+    },
+    shortid::ShortId,
+};
+
+const ENTITY_ON_CALL_SCHEDULE: &str = "on-call schedule";
+
+#[derive(sqlx::Type, Debug, Clone, Copy)]
+#[sqlx(type_name = "rotation_period", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RotationPeriod {
+    Daily,
+    Weekly,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct OnCallSchedule {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub name: String,
+    pub rotation: RotationPeriod,
+    pub handoff_at: NaiveDateTime,
+    pub member_uuids: Vec<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct OnCallOverride {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub user_uuid: Uuid,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+/// The user who is on call for a schedule, and whether they are on call because of an
+/// override rather than the rotation.
+#[derive(sqlx::FromRow, Clone)]
+pub struct OnCallUser {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub email: String,
+    pub phone_number: Option<String>,
+    pub overridden: bool,
+}
+
+pub struct CreateOnCallSchedule {
+    pub name: Option<String>,
+    pub rotation: RotationPeriod,
+    pub handoff_at: NaiveDateTime,
+    pub member_uuids: Vec<Uuid>,
+}
+
+pub struct UpdateOnCallSchedule {
+    pub name: Option<String>,
+    pub rotation: Option<RotationPeriod>,
+    pub handoff_at: Option<NaiveDateTime>,
+    pub member_uuids: Option<Vec<Uuid>>,
+}
+
+pub struct CreateOnCallOverride {
+    pub user_uuid: Uuid,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+pub struct OnCallScheduleRepository {
+    database: Database,
+}
+
+impl OnCallScheduleRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Reads an on-call schedule with its overrides that have not ended yet.
+    pub async fn read_one(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<(OnCallSchedule, Vec<OnCallOverride>)> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "reading on-call schedule"
+        );
+
+        let schedule = read_schedule(&mut conn, uuid, project_id, account_id).await?;
+
+        let sql = r"
+            SELECT
+                o.id,
+                o.uuid,
+                u.uuid AS user_uuid,
+                o.starts_at,
+                o.ends_at,
+                o.created_at
+            FROM
+                on_call_overrides o
+                INNER JOIN
+                users u ON u.id = o.user_id
+            WHERE
+                o.schedule_id = $1
+                AND
+                o.ends_at > NOW() AT TIME ZONE 'UTC'
+            ORDER BY
+                o.starts_at ASC
+        ";
+
+        let overrides: Vec<OnCallOverride> = sqlx::query_as(sql)
+            .bind(schedule.id)
+            .fetch_all(&mut conn)
+            .await?;
+
+        Ok((schedule, overrides))
+    }
+
+    pub async fn read_all(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+    ) -> Result<Vec<OnCallSchedule>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            "reading on-call schedules"
+        );
+
+        let sql = r"
+            SELECT
+                s.*,
+                ARRAY(
+                    SELECT u.uuid
+                    FROM on_call_schedule_members m
+                    INNER JOIN users u ON u.id = m.user_id
+                    WHERE m.schedule_id = s.id
+                    ORDER BY m.position ASC
+                ) AS member_uuids
+            FROM
+                on_call_schedules s
+            WHERE
+                s.project_id = $1
+                AND
+                s.account_id = $2
+                AND
+                s.deleted = false
+            ORDER BY
+                s.created_at ASC
+        ";
+
+        Ok(sqlx::query_as(sql)
+            .bind(project_id)
+            .bind(account_id)
+            .fetch_all(&mut conn)
+            .await?)
+    }
+
+    pub async fn create(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        request: CreateOnCallSchedule,
+    ) -> Result<OnCallSchedule> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            INSERT INTO on_call_schedules (
+                account_id,
+                project_id,
+                uuid,
+                shortid,
+                name,
+                rotation,
+                handoff_at,
+                created_by
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8
+            )
+            RETURNING id
+        ";
+
+        let uuid = Uuid::new_v4();
+        let short_id: ShortId = uuid.into();
+
+        let (id,): (i64,) = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(short_id.to_string())
+            .bind(request.name.as_deref().unwrap_or(""))
+            .bind(request.rotation)
+            .bind(request.handoff_at)
+            .bind(identity.user_id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        insert_members(&mut tx, id, account_id, &request.member_uuids).await?;
+
+        let schedule = read_schedule(&mut tx, &uuid, project_id, account_id).await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            name = request.name,
+            "on-call schedule created"
+        );
+
+        Ok(schedule)
+    }
+
+    /// Updates an on-call schedule. If `member_uuids` are given, they replace the
+    /// existing rotation.
+    pub async fn update(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+        request: UpdateOnCallSchedule,
+    ) -> Result<OnCallSchedule> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let sql = r"
+            UPDATE
+                on_call_schedules
+            SET
+                name = COALESCE($4, name),
+                rotation = COALESCE($5, rotation),
+                handoff_at = COALESCE($6, handoff_at),
+                updated_at = NOW() AT TIME ZONE 'UTC',
+                updated_by = $7
+            WHERE
+                account_id = $1
+                AND
+                project_id = $2
+                AND
+                uuid = $3
+                AND
+                deleted = false
+            RETURNING id
+        ";
+
+        let (id,): (i64,) = sqlx::query_as(sql)
+            .bind(account_id)
+            .bind(project_id)
+            .bind(uuid)
+            .bind(&request.name)
+            .bind(request.rotation)
+            .bind(request.handoff_at)
+            .bind(identity.user_id)
+            .fetch_optional(&mut tx)
+            .await?
+            .ok_or_else(|| RepositoryError::NotFound {
+                entity_type: ENTITY_ON_CALL_SCHEDULE.to_string(),
+                id: ShortId::from_uuid(uuid).to_string(),
+            })?;
+
+        if let Some(member_uuids) = &request.member_uuids {
+            sqlx::query("DELETE FROM on_call_schedule_members WHERE schedule_id = $1")
+                .bind(id)
+                .execute(&mut tx)
+                .await?;
+            insert_members(&mut tx, id, account_id, member_uuids).await?;
+        }
+
+        let schedule = read_schedule(&mut tx, uuid, project_id, account_id).await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "on-call schedule updated"
+        );
+
+        Ok(schedule)
+    }
+
+    /// Deletes an on-call schedule, channels that routed to it are sent to their own
+    /// recipient again.
+    pub async fn delete(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        tracing::trace!(
+            project_uuid = project_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "deleting on-call schedule"
+        );
+
+        let sql = r"
+            UPDATE
+                on_call_schedules
+            SET
+                deleted = true,
+                deleted_at = NOW() AT TIME ZONE 'UTC',
+                deleted_by = $4
+            WHERE
+                project_id = $1
+                AND
+                account_id = $2
+                AND
+                uuid = $3
+                AND
+                deleted = false
+            RETURNING id
+        ";
+
+        let deleted: Option<(i64,)> = sqlx::query_as(sql)
+            .bind(project_id)
+            .bind(account_id)
+            .bind(uuid)
+            .bind(identity.user_id)
+            .fetch_optional(&mut tx)
+            .await?;
+
+        if let Some((id,)) = deleted {
+            sqlx::query(
+                "UPDATE notifications SET on_call_schedule_id = NULL WHERE on_call_schedule_id = $1",
+            )
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        if deleted.is_some() {
+            tracing::trace!(uuid = uuid.to_string(), "on-call schedule deleted");
+        }
+
+        Ok(deleted.is_some())
+    }
+
+    /// Puts a user on call for a schedule between `starts_at` and `ends_at`, regardless
+    /// of the rotation. When overrides overlap, the most recently created one wins.
+    pub async fn create_override(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        schedule_uuid: &Uuid,
+        request: CreateOnCallOverride,
+    ) -> Result<OnCallOverride> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        if request.ends_at <= request.starts_at {
+            return Err(RepositoryError::BadArgument(
+                "an on-call override must end after it starts".to_string(),
+            ));
+        }
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let schedule = read_schedule(&mut tx, schedule_uuid, project_id, account_id).await?;
+        let user_ids = get_account_user_ids(&mut tx, account_id, &[request.user_uuid]).await?;
+
+        let sql = r"
+            INSERT INTO on_call_overrides (
+                schedule_id,
+                user_id,
+                starts_at,
+                ends_at,
+                created_by
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5
+            )
+            RETURNING
+                id,
+                uuid,
+                $6::UUID AS user_uuid,
+                starts_at,
+                ends_at,
+                created_at
+        ";
+
+        let on_call_override: OnCallOverride = sqlx::query_as(sql)
+            .bind(schedule.id)
+            .bind(user_ids[0])
+            .bind(request.starts_at)
+            .bind(request.ends_at)
+            .bind(identity.user_id)
+            .bind(request.user_uuid)
+            .fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            schedule_uuid = schedule_uuid.to_string(),
+            uuid = on_call_override.uuid.to_string(),
+            "on-call override created"
+        );
+
+        Ok(on_call_override)
+    }
+
+    pub async fn delete_override(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        schedule_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let schedule = read_schedule(&mut tx, schedule_uuid, project_id, account_id).await?;
+
+        let deleted =
+            sqlx::query("DELETE FROM on_call_overrides WHERE schedule_id = $1 AND uuid = $2")
+                .bind(schedule.id)
+                .bind(uuid)
+                .execute(&mut tx)
+                .await?
+                .rows_affected()
+                > 0;
+
+        tx.commit().await?;
+
+        if deleted {
+            tracing::trace!(uuid = uuid.to_string(), "on-call override deleted");
+        }
+
+        Ok(deleted)
+    }
+
+    /// Reads who is on call for a schedule right now, if anyone.
+    pub async fn read_on_call(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<Option<OnCallUser>> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        let schedule = read_schedule(&mut conn, uuid, project_id, account_id).await?;
+
+        read_on_call_user(&mut conn, &schedule, Utc::now().naive_utc()).await
+    }
+
+    /// Reads who is on call right now for the schedule with `id`, so alerts can be
+    /// delivered to them.
+    ///
+    /// [`read_on_call_by_id`] not called by APIs, so no access checks needed.
+    pub async fn read_on_call_by_id(&self, id: i64) -> Result<Option<OnCallUser>> {
+        let mut conn = self.database.connection().await?;
+
+        read_on_call_user_by_schedule_id(&mut conn, id, Utc::now().naive_utc()).await
+    }
+
+    /// Reads the on-call schedule a channel sends its alerts to.
+    pub async fn read_for_channel(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        channel_uuid: &Uuid,
+    ) -> Result<OnCallSchedule> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut conn = self.database.connection().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut conn, project_uuid, &identity.account_ids()).await?;
+
+        let channel_id = get_channel_id(&mut conn, channel_uuid, project_id, account_id).await?;
+
+        let sql = r"
+            SELECT
+                s.uuid
+            FROM
+                on_call_schedules s
+                INNER JOIN
+                notifications n ON n.on_call_schedule_id = s.id
+            WHERE
+                n.id = $1
+                AND
+                s.deleted = false
+        ";
+
+        let (uuid,): (Uuid,) = sqlx::query_as(sql)
+            .bind(channel_id)
+            .fetch_optional(&mut conn)
+            .await?
+            .ok_or_else(|| RepositoryError::NotFound {
+                entity_type: ENTITY_ON_CALL_SCHEDULE.to_string(),
+                id: ShortId::from_uuid(channel_uuid).to_string(),
+            })?;
+
+        read_schedule(&mut conn, &uuid, project_id, account_id).await
+    }
+
+    /// Makes a channel send its alerts to whoever is on call for a schedule, instead of
+    /// its own email address or phone number. Only email and SMS channels can be routed
+    /// to an on-call schedule.
+    pub async fn attach(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        channel_uuid: &Uuid,
+        uuid: &Uuid,
+    ) -> Result<()> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let channel_id = get_channel_id(&mut tx, channel_uuid, project_id, account_id).await?;
+        let schedule = read_schedule(&mut tx, uuid, project_id, account_id).await?;
+
+        let sql = r"
+            UPDATE
+                notifications
+            SET
+                on_call_schedule_id = $2
+            WHERE
+                id = $1
+                AND
+                notification_type IN ('EMAIL', 'SMS')
+        ";
+
+        let attached = sqlx::query(sql)
+            .bind(channel_id)
+            .bind(schedule.id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        if !attached {
+            return Err(RepositoryError::BadArgument(format!(
+                "only email and SMS {}s can be routed to an on-call schedule",
+                ENTITY_CHANNEL
+            )));
+        }
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            channel_uuid = channel_uuid.to_string(),
+            uuid = uuid.to_string(),
+            "on-call schedule attached to channel"
+        );
+
+        Ok(())
+    }
+
+    /// Makes a channel send its alerts to its own recipient again.
+    pub async fn detach(
+        &self,
+        identity: &Identity,
+        project_uuid: &Uuid,
+        channel_uuid: &Uuid,
+    ) -> Result<bool> {
+        identity.ensure_assigned_to_project(project_uuid)?;
+
+        let mut tx = self.database.transaction().await?;
+
+        let (project_id, account_id) =
+            get_project_account_id(&mut tx, project_uuid, &identity.account_ids()).await?;
+
+        if !identity.can_manage_notifications_in_project_with_id(project_id) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        let channel_id = get_channel_id(&mut tx, channel_uuid, project_id, account_id).await?;
+
+        let sql = r"
+            UPDATE
+                notifications
+            SET
+                on_call_schedule_id = NULL
+            WHERE
+                id = $1
+                AND
+                on_call_schedule_id IS NOT NULL
+        ";
+
+        let detached = sqlx::query(sql)
+            .bind(channel_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        tx.commit().await?;
+
+        if detached {
+            tracing::trace!(
+                channel_uuid = channel_uuid.to_string(),
+                "on-call schedule detached from channel"
+            );
+        }
+
+        Ok(detached)
+    }
+}
+
+async fn read_schedule(
+    conn: &mut DbConnection,
+    uuid: &Uuid,
+    project_id: i64,
+    account_id: i64,
+) -> Result<OnCallSchedule> {
+    let sql = r"
+            SELECT
+                s.*,
+                ARRAY(
+                    SELECT u.uuid
+                    FROM on_call_schedule_members m
+                    INNER JOIN users u ON u.id = m.user_id
+                    WHERE m.schedule_id = s.id
+                    ORDER BY m.position ASC
+                ) AS member_uuids
+            FROM
+                on_call_schedules s
+            WHERE
+                s.uuid = $1
+                AND
+                s.project_id = $2
+                AND
+                s.account_id = $3
+                AND
+                s.deleted = false
+        ";
+
+    let schedule: Option<OnCallSchedule> = sqlx::query_as(sql)
+        .bind(uuid)
+        .bind(project_id)
+        .bind(account_id)
+        .fetch_optional(conn)
+        .await?;
+
+    schedule.ok_or_else(|| RepositoryError::NotFound {
+        entity_type: ENTITY_ON_CALL_SCHEDULE.to_string(),
+        id: ShortId::from_uuid(uuid).to_string(),
+    })
+}
+
+/// Looks up the ids of users that belong to the account, in the order of `uuids`.
+async fn get_account_user_ids(
+    conn: &mut DbConnection,
+    account_id: i64,
+    uuids: &[Uuid],
+) -> Result<Vec<i64>> {
+    let sql = r"
+            SELECT
+                u.id,
+                u.uuid
+            FROM
+                users u
+                INNER JOIN
+                user_accounts ua ON ua.user_id = u.id
+            WHERE
+                u.uuid = ANY($1)
+                AND
+                ua.account_id = $2
+                AND
+                u.deleted = false
+        ";
+
+    let users: Vec<(i64, Uuid)> = sqlx::query_as(sql)
+        .bind(uuids)
+        .bind(account_id)
+        .fetch_all(conn)
+        .await?;
+
+    uuids
+        .iter()
+        .map(|uuid| {
+            users
+                .iter()
+                .find(|(_, u)| u == uuid)
+                .map(|(id, _)| *id)
+                .ok_or_else(|| RepositoryError::NotFound {
+                    entity_type: ENTITY_USER.to_string(),
+                    id: ShortId::from_uuid(uuid).to_string(),
+                })
+        })
+        .collect()
+}
+
+async fn insert_members(
+    conn: &mut DbConnection,
+    schedule_id: i64,
+    account_id: i64,
+    member_uuids: &[Uuid],
+) -> Result<()> {
+    if member_uuids.is_empty() {
+        return Err(RepositoryError::BadArgument(
+            "an on-call schedule needs at least one member".to_string(),
+        ));
+    }
+
+    let user_ids = get_account_user_ids(&mut *conn, account_id, member_uuids).await?;
+
+    let sql = r"
+            INSERT INTO on_call_schedule_members (
+                schedule_id,
+                position,
+                user_id
+            )
+            SELECT
+                $1,
+                m.position - 1,
+                m.user_id
+            FROM
+                UNNEST($2::BIGINT[]) WITH ORDINALITY AS m(user_id, position)
+        ";
+
+    sqlx::query(sql)
+        .bind(schedule_id)
+        .bind(&user_ids)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Resolves who is on call at `at` for the schedule with `id`, if it still exists.
+pub(super) async fn read_on_call_user_by_schedule_id(
+    conn: &mut DbConnection,
+    id: i64,
+    at: NaiveDateTime,
+) -> Result<Option<OnCallUser>> {
+    let sql = r"
+            SELECT
+                s.*,
+                ARRAY[]::UUID[] AS member_uuids
+            FROM
+                on_call_schedules s
+            WHERE
+                s.id = $1
+                AND
+                s.deleted = false
+        ";
+
+    let schedule: Option<OnCallSchedule> = sqlx::query_as(sql)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    match schedule {
+        Some(schedule) => read_on_call_user(conn, &schedule, at).await,
+        None => Ok(None),
+    }
+}
+
+/// Resolves who is on call for a schedule at `at`: the most recently created override
+/// covering `at`, otherwise the member whose turn it is in the rotation.
+async fn read_on_call_user(
+    conn: &mut DbConnection,
+    schedule: &OnCallSchedule,
+    at: NaiveDateTime,
+) -> Result<Option<OnCallUser>> {
+    let sql = r"
+            SELECT
+                u.id,
+                u.uuid,
+                u.email,
+                u.phone_number,
+                true AS overridden
+            FROM
+                on_call_overrides o
+                INNER JOIN
+                users u ON u.id = o.user_id
+            WHERE
+                o.schedule_id = $1
+                AND
+                o.starts_at <= $2
+                AND
+                o.ends_at > $2
+                AND
+                u.deleted = false
+            ORDER BY
+                o.created_at DESC,
+                o.id DESC
+            LIMIT 1
+        ";
+
+    let on_call: Option<OnCallUser> = sqlx::query_as(sql)
+        .bind(schedule.id)
+        .bind(at)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    if on_call.is_some() {
+        return Ok(on_call);
+    }
+
+    let sql = r"
+            SELECT
+                u.id,
+                u.uuid,
+                u.email,
+                u.phone_number,
+                false AS overridden
+            FROM
+                on_call_schedule_members m
+                INNER JOIN
+                users u ON u.id = m.user_id
+            WHERE
+                m.schedule_id = $1
+                AND
+                u.deleted = false
+            ORDER BY
+                m.position ASC
+        ";
+
+    let members: Vec<OnCallUser> = sqlx::query_as(sql)
+        .bind(schedule.id)
+        .fetch_all(conn)
+        .await?;
+
+    if members.is_empty() {
+        return Ok(None);
+    }
+
+    let index = rotation_index(schedule.handoff_at, at, schedule.rotation, members.len());
+    Ok(members.into_iter().nth(index))
+}
+
+/// Index of the member on call at `at`, for a rotation of `members` that starts with
+/// the first member at `handoff_at` and hands off every `rotation` period. Before
+/// `handoff_at` the rotation runs backwards, so the last member precedes the first.
+fn rotation_index(
+    handoff_at: NaiveDateTime,
+    at: NaiveDateTime,
+    rotation: RotationPeriod,
+    members: usize,
+) -> usize {
+    let period = match rotation {
+        RotationPeriod::Daily => Duration::days(1),
+        RotationPeriod::Weekly => Duration::weeks(1),
+    };
+
+    let handoffs = (at - handoff_at)
+        .num_seconds()
+        .div_euclid(period.num_seconds());
+
+    handoffs.rem_euclid(members as i64) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn rotation_hands_off_every_period() {
+        let handoff_at = at("2022-08-01 09:00:00");
+
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-08-01 09:00:00"),
+                RotationPeriod::Daily,
+                3
+            ),
+            0
+        );
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-08-02 08:59:59"),
+                RotationPeriod::Daily,
+                3
+            ),
+            0
+        );
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-08-02 09:00:00"),
+                RotationPeriod::Daily,
+                3
+            ),
+            1
+        );
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-08-04 12:00:00"),
+                RotationPeriod::Daily,
+                3
+            ),
+            0
+        );
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-08-14 12:00:00"),
+                RotationPeriod::Weekly,
+                3
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn rotation_runs_backwards_before_handoff() {
+        let handoff_at = at("2022-08-01 09:00:00");
+
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-08-01 08:59:59"),
+                RotationPeriod::Daily,
+                3
+            ),
+            2
+        );
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-07-29 10:00:00"),
+                RotationPeriod::Daily,
+                3
+            ),
+            0
+        );
+        assert_eq!(
+            rotation_index(
+                handoff_at,
+                at("2022-07-31 09:00:00"),
+                RotationPeriod::Weekly,
+                2
+            ),
+            1
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use chrono::{NaiveTime, Utc};
+use chrono_tz::Tz;
+use up_core::auth::Scope;
+
+use crate::{
+    auth::Identity,
+    database::{Database, DbConnection},
+    quiet_hours::QuietHours,
+    repository::{
+        notification::{NotificationAlert, NotificationType},
+        on_call::read_on_call_user_by_schedule_id,
+        RepositoryError, Result,
+    },
+};
+
+/// The notification preferences of a user.
+pub struct NotificationPreferences {
+    pub timezone: String,
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    pub channels: Vec<ChannelPreference>,
+}
+
+/// The preferences of a user for one type of channel.
+#[derive(sqlx::FromRow)]
+pub struct ChannelPreference {
+    pub notification_type: NotificationType,
+    /// Whether alerts for low severity checks sent through this type of channel are
+    /// held during quiet hours.
+    pub quiet_hours: bool,
+}
+
+#[derive(Clone)]
+pub struct PreferencesRepository {
+    database: Database,
+}
+
+impl PreferencesRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Reads the notification preferences of the user making the request.
+    pub async fn read(&self, identity: &Identity) -> Result<NotificationPreferences> {
+        let mut conn = self.database.connection().await?;
+
+        tracing::trace!(
+            user_id = identity.user_id,
+            "reading notification preferences"
+        );
+
+        read_preferences(&mut conn, identity.user_id).await
+    }
+
+    /// Replaces the notification preferences of the user making the request.
+    pub async fn update(
+        &self,
+        identity: &Identity,
+        request: NotificationPreferences,
+    ) -> Result<NotificationPreferences> {
+        if !identity.has_scope(Scope::ManageNotifications) {
+            return Err(RepositoryError::Forbidden);
+        }
+
+        if request.timezone.parse::<Tz>().is_err() {
+            return Err(RepositoryError::BadArgument(format!(
+                "{} is not a known time zone",
+                request.timezone
+            )));
+        }
+
+        if request.quiet_hours_start.is_some() != request.quiet_hours_end.is_some() {
+            return Err(RepositoryError::BadArgument(
+                "quiet hours need both a start and an end".to_string(),
+            ));
+        }
+
+        let mut tx = self.database.transaction().await?;
+
+        let sql = r"
+            UPDATE
+                users
+            SET
+                timezone = $2,
+                quiet_hours_start = $3,
+                quiet_hours_end = $4,
+                updated_at = NOW() AT TIME ZONE 'UTC',
+                updated_by = $1
+            WHERE
+                id = $1
+        ";
+
+        sqlx::query(sql)
+            .bind(identity.user_id)
+            .bind(&request.timezone)
+            .bind(request.quiet_hours_start)
+            .bind(request.quiet_hours_end)
+            .execute(&mut tx)
+            .await?;
+
+        sqlx::query("DELETE FROM user_notification_preferences WHERE user_id = $1")
+            .bind(identity.user_id)
+            .execute(&mut tx)
+            .await?;
+
+        let sql = r"
+            INSERT INTO user_notification_preferences (
+                user_id,
+                notification_type,
+                quiet_hours
+            ) VALUES (
+                $1,
+                $2,
+                $3
+            )
+        ";
+
+        for channel in request.channels.iter() {
+            sqlx::query(sql)
+                .bind(identity.user_id)
+                .bind(&channel.notification_type)
+                .bind(channel.quiet_hours)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        let preferences = read_preferences(&mut tx, identity.user_id).await?;
+
+        tx.commit().await?;
+
+        tracing::trace!(
+            user_id = identity.user_id,
+            "notification preferences updated"
+        );
+
+        Ok(preferences)
+    }
+
+    /// Reads the quiet hours that apply to an alert, if it is delivered to a user who
+    /// has quiet hours for its type of channel. The recipient is whoever is on call if
+    /// the notification routes to an on-call schedule, otherwise the user in the account
+    /// with the email address or phone number of the notification.
+    ///
+    /// [`read_quiet_hours_for_alert`] not called by APIs, so no access checks needed.
+    pub async fn read_quiet_hours_for_alert(
+        &self,
+        alert: &NotificationAlert,
+    ) -> Result<Option<QuietHours>> {
+        let mut conn = self.database.connection().await?;
+
+        let on_call_user_id = match alert.on_call_schedule_id {
+            Some(schedule_id) => {
+                match read_on_call_user_by_schedule_id(
+                    &mut conn,
+                    schedule_id,
+                    Utc::now().naive_utc(),
+                )
+                .await?
+                {
+                    Some(user) => Some(user.id),
+                    None => return Ok(None),
+                }
+            }
+            None => None,
+        };
+
+        let sql = r"
+            SELECT
+                u.timezone,
+                u.quiet_hours_start,
+                u.quiet_hours_end
+            FROM
+                users u
+                INNER JOIN
+                user_accounts ua ON ua.user_id = u.id
+                LEFT JOIN
+                user_notification_preferences p ON p.user_id = u.id AND p.notification_type = $2
+            WHERE
+                ua.account_id = $1
+                AND
+                u.deleted = false
+                AND
+                u.quiet_hours_start IS NOT NULL
+                AND
+                u.quiet_hours_end IS NOT NULL
+                AND
+                COALESCE(p.quiet_hours, true)
+                AND
+                (
+                    u.id = $3::BIGINT
+                    OR
+                    (
+                        $3::BIGINT IS NULL
+                        AND
+                        (u.email = $4 OR u.phone_number = $5)
+                    )
+                )
+            LIMIT 1
+        ";
+
+        let quiet_hours: Option<(String, NaiveTime, NaiveTime)> = sqlx::query_as(sql)
+            .bind(alert.account_id)
+            .bind(&alert.notification_type)
+            .bind(on_call_user_id)
+            .bind(&alert.email)
+            .bind(&alert.phone_number)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        Ok(quiet_hours.map(|(timezone, start, end)| {
+            let timezone = timezone.parse::<Tz>().unwrap_or_else(|_| {
+                tracing::warn!(
+                    timezone = timezone,
+                    "unknown time zone for quiet hours, using UTC"
+                );
+                chrono_tz::UTC
+            });
+            QuietHours::new(timezone, start, end)
+        }))
+    }
+}
+
+async fn read_preferences(
+    conn: &mut DbConnection,
+    user_id: i64,
+) -> Result<NotificationPreferences> {
+    let sql = r"
+            SELECT
+                timezone,
+                quiet_hours_start,
+                quiet_hours_end
+            FROM
+                users
+            WHERE
+                id = $1
+        ";
+
+    let (timezone, quiet_hours_start, quiet_hours_end): (
+        String,
+        Option<NaiveTime>,
+        Option<NaiveTime>,
+    ) = sqlx::query_as(sql)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let sql = r"
+            SELECT
+                notification_type,
+                quiet_hours
+            FROM
+                user_notification_preferences
+            WHERE
+                user_id = $1
+            ORDER BY
+                notification_type ASC
+        ";
+
+    let channels: Vec<ChannelPreference> =
+        sqlx::query_as(sql).bind(user_id).fetch_all(conn).await?;
+
+    Ok(NotificationPreferences {
+        timezone,
+        quiet_hours_start,
+        quiet_hours_end,
+        channels,
+    })
+}
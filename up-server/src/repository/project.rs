@@ -155,10 +155,12 @@ impl ProjectRepository {
         let sql = r"
             INSERT INTO user_projects (
                 user_id,
-                project_id
+                project_id,
+                role
             ) VALUES (
                 $1,
-                $2
+                $2,
+                'ADMINISTRATOR'
             )
         ";
 
@@ -202,7 +204,7 @@ impl ProjectRepository {
         let (project_id, account_id) =
             get_project_account_id(&mut tx, uuid, &identity.account_ids()).await?;
 
-        if !identity.is_administrator_in_account_with_id(account_id) {
+        if !identity.is_administrator_in_project_with_id(project_id) {
             return Err(RepositoryError::Forbidden);
         }
 
@@ -0,0 +1,474 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use handlebars::{no_escape, Handlebars};
+use miette::Diagnostic;
+use rust_embed::RustEmbed;
+use serde::Serialize;
+use thiserror::Error;
+
+pub type Result<T> = miette::Result<T, TemplateError>;
+
+pub const ALERT_SUBJECT_TEMPLATE: &str = "alert.subject.hbs";
+pub const ALERT_TEXT_TEMPLATE: &str = "alert.text.hbs";
+pub const ALERT_HTML_TEMPLATE: &str = "alert.html.hbs";
+pub const DIGEST_SUBJECT_TEMPLATE: &str = "digest.subject.hbs";
+pub const DIGEST_TEXT_TEMPLATE: &str = "digest.text.hbs";
+pub const DIGEST_HTML_TEMPLATE: &str = "digest.html.hbs";
+pub const PASSWORD_RESET_SUBJECT_TEMPLATE: &str = "password_reset.subject.hbs";
+pub const PASSWORD_RESET_TEXT_TEMPLATE: &str = "password_reset.text.hbs";
+pub const PASSWORD_RESET_HTML_TEMPLATE: &str = "password_reset.html.hbs";
+pub const MAGIC_LINK_SUBJECT_TEMPLATE: &str = "magic_link.subject.hbs";
+pub const MAGIC_LINK_TEXT_TEMPLATE: &str = "magic_link.text.hbs";
+pub const MAGIC_LINK_HTML_TEMPLATE: &str = "magic_link.html.hbs";
+pub const INVITATION_SUBJECT_TEMPLATE: &str = "invitation.subject.hbs";
+pub const INVITATION_TEXT_TEMPLATE: &str = "invitation.text.hbs";
+pub const INVITATION_HTML_TEMPLATE: &str = "invitation.html.hbs";
+
+const TEXT_TEMPLATES: &[&str] = &[
+    ALERT_SUBJECT_TEMPLATE,
+    ALERT_TEXT_TEMPLATE,
+    DIGEST_SUBJECT_TEMPLATE,
+    DIGEST_TEXT_TEMPLATE,
+    PASSWORD_RESET_SUBJECT_TEMPLATE,
+    PASSWORD_RESET_TEXT_TEMPLATE,
+    MAGIC_LINK_SUBJECT_TEMPLATE,
+    MAGIC_LINK_TEXT_TEMPLATE,
+    INVITATION_SUBJECT_TEMPLATE,
+    INVITATION_TEXT_TEMPLATE,
+];
+const HTML_TEMPLATES: &[&str] = &[
+    ALERT_HTML_TEMPLATE,
+    DIGEST_HTML_TEMPLATE,
+    PASSWORD_RESET_HTML_TEMPLATE,
+    MAGIC_LINK_HTML_TEMPLATE,
+    INVITATION_HTML_TEMPLATE,
+];
+
+/// The `RustEmbed` asset containing the default templates.
+#[derive(RustEmbed)]
+#[folder = "templates"]
+struct DefaultTemplates;
+
+/// Templates used to render notification messages. Defaults are embedded in the
+/// binary, any template with the same file name in the override directory is used instead.
+#[derive(Clone)]
+pub struct Templates {
+    text: Handlebars<'static>,
+    html: Handlebars<'static>,
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum TemplateError {
+    #[error("template {0} does not exist")]
+    #[diagnostic(code(up::config::invalid))]
+    NotFound(String),
+    #[error("failed to read template file {0}: {1}")]
+    #[diagnostic(code(up::config::invalid))]
+    ReadError(Utf8PathBuf, std::io::Error),
+    #[error("template {0} is not UTF-8")]
+    #[diagnostic(code(up::config::invalid))]
+    EncodingError(String),
+    #[error("failed to parse template: {0}")]
+    #[diagnostic(code(up::config::invalid))]
+    ParseError(Box<handlebars::TemplateError>),
+    #[error("failed to render template: {0}")]
+    #[diagnostic(code(up::error::template))]
+    RenderError(Box<handlebars::RenderError>),
+}
+
+impl From<handlebars::TemplateError> for TemplateError {
+    fn from(e: handlebars::TemplateError) -> Self {
+        TemplateError::ParseError(Box::new(e))
+    }
+}
+
+impl From<handlebars::RenderError> for TemplateError {
+    fn from(e: handlebars::RenderError) -> Self {
+        TemplateError::RenderError(Box::new(e))
+    }
+}
+
+/// A rendered multi-part email.
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+impl Templates {
+    pub fn new(override_dir: Option<&Utf8Path>) -> Result<Self> {
+        let mut text = Handlebars::new();
+        text.set_strict_mode(true);
+        text.register_escape_fn(no_escape);
+        for name in TEXT_TEMPLATES {
+            text.register_template_string(name, load_template(name, override_dir)?)?;
+        }
+
+        let mut html = Handlebars::new();
+        html.set_strict_mode(true);
+        for name in HTML_TEMPLATES {
+            html.register_template_string(name, load_template(name, override_dir)?)?;
+        }
+
+        Ok(Self { text, html })
+    }
+
+    pub fn render_email<T: Serialize>(
+        &self,
+        subject_template: &str,
+        text_template: &str,
+        html_template: &str,
+        data: &T,
+    ) -> Result<RenderedEmail> {
+        Ok(RenderedEmail {
+            subject: self.text.render(subject_template, data)?.trim().to_string(),
+            text: self.text.render(text_template, data)?,
+            html: self.html.render(html_template, data)?,
+        })
+    }
+}
+
+fn load_template(name: &str, override_dir: Option<&Utf8Path>) -> Result<String> {
+    if let Some(dir) = override_dir {
+        let path = dir.join(name);
+        if path.is_file() {
+            tracing::debug!(path = path.as_str(), "using template override");
+            return std::fs::read_to_string(&path).map_err(|e| TemplateError::ReadError(path, e));
+        }
+    }
+
+    let file =
+        DefaultTemplates::get(name).ok_or_else(|| TemplateError::NotFound(name.to_string()))?;
+    String::from_utf8(file.data.into_owned())
+        .map_err(|_| TemplateError::EncodingError(name.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn default_alert_templates_render() {
+        let templates = Templates::new(None).unwrap();
+        let data = json!({
+            "test": false,
+            "product_name": "up.io",
+            "status": "DOWN",
+            "name": "<backups>",
+            "project_name": "Infrastructure",
+            "check_name": "<backups>",
+            "last_ping_at": null,
+            "schedule": "every 1 day",
+            "grace_period": "1 hour",
+            "check_url": "http://localhost:8080/checks/2",
+        });
+
+        let email = templates
+            .render_email(
+                ALERT_SUBJECT_TEMPLATE,
+                ALERT_TEXT_TEMPLATE,
+                ALERT_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!("[DOWN] <backups>", email.subject);
+        assert!(email.text.contains("Last ping:     never"));
+        assert!(email.text.contains("Sent by up.io"));
+        assert!(email.html.contains("&lt;backups&gt;"));
+        assert!(!email.html.contains("<backups>"));
+        assert!(!email.text.contains("test notification"));
+        assert!(!email.text.contains("Acknowledge"));
+    }
+
+    #[test]
+    fn test_alerts_are_labelled() {
+        let templates = Templates::new(None).unwrap();
+        let data = json!({
+            "test": true,
+            "product_name": "up.io",
+            "status": "DOWN",
+            "name": "backups",
+            "project_name": "Infrastructure",
+            "check_name": "backups",
+            "last_ping_at": null,
+            "schedule": "every 1 day",
+            "grace_period": "1 hour",
+            "check_url": "http://localhost:8080/checks/2",
+        });
+
+        let email = templates
+            .render_email(
+                ALERT_SUBJECT_TEMPLATE,
+                ALERT_TEXT_TEMPLATE,
+                ALERT_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!("[TEST] [DOWN] backups", email.subject);
+        assert!(email
+            .text
+            .starts_with("This is a test notification, backups is not actually DOWN."));
+        assert!(email.html.contains("This is a test notification"));
+    }
+
+    #[test]
+    fn acknowledge_link_is_included() {
+        let templates = Templates::new(None).unwrap();
+        let data = json!({
+            "test": false,
+            "product_name": "up.io",
+            "status": "DOWN",
+            "name": "backups",
+            "project_name": "Infrastructure",
+            "check_name": "backups",
+            "last_ping_at": null,
+            "schedule": "every 1 day",
+            "grace_period": "1 hour",
+            "check_url": "http://localhost:8080/checks/2",
+            "acknowledge_url": "http://localhost:8080/api/v1/acknowledge/token",
+        });
+
+        let email = templates
+            .render_email(
+                ALERT_SUBJECT_TEMPLATE,
+                ALERT_TEXT_TEMPLATE,
+                ALERT_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert!(email
+            .text
+            .contains("Acknowledge:    http://localhost:8080/api/v1/acknowledge/token"));
+        assert!(email.html.contains(
+            "<a href=\"http://localhost:8080/api/v1/acknowledge/token\">Acknowledge</a>"
+        ));
+    }
+
+    #[test]
+    fn default_digest_templates_render() {
+        let templates = Templates::new(None).unwrap();
+        let alert = |name: &str| {
+            json!({
+                "status": "DOWN",
+                "project_name": "Infrastructure",
+                "check_name": name,
+                "last_ping_at": null,
+                "schedule": "every 1 day",
+                "grace_period": "1 hour",
+                "check_url": "http://localhost:8080/checks/2",
+            })
+        };
+        let data = json!({
+            "product_name": "up.io",
+            "status": "DOWN",
+            "count": 2,
+            "alerts": [alert("backups"), alert("<billing>")],
+        });
+
+        let email = templates
+            .render_email(
+                DIGEST_SUBJECT_TEMPLATE,
+                DIGEST_TEXT_TEMPLATE,
+                DIGEST_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!("[DOWN] 2 checks are DOWN", email.subject);
+        assert!(email.text.contains("backups (Infrastructure) is DOWN"));
+        assert!(email.text.contains("<billing> (Infrastructure) is DOWN"));
+        assert!(email.html.contains("&lt;billing&gt;"));
+        assert!(!email.text.contains("quiet hours"));
+    }
+
+    #[test]
+    fn mixed_digest_shows_status_of_each_alert() {
+        let templates = Templates::new(None).unwrap();
+        let alert = |name: &str, status: &str| {
+            json!({
+                "status": status,
+                "project_name": "Infrastructure",
+                "check_name": name,
+                "last_ping_at": null,
+                "schedule": "every 1 day",
+                "grace_period": "1 hour",
+                "check_url": "http://localhost:8080/checks/2",
+            })
+        };
+        let data = json!({
+            "product_name": "up.io",
+            "status": "DOWN",
+            "mixed": true,
+            "count": 2,
+            "alerts": [alert("backups", "UP"), alert("billing", "DOWN")],
+        });
+
+        let email = templates
+            .render_email(
+                DIGEST_SUBJECT_TEMPLATE,
+                DIGEST_TEXT_TEMPLATE,
+                DIGEST_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!("[DOWN] 2 checks changed status", email.subject);
+        assert!(email.text.contains("backups (Infrastructure) is UP"));
+        assert!(email.text.contains("billing (Infrastructure) is DOWN"));
+        assert!(email.html.contains("<td>UP</td>"));
+    }
+
+    #[test]
+    fn held_digest_is_labelled() {
+        let templates = Templates::new(None).unwrap();
+        let data = json!({
+            "product_name": "up.io",
+            "status": "DOWN",
+            "held": true,
+            "count": 1,
+            "alerts": [{
+                "status": "DOWN",
+                "project_name": "Infrastructure",
+                "check_name": "backups",
+                "last_ping_at": null,
+                "schedule": "every 1 day",
+                "grace_period": "1 hour",
+                "check_url": "http://localhost:8080/checks/2",
+            }],
+        });
+
+        let email = templates
+            .render_email(
+                DIGEST_SUBJECT_TEMPLATE,
+                DIGEST_TEXT_TEMPLATE,
+                DIGEST_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert!(email.text.starts_with(
+            "1 checks are DOWN\n\nThese alerts were held during your quiet hours.\n\nbackups"
+        ));
+        assert!(email.html.contains("held during your quiet hours"));
+    }
+
+    #[test]
+    fn password_reset_templates_render() {
+        let templates = Templates::new(None).unwrap();
+        let data = json!({
+            "product_name": "up.io",
+            "reset_url": "http://localhost:8080/reset-password?token=abc&x",
+            "expiry_minutes": 60,
+        });
+
+        let email = templates
+            .render_email(
+                PASSWORD_RESET_SUBJECT_TEMPLATE,
+                PASSWORD_RESET_TEXT_TEMPLATE,
+                PASSWORD_RESET_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!("Reset your up.io password", email.subject);
+        assert!(email
+            .text
+            .contains("http://localhost:8080/reset-password?token=abc&x"));
+        assert!(email.text.contains("within 60 minutes"));
+        assert!(email.html.contains("abc&amp;x"));
+    }
+
+    #[test]
+    fn magic_link_templates_render() {
+        let templates = Templates::new(None).unwrap();
+        let data = json!({
+            "product_name": "up.io",
+            "login_url": "http://localhost:8080/magic-link?token=abc&x",
+            "expiry_minutes": 15,
+        });
+
+        let email = templates
+            .render_email(
+                MAGIC_LINK_SUBJECT_TEMPLATE,
+                MAGIC_LINK_TEXT_TEMPLATE,
+                MAGIC_LINK_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!("Log in to up.io", email.subject);
+        assert!(email
+            .text
+            .contains("http://localhost:8080/magic-link?token=abc&x"));
+        assert!(email.text.contains("within 15 minutes"));
+        assert!(email.html.contains("abc&amp;x"));
+    }
+
+    #[test]
+    fn invitation_templates_render() {
+        let templates = Templates::new(None).unwrap();
+        let data = json!({
+            "product_name": "up.io",
+            "account_name": "Acme & Co",
+            "inviter_email": "admin@example.com",
+            "role": "member",
+            "accept_url": "http://localhost:8080/accept-invite?token=abc&x",
+            "expiry_days": 7,
+        });
+
+        let email = templates
+            .render_email(
+                INVITATION_SUBJECT_TEMPLATE,
+                INVITATION_TEXT_TEMPLATE,
+                INVITATION_HTML_TEMPLATE,
+                &data,
+            )
+            .unwrap();
+
+        assert_eq!("Join Acme & Co on up.io", email.subject);
+        assert!(email
+            .text
+            .contains("http://localhost:8080/accept-invite?token=abc&x"));
+        assert!(email.text.contains("within 7 days"));
+        assert!(email.html.contains("Acme &amp; Co"));
+    }
+
+    #[test]
+    fn override_template_is_preferred() {
+        let dir = std::env::temp_dir().join(format!("up-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(ALERT_SUBJECT_TEMPLATE), "Alert: {{name}}").unwrap();
+        let dir = Utf8PathBuf::from_path_buf(dir).unwrap();
+
+        let templates = Templates::new(Some(&dir)).unwrap();
+        let email = templates
+            .render_email(
+                ALERT_SUBJECT_TEMPLATE,
+                ALERT_TEXT_TEMPLATE,
+                ALERT_HTML_TEMPLATE,
+                &json!({
+                        "test": false,
+                        "product_name": "up.io",
+                "status": "DOWN",
+                        "name": "backups",
+                        "project_name": "Infrastructure",
+                        "check_name": "backups",
+                        "last_ping_at": "2022-08-01 00:00:00 UTC",
+                        "schedule": "every 1 day",
+                        "grace_period": "1 hour",
+                        "check_url": "http://localhost:8080/checks/2",
+                    }),
+            )
+            .unwrap();
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!("Alert: backups", email.subject);
+        assert!(email
+            .text
+            .contains("Last ping:     2022-08-01 00:00:00 UTC"));
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+use crate::keys::KeyStore;
+
+/// Length of secret tokens, 43 alphanumeric characters are over 256 bits.
+const SECRET_LENGTH: usize = 43;
+
+/// Issues the JWTs users log in with, signed with the server key so that the server
+/// verifies them against its JWKS like JWTs generated with `upcli generate jwt`. They
+/// are short-lived, users stay logged in by exchanging refresh tokens for new ones.
+#[derive(Clone)]
+pub struct TokenIssuer {
+    keys: KeyStore,
+    expiry_minutes: i64,
+}
+
+/// A JWT issued to a user.
+pub struct IssuedToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl TokenIssuer {
+    /// Creates an issuer signing with the current signing key of `keys`. Without a key,
+    /// no JWTs are issued and users can't log in.
+    pub fn new(keys: KeyStore, expiry_minutes: i64) -> Self {
+        Self {
+            keys,
+            expiry_minutes,
+        }
+    }
+
+    /// Issues a JWT for a subject, or `None` if there is no key to sign it with.
+    pub fn issue(&self, subject: &str) -> Result<Option<IssuedToken>, up_core::Error> {
+        let generator = match self.keys.token_generator() {
+            Some(generator) => generator,
+            None => return Ok(None),
+        };
+
+        let expiry = Duration::minutes(self.expiry_minutes);
+        let expires_at = Utc::now() + expiry;
+        let token = generator.generate_with_expiry(subject, expiry, None)?;
+
+        Ok(Some(IssuedToken { token, expires_at }))
+    }
+}
+
+/// Generates a random secret token, e.g. for a password reset link. Tokens are
+/// alphanumeric so they can be used in URLs as is.
+pub fn generate_secret() -> String {
+    rand::rngs::OsRng
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Hashes a secret token for storage. Secrets are random rather than chosen by users,
+/// so a fast hash is enough and lets them be looked up by hash.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+    use openssl::rsa::Rsa;
+    use up_core::{
+        jwks::Jwks,
+        jwt::{self, DEFAULT_AUDIENCE, DEFAULT_ISSUER},
+    };
+
+    use super::*;
+
+    fn generate_pem() -> Vec<u8> {
+        let rsa = Rsa::generate(2048).unwrap();
+        let mut pem = rsa.private_key_to_pem().unwrap();
+        pem.extend(rsa.public_key_to_pem().unwrap());
+        pem
+    }
+
+    #[test]
+    fn issued_token_verifies() {
+        let pem = generate_pem();
+        let jwks = Jwks::from_pem(&pem).unwrap().to_string();
+        let verifier =
+            jwt::Verifier::new_from_jwks(&jwks, Some(DEFAULT_ISSUER), Some(DEFAULT_AUDIENCE))
+                .unwrap();
+        let issuer = TokenIssuer::new(KeyStore::from_jwks(&jwks, Some(&pem)).unwrap(), 15);
+
+        let issued = issuer.issue("subject").unwrap().unwrap();
+        let claims = verifier.verify(&issued.token).unwrap();
+        assert_eq!(Some("subject".to_string()), claims.subject);
+        assert!(issued.expires_at > Utc::now());
+    }
+
+    #[test]
+    fn secrets_are_random_and_hashed() {
+        let secret = generate_secret();
+
+        assert_eq!(SECRET_LENGTH, secret.len());
+        assert_ne!(secret, generate_secret());
+        assert_eq!(hash_secret(&secret), hash_secret(&secret));
+        assert_eq!(64, hash_secret(&secret).len());
+    }
+
+    #[test]
+    fn no_tokens_without_key() {
+        let jwks = Jwks::from_pem(&generate_pem()).unwrap().to_string();
+        let issuer = TokenIssuer::new(KeyStore::from_jwks(&jwks, None).unwrap(), 15);
+
+        assert!(issuer.issue("subject").unwrap().is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use up_core::auth::Role;
+use up_server::api::{
+    v1::{
+        accounts::{Account, Signup, UpdateAccount},
+        auth::Token,
+        projects::Project,
+    },
+    GenericResponse,
+};
+
+use crate::{assert_status, TestApp, TestClient, TestUser};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+async fn signup(client: &TestClient, email: &str) -> Result<Token, crate::TestError> {
+    let request = Signup {
+        email: email.to_string(),
+        password: PASSWORD.to_string(),
+        account_name: "Acme".to_string(),
+    };
+    client.post("/api/v1/auth/signup", request).await
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn signup_creates_user_account_and_project() {
+    let app = TestApp::start_with_args(|args| args.allow_signup = true).await;
+    let anonymous = app.connect(TestUser::Anonymous).await.unwrap();
+
+    let token = signup(&anonymous, "founder@example.com")
+        .await
+        .expect("failed to sign up");
+    let client = app.connect_with_token(token.token);
+
+    let accounts: Vec<Account> = client
+        .get("/api/v1/accounts")
+        .await
+        .expect("failed to read accounts");
+    assert_eq!(1, accounts.len());
+    assert_eq!("Acme", accounts[0].name);
+    assert_eq!(Role::Administrator, accounts[0].role);
+
+    let projects: Vec<Project> = client
+        .get("/api/v1/projects")
+        .await
+        .expect("failed to read projects");
+    assert_eq!(1, projects.len());
+    assert_eq!("Default", projects[0].name);
+
+    assert_status(400, signup(&anonymous, "Founder@Example.com").await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn signup_is_disabled_by_default() {
+    let (_app, anonymous) = TestApp::start_and_connect(TestUser::Anonymous).await;
+
+    assert_status(403, signup(&anonymous, "founder@example.com").await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn administrator_can_rename_and_delete_account() {
+    let app = TestApp::start_with_args(|args| args.allow_signup = true).await;
+    let anonymous = app.connect(TestUser::Anonymous).await.unwrap();
+    let token = signup(&anonymous, "founder@example.com")
+        .await
+        .expect("failed to sign up");
+    let client = app.connect_with_token(token.token);
+    let accounts: Vec<Account> = client
+        .get("/api/v1/accounts")
+        .await
+        .expect("failed to read accounts");
+    let account_uri = format!("/api/v1/accounts/{}", accounts[0].id);
+
+    let request = UpdateAccount {
+        name: Some("Acme Corp".to_string()),
+    };
+    let account: Account = client
+        .patch(&account_uri, request)
+        .await
+        .expect("failed to rename account");
+    assert_eq!("Acme Corp", account.name);
+    assert!(account.updated_at.is_some());
+
+    client
+        .delete::<GenericResponse>(&account_uri)
+        .await
+        .expect("failed to delete account");
+    let accounts: Vec<Account> = client
+        .get("/api/v1/accounts")
+        .await
+        .expect("failed to read accounts");
+    assert!(accounts.is_empty());
+    let projects: Vec<Project> = client
+        .get("/api/v1/projects")
+        .await
+        .expect("failed to read projects");
+    assert!(projects.is_empty());
+
+    assert_status(404, client.delete::<GenericResponse>(&account_uri).await);
+}
@@ -0,0 +1,219 @@
+use serde_json::json;
+use up_core::auth::Scope;
+use up_server::{
+    api::{
+        v1::{
+            api_keys::{ApiKey, CreateApiKey, CreatedApiKey},
+            auth::{Login, Token},
+            checks::{Check, CreateCheck},
+            projects::Project,
+        },
+        GenericResponse,
+    },
+    shortid::ShortId,
+};
+
+use crate::{assert_status, TestApp, TestClient, TestUser};
+
+const EMAIL: &str = "automation@example.com";
+const PASSWORD: &str = "correct horse battery staple";
+
+/// A member of an account with two projects, logged in with a JWT.
+struct Member {
+    account_id: ShortId,
+    project_ids: [ShortId; 2],
+    client: TestClient,
+}
+
+async fn member(app: &TestApp) -> Member {
+    let user_id = app.create_user_with_password(EMAIL, PASSWORD).await;
+    let account_id = ShortId::new();
+    app.create_account(&account_id, "automation", user_id).await;
+    app.add_user_to_account(user_id, &account_id, "MEMBER")
+        .await;
+    let project_ids = [
+        app.create_project(&account_id, "first", user_id).await,
+        app.create_project(&account_id, "second", user_id).await,
+    ];
+
+    let request = Login {
+        email: EMAIL.to_string(),
+        password: PASSWORD.to_string(),
+    };
+    let token: Token = app
+        .connect(TestUser::Anonymous)
+        .await
+        .unwrap()
+        .post("/api/v1/auth/login", request)
+        .await
+        .expect("failed to log in");
+
+    Member {
+        account_id,
+        project_ids,
+        client: app.connect_with_token(token.token),
+    }
+}
+
+async fn create_key(
+    client: &TestClient,
+    scopes: Vec<Scope>,
+    project_id: Option<ShortId>,
+) -> CreatedApiKey {
+    let request = CreateApiKey {
+        name: Some("ci".to_string()),
+        scopes,
+        project_id,
+        expires_at: None,
+    };
+    client
+        .post("/api/v1/api-keys", request)
+        .await
+        .expect("failed to create API key")
+}
+
+fn create_check_request(member: &Member) -> CreateCheck {
+    CreateCheck {
+        account_id: member.account_id,
+        project_id: member.project_ids[0],
+        name: "backups".to_string(),
+        severity: None,
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn api_key_acts_as_user_within_scopes() {
+    let app = TestApp::start().await;
+    let member = member(&app).await;
+    let checks_path = format!("/api/v1/projects/{}/checks", member.project_ids[0]);
+
+    let read_only = create_key(&member.client, vec![Scope::ReadOnly], None).await;
+    assert!(read_only.key.starts_with(&read_only.api_key.prefix));
+    let client = app.connect_with_token(read_only.key);
+    let identity: serde_json::Value = client
+        .get("/api/v1/identity")
+        .await
+        .expect("failed to read identity with API key");
+    assert_eq!(EMAIL, identity["email"]);
+    assert_eq!(json!(["READ_ONLY"]), identity["api_key"]["scopes"]);
+    let result = client
+        .post::<CreateCheck, Check>(&checks_path, create_check_request(&member))
+        .await;
+    assert_status(403, result);
+
+    let manage_checks = create_key(&member.client, vec![Scope::ManageChecks], None).await;
+    let client = app.connect_with_token(manage_checks.key);
+    client
+        .post::<CreateCheck, Check>(&checks_path, create_check_request(&member))
+        .await
+        .expect("failed to create check with API key");
+    let checks: Vec<Check> = client
+        .get(&checks_path)
+        .await
+        .expect("failed to read checks with API key");
+    assert_eq!(1, checks.len());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn api_key_is_restricted_to_project() {
+    let app = TestApp::start().await;
+    let member = member(&app).await;
+
+    let created = create_key(
+        &member.client,
+        vec![Scope::ReadOnly],
+        Some(member.project_ids[1]),
+    )
+    .await;
+    let client = app.connect_with_token(created.key);
+
+    let projects: Vec<Project> = client
+        .get("/api/v1/projects")
+        .await
+        .expect("failed to read projects with API key");
+    assert_eq!(1, projects.len());
+    assert_eq!(
+        member.project_ids[1].to_string(),
+        projects[0].id.to_string()
+    );
+    let result = client
+        .get::<Vec<Check>>(&format!(
+            "/api/v1/projects/{}/checks",
+            member.project_ids[0]
+        ))
+        .await;
+    assert_status(404, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn revoked_api_key_is_rejected() {
+    let app = TestApp::start().await;
+    let member = member(&app).await;
+
+    let created = create_key(&member.client, vec![Scope::ReadOnly], None).await;
+    let client = app.connect_with_token(created.key);
+    client
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await
+        .expect("failed to read identity with API key");
+
+    let keys: Vec<ApiKey> = member
+        .client
+        .get("/api/v1/api-keys")
+        .await
+        .expect("failed to read API keys");
+    assert_eq!(1, keys.len());
+    assert!(keys[0].last_used_at.is_some());
+
+    let response: GenericResponse = member
+        .client
+        .delete(&format!("/api/v1/api-keys/{}", created.api_key.id))
+        .await
+        .expect("failed to revoke API key");
+    assert_eq!("revoked", response.message.unwrap());
+    let result = client.get::<serde_json::Value>("/api/v1/identity").await;
+    assert_status(401, result);
+    let keys: Vec<ApiKey> = member
+        .client
+        .get("/api/v1/api-keys")
+        .await
+        .expect("failed to read API keys");
+    assert!(keys.is_empty());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn api_key_cant_create_api_keys() {
+    let app = TestApp::start().await;
+    let member = member(&app).await;
+
+    let created = create_key(
+        &member.client,
+        vec![Scope::ManageChecks, Scope::ManageNotifications],
+        None,
+    )
+    .await;
+    let client = app.connect_with_token(created.key);
+
+    let request = CreateApiKey {
+        name: None,
+        scopes: vec![Scope::ManageChecks],
+        project_id: None,
+        expires_at: None,
+    };
+    let result = client
+        .post::<CreateApiKey, CreatedApiKey>("/api/v1/api-keys", request)
+        .await;
+    assert_status(403, result);
+
+    let request = CreateApiKey {
+        name: None,
+        scopes: vec![Scope::ReadOnly],
+        project_id: None,
+        expires_at: Some(chrono::Utc::now() - chrono::Duration::hours(1)),
+    };
+    let result = member
+        .client
+        .post::<CreateApiKey, CreatedApiKey>("/api/v1/api-keys", request)
+        .await;
+    assert_status(400, result);
+}
@@ -0,0 +1,293 @@
+use up_server::api::{
+    v1::auth::{MagicLinkCallback, Refresh, ResetPassword, SetPassword, Token},
+    GenericResponse,
+};
+
+use super::PASSWORD;
+use crate::{assert_status, TestApp, TestClient, TestResult, TestUser};
+
+async fn login(client: &TestClient, password: &str) -> TestResult<Token> {
+    super::login(client, "login@example.com", password).await
+}
+
+async fn refresh(client: &TestClient, refresh_token: &str) -> TestResult<Token> {
+    let request = Refresh {
+        refresh_token: refresh_token.to_string(),
+    };
+    client.post("/api/v1/auth/refresh", request).await
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn user_can_log_in_with_password() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    app.create_user_with_password("Login@Example.com", PASSWORD)
+        .await;
+
+    let token = login(&client, PASSWORD).await.expect("failed to log in");
+
+    let identity: serde_json::Value = app
+        .connect_with_token(token.token)
+        .get("/api/v1/identity")
+        .await
+        .expect("failed to read identity with issued JWT");
+    assert_eq!("Login@Example.com", identity["email"]);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn user_cant_log_in_with_wrong_password() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+
+    assert_status(401, login(&client, "incorrect horse battery staple").await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn user_is_locked_out_after_failed_logins() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+
+    for _ in 0..5 {
+        assert_status(401, login(&client, "incorrect horse battery staple").await);
+    }
+
+    // Locked out users are rejected like unknown ones, even with the right password.
+    assert_status(401, login(&client, PASSWORD).await);
+    let result = super::login(&client, "unknown@example.com", PASSWORD).await;
+    assert_status(401, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn user_can_change_password() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let token = login(&client, PASSWORD).await.expect("failed to log in");
+    let user_client = app.connect_with_token(token.token);
+
+    let request = SetPassword {
+        current_password: Some("incorrect horse battery staple".to_string()),
+        new_password: "battery staple correct horse".to_string(),
+    };
+    let result = user_client
+        .put::<SetPassword, GenericResponse>("/api/v1/auth/password", request)
+        .await;
+    assert_status(400, result);
+
+    let request = SetPassword {
+        current_password: Some(PASSWORD.to_string()),
+        new_password: "battery staple correct horse".to_string(),
+    };
+    user_client
+        .put::<SetPassword, GenericResponse>("/api/v1/auth/password", request)
+        .await
+        .expect("failed to change password");
+
+    let result = user_client
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await;
+    assert_status(401, result);
+    assert_status(401, refresh(&client, &token.refresh_token).await);
+    assert_status(401, login(&client, PASSWORD).await);
+    login(&client, "battery staple correct horse")
+        .await
+        .expect("failed to log in with new password");
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn user_can_reset_password_once() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    let user_id = app
+        .create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let session = login(&client, PASSWORD).await.expect("failed to log in");
+    let token = app.create_password_reset_token(user_id).await;
+    let other_token = app.create_password_reset_token(user_id).await;
+
+    let request = ResetPassword {
+        token: token.clone(),
+        new_password: "short".to_string(),
+    };
+    let result = client
+        .post::<ResetPassword, GenericResponse>("/api/v1/auth/password-reset/confirm", request)
+        .await;
+    assert_status(400, result);
+
+    let request = ResetPassword {
+        token: token.clone(),
+        new_password: "battery staple correct horse".to_string(),
+    };
+    client
+        .post::<ResetPassword, GenericResponse>("/api/v1/auth/password-reset/confirm", request)
+        .await
+        .expect("failed to reset password");
+    let result = app
+        .connect_with_token(session.token)
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await;
+    assert_status(401, result);
+    assert_status(401, refresh(&client, &session.refresh_token).await);
+    login(&client, "battery staple correct horse")
+        .await
+        .expect("failed to log in with new password");
+
+    // Neither the used token nor any other token sent before it can be used again.
+    for token in [token, other_token] {
+        let request = ResetPassword {
+            token,
+            new_password: "staple correct horse battery".to_string(),
+        };
+        let result = client
+            .post::<ResetPassword, GenericResponse>("/api/v1/auth/password-reset/confirm", request)
+            .await;
+        assert_status(400, result);
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn password_resets_are_rate_limited() {
+    let app = TestApp::start().await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let repository = app.repository();
+
+    for _ in 0..5 {
+        let reset = repository
+            .auth()
+            .create_password_reset("Login@Example.com")
+            .await
+            .expect("failed to create password reset");
+        assert!(reset.is_some());
+    }
+    let reset = repository
+        .auth()
+        .create_password_reset("login@example.com")
+        .await
+        .expect("failed to create password reset");
+    assert!(reset.is_none());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn user_can_log_in_with_magic_link_once() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    let user_id = app
+        .create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let token = app.create_magic_link_token(user_id).await;
+
+    let request = MagicLinkCallback {
+        token: token.clone(),
+    };
+    let issued: Token = client
+        .post("/api/v1/auth/magic-link/callback", request)
+        .await
+        .expect("failed to log in with magic link");
+    let identity: serde_json::Value = app
+        .connect_with_token(issued.token)
+        .get("/api/v1/identity")
+        .await
+        .expect("failed to read identity with issued JWT");
+    assert_eq!("login@example.com", identity["email"]);
+
+    let request = MagicLinkCallback { token };
+    let result = client
+        .post::<MagicLinkCallback, Token>("/api/v1/auth/magic-link/callback", request)
+        .await;
+    assert_status(400, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn magic_links_are_rate_limited() {
+    let app = TestApp::start().await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let repository = app.repository();
+
+    for _ in 0..5 {
+        let link = repository
+            .auth()
+            .create_magic_link("Login@Example.com")
+            .await
+            .expect("failed to create magic link");
+        assert!(link.is_some());
+    }
+    let link = repository
+        .auth()
+        .create_magic_link("login@example.com")
+        .await
+        .expect("failed to create magic link");
+    assert!(link.is_none());
+    let link = repository
+        .auth()
+        .create_magic_link("nobody@example.com")
+        .await
+        .expect("failed to create magic link");
+    assert!(link.is_none());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn refresh_token_rotates_and_reuse_revokes_it() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let first = login(&client, PASSWORD).await.expect("failed to log in");
+
+    let second = refresh(&client, &first.refresh_token)
+        .await
+        .expect("failed to refresh token");
+    assert_ne!(first.refresh_token, second.refresh_token);
+    app.connect_with_token(second.token)
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await
+        .expect("failed to read identity with refreshed JWT");
+
+    assert_status(401, refresh(&client, &first.refresh_token).await);
+    assert_status(401, refresh(&client, &second.refresh_token).await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn logout_revokes_refresh_token() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let token = login(&client, PASSWORD).await.expect("failed to log in");
+
+    let request = Refresh {
+        refresh_token: token.refresh_token.clone(),
+    };
+    let response: GenericResponse = client
+        .post("/api/v1/auth/logout", request)
+        .await
+        .expect("failed to log out");
+    assert_eq!("logged out", response.message.unwrap());
+
+    assert_status(401, refresh(&client, &token.refresh_token).await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn rotating_subject_invalidates_tokens() {
+    let (app, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+    app.create_user_with_password("login@example.com", PASSWORD)
+        .await;
+    let token = login(&client, PASSWORD).await.expect("failed to log in");
+    let user_client = app.connect_with_token(token.token);
+
+    user_client
+        .post::<(), GenericResponse>("/api/v1/auth/rotate-subject", ())
+        .await
+        .expect("failed to rotate subject");
+
+    let result = user_client
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await;
+    assert_status(401, result);
+    assert_status(401, refresh(&client, &token.refresh_token).await);
+    let token = login(&client, PASSWORD)
+        .await
+        .expect("failed to log in again");
+    app.connect_with_token(token.token)
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await
+        .expect("failed to read identity after logging in again");
+}
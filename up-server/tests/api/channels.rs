@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use reqwest::Method;
+use up_server::{
+    api::v1::notifications::{
+        CreateNotification, Notification, NotificationType, UpdateNotification,
+    },
+    shortid::ShortId,
+};
+use uuid::Uuid;
+
+use super::{notifications::notification, project, Project};
+use crate::{assert_status, TestApp};
+
+async fn create_channel(project: &Project, request: CreateNotification) -> ShortId {
+    let channel: Notification = project
+        .member
+        .post(
+            &format!("/api/v1/projects/{}/channels", project.id),
+            request,
+        )
+        .await
+        .expect("failed to create channel");
+    channel.id
+}
+
+fn email_channel() -> CreateNotification {
+    CreateNotification {
+        email: Some("ops@example.com".to_string()),
+        ..notification(NotificationType::Email)
+    }
+}
+
+fn channel_uri(project: &Project, check_id: ShortId, channel_id: ShortId) -> String {
+    format!(
+        "/api/v1/projects/{}/checks/{}/channels/{}",
+        project.id, check_id, channel_id
+    )
+}
+
+async fn channels_for_check(project: &Project, check_id: ShortId) -> Vec<ShortId> {
+    let channels: Vec<Notification> = project
+        .member
+        .get(&format!(
+            "/api/v1/projects/{}/checks/{}/channels",
+            project.id, check_id
+        ))
+        .await
+        .expect("failed to read channels");
+    channels.into_iter().map(|c| c.id).collect()
+}
+
+/// The notifications and checks of all alerts enqueued so far.
+async fn alerts(app: &TestApp) -> HashSet<(Uuid, Uuid)> {
+    let alerts: Vec<(Uuid, Uuid)> = sqlx::query_as(
+        "SELECT n.uuid, c.uuid FROM notification_alerts a INNER JOIN notifications n ON n.id = a.notification_id INNER JOIN checks c ON c.id = a.check_id",
+    )
+    .fetch_all(&mut app.database.connection().await.unwrap())
+    .await
+    .expect("failed to read alerts");
+    alerts.into_iter().collect()
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn channel_without_destination_is_rejected() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let path = format!("/api/v1/projects/{}/channels", project.id);
+
+    let result = project
+        .member
+        .post::<_, Notification>(&path, notification(NotificationType::Email))
+        .await;
+    assert_status(400, result);
+
+    let request = CreateNotification {
+        phone_number: Some("+15551234567".to_string()),
+        ..notification(NotificationType::Webhook)
+    };
+    let result = project.member.post::<_, Notification>(&path, request).await;
+    assert_status(400, result);
+
+    let channel_id = create_channel(&project, email_channel()).await;
+    let request = UpdateNotification {
+        name: None,
+        notification_type: None,
+        email: Some("".to_string()),
+        url: None,
+        phone_number: None,
+        max_retries: None,
+        retry_backoff_seconds: None,
+        retry_backoff_max_seconds: None,
+        digest_window_seconds: None,
+    };
+    let result = project
+        .member
+        .patch::<_, Notification>(&format!("{}/{}", path, channel_id), request)
+        .await;
+    assert_status(400, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn channel_can_be_attached_and_detached() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let channel_id = create_channel(&project, email_channel()).await;
+    let uri = channel_uri(&project, project.check_id, channel_id);
+
+    assert!(channels_for_check(&project, project.check_id)
+        .await
+        .is_empty());
+
+    project
+        .member
+        .send(Method::PUT, &uri)
+        .await
+        .expect("failed to attach channel");
+    project
+        .member
+        .send(Method::PUT, &uri)
+        .await
+        .expect("failed to attach channel again");
+    assert_eq!(
+        vec![channel_id],
+        channels_for_check(&project, project.check_id).await
+    );
+
+    project
+        .member
+        .send(Method::DELETE, &uri)
+        .await
+        .expect("failed to detach channel");
+    assert!(channels_for_check(&project, project.check_id)
+        .await
+        .is_empty());
+
+    let result = project
+        .member
+        .send(
+            Method::PUT,
+            &channel_uri(&project, project.check_id, ShortId::new()),
+        )
+        .await;
+    assert_status(404, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn alerts_fan_out_to_attached_channels() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let other_check_id = project.create_check("hourly").await;
+
+    let check_notification: Notification = project
+        .member
+        .post(
+            &format!(
+                "/api/v1/projects/{}/checks/{}/notifications",
+                project.id, project.check_id
+            ),
+            email_channel(),
+        )
+        .await
+        .expect("failed to create notification");
+    let email_id = create_channel(&project, email_channel()).await;
+    let webhook_id = create_channel(
+        &project,
+        CreateNotification {
+            url: Some("https://hooks.example.com/up".to_string()),
+            ..notification(NotificationType::Webhook)
+        },
+    )
+    .await;
+    let unattached_id = create_channel(&project, email_channel()).await;
+
+    for channel_id in [email_id, webhook_id] {
+        project
+            .member
+            .send(
+                Method::PUT,
+                &channel_uri(&project, project.check_id, channel_id),
+            )
+            .await
+            .expect("failed to attach channel");
+    }
+    let uri = channel_uri(&project, other_check_id, email_id);
+    project
+        .member
+        .send(Method::PUT, &uri)
+        .await
+        .expect("failed to attach channel");
+    project
+        .member
+        .send(Method::DELETE, &uri)
+        .await
+        .expect("failed to detach channel");
+
+    app.make_checks_overdue(&[project.check_id, other_check_id])
+        .await;
+    app.repository()
+        .check()
+        .enqueue_alerts_for_overdue_pings()
+        .await
+        .expect("failed to enqueue alerts");
+
+    let expected: HashSet<_> = [check_notification.id, email_id, webhook_id]
+        .into_iter()
+        .map(|id| (id.into_uuid(), project.check_id.into_uuid()))
+        .collect();
+    let alerts = alerts(&app).await;
+    assert_eq!(expected, alerts);
+    assert!(!alerts.iter().any(|(id, _)| id == unattached_id.as_uuid()));
+}
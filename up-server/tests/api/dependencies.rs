@@ -0,0 +1,92 @@
+use reqwest::Method;
+use up_server::{api::v1::checks::Check, shortid::ShortId};
+
+use super::{alert_overdue_checks, checks_with_notifications, project, uuids, Project};
+use crate::{assert_status, TestApp, TestResult};
+
+fn dependency_uri(project: &Project, check_id: ShortId, parent_check_id: ShortId) -> String {
+    format!(
+        "/api/v1/projects/{}/checks/{}/dependencies/{}",
+        project.id, check_id, parent_check_id
+    )
+}
+
+async fn add_dependency(
+    project: &Project,
+    check_id: ShortId,
+    parent_check_id: ShortId,
+) -> TestResult<()> {
+    project
+        .member
+        .send(
+            Method::PUT,
+            &dependency_uri(project, check_id, parent_check_id),
+        )
+        .await
+}
+
+async fn dependencies(project: &Project, check_id: ShortId) -> Vec<ShortId> {
+    let checks: Vec<Check> = project
+        .member
+        .get(&format!(
+            "/api/v1/projects/{}/checks/{}/dependencies",
+            project.id, check_id
+        ))
+        .await
+        .expect("failed to read dependencies");
+    checks.into_iter().map(|c| c.id).collect()
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn dependency_cycles_are_rejected() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let api = project.check_id;
+    let database = project.create_check("database").await;
+    let network = project.create_check("network").await;
+
+    assert_status(400, add_dependency(&project, api, api).await);
+
+    add_dependency(&project, api, database)
+        .await
+        .expect("failed to add dependency");
+    assert_status(400, add_dependency(&project, database, api).await);
+
+    add_dependency(&project, database, network)
+        .await
+        .expect("failed to add dependency");
+    assert_status(400, add_dependency(&project, network, api).await);
+
+    assert_eq!(vec![database], dependencies(&project, api).await);
+    assert_eq!(vec![network], dependencies(&project, database).await);
+    assert!(dependencies(&project, network).await.is_empty());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn alerts_are_suppressed_while_parent_is_down() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let check_ids = checks_with_notifications(&project, &["api", "database"]).await;
+    let (api, database) = (check_ids[0], check_ids[1]);
+    add_dependency(&project, api, database)
+        .await
+        .expect("failed to add dependency");
+
+    // Both checks become overdue in the same sweep, only the parent is alerted for.
+    assert_eq!(
+        uuids(&[database]),
+        alert_overdue_checks(&app, &check_ids).await
+    );
+
+    // The parent is still down when the child is overdue again.
+    let alerted = alert_overdue_checks(&app, &[api]).await;
+    assert!(!alerted.contains(api.as_uuid()));
+
+    project
+        .member
+        .send(Method::DELETE, &dependency_uri(&project, api, database))
+        .await
+        .expect("failed to remove dependency");
+    let alerted = alert_overdue_checks(&app, &[api]).await;
+    assert!(alerted.contains(api.as_uuid()));
+}
@@ -0,0 +1,187 @@
+use reqwest::Method;
+use up_server::{
+    api::v1::{
+        escalation_policies::{CreateEscalationPolicy, EscalationPolicy, EscalationTier},
+        notifications::{CreateNotification, Notification, NotificationType},
+    },
+    shortid::ShortId,
+};
+use uuid::Uuid;
+
+use super::{notifications::notification, project, Project};
+use crate::TestApp;
+
+const ESCALATE_AFTER_MINUTES: i32 = 5;
+
+/// A project whose check escalates through a policy with a tier for each of
+/// `notification_ids`.
+struct Escalation {
+    project: Project,
+    notification_ids: Vec<ShortId>,
+}
+
+async fn escalation(app: &TestApp, tiers: usize) -> Escalation {
+    let project = project(app).await;
+
+    let mut notification_ids = Vec::new();
+    for tier in 0..tiers {
+        let request = CreateNotification {
+            email: Some(format!("tier{}@example.com", tier)),
+            ..notification(NotificationType::Email)
+        };
+        let channel: Notification = project
+            .member
+            .post(
+                &format!("/api/v1/projects/{}/channels", project.id),
+                request,
+            )
+            .await
+            .expect("failed to create channel");
+        notification_ids.push(channel.id);
+    }
+
+    let request = CreateEscalationPolicy {
+        name: Some("on call".to_string()),
+        tiers: notification_ids
+            .iter()
+            .map(|id| EscalationTier {
+                escalate_after_minutes: Some(ESCALATE_AFTER_MINUTES),
+                notification_ids: vec![*id],
+            })
+            .collect(),
+    };
+    let policy: EscalationPolicy = project
+        .member
+        .post(
+            &format!("/api/v1/projects/{}/escalation-policies", project.id),
+            request,
+        )
+        .await
+        .expect("failed to create escalation policy");
+    project
+        .member
+        .send(
+            Method::PUT,
+            &format!(
+                "/api/v1/projects/{}/checks/{}/escalation-policy/{}",
+                project.id, project.check_id, policy.id
+            ),
+        )
+        .await
+        .expect("failed to attach escalation policy");
+
+    Escalation {
+        project,
+        notification_ids,
+    }
+}
+
+impl Escalation {
+    /// The notifications of the tiers up to and including `tier`.
+    fn tiers_up_to(&self, tier: usize) -> Vec<Uuid> {
+        self.notification_ids[..=tier]
+            .iter()
+            .map(|id| id.into_uuid())
+            .collect()
+    }
+}
+
+/// The notifications alerted so far, in the order they were alerted.
+async fn alerted_notifications(app: &TestApp) -> Vec<Uuid> {
+    sqlx::query_scalar(
+        "SELECT n.uuid FROM notification_alerts a INNER JOIN notifications n ON n.id = a.notification_id ORDER BY a.id",
+    )
+    .fetch_all(&mut app.database.connection().await.unwrap())
+    .await
+    .expect("failed to read alerts")
+}
+
+/// Makes the current tier of every incident have waited for longer than it has to
+/// respond.
+async fn let_tier_time_out(app: &TestApp) {
+    sqlx::query("UPDATE incidents SET escalated_at = escalated_at - $1 * INTERVAL '1 minute'")
+        .bind(ESCALATE_AFTER_MINUTES + 1)
+        .execute(&mut app.database.connection().await.unwrap())
+        .await
+        .expect("failed to backdate incidents");
+}
+
+async fn escalate_incidents(app: &TestApp) {
+    app.repository()
+        .escalation_policy()
+        .escalate_incidents()
+        .await
+        .expect("failed to escalate incidents");
+}
+
+async fn go_down(app: &TestApp, escalation: &Escalation) {
+    app.make_checks_overdue(&[escalation.project.check_id])
+        .await;
+    app.repository()
+        .check()
+        .enqueue_alerts_for_overdue_pings()
+        .await
+        .expect("failed to enqueue alerts");
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn incident_escalates_to_next_tier_after_delay() {
+    let app = TestApp::start().await;
+    let escalation = escalation(&app, 3).await;
+
+    go_down(&app, &escalation).await;
+    assert_eq!(escalation.tiers_up_to(0), alerted_notifications(&app).await);
+
+    escalate_incidents(&app).await;
+    assert_eq!(
+        escalation.tiers_up_to(0),
+        alerted_notifications(&app).await,
+        "incident escalated before the tier had time to respond"
+    );
+
+    let_tier_time_out(&app).await;
+    escalate_incidents(&app).await;
+    assert_eq!(escalation.tiers_up_to(1), alerted_notifications(&app).await);
+
+    let_tier_time_out(&app).await;
+    escalate_incidents(&app).await;
+    assert_eq!(escalation.tiers_up_to(2), alerted_notifications(&app).await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn escalation_stops_at_last_tier() {
+    let app = TestApp::start().await;
+    let escalation = escalation(&app, 2).await;
+
+    go_down(&app, &escalation).await;
+    let_tier_time_out(&app).await;
+    escalate_incidents(&app).await;
+    assert_eq!(escalation.tiers_up_to(1), alerted_notifications(&app).await);
+
+    for _ in 0..2 {
+        let_tier_time_out(&app).await;
+        escalate_incidents(&app).await;
+    }
+    assert_eq!(escalation.tiers_up_to(1), alerted_notifications(&app).await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn escalation_stops_once_acknowledged() {
+    let app = TestApp::start().await;
+    let escalation = escalation(&app, 3).await;
+
+    go_down(&app, &escalation).await;
+    let alert_uuid: Uuid = sqlx::query_scalar("SELECT uuid FROM notification_alerts")
+        .fetch_one(&mut app.database.connection().await.unwrap())
+        .await
+        .expect("failed to read alert");
+    app.repository()
+        .incident()
+        .acknowledge(&alert_uuid)
+        .await
+        .expect("failed to acknowledge incident");
+
+    let_tier_time_out(&app).await;
+    escalate_incidents(&app).await;
+    assert_eq!(escalation.tiers_up_to(0), alerted_notifications(&app).await);
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use camino::Utf8PathBuf;
+use openssl::rsa::Rsa;
+use up_core::{
+    jwks::Jwks,
+    jwt::{self, DEFAULT_AUDIENCE, DEFAULT_ISSUER},
+    JWKS_ENV,
+};
+use up_server::api::v1::auth::{Login, Token};
+
+use crate::{assert_status, TestApp, TestUser};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+fn server_jwks() -> String {
+    std::env::var(JWKS_ENV).expect("missing JWKS environment variable")
+}
+
+fn generate_pem() -> Vec<u8> {
+    let rsa = Rsa::generate(2048).unwrap();
+    let mut pem = rsa.private_key_to_pem().unwrap();
+    pem.extend(rsa.public_key_to_pem().unwrap());
+    pem
+}
+
+/// Combines the keys of several JWKS into one, as when a key is being rotated.
+fn combine(jwks: &[&str]) -> String {
+    let keys: Vec<serde_json::Value> = jwks
+        .iter()
+        .flat_map(|jwks| {
+            let jwks: serde_json::Value = serde_json::from_str(jwks).unwrap();
+            jwks["keys"].as_array().unwrap().clone()
+        })
+        .collect();
+    serde_json::json!({ "keys": keys }).to_string()
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn jwks_is_published() {
+    let (_, client) = TestApp::start_and_connect(TestUser::Anonymous).await;
+
+    let jwks: Jwks = client
+        .get("/.well-known/jwks.json")
+        .await
+        .expect("failed to read JWKS");
+
+    let expected: Jwks = server_jwks().parse().unwrap();
+    assert_eq!(expected.key_ids(), jwks.key_ids());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn rotated_keys_are_reloaded() {
+    let path = Utf8PathBuf::from_path_buf(
+        std::env::temp_dir().join(format!("jwks-{}.json", uuid::Uuid::new_v4())),
+    )
+    .unwrap();
+    std::fs::write(&path, server_jwks()).unwrap();
+
+    let jwks_file = path.clone();
+    let app = TestApp::start_with_args(|args| {
+        args.jwks_file = Some(jwks_file);
+        args.jwks_reload_interval = 1;
+    })
+    .await;
+    let anonymous = app.connect(TestUser::Anonymous).await.unwrap();
+    let user_id = app
+        .create_user_with_password("rotate@example.com", PASSWORD)
+        .await;
+    let subject = app.user_subject(user_id).await;
+
+    let pem = generate_pem();
+    let generator = jwt::Generator::new_from_pem(&pem, DEFAULT_ISSUER, DEFAULT_AUDIENCE).unwrap();
+    let client = app.connect_with_token(generator.generate(&subject, 1, None).unwrap());
+    let result = client.get::<serde_json::Value>("/api/v1/identity").await;
+    assert_status(401, result);
+
+    let new_jwks = Jwks::from_pem(&pem).unwrap().to_string();
+    std::fs::write(&path, combine(&[&server_jwks(), &new_jwks])).unwrap();
+
+    let mut remaining_tries = 50;
+    while client
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await
+        .is_err()
+    {
+        remaining_tries -= 1;
+        assert!(remaining_tries > 0, "rotated keys were not reloaded");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let jwks: Jwks = client
+        .get("/.well-known/jwks.json")
+        .await
+        .expect("failed to read JWKS");
+    assert_eq!(2, jwks.key_ids().len());
+
+    let request = Login {
+        email: "rotate@example.com".to_string(),
+        password: PASSWORD.to_string(),
+    };
+    let token: Token = anonymous
+        .post("/api/v1/auth/login", request)
+        .await
+        .expect("failed to log in");
+    app.connect_with_token(token.token)
+        .get::<serde_json::Value>("/api/v1/identity")
+        .await
+        .expect("JWT signed with previous key was rejected");
+
+    std::fs::remove_file(&path).unwrap();
+}
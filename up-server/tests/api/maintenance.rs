@@ -0,0 +1,213 @@
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Method;
+use up_server::api::v1::{
+    checks::PeriodUnits,
+    maintenance::{CreateMaintenanceWindow, MaintenanceWindow},
+};
+
+use super::{alert_overdue_checks, checks_with_notifications, project, uuids, Project};
+use crate::{assert_status, TestApp, TestResult};
+
+fn window(starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> CreateMaintenanceWindow {
+    CreateMaintenanceWindow {
+        name: Some("deploy".to_string()),
+        starts_at,
+        ends_at,
+        repeat_every: None,
+        repeat_every_units: None,
+        tag: None,
+        check_ids: Vec::new(),
+    }
+}
+
+/// A window that started an hour ago and ends in an hour.
+fn active_window() -> CreateMaintenanceWindow {
+    window(
+        Utc::now() - Duration::hours(1),
+        Utc::now() + Duration::hours(1),
+    )
+}
+
+/// A window of an hour that repeats daily, the last occurrence of which started
+/// `started_minutes_ago` minutes ago.
+fn daily_window(started_minutes_ago: i64) -> CreateMaintenanceWindow {
+    let starts_at = Utc::now() - Duration::days(2) - Duration::minutes(started_minutes_ago);
+    CreateMaintenanceWindow {
+        repeat_every: Some(1),
+        repeat_every_units: Some(PeriodUnits::Days),
+        ..window(starts_at, starts_at + Duration::hours(1))
+    }
+}
+
+async fn create_window(
+    project: &Project,
+    request: CreateMaintenanceWindow,
+) -> TestResult<MaintenanceWindow> {
+    project
+        .member
+        .post(
+            &format!("/api/v1/projects/{}/maintenance-windows", project.id),
+            request,
+        )
+        .await
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn invalid_maintenance_windows_are_rejected() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let now = Utc::now();
+
+    let result = create_window(&project, window(now, now - Duration::hours(1))).await;
+    assert_status(400, result);
+
+    let request = CreateMaintenanceWindow {
+        repeat_every: Some(1),
+        ..window(now, now + Duration::hours(1))
+    };
+    assert_status(400, create_window(&project, request).await);
+
+    let request = CreateMaintenanceWindow {
+        repeat_every_units: Some(PeriodUnits::Days),
+        ..window(now, now + Duration::hours(1))
+    };
+    assert_status(400, create_window(&project, request).await);
+
+    for hours in [1, 2] {
+        let request = CreateMaintenanceWindow {
+            repeat_every: Some(1),
+            repeat_every_units: Some(PeriodUnits::Hours),
+            ..window(now, now + Duration::hours(hours))
+        };
+        assert_status(400, create_window(&project, request).await);
+    }
+
+    let request = CreateMaintenanceWindow {
+        repeat_every: Some(2),
+        repeat_every_units: Some(PeriodUnits::Hours),
+        ..window(now, now + Duration::hours(1))
+    };
+    create_window(&project, request)
+        .await
+        .expect("failed to create recurring window");
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn one_off_window_suppresses_alerts_while_active() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let check_ids = checks_with_notifications(&project, &["api", "worker"]).await;
+
+    let now = Utc::now();
+    create_window(
+        &project,
+        window(now - Duration::hours(2), now - Duration::hours(1)),
+    )
+    .await
+    .expect("failed to create past window");
+    create_window(
+        &project,
+        window(now + Duration::hours(1), now + Duration::hours(2)),
+    )
+    .await
+    .expect("failed to create future window");
+    assert_eq!(
+        uuids(&check_ids),
+        alert_overdue_checks(&app, &check_ids).await
+    );
+
+    let active = create_window(&project, active_window())
+        .await
+        .expect("failed to create window");
+    assert!(alert_overdue_checks(&app, &check_ids).await.is_empty());
+
+    let statuses: Vec<String> =
+        sqlx::query_scalar("SELECT status::TEXT FROM checks WHERE uuid = ANY($1)")
+            .bind(
+                check_ids
+                    .iter()
+                    .map(|id| id.into_uuid())
+                    .collect::<Vec<_>>(),
+            )
+            .fetch_all(&mut app.database.connection().await.unwrap())
+            .await
+            .unwrap();
+    assert_eq!(vec!["DOWN", "DOWN"], statuses);
+
+    project
+        .member
+        .send(
+            Method::DELETE,
+            &format!(
+                "/api/v1/projects/{}/maintenance-windows/{}",
+                project.id, active.id
+            ),
+        )
+        .await
+        .expect("failed to cancel window");
+    assert_eq!(
+        uuids(&check_ids),
+        alert_overdue_checks(&app, &check_ids).await
+    );
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn recurring_window_suppresses_alerts_of_listed_checks_during_occurrences() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let check_ids = checks_with_notifications(&project, &["api", "worker"]).await;
+    let (api, worker) = (check_ids[0], check_ids[1]);
+
+    create_window(
+        &project,
+        CreateMaintenanceWindow {
+            check_ids: vec![api],
+            ..daily_window(30)
+        },
+    )
+    .await
+    .expect("failed to create window");
+    create_window(
+        &project,
+        CreateMaintenanceWindow {
+            check_ids: vec![worker],
+            ..daily_window(90)
+        },
+    )
+    .await
+    .expect("failed to create window");
+
+    assert_eq!(
+        uuids(&[worker]),
+        alert_overdue_checks(&app, &check_ids).await
+    );
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn tagged_window_suppresses_alerts_of_tagged_checks() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let check_ids = checks_with_notifications(&project, &["primary", "api"]).await;
+    let (database, api) = (check_ids[0], check_ids[1]);
+
+    sqlx::query(
+        "WITH t AS (INSERT INTO tags (account_id, name) SELECT id, 'database' FROM accounts WHERE uuid = $1 RETURNING id) INSERT INTO check_tags (check_id, tag_id) SELECT c.id, t.id FROM checks c, t WHERE c.uuid = $2",
+    )
+    .bind(project.account_id.as_uuid())
+    .bind(database.as_uuid())
+    .execute(&mut app.database.connection().await.unwrap())
+    .await
+    .expect("failed to tag check");
+
+    create_window(
+        &project,
+        CreateMaintenanceWindow {
+            tag: Some("database".to_string()),
+            ..active_window()
+        },
+    )
+    .await
+    .expect("failed to create window");
+
+    assert_eq!(uuids(&[api]), alert_overdue_checks(&app, &check_ids).await);
+}
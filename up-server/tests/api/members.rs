@@ -0,0 +1,301 @@
+use up_core::auth::Role;
+use up_server::{
+    api::{
+        v1::{
+            auth::Token,
+            checks::Check,
+            members::{
+                AcceptInvitation, AssignToProject, CreateInvitation, Invitation, Member, UpdateRole,
+            },
+        },
+        GenericResponse,
+    },
+    shortid::ShortId,
+};
+
+use super::{create_check, MEMBER_EMAIL, PASSWORD};
+use crate::{assert_status, TestApp, TestClient, TestUser};
+
+const ADMIN_EMAIL: &str = "admin@example.com";
+
+/// An account with an administrator and a member, both logged in.
+struct Account {
+    id: ShortId,
+    admin_id: i64,
+    admin: TestClient,
+    member: TestClient,
+}
+
+async fn account(app: &TestApp) -> Account {
+    let admin_id = app.create_user_with_password(ADMIN_EMAIL, PASSWORD).await;
+    let member_id = app.create_user_with_password(MEMBER_EMAIL, PASSWORD).await;
+    let id = ShortId::new();
+    app.create_account(&id, "acme", admin_id).await;
+    app.add_user_to_account(admin_id, &id, "ADMINISTRATOR")
+        .await;
+    app.add_user_to_account(member_id, &id, "MEMBER").await;
+
+    Account {
+        id,
+        admin_id,
+        admin: app.login(ADMIN_EMAIL, PASSWORD).await,
+        member: app.login(MEMBER_EMAIL, PASSWORD).await,
+    }
+}
+
+async fn members(account: &Account) -> Vec<Member> {
+    account
+        .admin
+        .get(&format!("/api/v1/accounts/{}/members", account.id))
+        .await
+        .expect("failed to read members")
+}
+
+fn find<'a>(members: &'a [Member], email: &str) -> &'a Member {
+    members
+        .iter()
+        .find(|m| m.email == email)
+        .expect("member not found")
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn administrator_can_invite_and_invitee_accepts() {
+    let app = TestApp::start().await;
+    let account = account(&app).await;
+
+    let request = CreateInvitation {
+        email: "new@example.com".to_string(),
+        role: Role::Viewer,
+    };
+    let invitation: Invitation = account
+        .admin
+        .post(
+            &format!("/api/v1/accounts/{}/invitations", account.id),
+            request,
+        )
+        .await
+        .expect("failed to invite");
+    assert_eq!("new@example.com", invitation.email);
+    assert_eq!(Role::Viewer, invitation.role);
+
+    let token = app
+        .create_invitation_token(&account.id, "new@example.com", "VIEWER", account.admin_id)
+        .await;
+    let anonymous = app.connect(TestUser::Anonymous).await.unwrap();
+    let request = AcceptInvitation {
+        token: token.clone(),
+        password: Some(PASSWORD.to_string()),
+    };
+    anonymous
+        .post::<AcceptInvitation, Token>("/api/v1/invitations/accept", request)
+        .await
+        .expect("failed to accept invitation");
+
+    let members = members(&account).await;
+    assert_eq!(3, members.len());
+    assert_eq!(Role::Viewer, find(&members, "new@example.com").role);
+    app.login("new@example.com", PASSWORD).await;
+
+    let request = AcceptInvitation {
+        token,
+        password: None,
+    };
+    let result = anonymous
+        .post::<AcceptInvitation, Token>("/api/v1/invitations/accept", request)
+        .await;
+    assert_status(400, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn existing_members_cannot_be_invited() {
+    let app = TestApp::start().await;
+    let account = account(&app).await;
+
+    let request = CreateInvitation {
+        email: "Member@Example.com".to_string(),
+        role: Role::Member,
+    };
+    let result = account
+        .admin
+        .post::<CreateInvitation, Invitation>(
+            &format!("/api/v1/accounts/{}/invitations", account.id),
+            request,
+        )
+        .await;
+    assert_status(400, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn members_cannot_manage_members() {
+    let app = TestApp::start().await;
+    let account = account(&app).await;
+    let admin_id = find(&members(&account).await, ADMIN_EMAIL).id;
+
+    let result = account
+        .member
+        .get::<Vec<Member>>(&format!("/api/v1/accounts/{}/members", account.id))
+        .await;
+    assert_status(403, result);
+
+    let request = CreateInvitation {
+        email: "new@example.com".to_string(),
+        role: Role::Administrator,
+    };
+    let result = account
+        .member
+        .post::<CreateInvitation, Invitation>(
+            &format!("/api/v1/accounts/{}/invitations", account.id),
+            request,
+        )
+        .await;
+    assert_status(403, result);
+
+    let result = account
+        .member
+        .delete::<GenericResponse>(&format!(
+            "/api/v1/accounts/{}/members/{}",
+            account.id, admin_id
+        ))
+        .await;
+    assert_status(403, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn last_administrator_cannot_be_demoted_or_removed() {
+    let app = TestApp::start().await;
+    let account = account(&app).await;
+    let members = members(&account).await;
+    let admin_id = find(&members, ADMIN_EMAIL).id;
+    let member_id = find(&members, MEMBER_EMAIL).id;
+
+    let role_uri = |id: ShortId| format!("/api/v1/accounts/{}/members/{}/role", account.id, id);
+
+    let result = account
+        .admin
+        .put::<UpdateRole, GenericResponse>(&role_uri(admin_id), UpdateRole { role: Role::Member })
+        .await;
+    assert_status(400, result);
+    let result = account
+        .admin
+        .delete::<GenericResponse>(&format!(
+            "/api/v1/accounts/{}/members/{}",
+            account.id, admin_id
+        ))
+        .await;
+    assert_status(400, result);
+
+    account
+        .admin
+        .put::<UpdateRole, GenericResponse>(
+            &role_uri(member_id),
+            UpdateRole {
+                role: Role::Administrator,
+            },
+        )
+        .await
+        .expect("failed to promote member");
+    account
+        .admin
+        .put::<UpdateRole, GenericResponse>(&role_uri(admin_id), UpdateRole { role: Role::Viewer })
+        .await
+        .expect("failed to demote administrator");
+
+    let result = account
+        .admin
+        .get::<Vec<Member>>(&format!("/api/v1/accounts/{}/members", account.id))
+        .await;
+    assert_status(403, result);
+    let members: Vec<Member> = account
+        .member
+        .get(&format!("/api/v1/accounts/{}/members", account.id))
+        .await
+        .expect("failed to read members");
+    assert_eq!(Role::Viewer, find(&members, ADMIN_EMAIL).role);
+    assert_eq!(Role::Administrator, find(&members, MEMBER_EMAIL).role);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn administrator_can_assign_and_remove_members() {
+    let app = TestApp::start().await;
+    let account = account(&app).await;
+    let project_id = app
+        .create_project(&account.id, "backend", account.admin_id)
+        .await;
+    let member_id = find(&members(&account).await, MEMBER_EMAIL).id;
+
+    let project_uri = format!("/api/v1/projects/{}/members/{}", project_id, member_id);
+    account
+        .admin
+        .put::<AssignToProject, GenericResponse>(&project_uri, AssignToProject { role: None })
+        .await
+        .expect("failed to assign member to project");
+    let listed = members(&account).await;
+    let projects = &find(&listed, MEMBER_EMAIL).projects;
+    assert_eq!(1, projects.len());
+    assert_eq!(project_id, projects[0].id);
+    assert_eq!(Role::Member, projects[0].role);
+
+    account
+        .admin
+        .delete::<GenericResponse>(&project_uri)
+        .await
+        .expect("failed to unassign member from project");
+    assert!(find(&members(&account).await, MEMBER_EMAIL)
+        .projects
+        .is_empty());
+
+    let result = account
+        .admin
+        .put::<AssignToProject, GenericResponse>(
+            &format!("/api/v1/projects/{}/members/{}", project_id, ShortId::new()),
+            AssignToProject { role: None },
+        )
+        .await;
+    assert_status(404, result);
+
+    account
+        .admin
+        .delete::<GenericResponse>(&format!(
+            "/api/v1/accounts/{}/members/{}",
+            account.id, member_id
+        ))
+        .await
+        .expect("failed to remove member");
+    assert_eq!(1, members(&account).await.len());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn project_roles_limit_what_members_can_change() {
+    let app = TestApp::start().await;
+    let account = account(&app).await;
+    let project_ids = [
+        app.create_project(&account.id, "frontend", account.admin_id)
+            .await,
+        app.create_project(&account.id, "backend", account.admin_id)
+            .await,
+    ];
+    let member_id = find(&members(&account).await, MEMBER_EMAIL).id;
+
+    for (project_id, role) in project_ids.iter().zip([Role::Member, Role::Viewer]) {
+        account
+            .admin
+            .put::<AssignToProject, GenericResponse>(
+                &format!("/api/v1/projects/{}/members/{}", project_id, member_id),
+                AssignToProject { role: Some(role) },
+            )
+            .await
+            .expect("failed to assign member to project");
+    }
+
+    create_check(&account.member, account.id, project_ids[0], "backups")
+        .await
+        .expect("failed to create check as project member");
+    let result = create_check(&account.member, account.id, project_ids[1], "backups").await;
+    assert_status(403, result);
+    let checks: Vec<Check> = account
+        .member
+        .get(&format!("/api/v1/projects/{}/checks", project_ids[1]))
+        .await
+        .expect("failed to read checks as project viewer");
+    assert!(checks.is_empty());
+}
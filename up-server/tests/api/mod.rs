@@ -1,2 +1,145 @@
+use std::collections::HashSet;
+
+use up_server::{
+    api::v1::{
+        auth::{Login, Token},
+        checks::{Check, CreateCheck},
+        notifications::{CreateNotification, Notification, NotificationType},
+    },
+    shortid::ShortId,
+};
+use uuid::Uuid;
+
+use crate::{TestApp, TestClient, TestResult};
+
+pub mod accounts;
+pub mod api_keys;
+pub mod auth;
+pub mod channels;
+pub mod dependencies;
+pub mod escalation;
 pub mod health;
+pub mod jwks;
+pub mod maintenance;
+pub mod members;
+pub mod notifications;
+pub mod oidc;
 pub mod projects;
+
+pub const MEMBER_EMAIL: &str = "member@example.com";
+pub const PASSWORD: &str = "correct horse battery staple";
+
+/// A project with a check, and a logged in member of the project.
+pub struct Project {
+    pub account_id: ShortId,
+    pub id: ShortId,
+    pub check_id: ShortId,
+    pub member: TestClient,
+}
+
+impl Project {
+    pub async fn create_check(&self, name: &str) -> ShortId {
+        create_check(&self.member, self.account_id, self.id, name)
+            .await
+            .expect("failed to create check")
+            .id
+    }
+}
+
+pub async fn project(app: &TestApp) -> Project {
+    let user_id = app.create_user_with_password(MEMBER_EMAIL, PASSWORD).await;
+    let account_id = ShortId::new();
+    app.create_account(&account_id, "acme", user_id).await;
+    app.add_user_to_account(user_id, &account_id, "MEMBER")
+        .await;
+    let id = app.create_project(&account_id, "backups", user_id).await;
+    let member = app.login(MEMBER_EMAIL, PASSWORD).await;
+    let check_id = create_check(&member, account_id, id, "nightly")
+        .await
+        .expect("failed to create check")
+        .id;
+
+    Project {
+        account_id,
+        id,
+        check_id,
+        member,
+    }
+}
+
+/// Creates checks with an email notification each, returning their IDs.
+pub async fn checks_with_notifications(project: &Project, names: &[&str]) -> Vec<ShortId> {
+    let mut check_ids = Vec::new();
+    for name in names {
+        let check_id = project.create_check(name).await;
+        let request = CreateNotification {
+            email: Some("ops@example.com".to_string()),
+            ..notifications::notification(NotificationType::Email)
+        };
+        let _: Notification = project
+            .member
+            .post(
+                &format!(
+                    "/api/v1/projects/{}/checks/{}/notifications",
+                    project.id, check_id
+                ),
+                request,
+            )
+            .await
+            .expect("failed to create notification");
+        check_ids.push(check_id);
+    }
+    check_ids
+}
+
+/// Makes the checks overdue and enqueues alerts, returning the checks alerted for.
+pub async fn alert_overdue_checks(app: &TestApp, check_ids: &[ShortId]) -> HashSet<Uuid> {
+    sqlx::query("DELETE FROM notification_alerts")
+        .execute(&mut app.database.connection().await.unwrap())
+        .await
+        .expect("failed to delete alerts");
+    app.make_checks_overdue(check_ids).await;
+    app.repository()
+        .check()
+        .enqueue_alerts_for_overdue_pings()
+        .await
+        .expect("failed to enqueue alerts");
+
+    let alerted: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT c.uuid FROM notification_alerts a INNER JOIN checks c ON c.id = a.check_id",
+    )
+    .fetch_all(&mut app.database.connection().await.unwrap())
+    .await
+    .expect("failed to read alerts");
+    alerted.into_iter().collect()
+}
+
+pub fn uuids(check_ids: &[ShortId]) -> HashSet<Uuid> {
+    check_ids.iter().map(|id| id.into_uuid()).collect()
+}
+
+/// Logs in with an email address and password.
+pub async fn login(client: &TestClient, email: &str, password: &str) -> TestResult<Token> {
+    let request = Login {
+        email: email.to_string(),
+        password: password.to_string(),
+    };
+    client.post("/api/v1/auth/login", request).await
+}
+
+pub async fn create_check(
+    client: &TestClient,
+    account_id: ShortId,
+    project_id: ShortId,
+    name: &str,
+) -> TestResult<Check> {
+    let request = CreateCheck {
+        account_id,
+        project_id,
+        name: name.to_string(),
+        severity: None,
+    };
+    client
+        .post(&format!("/api/v1/projects/{}/checks", project_id), request)
+        .await
+}
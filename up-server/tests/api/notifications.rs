@@ -0,0 +1,192 @@
+use chrono::{Duration, Utc};
+use up_server::{
+    api::v1::notifications::{
+        Alert, CreateNotification, DeliveryStatus, Notification, NotificationType,
+        UpdateNotification,
+    },
+    notifier::Delivery,
+    shortid::ShortId,
+};
+
+use super::{project, PASSWORD};
+use crate::{assert_status, TestApp};
+
+pub fn notification(notification_type: NotificationType) -> CreateNotification {
+    CreateNotification {
+        name: Some("on call".to_string()),
+        notification_type,
+        email: None,
+        url: None,
+        phone_number: None,
+        max_retries: Some(3),
+        retry_backoff_seconds: None,
+        retry_backoff_max_seconds: None,
+        digest_window_seconds: None,
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn notification_without_destination_is_rejected() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let path = format!(
+        "/api/v1/projects/{}/checks/{}/notifications",
+        project.id, project.check_id
+    );
+
+    let result = project
+        .member
+        .post::<_, Notification>(&path, notification(NotificationType::Sms))
+        .await;
+    assert_status(400, result);
+
+    let request = CreateNotification {
+        email: Some("ops@example.com".to_string()),
+        ..notification(NotificationType::Sms)
+    };
+    let result = project.member.post::<_, Notification>(&path, request).await;
+    assert_status(400, result);
+
+    let request = CreateNotification {
+        url: Some("not a URL".to_string()),
+        ..notification(NotificationType::Webhook)
+    };
+    let result = project.member.post::<_, Notification>(&path, request).await;
+    assert_status(400, result);
+
+    let request = CreateNotification {
+        phone_number: Some("+15005550006".to_string()),
+        ..notification(NotificationType::Sms)
+    };
+    let created: Notification = project
+        .member
+        .post(&path, request)
+        .await
+        .expect("failed to create notification");
+
+    let request = UpdateNotification {
+        name: None,
+        notification_type: None,
+        email: None,
+        url: None,
+        phone_number: Some(" ".to_string()),
+        max_retries: None,
+        retry_backoff_seconds: None,
+        retry_backoff_max_seconds: None,
+        digest_window_seconds: None,
+    };
+    let result = project
+        .member
+        .patch::<_, Notification>(&format!("{}/{}", path, created.id), request)
+        .await;
+    assert_status(400, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn delivery_log_lists_alerts_and_attempts() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let path = format!(
+        "/api/v1/projects/{}/checks/{}/notifications",
+        project.id, project.check_id
+    );
+    let request = CreateNotification {
+        email: Some("ops@example.com".to_string()),
+        ..notification(NotificationType::Email)
+    };
+    let notification: Notification = project
+        .member
+        .post(&path, request)
+        .await
+        .expect("failed to create notification");
+    let alerts_path = format!("{}/{}/alerts", path, notification.id);
+
+    let alerts: Vec<Alert> = project
+        .member
+        .get(&alerts_path)
+        .await
+        .expect("failed to read alerts");
+    assert!(alerts.is_empty());
+
+    app.make_checks_overdue(&[project.check_id]).await;
+    let repository = app.repository();
+    repository
+        .check()
+        .enqueue_alerts_for_overdue_pings()
+        .await
+        .expect("failed to enqueue alerts");
+    let claimed = repository
+        .notification()
+        .claim_alert_batch(10, Utc::now().naive_utc() - Duration::minutes(5))
+        .await
+        .expect("failed to claim alerts");
+    assert_eq!(1, claimed.len());
+    let delivery = Delivery {
+        http_status: Some(200),
+        provider_message_id: Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817".to_string()),
+    };
+    repository
+        .notification()
+        .record_alert_delivered(&claimed[0], &delivery)
+        .await
+        .expect("failed to record delivery");
+
+    let alerts: Vec<Alert> = project
+        .member
+        .get(&alerts_path)
+        .await
+        .expect("failed to read alerts");
+    assert_eq!(1, alerts.len());
+    assert_eq!(project.check_id, alerts[0].check_id);
+    assert!(matches!(
+        alerts[0].delivery_status,
+        DeliveryStatus::Delivered
+    ));
+    assert!(alerts[0].finished_at.is_some());
+    assert_eq!(1, alerts[0].attempts.len());
+    let attempt = &alerts[0].attempts[0];
+    assert!(matches!(attempt.delivery_status, DeliveryStatus::Delivered));
+    assert_eq!(Some(200), attempt.http_status);
+    assert_eq!(delivery.provider_message_id, attempt.provider_message_id);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn delivery_log_of_inaccessible_check_is_not_found() {
+    let app = TestApp::start().await;
+    let project = project(&app).await;
+    let request = CreateNotification {
+        email: Some("ops@example.com".to_string()),
+        ..notification(NotificationType::Email)
+    };
+    let path = format!(
+        "/api/v1/projects/{}/checks/{}/notifications",
+        project.id, project.check_id
+    );
+    let notification: Notification = project
+        .member
+        .post(&path, request)
+        .await
+        .expect("failed to create notification");
+    let alerts_path = format!("{}/{}/alerts", path, notification.id);
+
+    let colleague_id = app
+        .create_user_with_password("colleague@example.com", PASSWORD)
+        .await;
+    app.add_user_to_account(colleague_id, &project.account_id, "MEMBER")
+        .await;
+    let colleague = app.login("colleague@example.com", PASSWORD).await;
+    assert_status(404, colleague.get::<Vec<Alert>>(&alerts_path).await);
+
+    let outsider_id = app
+        .create_user_with_password("outsider@example.com", PASSWORD)
+        .await;
+    let other_account_id = ShortId::new();
+    app.create_account(&other_account_id, "globex", outsider_id)
+        .await;
+    app.add_user_to_account(outsider_id, &other_account_id, "ADMINISTRATOR")
+        .await;
+    app.create_project(&other_account_id, "backups", outsider_id)
+        .await;
+    let outsider = app.login("outsider@example.com", PASSWORD).await;
+    assert_status(404, outsider.get::<Vec<Alert>>(&alerts_path).await);
+}
@@ -0,0 +1,279 @@
+use chrono::Utc;
+use openssl::{
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    sign::Signer,
+};
+use serde_json::json;
+use up_core::{jwks::Jwks, jwt};
+use up_server::{api::v1::auth::Token, shortid::ShortId};
+use url::Url;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::{assert_status, TestApp, TestClient, TestResult, TestUser};
+
+const CLIENT_ID: &str = "up";
+const CLIENT_SECRET: &str = "secret";
+const CODE: &str = "authorization-code";
+const PASSWORD: &str = "correct horse battery staple";
+
+/// A local OpenID Connect provider, which issues ID tokens signed with its own key.
+struct MockIdp {
+    server: MockServer,
+    private_key: PKey<Private>,
+    key_id: String,
+}
+
+impl MockIdp {
+    async fn start() -> Self {
+        let server = MockServer::start().await;
+        let rsa = Rsa::generate(2048).unwrap();
+        let mut pem = rsa.private_key_to_pem().unwrap();
+        pem.extend(rsa.public_key_to_pem().unwrap());
+        let private_key = PKey::private_key_from_pem(&pem).unwrap();
+        let key_id = jwt::compute_key_id(&PKey::public_key_from_pem(&pem).unwrap()).unwrap();
+        let jwks: serde_json::Value =
+            serde_json::from_str(&Jwks::from_pem(&pem).unwrap().to_string()).unwrap();
+
+        let issuer = server.uri();
+        Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{}/authorize", issuer),
+                "token_endpoint": format!("{}/token", issuer),
+                "jwks_uri": format!("{}/jwks", issuer),
+                "response_types_supported": ["code"],
+                "subject_types_supported": ["public"],
+                "id_token_signing_alg_values_supported": ["RS256"],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jwks))
+            .mount(&server)
+            .await;
+
+        Self {
+            server,
+            private_key,
+            key_id,
+        }
+    }
+
+    fn issuer(&self) -> String {
+        self.server.uri()
+    }
+
+    /// Issues an ID token for `subject` when the authorization code is exchanged with
+    /// the PKCE verifier.
+    async fn expect_token_exchange(
+        &self,
+        subject: &str,
+        email: &str,
+        email_verified: bool,
+        nonce: &str,
+    ) {
+        let now = Utc::now().timestamp();
+        let id_token = self.sign(&json!({
+            "iss": self.issuer(),
+            "sub": subject,
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": nonce,
+            "email": email,
+            "email_verified": email_verified,
+        }));
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains(format!("code={}", CODE)))
+            .and(body_string_contains("code_verifier="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access-token",
+                "token_type": "Bearer",
+                "expires_in": 300,
+                "id_token": id_token,
+            })))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    fn sign(&self, claims: &serde_json::Value) -> String {
+        let header = json!({ "alg": "RS256", "typ": "JWT", "kid": self.key_id });
+        let text = format!(
+            "{}.{}",
+            base64_url(header.to_string().as_bytes()),
+            base64_url(claims.to_string().as_bytes())
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.private_key).unwrap();
+        signer.update(text.as_bytes()).unwrap();
+        format!("{}.{}", text, base64_url(&signer.sign_to_vec().unwrap()))
+    }
+}
+
+fn base64_url(bytes: &[u8]) -> String {
+    openssl::base64::encode_block(bytes)
+        .replace('+', "-")
+        .replace('/', "_")
+        .trim_end_matches('=')
+        .to_string()
+}
+
+async fn start_app(idp: &MockIdp, default_account: Option<ShortId>) -> (TestApp, TestClient) {
+    let issuer = idp.issuer();
+    let app = TestApp::start_with_args(|args| {
+        args.oidc_issuer_url = Some(issuer);
+        args.oidc_client_id = Some(CLIENT_ID.to_string());
+        args.oidc_client_secret = Some(CLIENT_SECRET.to_string());
+        args.oidc_default_account = default_account;
+    })
+    .await;
+    let client = app.connect(TestUser::Anonymous).await.unwrap();
+    (app, client)
+}
+
+/// Starts a login, returning the query of the URL the user is redirected to at the
+/// provider.
+async fn start_login(app: &TestApp) -> Url {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = client
+        .get(app.url().join("/api/v1/auth/oidc/login").unwrap())
+        .send()
+        .await
+        .expect("failed to start login");
+    assert!(response.status().is_redirection());
+    response.headers()["location"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+fn query_param(url: &Url, name: &str) -> String {
+    url.query_pairs()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.to_string())
+        .unwrap_or_else(|| panic!("no {} in {}", name, url))
+}
+
+async fn complete_login(client: &TestClient, state: &str) -> TestResult<Token> {
+    client
+        .get(&format!(
+            "/api/v1/auth/oidc/callback?code={}&state={}",
+            CODE, state
+        ))
+        .await
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn new_user_is_provisioned_on_oidc_login() {
+    let idp = MockIdp::start().await;
+    let account_id = ShortId::new();
+    let (app, client) = start_app(&idp, Some(account_id)).await;
+    let admin_id = app
+        .create_user_with_password("admin@example.com", PASSWORD)
+        .await;
+    app.create_account(&account_id, "Example", admin_id).await;
+
+    let authorize_url = start_login(&app).await;
+    assert_eq!("S256", query_param(&authorize_url, "code_challenge_method"));
+    let state = query_param(&authorize_url, "state");
+    idp.expect_token_exchange(
+        "idp-subject",
+        "new@example.com",
+        false,
+        &query_param(&authorize_url, "nonce"),
+    )
+    .await;
+
+    let token = complete_login(&client, &state)
+        .await
+        .expect("failed to log in with OIDC");
+
+    let identity: serde_json::Value = app
+        .connect_with_token(token.token)
+        .get("/api/v1/identity")
+        .await
+        .expect("failed to read identity with issued JWT");
+    assert_eq!("new@example.com", identity["email"]);
+
+    // The state can only be used once.
+    assert_status(400, complete_login(&client, &state).await);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn existing_user_is_linked_by_verified_email() {
+    let idp = MockIdp::start().await;
+    let (app, client) = start_app(&idp, None).await;
+    app.create_user_with_password("existing@example.com", PASSWORD)
+        .await;
+
+    for _ in 0..2 {
+        let authorize_url = start_login(&app).await;
+        idp.expect_token_exchange(
+            "idp-subject",
+            "Existing@Example.com",
+            true,
+            &query_param(&authorize_url, "nonce"),
+        )
+        .await;
+
+        let token = complete_login(&client, &query_param(&authorize_url, "state"))
+            .await
+            .expect("failed to log in with OIDC");
+
+        let identity: serde_json::Value = app
+            .connect_with_token(token.token)
+            .get("/api/v1/identity")
+            .await
+            .expect("failed to read identity with issued JWT");
+        assert_eq!("existing@example.com", identity["email"]);
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn unverified_email_is_not_linked_without_provisioning() {
+    let idp = MockIdp::start().await;
+    let (app, client) = start_app(&idp, None).await;
+    app.create_user_with_password("existing@example.com", PASSWORD)
+        .await;
+
+    let authorize_url = start_login(&app).await;
+    idp.expect_token_exchange(
+        "idp-subject",
+        "existing@example.com",
+        false,
+        &query_param(&authorize_url, "nonce"),
+    )
+    .await;
+
+    let result = complete_login(&client, &query_param(&authorize_url, "state")).await;
+    assert_status(403, result);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn id_token_with_wrong_nonce_is_rejected() {
+    let idp = MockIdp::start().await;
+    let (app, client) = start_app(&idp, None).await;
+    app.create_user_with_password("existing@example.com", PASSWORD)
+        .await;
+
+    let authorize_url = start_login(&app).await;
+    idp.expect_token_exchange("idp-subject", "existing@example.com", true, "replayed")
+        .await;
+
+    let result = complete_login(&client, &query_param(&authorize_url, "state")).await;
+    assert_status(401, result);
+}
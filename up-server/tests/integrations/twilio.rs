@@ -0,0 +1,62 @@
+use serde_json::json;
+use up_server::integrations::twilio::{TwilioClient, TwilioError};
+use wiremock::{
+    matchers::{basic_auth, body_string_contains, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const ACCOUNT_SID: &str = "AC00000000000000000000000000000000";
+const AUTH_TOKEN: &str = "secret";
+const FROM_NUMBER: &str = "+15550000000";
+
+#[test_log::test(tokio::test)]
+pub async fn sms_is_sent_with_credentials() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!(
+            "/2010-04-01/Accounts/{}/Messages.json",
+            ACCOUNT_SID
+        )))
+        .and(basic_auth(ACCOUNT_SID, AUTH_TOKEN))
+        .and(body_string_contains("Body=%5BDOWN%5D+backups"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+            "sid": "SM00000000000000000000000000000000",
+            "status": "queued",
+            "error_code": null,
+            "error_message": null
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client =
+        TwilioClient::with_credentials(ACCOUNT_SID, AUTH_TOKEN, FROM_NUMBER, &server.uri())
+            .unwrap();
+
+    let response = client
+        .send_sms("+15551234567", "[DOWN] backups")
+        .await
+        .expect("failed to send SMS");
+
+    assert_eq!("SM00000000000000000000000000000000", response.sid);
+    assert_eq!(Some(201), response.http_status);
+}
+
+#[test_log::test(tokio::test)]
+pub async fn sms_http_error_is_reported() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("unauthorized"))
+        .mount(&server)
+        .await;
+
+    let client =
+        TwilioClient::with_credentials(ACCOUNT_SID, AUTH_TOKEN, FROM_NUMBER, &server.uri())
+            .unwrap();
+
+    let result = client.send_sms("+15551234567", "[DOWN] backups").await;
+
+    assert!(matches!(result, Err(TwilioError::ApiHttpError(status, _)) if status.as_u16() == 401));
+}
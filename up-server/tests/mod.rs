@@ -16,8 +16,12 @@ use url::Url;
 use uuid::Uuid;
 
 use up_server::{
+    api::v1::auth::{Login, Token},
     app::{App, Args},
+    auth::Identity,
     database::Database,
+    repository::Repository,
+    shortid::ShortId,
 };
 
 const BASE_DATABASE_URL: &str = "postgres://127.0.0.1:5432";
@@ -27,6 +31,8 @@ const SUBJECT_VIEWER: &str = "55201Y1AT28PCARNW21BY1K839";
 const SUBJECT_NO_ACCOUNT_VIEWER: &str = "50AMCDE3BA97WSBMJ85C51D8GC";
 
 pub mod api;
+pub mod integrations;
+pub mod notifier;
 
 pub struct TestApp {
     database_name: String,
@@ -69,6 +75,11 @@ impl TestApp {
     }
 
     pub async fn start() -> Self {
+        Self::start_with_args(|_| {}).await
+    }
+
+    /// Starts the app with arguments changed by `configure`.
+    pub async fn start_with_args<F: FnOnce(&mut Args)>(configure: F) -> Self {
         dotenv::dotenv().ok();
 
         let database_name = format!("it_{}", Uuid::new_v4().to_string());
@@ -96,12 +107,14 @@ impl TestApp {
         let port = next_available_port();
         let listen_address = SocketAddr::from(([127, 0, 0, 1], port));
 
-        let app = App::with_args(Args {
+        let mut args = Args {
             listen_address,
             database_url,
             disable_background_jobs: true,
             ..Args::default()
-        });
+        };
+        configure(&mut args);
+        let app = App::with_args(args);
 
         let server_certificate_pem = std::env::var(SERVER_CERTIFICATE_ENV)
             .expect("missing SERVER_CERTIFICATE environment variable, needed to issue test JWT")
@@ -182,6 +195,249 @@ impl TestApp {
     pub fn url(&self) -> &Url {
         &self.url
     }
+
+    /// Returns a client authenticating with a JWT obtained some other way, e.g. by
+    /// logging in.
+    pub fn connect_with_token(&self, token: String) -> TestClient {
+        TestClient(reqwest::Client::new(), self.url.clone(), Some(token))
+    }
+
+    /// Creates a user who can log in with an email address and password, returning
+    /// the ID of the user.
+    pub async fn create_user_with_password(&self, email: &str, password: &str) -> i64 {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        let subject = ulid::Ulid::new().to_string();
+        sqlx::query_scalar(
+            "INSERT INTO users (shortid, subject, email, password_hash) VALUES ($1, $1, $2, $3) RETURNING id",
+        )
+        .bind(subject)
+        .bind(email)
+        .bind(up_server::password::hash(password).expect("failed to hash password"))
+        .fetch_one(&mut conn)
+        .await
+        .expect("failed to create user")
+    }
+
+    /// Logs a user in with their email address and password.
+    pub async fn login(&self, email: &str, password: &str) -> TestClient {
+        let request = Login {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        let token: Token = self
+            .connect(TestUser::Anonymous)
+            .await
+            .unwrap()
+            .post("/api/v1/auth/login", request)
+            .await
+            .expect("failed to log in");
+        self.connect_with_token(token.token)
+    }
+
+    /// Returns the identity of a user, as if they had been authenticated by the API.
+    pub async fn identity(&self, user_id: i64) -> Identity {
+        let subject = self.user_subject(user_id).await;
+        self.repository()
+            .auth()
+            .find_user_by_subject(&subject)
+            .await
+            .expect("failed to read user")
+            .expect("user not found")
+            .into()
+    }
+
+    /// Returns the subject of a user, which JWTs identify them by.
+    pub async fn user_subject(&self, user_id: i64) -> String {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        sqlx::query_scalar("SELECT subject FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&mut conn)
+            .await
+            .expect("failed to read user subject")
+    }
+
+    /// Creates an account with an ID chosen up front, e.g. to configure the app with.
+    pub async fn create_account(&self, id: &ShortId, name: &str, created_by: i64) {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        sqlx::query(
+            "INSERT INTO accounts (uuid, shortid, name, created_by) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(id.as_uuid())
+        .bind(id.to_string())
+        .bind(name)
+        .bind(created_by)
+        .execute(&mut conn)
+        .await
+        .expect("failed to create account");
+    }
+
+    /// Adds a user to an account with a role.
+    pub async fn add_user_to_account(&self, user_id: i64, account_id: &ShortId, role: &str) {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        sqlx::query(
+            "INSERT INTO user_accounts (user_id, account_id) SELECT $1, id FROM accounts WHERE uuid = $2",
+        )
+        .bind(user_id)
+        .bind(account_id.as_uuid())
+        .execute(&mut conn)
+        .await
+        .expect("failed to add user to account");
+        sqlx::query(
+            "INSERT INTO user_roles (user_id, account_id, role) SELECT $1, id, $3::user_role FROM accounts WHERE uuid = $2",
+        )
+        .bind(user_id)
+        .bind(account_id.as_uuid())
+        .bind(role)
+        .execute(&mut conn)
+        .await
+        .expect("failed to give user role in account");
+    }
+
+    /// Creates a project in an account that the user creating it is assigned to, with
+    /// their role in the account.
+    pub async fn create_project(
+        &self,
+        account_id: &ShortId,
+        name: &str,
+        created_by: i64,
+    ) -> ShortId {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        let id = ShortId::new();
+        sqlx::query(
+            "INSERT INTO projects (account_id, uuid, shortid, name, created_by) SELECT id, $2, $3, $4, $5 FROM accounts WHERE uuid = $1",
+        )
+        .bind(account_id.as_uuid())
+        .bind(id.as_uuid())
+        .bind(id.to_string())
+        .bind(name)
+        .bind(created_by)
+        .execute(&mut conn)
+        .await
+        .expect("failed to create project");
+        sqlx::query(
+            "INSERT INTO user_projects (user_id, project_id, role) SELECT ur.user_id, p.id, ur.role FROM projects p INNER JOIN user_roles ur ON ur.account_id = p.account_id WHERE ur.user_id = $1 AND p.uuid = $2",
+        )
+        .bind(created_by)
+        .bind(id.as_uuid())
+        .execute(&mut conn)
+        .await
+        .expect("failed to assign user to project");
+        id
+    }
+
+    /// Creates a password reset token for a user, as if it had been emailed to them.
+    pub async fn create_password_reset_token(&self, user_id: i64) -> String {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        let token = up_server::tokens::generate_secret();
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, NOW() + INTERVAL '1 hour')",
+        )
+        .bind(user_id)
+        .bind(up_server::tokens::hash_secret(&token))
+        .execute(&mut conn)
+        .await
+        .expect("failed to create password reset token");
+        token
+    }
+
+    pub async fn create_magic_link_token(&self, user_id: i64) -> String {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        let token = up_server::tokens::generate_secret();
+        sqlx::query(
+            "INSERT INTO magic_link_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, NOW() + INTERVAL '15 minutes')",
+        )
+        .bind(user_id)
+        .bind(up_server::tokens::hash_secret(&token))
+        .execute(&mut conn)
+        .await
+        .expect("failed to create magic link token");
+        token
+    }
+
+    /// Creates an invitation to an account, as if it had been emailed to `email`.
+    pub async fn create_invitation_token(
+        &self,
+        account_id: &ShortId,
+        email: &str,
+        role: &str,
+        created_by: i64,
+    ) -> String {
+        let mut conn = self
+            .database
+            .connection()
+            .await
+            .expect("failed to connect to test database");
+        let id = ShortId::new();
+        let token = up_server::tokens::generate_secret();
+        sqlx::query(
+            "INSERT INTO invitations (uuid, shortid, account_id, email, role, token_hash, created_by, expires_at) SELECT $2, $3, id, $4, $5::user_role, $6, $7, NOW() + INTERVAL '7 days' FROM accounts WHERE uuid = $1",
+        )
+        .bind(account_id.as_uuid())
+        .bind(id.as_uuid())
+        .bind(id.to_string())
+        .bind(email)
+        .bind(role)
+        .bind(up_server::tokens::hash_secret(&token))
+        .bind(created_by)
+        .execute(&mut conn)
+        .await
+        .expect("failed to create invitation");
+        token
+    }
+
+    /// Makes checks overdue, as if they had been up and were last pinged days ago.
+    pub async fn make_checks_overdue(&self, check_ids: &[ShortId]) {
+        let uuids: Vec<Uuid> = check_ids.iter().map(|id| id.into_uuid()).collect();
+        sqlx::query(
+            "UPDATE checks SET status = 'UP', last_ping_at = NOW() AT TIME ZONE 'UTC' - INTERVAL '3 days' WHERE uuid = ANY($1)",
+        )
+        .bind(uuids)
+        .execute(&mut self.database.connection().await.unwrap())
+        .await
+        .expect("failed to make checks overdue");
+    }
+
+    pub fn repository(&self) -> Repository {
+        Repository::new(self.database.clone())
+    }
+
+    /// Returns a repository with its own connection pool, for tests that need several
+    /// connections to the database at the same time.
+    pub async fn pooled_repository(&self, max_connections: u32) -> Repository {
+        let database_url = format!("{}/{}", BASE_DATABASE_URL, self.database_name);
+        let database = Database::new(&database_url, 1, max_connections)
+            .await
+            .expect("failed to connect to test database");
+        Repository::new(database)
+    }
 }
 
 pub struct TestClient(reqwest::Client, Url, Option<String>);
@@ -214,11 +470,40 @@ impl TestClient {
             .await
     }
 
+    pub async fn put<RQ: Serialize, RS: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: RQ,
+    ) -> TestResult<RS> {
+        self.execute_json_request_response(reqwest::Method::PUT, path, Some(body))
+            .await
+    }
+
+    pub async fn patch<RQ: Serialize, RS: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: RQ,
+    ) -> TestResult<RS> {
+        self.execute_json_request_response(reqwest::Method::PATCH, path, Some(body))
+            .await
+    }
+
     pub async fn delete<RS: DeserializeOwned>(&self, path: &str) -> TestResult<RS> {
         self.execute_json_request_response(reqwest::Method::DELETE, path, None::<()>)
             .await
     }
 
+    /// Sends a request without a body to an endpoint that doesn't respond with one.
+    pub async fn send(&self, method: reqwest::Method, path: &str) -> TestResult<()> {
+        self.0
+            .request(method, self.1.join(path)?)
+            .headers(self.headers())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
     async fn execute_json_request_response<RQ: Serialize, RS: DeserializeOwned>(
         &self,
         method: reqwest::Method,
@@ -295,6 +580,15 @@ impl Drop for TestApp {
     }
 }
 
+/// Asserts that a request failed with an HTTP status.
+pub fn assert_status<T>(status: u16, result: TestResult<T>) {
+    if let Err(TestError::RequestError(e)) = result {
+        assert_eq!(status, e.status().unwrap().as_u16());
+    } else {
+        panic!("expected request to fail with {}", status);
+    }
+}
+
 fn next_available_port() -> u16 {
     for _ in 0..10 {
         if let Some(port) = bind_os_available_port() {
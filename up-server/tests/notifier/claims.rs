@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use futures::future::join_all;
+use up_server::{
+    notifier::Delivery,
+    repository::dto::{CreateNotification, DeliveryStatus, NotificationType},
+};
+
+use super::{check, create_notification, enqueue_alerts, notification, Check};
+use crate::TestApp;
+
+const CLAIM_TIMEOUT_MINUTES: i64 = 5;
+
+fn stale_before() -> NaiveDateTime {
+    Utc::now().naive_utc() - Duration::minutes(CLAIM_TIMEOUT_MINUTES)
+}
+
+async fn create_email_notification(app: &TestApp, check: &Check) {
+    create_notification(
+        app,
+        check,
+        CreateNotification {
+            email: Some("ops@example.com".to_string()),
+            ..notification(NotificationType::Email)
+        },
+    )
+    .await;
+}
+
+async fn delivery_status(app: &TestApp, alert_id: i64) -> DeliveryStatus {
+    sqlx::query_scalar("SELECT delivery_status FROM notification_alerts WHERE id = $1")
+        .bind(alert_id)
+        .fetch_one(&mut app.database.connection().await.unwrap())
+        .await
+        .expect("failed to read alert")
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn stale_claims_are_reclaimed() {
+    let app = TestApp::start().await;
+    let check = check(&app).await;
+    create_email_notification(&app, &check).await;
+    enqueue_alerts(&app, &check).await;
+    let notifications = app.repository().notification().clone();
+
+    let claimed = notifications
+        .claim_alert_batch(10, stale_before())
+        .await
+        .expect("failed to claim alerts");
+    assert_eq!(1, claimed.len());
+
+    let claimed_again = notifications
+        .claim_alert_batch(10, stale_before())
+        .await
+        .expect("failed to claim alerts");
+    assert!(claimed_again.is_empty(), "fresh claim was reclaimed");
+
+    // Once the claim timeout has passed, the claim is stale.
+    let later = Utc::now().naive_utc() + Duration::seconds(1);
+    let reclaimed = notifications
+        .claim_alert_batch(10, later)
+        .await
+        .expect("failed to claim alerts");
+    assert_eq!(1, reclaimed.len());
+    assert_eq!(claimed[0].id, reclaimed[0].id);
+    assert!(reclaimed[0].claimed_at > claimed[0].claimed_at);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn outdated_claim_cannot_record_outcome() {
+    let app = TestApp::start().await;
+    let check = check(&app).await;
+    create_email_notification(&app, &check).await;
+    enqueue_alerts(&app, &check).await;
+    let notifications = app.repository().notification().clone();
+
+    let claimed = notifications
+        .claim_alert_batch(10, stale_before())
+        .await
+        .expect("failed to claim alerts")
+        .remove(0);
+    let later = Utc::now().naive_utc() + Duration::seconds(1);
+    let reclaimed = notifications
+        .claim_alert_batch(10, later)
+        .await
+        .expect("failed to claim alerts")
+        .remove(0);
+
+    let delivery = Delivery::default();
+    notifications
+        .record_alert_delivered(&claimed, &delivery)
+        .await
+        .expect("failed to record delivery");
+    assert_eq!(
+        DeliveryStatus::Running,
+        delivery_status(&app, claimed.id).await
+    );
+
+    notifications
+        .record_alert_delivered(&reclaimed, &delivery)
+        .await
+        .expect("failed to record delivery");
+    assert_eq!(
+        DeliveryStatus::Delivered,
+        delivery_status(&app, claimed.id).await
+    );
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn concurrent_claimers_never_share_alerts() {
+    const ALERTS: usize = 20;
+    const CLAIMERS: usize = 4;
+
+    let app = TestApp::start().await;
+    let check = check(&app).await;
+    for _ in 0..ALERTS {
+        create_email_notification(&app, &check).await;
+    }
+    enqueue_alerts(&app, &check).await;
+    let repository = app.pooled_repository(CLAIMERS as u32).await;
+
+    let claimers = (0..CLAIMERS).map(|_| {
+        let repository = repository.clone();
+        tokio::spawn(async move {
+            let mut claimed = Vec::new();
+            loop {
+                let batch = repository
+                    .notification()
+                    .claim_alert_batch(2, stale_before())
+                    .await
+                    .expect("failed to claim alerts");
+                if batch.is_empty() {
+                    return claimed;
+                }
+                claimed.extend(batch.into_iter().map(|a| a.id));
+            }
+        })
+    });
+    let claimed: Vec<i64> = join_all(claimers)
+        .await
+        .into_iter()
+        .flat_map(|c| c.expect("claimer failed"))
+        .collect();
+
+    let unique: HashSet<i64> = claimed.iter().copied().collect();
+    assert_eq!(ALERTS, claimed.len(), "alerts claimed more than once");
+    assert_eq!(ALERTS, unique.len());
+}
@@ -0,0 +1,256 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use up_server::{
+    jobs::SendAlerts,
+    repository::dto::{
+        CheckSeverity, CreateCheck, CreateNotification, DeliveryStatus, NotificationType,
+    },
+    shortid::ShortId,
+};
+use uuid::Uuid;
+
+use super::{check, create_notification, notification, Check, Providers};
+use crate::TestApp;
+
+const DIGEST_WINDOW_SECONDS: i32 = 300;
+
+/// Creates another check in the project of `check`, returning its ID.
+async fn create_check(
+    app: &TestApp,
+    check: &Check,
+    name: &str,
+    severity: Option<CheckSeverity>,
+) -> Uuid {
+    let request = CreateCheck {
+        project_uuid: check.project_id,
+        name: name.to_string(),
+        severity,
+    };
+    app.repository()
+        .check()
+        .create(&check.identity, &check.project_id, request)
+        .await
+        .expect("failed to create check")
+        .uuid
+}
+
+/// Creates a channel with a digest window and attaches it to `check_ids`.
+async fn digest_channel(
+    app: &TestApp,
+    check: &Check,
+    request: CreateNotification,
+    check_ids: &[Uuid],
+) {
+    let channels = app.repository().channel().clone();
+    let channel = channels
+        .create(
+            &check.identity,
+            &check.project_id,
+            CreateNotification {
+                digest_window_seconds: Some(DIGEST_WINDOW_SECONDS),
+                ..request
+            },
+        )
+        .await
+        .expect("failed to create channel");
+    for check_id in check_ids {
+        channels
+            .attach(&check.identity, &check.project_id, check_id, &channel.uuid)
+            .await
+            .expect("failed to attach channel");
+    }
+}
+
+/// Makes checks overdue and enqueues alerts for them, as the `EnqueueAlerts` job would.
+async fn enqueue_alerts(app: &TestApp, check_ids: &[Uuid]) {
+    let check_ids: Vec<ShortId> = check_ids.iter().map(ShortId::from_uuid).collect();
+    app.make_checks_overdue(&check_ids).await;
+    app.repository()
+        .check()
+        .enqueue_alerts_for_overdue_pings()
+        .await
+        .expect("failed to enqueue alerts");
+}
+
+/// Makes every queued alert due now.
+async fn close_digest_windows(app: &TestApp) {
+    sqlx::query("UPDATE notification_alerts SET next_attempt_at = NOW() AT TIME ZONE 'UTC'")
+        .execute(&mut app.database.connection().await.unwrap())
+        .await
+        .expect("failed to close digest windows");
+}
+
+async fn next_attempts(app: &TestApp) -> Vec<NaiveDateTime> {
+    sqlx::query_scalar("SELECT next_attempt_at FROM notification_alerts ORDER BY id")
+        .fetch_all(&mut app.database.connection().await.unwrap())
+        .await
+        .expect("failed to read alerts")
+}
+
+/// Runs the `SendAlerts` job until every queued alert has been delivered or failed.
+async fn send_alerts(app: &TestApp, providers: &Providers) {
+    let notifier = providers.notifier(app).await;
+    let mut job = SendAlerts::with_repository(
+        app.pooled_repository(4).await,
+        notifier,
+        2,
+        StdDuration::from_secs(300),
+    );
+    job.spawn().await;
+
+    for _ in 0..50 {
+        let pending: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notification_alerts WHERE delivery_status IN ('QUEUED', 'RUNNING')",
+        )
+        .fetch_one(&mut app.database.connection().await.unwrap())
+        .await
+        .expect("failed to count alerts");
+        if pending == 0 {
+            break;
+        }
+        tokio::time::sleep(StdDuration::from_millis(100)).await;
+    }
+
+    job.stop().await;
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn alerts_join_the_open_digest_window() {
+    let app = TestApp::start().await;
+    let check = check(&app).await;
+    let weekly = create_check(&app, &check, "weekly", None).await;
+    digest_channel(
+        &app,
+        &check,
+        CreateNotification {
+            email: Some("ops@example.com".to_string()),
+            ..notification(NotificationType::Email)
+        },
+        &[check.id, weekly],
+    )
+    .await;
+
+    enqueue_alerts(&app, &[check.id]).await;
+    let opened_at = Utc::now().naive_utc();
+    enqueue_alerts(&app, &[weekly]).await;
+
+    let next_attempts = next_attempts(&app).await;
+    assert_eq!(2, next_attempts.len());
+    assert_eq!(next_attempts[0], next_attempts[1]);
+    assert!(next_attempts[0] > opened_at + Duration::seconds(DIGEST_WINDOW_SECONDS as i64 - 60));
+
+    let claimed = app
+        .repository()
+        .notification()
+        .claim_alert_batch(10, Utc::now().naive_utc() - Duration::minutes(5))
+        .await
+        .expect("failed to claim alerts");
+    assert!(
+        claimed.is_empty(),
+        "alerts claimed before the window closed"
+    );
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn digest_is_posted_to_webhook_once() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    let check = check(&app).await;
+    let weekly = create_check(&app, &check, "weekly", None).await;
+    digest_channel(
+        &app,
+        &check,
+        CreateNotification {
+            url: Some(providers.webhook_url()),
+            ..notification(NotificationType::Webhook)
+        },
+        &[check.id, weekly],
+    )
+    .await;
+    create_notification(
+        &app,
+        &check,
+        CreateNotification {
+            url: Some(providers.webhook_url()),
+            ..notification(NotificationType::Webhook)
+        },
+    )
+    .await;
+
+    enqueue_alerts(&app, &[check.id, weekly]).await;
+    close_digest_windows(&app).await;
+    send_alerts(&app, &providers).await;
+
+    let mut calls = providers.webhook_calls().await;
+    assert_eq!(2, calls.len());
+    calls.sort_by_key(|call| call.get("alerts").is_some());
+    assert_eq!("nightly", calls[0]["check_name"]);
+
+    let digest = &calls[1];
+    assert_eq!("DOWN", digest["status"]);
+    assert_eq!(2, digest["count"]);
+    let mut check_names: Vec<&str> = digest["alerts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|alert| alert["check_name"].as_str().unwrap())
+        .collect();
+    check_names.sort_unstable();
+    assert_eq!(vec!["nightly", "weekly"], check_names);
+
+    let attempts: Vec<(DeliveryStatus, Option<i32>)> = sqlx::query_as(
+        "SELECT delivery_status, http_status FROM notification_alert_attempts ORDER BY id",
+    )
+    .fetch_all(&mut app.database.connection().await.unwrap())
+    .await
+    .unwrap();
+    assert_eq!(
+        vec![(DeliveryStatus::Delivered, Some(204)); 3],
+        attempts,
+        "every alert should be delivered in a single attempt"
+    );
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn alerts_do_not_wait_for_alerts_held_during_quiet_hours() {
+    let app = TestApp::start().await;
+    let check = check(&app).await;
+    let low = create_check(&app, &check, "cleanup", Some(CheckSeverity::Low)).await;
+    let high = create_check(&app, &check, "payments", Some(CheckSeverity::High)).await;
+    digest_channel(
+        &app,
+        &check,
+        CreateNotification {
+            email: Some("ops@example.com".to_string()),
+            ..notification(NotificationType::Email)
+        },
+        &[low, high],
+    )
+    .await;
+
+    enqueue_alerts(&app, &[low]).await;
+    close_digest_windows(&app).await;
+    let notifications = app.repository().notification().clone();
+    let held = notifications
+        .claim_alert_batch(10, Utc::now().naive_utc() - Duration::minutes(5))
+        .await
+        .expect("failed to claim alerts")
+        .remove(0);
+    let morning = Utc::now().naive_utc() + Duration::hours(8);
+    notifications
+        .hold_alert(&held, morning)
+        .await
+        .expect("failed to hold alert");
+
+    let enqueued_at = Utc::now().naive_utc();
+    enqueue_alerts(&app, &[high]).await;
+
+    let next_attempts = next_attempts(&app).await;
+    assert_eq!(2, next_attempts.len());
+    assert!(next_attempts[0] > enqueued_at + Duration::hours(7));
+    assert!(
+        next_attempts[1] <= enqueued_at + Duration::seconds(DIGEST_WINDOW_SECONDS as i64 + 60),
+        "alert waits for an alert held during quiet hours"
+    );
+}
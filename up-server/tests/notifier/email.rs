@@ -0,0 +1,114 @@
+use chrono::{Duration, Utc};
+use up_server::{
+    repository::dto::{CheckStatus, CreateNotification, NotificationType},
+    shortid::ShortId,
+};
+
+use super::{
+    check, create_notification, enqueue_alerts, notification, read_alert, Providers, BASE_URL,
+    EMAIL_FROM, PRODUCT_NAME,
+};
+use crate::TestApp;
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn account_branding_overrides_server_branding() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    let notifier = providers.notifier(&app).await;
+    let check = check(&app).await;
+    let notification_id = create_notification(
+        &app,
+        &check,
+        CreateNotification {
+            email: Some("ops@example.com".to_string()),
+            ..notification(NotificationType::Email)
+        },
+    )
+    .await;
+
+    let alert = read_alert(&app, &check, &notification_id).await;
+    notifier
+        .send_alert(&alert)
+        .await
+        .expect("failed to send alert");
+
+    sqlx::query(
+        "UPDATE accounts SET email_from = $1, email_reply_to = $2, product_name = $3, base_url = $4",
+    )
+    .bind("Acme Status <status@acme.example>")
+    .bind("support@acme.example")
+    .bind("Acme Status")
+    .bind("https://status.acme.example/")
+    .execute(&mut app.database.connection().await.unwrap())
+    .await
+    .unwrap();
+
+    let alert = read_alert(&app, &check, &notification_id).await;
+    notifier
+        .send_alert(&alert)
+        .await
+        .expect("failed to send alert");
+
+    let emails = providers.emails().await;
+    assert_eq!(2, emails.len());
+    let check_id = ShortId::from_uuid(&check.id);
+
+    let server = &emails[0];
+    let text = server["TextBody"].as_str().unwrap();
+    assert_eq!(EMAIL_FROM, server["From"]);
+    assert!(server.get("ReplyTo").is_none());
+    assert!(text.contains(&format!("Sent by {}", PRODUCT_NAME)));
+    assert!(text.contains(&format!("{}checks/{}", BASE_URL, check_id)));
+
+    let account = &emails[1];
+    let text = account["TextBody"].as_str().unwrap();
+    assert_eq!("Acme Status <status@acme.example>", account["From"]);
+    assert_eq!("support@acme.example", account["ReplyTo"]);
+    assert!(text.contains("Sent by Acme Status"));
+    assert!(text.contains(&format!("https://status.acme.example/checks/{}", check_id)));
+    assert!(!text.contains(BASE_URL));
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn digest_shows_status_of_each_alert() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    let notifier = providers.notifier(&app).await;
+    let check = check(&app).await;
+    let notification_id = create_notification(
+        &app,
+        &check,
+        CreateNotification {
+            email: Some("ops@example.com".to_string()),
+            ..notification(NotificationType::Email)
+        },
+    )
+    .await;
+
+    enqueue_alerts(&app, &check).await;
+    let mut alerts = app
+        .repository()
+        .notification()
+        .claim_alert_batch(10, Utc::now().naive_utc() - Duration::minutes(5))
+        .await
+        .expect("failed to claim alerts");
+    assert_eq!(1, alerts.len());
+    assert_eq!(CheckStatus::Down, alerts[0].check_status);
+
+    let mut recovered = read_alert(&app, &check, &notification_id).await;
+    recovered.check_name = "backups".to_string();
+    recovered.check_status = CheckStatus::Up;
+    alerts.push(recovered);
+
+    notifier
+        .send_digest(&alerts)
+        .await
+        .expect("failed to send digest");
+
+    let emails = providers.emails().await;
+    assert_eq!(1, emails.len());
+    let text = emails[0]["TextBody"].as_str().unwrap();
+    assert_eq!("[DOWN] 2 checks changed status", emails[0]["Subject"]);
+    assert!(text.contains("nightly (backups) is DOWN"));
+    assert!(text.contains("backups (backups) is UP"));
+}
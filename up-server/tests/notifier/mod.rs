@@ -0,0 +1,215 @@
+use serde_json::json;
+use up_core::{JWKS_ENV, SERVER_CERTIFICATE_ENV};
+use up_server::{
+    acknowledgement::AcknowledgementLinks,
+    auth::Identity,
+    integrations::{postmark::PostmarkClient, twilio::TwilioClient},
+    keys::KeyStore,
+    notifier::{Branding, Notifier},
+    repository::dto::{CreateCheck, CreateNotification, NotificationAlert, NotificationType},
+    shortid::ShortId,
+    templates::Templates,
+};
+use uuid::Uuid;
+use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+use crate::TestApp;
+
+pub mod claims;
+pub mod digests;
+pub mod email;
+pub mod sms;
+pub mod webhook;
+
+const ACCOUNT_SID: &str = "AC00000000000000000000000000000000";
+const SMS_RATE_LIMIT: u32 = 3;
+const EMAIL_FROM: &str = "up.io <no-reply@example.com>";
+const PRODUCT_NAME: &str = "up.io";
+const BASE_URL: &str = "http://localhost:8080/";
+
+/// A check in a project, and the identity of a member of the project.
+pub struct Check {
+    identity: Identity,
+    project_id: Uuid,
+    id: Uuid,
+}
+
+async fn check(app: &TestApp) -> Check {
+    let user_id = app
+        .create_user_with_password("member@example.com", "correct horse battery staple")
+        .await;
+    let account_id = ShortId::new();
+    app.create_account(&account_id, "acme", user_id).await;
+    app.add_user_to_account(user_id, &account_id, "MEMBER")
+        .await;
+    let project_id = app.create_project(&account_id, "backups", user_id).await;
+    let identity = app.identity(user_id).await;
+
+    let request = CreateCheck {
+        project_uuid: project_id.into_uuid(),
+        name: "nightly".to_string(),
+        severity: None,
+    };
+    let check = app
+        .repository()
+        .check()
+        .create(&identity, project_id.as_uuid(), request)
+        .await
+        .expect("failed to create check");
+
+    Check {
+        identity,
+        project_id: project_id.into_uuid(),
+        id: check.uuid,
+    }
+}
+
+fn notification(notification_type: NotificationType) -> CreateNotification {
+    CreateNotification {
+        notification_type,
+        name: Some("on call".to_string()),
+        email: None,
+        url: None,
+        phone_number: None,
+        max_retries: Some(3),
+        retry_backoff_seconds: None,
+        retry_backoff_max_seconds: None,
+        digest_window_seconds: None,
+    }
+}
+
+/// Creates a notification for a check, returning its ID.
+async fn create_notification(app: &TestApp, check: &Check, request: CreateNotification) -> Uuid {
+    app.repository()
+        .notification()
+        .create(&check.identity, &check.project_id, &check.id, request)
+        .await
+        .expect("failed to create notification")
+        .uuid
+}
+
+/// Returns an alert for a notification as if its check had just gone down.
+async fn read_alert(app: &TestApp, check: &Check, notification_id: &Uuid) -> NotificationAlert {
+    app.repository()
+        .notification()
+        .read_test_alert(
+            &check.identity,
+            &check.project_id,
+            &check.id,
+            notification_id,
+        )
+        .await
+        .expect("failed to read alert")
+}
+
+/// Creates a notification for a check, returning an alert for it as if the check had
+/// just gone down.
+async fn alert(app: &TestApp, check: &Check, request: CreateNotification) -> NotificationAlert {
+    let notification_id = create_notification(app, check, request).await;
+    read_alert(app, check, &notification_id).await
+}
+
+/// Makes a check overdue and enqueues alerts for it, as the `EnqueueAlerts` job would.
+async fn enqueue_alerts(app: &TestApp, check: &Check) {
+    app.make_checks_overdue(&[check.id.into()]).await;
+    app.repository()
+        .check()
+        .enqueue_alerts_for_overdue_pings()
+        .await
+        .expect("failed to enqueue alerts");
+}
+
+/// Mock Twilio and Postmark APIs and a mock webhook that a notifier sends alerts to.
+pub struct Providers {
+    twilio: MockServer,
+    postmark: MockServer,
+    webhook: MockServer,
+}
+
+impl Providers {
+    /// Starts mock APIs, with Postmark accepting every email and the webhook accepting
+    /// every call.
+    async fn start() -> Self {
+        let postmark = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                "To": "ops@example.com"
+            })))
+            .mount(&postmark)
+            .await;
+
+        let webhook = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&webhook)
+            .await;
+
+        Self {
+            twilio: MockServer::start().await,
+            postmark,
+            webhook,
+        }
+    }
+
+    /// Creates a notifier that sends alerts to the mock APIs.
+    async fn notifier(&self, app: &TestApp) -> Notifier {
+        let postmark_client = PostmarkClient::with_token("secret", &self.postmark.uri()).unwrap();
+        let twilio_client = TwilioClient::with_credentials(
+            ACCOUNT_SID,
+            "secret",
+            "+15550000000",
+            &self.twilio.uri(),
+        )
+        .unwrap();
+        let jwks = std::env::var(JWKS_ENV).expect("missing JWKS environment variable");
+        let pem = std::env::var(SERVER_CERTIFICATE_ENV)
+            .expect("missing SERVER_CERTIFICATE environment variable");
+        let keys = KeyStore::from_jwks(&jwks, Some(pem.as_bytes())).unwrap();
+        let acknowledgements = AcknowledgementLinks::new(keys, 72);
+
+        Notifier::new(
+            app.pooled_repository(SMS_RATE_LIMIT * 3).await,
+            postmark_client,
+            Some(twilio_client),
+            Templates::new(None).unwrap(),
+            Branding {
+                email_from: EMAIL_FROM.to_string(),
+                email_reply_to: None,
+                product_name: PRODUCT_NAME.to_string(),
+                base_url: BASE_URL.parse().unwrap(),
+            },
+            SMS_RATE_LIMIT,
+            acknowledgements,
+        )
+    }
+
+    /// The URL of the mock webhook.
+    fn webhook_url(&self) -> String {
+        format!("{}/alerts", self.webhook.uri())
+    }
+
+    /// The bodies of the calls to the webhook so far.
+    async fn webhook_calls(&self) -> Vec<serde_json::Value> {
+        self.webhook
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|r| serde_json::from_slice(&r.body).expect("webhook call is not JSON"))
+            .collect()
+    }
+
+    /// The emails sent to Postmark so far.
+    async fn emails(&self) -> Vec<serde_json::Value> {
+        self.postmark
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|r| serde_json::from_slice(&r.body).expect("email is not JSON"))
+            .collect()
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use futures::future::join_all;
+use serde_json::json;
+use up_server::{
+    notifier::NotifierError,
+    repository::dto::{CreateNotification, NotificationType},
+};
+use wiremock::{matchers::method, Mock, ResponseTemplate};
+
+use super::{alert, check, notification, Providers, SMS_RATE_LIMIT};
+use crate::TestApp;
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn concurrent_sms_alerts_respect_rate_limit() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+            "sid": "SM00000000000000000000000000000000",
+            "status": "queued",
+            "error_code": null,
+            "error_message": null
+        })))
+        .expect(u64::from(SMS_RATE_LIMIT))
+        .mount(&providers.twilio)
+        .await;
+
+    let check = check(&app).await;
+    let alert = alert(
+        &app,
+        &check,
+        CreateNotification {
+            phone_number: Some("+15551234567".to_string()),
+            ..notification(NotificationType::Sms)
+        },
+    )
+    .await;
+    let notifier = providers.notifier(&app).await;
+
+    let alert = Arc::new(alert);
+    let attempts = (0..SMS_RATE_LIMIT * 3).map(|_| {
+        let notifier = notifier.clone();
+        let alert = alert.clone();
+        tokio::spawn(async move { notifier.send_alert(&alert).await })
+    });
+    let results: Vec<_> = join_all(attempts)
+        .await
+        .into_iter()
+        .map(|r| r.expect("failed to send alert"))
+        .collect();
+
+    let sent = results.iter().filter(|r| r.is_ok()).count();
+    let limited = results
+        .iter()
+        .filter(|r| matches!(r, Err(NotifierError::SmsRateLimitExceeded(_))))
+        .count();
+    assert_eq!(SMS_RATE_LIMIT as usize, sent);
+    assert_eq!(results.len() - sent, limited);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn failed_sms_does_not_count_towards_rate_limit() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(u64::from(SMS_RATE_LIMIT + 1))
+        .mount(&providers.twilio)
+        .await;
+
+    let check = check(&app).await;
+    let alert = alert(
+        &app,
+        &check,
+        CreateNotification {
+            phone_number: Some("+15551234567".to_string()),
+            ..notification(NotificationType::Sms)
+        },
+    )
+    .await;
+    let notifier = providers.notifier(&app).await;
+
+    for _ in 0..=SMS_RATE_LIMIT {
+        let result = notifier.send_alert(&alert).await;
+        assert!(matches!(result, Err(NotifierError::SmsSendError(_))));
+    }
+}
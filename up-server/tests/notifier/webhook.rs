@@ -0,0 +1,132 @@
+use chrono::{Duration, Utc};
+use up_server::{
+    api::v1::incidents::Incident,
+    repository::dto::{CreateNotification, NotificationAlert, NotificationType},
+    shortid::ShortId,
+};
+use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+use super::{check, create_notification, enqueue_alerts, notification, Check, Providers, BASE_URL};
+use crate::{TestApp, TestUser};
+
+/// Follows the acknowledge link in a webhook payload, returning the acknowledged
+/// incident.
+async fn acknowledge(app: &TestApp, payload: &serde_json::Value) -> Incident {
+    let acknowledge_url = payload["acknowledge_url"]
+        .as_str()
+        .expect("webhook payload has no acknowledge URL");
+    let path = acknowledge_url
+        .strip_prefix(BASE_URL)
+        .expect("acknowledge URL is not on the base URL");
+    assert!(path.starts_with("api/v1/acknowledge/"));
+
+    let client = app.connect(TestUser::Anonymous).await.unwrap();
+    client
+        .get(&format!("/{}", path))
+        .await
+        .expect("failed to acknowledge incident")
+}
+
+/// Creates a webhook notification for a check and returns the alert enqueued for it
+/// when the check goes down.
+async fn webhook_alert(app: &TestApp, check: &Check, url: String) -> NotificationAlert {
+    create_notification(
+        app,
+        check,
+        CreateNotification {
+            url: Some(url),
+            ..notification(NotificationType::Webhook)
+        },
+    )
+    .await;
+    enqueue_alerts(app, check).await;
+
+    let mut alerts = app
+        .repository()
+        .notification()
+        .claim_alert_batch(10, Utc::now().naive_utc() - Duration::minutes(5))
+        .await
+        .expect("failed to claim alerts");
+    assert_eq!(1, alerts.len());
+    alerts.remove(0)
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn webhook_is_called_with_acknowledge_link() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    let notifier = providers.notifier(&app).await;
+    let check = check(&app).await;
+    let alert = webhook_alert(&app, &check, providers.webhook_url()).await;
+
+    let delivery = notifier
+        .send_alert(&alert)
+        .await
+        .expect("failed to call webhook");
+    assert_eq!(Some(204), delivery.http_status);
+
+    let calls = providers.webhook_calls().await;
+    assert_eq!(1, calls.len());
+    let payload = &calls[0];
+    assert_eq!("DOWN", payload["status"]);
+    assert_eq!("nightly", payload["check_name"]);
+    assert_eq!(
+        ShortId::from_uuid(&check.id).to_string(),
+        payload["check_id"]
+    );
+    assert_eq!(false, payload["test"]);
+
+    let incident = acknowledge(&app, payload).await;
+    assert!(incident.acknowledged_at.is_some());
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn acknowledged_webhook_alert_does_not_expose_url() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    let notifier = providers.notifier(&app).await;
+    let check = check(&app).await;
+    let alert = webhook_alert(&app, &check, providers.webhook_url()).await;
+    notifier
+        .send_alert(&alert)
+        .await
+        .expect("failed to call webhook");
+
+    let calls = providers.webhook_calls().await;
+    let incident = acknowledge(&app, &calls[0]).await;
+    assert_eq!(Some("on call"), incident.acknowledged_by.as_deref());
+
+    let incidents = app
+        .repository()
+        .incident()
+        .read_all(&check.identity, &check.project_id, &check.id)
+        .await
+        .expect("failed to read incidents");
+    assert_eq!(1, incidents.len());
+    assert_eq!(Some("on call"), incidents[0].acknowledged_by.as_deref());
+    assert!(!incident
+        .acknowledged_by
+        .unwrap_or_default()
+        .contains(&providers.webhook_url()));
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+pub async fn failed_webhook_call_is_reported() {
+    let app = TestApp::start().await;
+    let providers = Providers::start().await;
+    let notifier = providers.notifier(&app).await;
+    let check = check(&app).await;
+
+    let failing = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&failing)
+        .await;
+    let alert = webhook_alert(&app, &check, failing.uri()).await;
+
+    let error = notifier
+        .send_alert(&alert)
+        .await
+        .expect_err("failed webhook call was not reported");
+    assert_eq!(Some(500), error.http_status());
+}